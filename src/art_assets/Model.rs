@@ -10,18 +10,17 @@ use gltf::Glb;
 use gltf::Gltf;
 use wgpu::wgt::TextureDataOrder;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use image;
 
 use crate::core::SimpleCamera::SimpleCamera;
+use crate::helpers::saved_data::ScriptState;
 use crate::core::Transform_2::{matrix4_to_raw_array, Transform};
 use crate::core::transform::create_empty_group_transform;
 use crate::core::vertex::{ModelVertex, Vertex};
-use crate::helpers::utilities::get_common_os_dir;
+use crate::helpers::pak::read_project_file;
 use crate::core::editor::WindowSize;
 
 #[derive(Debug, Clone)]
@@ -96,7 +95,7 @@ pub struct Model {
     pub joint_matrices_buffer: Option<wgpu::Buffer>,
     pub skin_bind_group: Option<wgpu::BindGroup>,
     pub hide_from_world: bool,
-    pub script_state: Option<HashMap<String, String>>,
+    pub script_state: Option<ScriptState>,
 }
 
 impl Model {
@@ -848,21 +847,94 @@ impl Model {
     }
 }
 
+// modelPath is relative to the project root, see File::load_path
 pub fn read_model(
     projectId: String,
-    modelFilename: String,
+    modelPath: String,
 ) -> Result<Vec<u8>, String> {
-    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
-    let model_path = sync_dir.join(format!(
-        "midpoint/projects/{}/models/{}",
-        projectId, modelFilename
-    ));
+    read_project_file(&projectId, &modelPath)
+        .map_err(|e| format!("Failed to read model: {}", e))
+}
+
+// Builds the same trimesh colliders and fixed bodies as `Model::from_glb` but skips all GPU work,
+// for simulations that run without a renderer
+pub fn read_model_physics(
+    model_component_id: &String,
+    bytes: &Vec<u8>,
+    isometry: Isometry3<f32>,
+    scale: Vector3<f32>,
+) -> Result<Vec<(RigidBody, Collider)>, String> {
+    let glb = Glb::from_slice(&bytes).map_err(|e| format!("Couldn't create glb from slice: {}", e))?;
+    let gltf = Gltf::from_slice(&glb.json).map_err(|e| format!("Failed to parse GLTF JSON: {}", e))?;
+
+    let buffer_data = match glb.bin {
+        Some(bin) => bin,
+        None => return Err("No binary data found in GLB file".to_string()),
+    };
+
+    let user_data = Uuid::from_str(&model_component_id)
+        .map_err(|e| format!("Couldn't extract uuid: {}", e))?
+        .as_u128();
+
+    let mut bodies = Vec::new();
+
+    for node in gltf.nodes() {
+        let (translation, rotation, node_scale) = node.transform().decomposed();
+
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                let reader = primitive.reader(|buffer| Some(&buffer_data));
+
+                let positions = match reader.read_positions() {
+                    Some(positions) => positions,
+                    None => continue,
+                };
+
+                let rapier_points: Vec<Point<f32>> = positions
+                    .map(|p| point![p[0] * (scale.x * node_scale[0]), p[1] * (scale.y * node_scale[1]), p[2] * (scale.z * node_scale[2])])
+                    .collect();
+
+                let indices_u32: Vec<u32> = reader
+                    .read_indices()
+                    .map(|iter| iter.into_u32().collect())
+                    .unwrap_or_default();
+
+                let rapier_indices: Vec<[u32; 3]> = indices_u32
+                    .chunks_exact(3)
+                    .map(|chunk| [chunk[0], chunk[1], chunk[2]])
+                    .collect();
+
+                let node_rot = UnitQuaternion::from_quaternion(Quaternion::new(
+                    rotation[3],
+                    rotation[0],
+                    rotation[1],
+                    rotation[2],
+                ));
 
-    let mut file = File::open(&model_path).map_err(|e| format!("Failed to open model: {}", e))?;
+                let new_rotation = isometry.rotation * node_rot;
+                let new_position = Vector3::new(translation[0] + isometry.translation.x, translation[1] + isometry.translation.y, translation[2] + isometry.translation.z);
+                let model_position = Translation3::new(new_position.x, new_position.y, new_position.z);
+                let model_final_iso = Isometry3::from_parts(model_position, new_rotation);
 
-    let mut bytes = Vec::new();
-    file.read_to_end(&mut bytes)
-        .map_err(|e| format!("Failed to read model: {}", e))?;
+                let rapier_collider = ColliderBuilder::trimesh(rapier_points, rapier_indices)
+                    .friction(0.7)
+                    .restitution(0.0)
+                    .density(1.0)
+                    .user_data(user_data)
+                    .build();
+
+                let fixed_body = RigidBodyBuilder::fixed()
+                    .additional_mass(70.0)
+                    .linear_damping(0.1)
+                    .position(model_final_iso)
+                    .locked_axes(LockedAxes::ROTATION_LOCKED_X | LockedAxes::ROTATION_LOCKED_Z)
+                    .user_data(user_data)
+                    .build();
+
+                bodies.push((fixed_body, rapier_collider));
+            }
+        }
+    }
 
-    Ok(bytes)
+    Ok(bodies)
 }
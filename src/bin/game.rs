@@ -1,13 +1,15 @@
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use entropy_engine::startup;
 
-use std::error::Error;
 use std::env;
 
+// Usage: game [project_id|game.pak]
 #[tokio::main]
 async fn main() {
-    let project_id = Some("5fa6dd47-4355-4de5-b5a3-a7f61e979fcc".to_string());
+    let project = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "5fa6dd47-4355-4de5-b5a3-a7f61e979fcc".to_string());
     
-    #[cfg(target_os = "windows")]
-    startup::run_game(project_id).expect("Couldn't run game");
+    #[cfg(not(target_arch = "wasm32"))]
+    startup::run_game(Some(project)).expect("Couldn't run game");
 }
@@ -0,0 +1,50 @@
+use entropy_engine::headless::{HeadlessConfig, HeadlessSim};
+use entropy_engine::helpers::pak;
+
+use std::env;
+
+// Usage: headless <project_id|game.pak> [--frames N] [--rate HZ] [--fast] [--json]
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let project_id = match args.get(1) {
+        Some(id) if !id.starts_with("--") => id.clone(),
+        _ => {
+            println!("Usage: headless <project_id|game.pak> [--frames N] [--rate HZ] [--fast] [--json]");
+            std::process::exit(2);
+        }
+    };
+
+    let mut config = HeadlessConfig::default();
+    let mut json = false;
+
+    let mut i = 2;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--frames" => {
+                i += 1;
+                config.frames = args.get(i).and_then(|v| v.parse().ok()).expect("Couldn't parse --frames");
+            }
+            "--rate" => {
+                i += 1;
+                config.tick_rate = args.get(i).and_then(|v| v.parse().ok()).expect("Couldn't parse --rate");
+            }
+            "--fast" => config.realtime = false,
+            "--json" => json = true,
+            other => println!("Ignoring unknown argument {}", other),
+        }
+        i += 1;
+    }
+
+    let project_id = pak::resolve_project(&project_id).expect("Couldn't mount package");
+
+    let mut sim = HeadlessSim::load(&project_id).await.expect("Couldn't load project for headless run");
+    let report = sim.run(&config);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).expect("Couldn't serialize report"));
+    } else {
+        report.print();
+    }
+}
@@ -0,0 +1,97 @@
+use entropy_engine::helpers::pak::{pack_project, PakArchive};
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+// Usage: pack <project_id|project_dir|midpoint.json> <out.pak> [--scripts DIR]
+//        pack --verify <game.pak>
+// Bundles a project and its scripts into one archive the game binary can run directly
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("--verify") {
+        let path = match args.get(2) {
+            Some(path) => path,
+            None => usage(),
+        };
+        verify(Path::new(path));
+        return;
+    }
+
+    let (target, output) = match (args.get(1), args.get(2)) {
+        (Some(target), Some(output)) if !target.starts_with("--") && !output.starts_with("--") => {
+            (target.clone(), PathBuf::from(output))
+        }
+        _ => usage(),
+    };
+
+    // scripts resolve against the working directory by default, same as the engine
+    let mut script_root = PathBuf::from(".");
+
+    let mut i = 3;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--scripts" => {
+                i += 1;
+                script_root = args.get(i).map(PathBuf::from).expect("Couldn't parse --scripts");
+            }
+            other => println!("Ignoring unknown argument {}", other),
+        }
+        i += 1;
+    }
+
+    match pack_project(&target, &script_root, &output) {
+        Ok(manifest) => {
+            for entry in &manifest.entries {
+                println!("  {} ({} bytes)", entry.path, entry.size);
+            }
+            println!(
+                "Packed {} ({}) into {:?}: {} files, {} bytes",
+                manifest.project_name,
+                manifest.project_id,
+                output,
+                manifest.entries.len(),
+                manifest.total_size()
+            );
+        }
+        Err(e) => {
+            println!("error: {}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn verify(path: &Path) {
+    let archive = match PakArchive::open(path) {
+        Ok(archive) => archive,
+        Err(e) => {
+            println!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let corrupt = archive.verify();
+    for entry in &corrupt {
+        println!("  hash mismatch: {}", entry);
+    }
+
+    println!(
+        "{} ({}), schema v{}, packed {}: {} files, {} corrupt",
+        archive.manifest.project_name,
+        archive.manifest.project_id,
+        archive.manifest.schema_version,
+        archive.manifest.created_at,
+        archive.manifest.entries.len(),
+        corrupt.len()
+    );
+
+    if !corrupt.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn usage() -> ! {
+    println!("Usage: pack <project_id|project_dir|midpoint.json> <out.pak> [--scripts DIR]");
+    println!("       pack --verify <game.pak>");
+    std::process::exit(2);
+}
@@ -0,0 +1,48 @@
+use entropy_engine::helpers::utilities::relocate_project;
+
+use std::env;
+
+// Usage: relocate <project_id|project_dir|midpoint.json> [--dry-run]
+// Rewrites absolute asset paths in midpoint.json so the project can move between machines
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let target = match args.get(1) {
+        Some(target) if !target.starts_with("--") => target.clone(),
+        _ => {
+            println!("Usage: relocate <project_id|project_dir|midpoint.json> [--dry-run]");
+            std::process::exit(2);
+        }
+    };
+    let dry_run = args.iter().any(|a| a == "--dry-run");
+
+    let report = match relocate_project(&target, dry_run) {
+        Ok(report) => report,
+        Err(e) => {
+            println!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    println!("Project file {:?}", report.json_path);
+
+    for (old, new) in &report.rewritten {
+        println!("  {} -> {}", old, new);
+    }
+    for path in &report.unresolved {
+        println!("  couldn't make relative: {}", path);
+    }
+    for path in &report.missing {
+        println!("  missing on disk: {}", path);
+    }
+
+    if dry_run {
+        println!("{} paths would be rewritten (dry run, nothing saved)", report.rewritten.len());
+    } else {
+        println!("{} paths rewritten", report.rewritten.len());
+    }
+
+    if !report.unresolved.is_empty() {
+        std::process::exit(1);
+    }
+}
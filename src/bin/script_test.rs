@@ -0,0 +1,55 @@
+use entropy_engine::script_tests::{fixture_paths, load_suite, run_suite, to_junit, to_tap};
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// Usage: script_test <fixture.json|dir>... [--junit] [--out FILE]
+// Prints TAP by default. Exits 0 when every case passes, 1 when any fails, 2 when a fixture can't be read
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut targets = Vec::new();
+    let mut junit = false;
+    let mut out = None;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--junit" => junit = true,
+            "--out" => {
+                i += 1;
+                out = Some(args.get(i).map(PathBuf::from).expect("Couldn't parse --out"));
+            }
+            other if other.starts_with("--") => println!("Ignoring unknown argument {}", other),
+            target => targets.push(PathBuf::from(target)),
+        }
+        i += 1;
+    }
+
+    if targets.is_empty() {
+        println!("Usage: script_test <fixture.json|dir>... [--junit] [--out FILE]");
+        std::process::exit(2);
+    }
+
+    let mut results = Vec::new();
+    for path in targets.iter().flat_map(|t| fixture_paths(t)) {
+        match load_suite(&path) {
+            Ok(suite) => results.extend(run_suite(&suite)),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let report = if junit { to_junit(&results) } else { to_tap(&results) };
+    match out {
+        Some(path) => fs::write(&path, report).expect("Couldn't write report"),
+        None => print!("{}", report),
+    }
+
+    if results.iter().any(|r| !r.passed()) {
+        std::process::exit(1);
+    }
+}
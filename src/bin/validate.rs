@@ -0,0 +1,58 @@
+use entropy_engine::validate::{load_for_validation, report_migration, validate_project, ValidateOptions};
+
+use std::env;
+use std::path::PathBuf;
+
+// Usage: validate <project_id|project_dir|midpoint.json> [--scripts DIR] [--json]
+// Exits 0 when clean, 1 with warnings, 2 with errors
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let target = match args.get(1) {
+        Some(target) if !target.starts_with("--") => target.clone(),
+        _ => {
+            println!("Usage: validate <project_id|project_dir|midpoint.json> [--scripts DIR] [--json]");
+            std::process::exit(2);
+        }
+    };
+
+    let mut script_root = env::current_dir().expect("Couldn't get current directory");
+    let mut json = false;
+
+    let mut i = 2;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--scripts" => {
+                i += 1;
+                script_root = args.get(i).map(PathBuf::from).expect("Couldn't parse --scripts");
+            }
+            "--json" => json = true,
+            other => println!("Ignoring unknown argument {}", other),
+        }
+        i += 1;
+    }
+
+    let (saved_state, project_dir, migration) = match load_for_validation(&target) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            if json {
+                println!("{}", serde_json::json!({ "project": target, "fatal": e.to_string() }));
+            } else {
+                println!("error: {}", e);
+            }
+            std::process::exit(2);
+        }
+    };
+
+    let options = ValidateOptions { project_dir, script_root };
+    let mut report = validate_project(&saved_state, &options);
+    report_migration(&migration, &mut report);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).expect("Couldn't serialize report"));
+    } else {
+        report.print();
+    }
+
+    std::process::exit(report.exit_code());
+}
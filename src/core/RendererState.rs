@@ -18,11 +18,12 @@ use crate::helpers::saved_data::GameSettings;
 use crate::heightfield_landscapes::QuadNode::QuadNode;
 use crate::heightfield_landscapes::TerrainManager::TerrainManager;
 use crate::model_components::Collectable::Collectable;
+use crate::rhai_engine::ComponentChanges;
 use crate::shape_primitives::Sphere::Sphere;
 use crate::core::skinned_pipeline::SkinnedPipeline;
 use crate::{
     core::Texture::Texture,
-    helpers::saved_data::{ComponentData, ComponentKind},
+    helpers::saved_data::{ComponentData, ComponentKind, ScriptState},
 };
 use std::collections::HashMap;
 use std::sync::{
@@ -32,7 +33,7 @@ use std::sync::{
 use wgpu::util::DeviceExt;
 use std::str::FromStr;
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
@@ -55,6 +56,9 @@ use crate::{
 };
 
 use super::Grid::GridConfig;
+use super::SceneHierarchy::SceneHierarchy;
+use super::World::{BodyTransform, ComponentCollision, World};
+use crate::game_behaviors::behavior_tree::ScriptLeafRequest;
 use crate::model_components::{PlayerCharacter::PlayerCharacter, NPC::NPC};
 use super::{
     Grid::Grid,
@@ -127,7 +131,12 @@ pub struct RendererState {
     pub particle_systems: Vec<ParticleSystem>,
     pub procedural_trees: Vec<ProceduralTrees>,
     pub water_planes: Vec<WaterPlane>,
+    pub water_plane_ids: Vec<String>, // component id of each water plane, same order
     pub point_lights: Vec<PointLight>,
+    pub point_light_ids: Vec<String>, // component id of each point light, same order
+    pub script_states: HashMap<String, ScriptState>, // for scripted components without a model to keep it on
+    pub collisions: Vec<ComponentCollision>, // from physics steps, waiting for the frame's scripts
+    pub script_leaves: Vec<ScriptLeafRequest>, // behavior tree leaves waiting on the frame's scripts
 
     // animations
     // pub active_animations: Vec<AnimationPlayback>,
@@ -149,24 +158,8 @@ pub struct RendererState {
     pub selected_entity_id: Option<String>,  // The model/house/entity ID (for rendering)
     pub selected_component_id: Option<String>,  // The component ID (for saving)
 
-    // physics
-    pub gravity: Vector<f32>,
-    pub integration_parameters: IntegrationParameters,
-    pub physics_pipeline: PhysicsPipeline,
-    pub island_manager: IslandManager,
-    pub broad_phase: BroadPhaseMultiSap,
-    pub narrow_phase: NarrowPhase,
-    pub impulse_joint_set: ImpulseJointSet,
-    pub multibody_joint_set: MultibodyJointSet,
-    pub ccd_solver: CCDSolver,
-    pub query_pipeline: QueryPipeline,
-    pub rigid_body_set: RigidBodySet,
-    pub collider_set: ColliderSet,
-
-    // model components
-    pub player_character: Option<PlayerCharacter>,
-    pub npcs: Vec<NPC>,
-    pub collectables: Vec<Collectable>,
+    // gameplay simulation, free of wgpu resources
+    pub world: World,
 
     // pub current_modifiers: ModifiersState,
     pub mouse_state: MouseState,
@@ -238,17 +231,6 @@ impl RendererState {
 
         let mut terrain_managers = Vec::new();
 
-        let integration_parameters = IntegrationParameters::default();
-        let physics_pipeline = PhysicsPipeline::new();
-        let island_manager = IslandManager::new();
-        let broad_phase = DefaultBroadPhase::new();
-        let narrow_phase = NarrowPhase::new();
-        let impulse_joint_set = ImpulseJointSet::new();
-        let multibody_joint_set = MultibodyJointSet::new();
-        let ccd_solver = CCDSolver::new();
-        let query_pipeline = QueryPipeline::new();
-        let mut rigid_body_set = RigidBodySet::new();
-        let mut collider_set = ColliderSet::new();
 
         let window_size = camera.viewport.window_size;
         let viewport = Rect {
@@ -301,8 +283,12 @@ impl RendererState {
             terrain_managers,
             // active_animations: Vec::new(),
             point_lights: Vec::new(),
+            point_light_ids: Vec::new(),
+            water_plane_ids: Vec::new(),
+            script_states: HashMap::new(),
+            collisions: Vec::new(),
+            script_leaves: Vec::new(),
             // light_state,
-            collectables: Vec::new(),
 
             // device,
             // queue,
@@ -332,19 +318,7 @@ impl RendererState {
             // scale_gizmo,
             // active_gizmo: "translate".to_string(),
 
-            gravity: vector![0.0, -9.81, 0.0],
-            integration_parameters,
-            physics_pipeline,
-            island_manager,
-            broad_phase,
-            narrow_phase,
-            impulse_joint_set,
-            multibody_joint_set,
-            ccd_solver,
-            query_pipeline,
-            rigid_body_set,
-            collider_set,
-            player_character: None,
+            world: World::new(),
 
             // current_modifiers: ModifiersState::empty(),
             mouse_state: MouseState {
@@ -365,13 +339,13 @@ impl RendererState {
             last_frame_time: None,
             current_mouse_position: None,
             last_mouse_position: None,
-            npcs: Vec::new(),
             // gizmo_drag_axis: None,
             navigation_speed: 5.0,
             game_mode,
             game_settings: GameSettings {
                 third_person: false,
-                show_hitscan_line: true
+                show_hitscan_line: true,
+                show_navmesh: false,
             },
             camera_pitch: 0.0,
             camera_yaw: 0.0,
@@ -391,44 +365,30 @@ impl RendererState {
         &self,
         player_handle: RigidBodyHandle,
     ) -> bool {
-        const GROUND_CHECK_DISTANCE: f32 = 10.0; // Small distance to check below the player
-
-        // Get player position
-        let player_rb = match self.rigid_body_set.get(player_handle) {
-            Some(rb) => rb,
-            None => return false,
-        };
-
-        let player_pos = player_rb.translation();
-
-        // Create a ray from the player's position downward
-        let ray_origin = point![player_pos.x, player_pos.y, player_pos.z];
-        let ray_direction = vector![0.0, -1.0, 0.0];
+        self.world.is_player_grounded(player_handle)
+    }
 
-        // Create the ray
-        let ray = Ray::new(ray_origin, ray_direction);
+    // Moves children after their parents outside of a physics step, e.g. while editing
+    pub fn update_hierarchy(&mut self) {
+        let moved = self.world.hierarchy.update(&mut self.world.rigid_body_set);
+        self.apply_moved_children(&moved);
+    }
 
-        // Set up query pipeline if it's not already part of your system
-        // This is a simplified version; you might need to adapt to your architecture
-        let rigidbody_set = &self.rigid_body_set;
-        let collider_set = &self.collider_set;
-        let query_pipeline = &self.query_pipeline;
+    fn apply_moved_children(&mut self, moved: &[(String, Isometry3<f32>)]) {
+        for (component_id, delta) in moved {
+            if let Some(model) = self.models.iter_mut().find(|m| &m.id == component_id) {
+                for mesh in &mut model.meshes {
+                    mesh.transform.apply_isometry(delta);
+                }
+            }
 
-        // Perform the raycast
-        if let Some((handle, intersection)) = query_pipeline.cast_ray(
-            rigidbody_set,
-            collider_set,
-            &ray,
-            GROUND_CHECK_DISTANCE,
-            true,
-            QueryFilter::default().exclude_rigid_body(player_handle),
-        ) {
-            // Ray hit something, player is grounded
-            return true;
+            if let Some(index) = self.point_light_ids.iter().position(|id| id == component_id) {
+                if let Some(light) = self.point_lights.get_mut(index) {
+                    let position = delta.transform_point(&Point3::from(light.position));
+                    light.position = [position.x, position.y, position.z];
+                }
+            }
         }
-
-        // No hit, player is not grounded
-        false
     }
 
     pub fn step_physics_pipeline(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, camera_binding: &mut CameraBinding, camera: &mut SimpleCamera) {
@@ -443,10 +403,10 @@ impl RendererState {
             0.0
         };
 
-        #[cfg(target_os = "windows")]
+        #[cfg(not(target_arch = "wasm32"))]
         let near_future = self.last_mouse_position_time.checked_add(Duration::from_millis(100));
 
-        #[cfg(target_os = "windows")]
+        #[cfg(not(target_arch = "wasm32"))]
         if let Some(future) = near_future {
             if future < now {
                 self.last_mouse_position = None;
@@ -467,56 +427,19 @@ impl RendererState {
 
         self.update_terrain_managers(device, dt, camera);
 
-        let step_time = Instant::now();
-
-        // Step the physics pipeline
-        let physics_hooks = ();
-        let event_handler = ();
-
-        self.physics_pipeline.step(
-            &self.gravity,
-            &self.integration_parameters,
-            &mut self.island_manager,
-            &mut self.broad_phase,
-            &mut self.narrow_phase,
-            &mut self.rigid_body_set,
-            &mut self.collider_set,
-            &mut self.impulse_joint_set,
-            &mut self.multibody_joint_set,
-            &mut self.ccd_solver,
-            Some(&mut self.query_pipeline),
-            &physics_hooks,
-            &event_handler,
-        );
-
-        let step_duration = step_time.elapsed();
-        // println!("  step_duration: {:?}", step_duration);
-
-        let physics_update_time = Instant::now();
+        // Step the gameplay simulation (physics, player, NPC behaviors), no GPU involved
+        let events = self.world.step(dt);
+        self.collisions.extend(events.collisions.iter().cloned());
+        self.script_leaves.extend(events.script_leaves.iter().cloned());
 
         // Collect all the necessary data first
-        let physics_updates: Vec<(Uuid, nalgebra::Vector3<f32>, (f32, f32, f32))> = self
-            .rigid_body_set
-            .iter()
-            .map(|(_, rigid_body)| {
-                let physics_position = rigid_body.position();
-                let position = physics_position.translation.vector;
-                let rotation = physics_position.rotation;
-                let euler = rotation.euler_angles();
-                let component_id = Uuid::from_u128(rigid_body.user_data);
-                (component_id, position, euler)
-            })
-            .collect();
-
-        let physics_update_duration = physics_update_time.elapsed();
-
-        let physics_update_time = Instant::now();
+        let physics_updates = self.world.body_transforms();
 
         // Update camera position if needed
         if self.game_mode {
-            if let Some(player_character) = &self.player_character {
+            if let Some(player_character) = &self.world.player_character {
                 if let Some(rb_handle) = player_character.movement_rigid_body_handle {
-                    if let Some(rb) = self.rigid_body_set.get(rb_handle) {
+                    if let Some(rb) = self.world.rigid_body_set.get(rb_handle) {
                         if self.game_settings.third_person {
                             // // third-person / 3rd person camera
                             // Retrieve player position
@@ -643,9 +566,9 @@ impl RendererState {
             } 
         }
         else {
-            // if let Some(player_character) = &self.player_character {
+            // if let Some(player_character) = &self.world.player_character {
             //     if let Some(rb_handle) = player_character.movement_rigid_body_handle {
-            //         if let Some(rb) = self.rigid_body_set.get(rb_handle) {
+            //         if let Some(rb) = self.world.rigid_body_set.get(rb_handle) {
             //             let pos = rb.translation();
             //             camera.position = Point3::new(pos.x, pos.y + 0.9, pos.z);
 
@@ -657,25 +580,17 @@ impl RendererState {
         }
 
         // Now process all updates without borrowing rigid_body_set
-        for (component_id, position, euler) in physics_updates {
+        // Mirror the simulation onto the render side
+        for BodyTransform { component_id, position, euler } in physics_updates {
             // Update models
             if let Some(instance_model_data) = self
                 .models
                 .iter_mut()
                 .find(|m| m.id == component_id.to_string())
             {
-                if let Some(character) = &mut self.player_character {
+                if let Some(character) = &self.world.player_character {
                     if let Some(model_id) = character.model_id.clone() { // character.model_id is the component id of the PlayerCharacter
                         if model_id == component_id.to_string() {
-                            // Update is_moving based on velocity
-                            if let Some(rb_handle) = character.movement_rigid_body_handle {
-                                if let Some(rb) = self.rigid_body_set.get(rb_handle) {
-                                    let velocity = rb.linvel();
-                                    let horizontal_speed = (velocity.x * velocity.x + velocity.z * velocity.z).sqrt();
-                                    character.is_moving = horizontal_speed > 0.1;
-                                }
-                            }
-
                             instance_model_data.meshes.iter_mut().for_each(|mesh| {
                                 mesh.transform
                                     .update_position([position.x, position.y, position.z]);
@@ -694,6 +609,7 @@ impl RendererState {
 
                 // Handle NPC updates
                 if let Some(instance_npc_data) = self
+                    .world
                     .npcs
                     .iter_mut()
                     .find(|m| m.model_id == component_id.to_string())
@@ -704,76 +620,14 @@ impl RendererState {
                         // mesh.transform.update_rotation([euler.0, euler.1, euler.2]); // TODO: update rotation based on direction of travel instead
                     });
 
-                    if let Some(player_character) = &mut self.player_character {
-                        if let Some(first_mesh) = instance_model_data.meshes.get_mut(0) {
-                            if !instance_npc_data.is_talking {
-                                let result = instance_npc_data.test_behavior.update(
-                                    &mut self.rigid_body_set,
-                                    &self.collider_set,
-                                    &self.query_pipeline,
-                                    first_mesh
-                                        .rigid_body_handle
-                                        .expect("Couldn't get rigid body handle"),
-                                    player_character
-                                        .movement_rigid_body_handle
-                                        .expect("Couldn't get rigid body handle"),
-                                    &first_mesh.rapier_collider,
-                                    &mut first_mesh.transform,
-                                    instance_npc_data.stats.stamina, // Use NPC's actual stamina
-                                    dt,
-                                );
-
-                                if let Some((damage, debug_line)) = result {
-                                    if damage > 0.0 {
-                                        player_character.handle_incoming_damage(damage);
-                                    }
-
-                                    if self.game_settings.show_hitscan_line {
-                                        if let Some((start, end)) = debug_line {
-                                            let mut debug_cube = Cube::new(
-                                                &device,
-                                                &queue,
-                                                &self.model_bind_group_layout,
-                                                &self.group_bind_group_layout,
-                                                &self.texture_render_mode_buffer,
-                                                camera,
-                                            );
-
-                                            let dir = (end - start).normalize();
-                                            let length = nalgebra::distance(&start, &end);
-                                            
-                                            debug_cube.transform.update_position([start.x, start.y, start.z]);
-                                            debug_cube.transform.update_scale([0.02, 0.02, length]);
-                                            
-                                            let rotation = UnitQuaternion::rotation_between(&Vector3::z(), &dir).unwrap_or_default();
-                                            debug_cube.transform.update_rotation_quat([
-                                                rotation.coords.x,
-                                                rotation.coords.y,
-                                                rotation.coords.z,
-                                                rotation.coords.w,
-                                            ]);
-                                            
-                                            debug_cube.transform.update_uniform_buffer(&queue);
-                                            
-                                            self.debug_rays.push(DebugRay {
-                                                cube: debug_cube,
-                                                expires_at: Instant::now() + Duration::from_millis(500),
-                                            });
-                                        }
-                                    }
-                                }
-                            }
-
-                            let desired_animation_name = instance_npc_data.test_behavior.get_animation_name();
+                    let desired_animation_name = instance_npc_data.test_behavior.get_animation_name();
 
-                            // Find the animation index in the model
-                            if let Some(animation_index) = instance_model_data.animations.iter().position(|anim| anim.name.contains(desired_animation_name)) {
-                                // If the animation is not already playing, switch to it
-                                if instance_npc_data.animation_state.animation_index != animation_index {
-                                    instance_npc_data.animation_state.animation_index = animation_index;
-                                    instance_npc_data.animation_state.current_time = 0.0; // Reset time
-                                }
-                            }
+                    // Find the animation index in the model
+                    if let Some(animation_index) = instance_model_data.animations.iter().position(|anim| anim.name.contains(desired_animation_name)) {
+                        // If the animation is not already playing, switch to it
+                        if instance_npc_data.animation_state.animation_index != animation_index {
+                            instance_npc_data.animation_state.animation_index = animation_index;
+                            instance_npc_data.animation_state.current_time = 0.0; // Reset time
                         }
                     }
                 } else {
@@ -804,10 +658,19 @@ impl RendererState {
             // }
         }
 
+        // children follow their parents, World::step has already moved their bodies
+        self.apply_moved_children(&events.moved_children);
+
+        if self.game_settings.show_hitscan_line {
+            for (start, end) in events.debug_lines {
+                self.add_debug_ray(device, queue, camera, start, end);
+            }
+        }
+
         // Collect matching indices only
         let mut matching_pairs: Vec<(usize, usize)> = Vec::new();
         for (model_idx, model) in self.models.iter().enumerate() {
-            if let Some(npc_idx) = self.npcs.iter().position(|n| n.model_id == model.id) {
+            if let Some(npc_idx) = self.world.npcs.iter().position(|n| n.model_id == model.id) {
                 matching_pairs.push((model_idx, npc_idx));
             }
         }
@@ -815,15 +678,221 @@ impl RendererState {
         // Pass the whole collections and indices to the animation system
         crate::core::animation_system::update_animations(
             &mut self.models,
-            &mut self.npcs,
-            &mut self.collectables,
-            &mut self.player_character,
+            &mut self.world.npcs,
+            &mut self.world.collectables,
+            &mut self.world.player_character,
             &matching_pairs,
             dt,
             queue,
         );
     }
 
+    // Tears down everything placed for the current level, except the player and what they carry
+    pub fn unload_level(&mut self) {
+        self.world.unload_level();
+
+        // the models of whatever World kept
+        let mut keep_model_ids: Vec<String> = self.world.collectables.iter().map(|c| c.model_id.clone()).collect();
+        if let Some(model_id) = self.world.player_character.as_ref().and_then(|p| p.model_id.clone()) {
+            keep_model_ids.push(model_id);
+        }
+
+        self.models.retain(|m| keep_model_ids.contains(&m.id));
+        self.procedural_houses.clear();
+        self.terrain_managers.clear();
+        self.landscapes.clear();
+        self.grasses.clear();
+        self.particle_systems.clear();
+        self.procedural_trees.clear();
+        self.water_planes.clear();
+        self.water_plane_ids.clear();
+        self.point_lights.clear();
+        self.point_light_ids.clear();
+        self.script_states.clear();
+        self.collisions.clear();
+        self.script_leaves.clear();
+        self.debug_rays.clear();
+
+        self.object_selected = None;
+        self.object_selected_kind = None;
+        self.object_selected_data = None;
+        self.selected_entity_id = None;
+        self.selected_component_id = None;
+
+        println!("Level unloaded, kept {} models", self.models.len());
+    }
+
+    pub fn teleport_player(&mut self, position: [f32; 3]) {
+        self.world.teleport_player(position);
+    }
+
+    // Collectable models are keyed by asset id, everything else by component id
+    fn component_model_id(&self, component_id: &str) -> String {
+        self.world
+            .collectables
+            .iter()
+            .find(|c| c.id == component_id)
+            .map(|c| c.model_id.clone())
+            .unwrap_or_else(|| component_id.to_string())
+    }
+
+    pub fn component_body(&self, component_id: &str) -> Option<RigidBodyHandle> {
+        if let Some(npc) = self.world.npcs.iter().find(|n| n.id == component_id) {
+            return Some(npc.rigid_body_handle);
+        }
+        if let Some(collectable) = self.world.collectables.iter().find(|c| c.id == component_id) {
+            return Some(collectable.rigid_body_handle);
+        }
+        self.models
+            .iter()
+            .find(|m| m.id == component_id)
+            .and_then(|m| m.meshes.first())
+            .and_then(|m| m.rigid_body_handle)
+            .or_else(|| self.world.component_body(component_id))
+    }
+
+    // Where a placed component is right now, from its model, its point light or its body
+    pub fn component_transform(&self, component_id: &str) -> Option<(Vector3<f32>, UnitQuaternion<f32>, Vector3<f32>)> {
+        let model_id = self.component_model_id(component_id);
+        if let Some(mesh) = self.models.iter().find(|m| m.id == model_id).and_then(|m| m.meshes.first()) {
+            return Some((mesh.transform.position, mesh.transform.rotation, mesh.transform.scale));
+        }
+
+        if let Some(index) = self.point_light_ids.iter().position(|id| id == component_id) {
+            let light = self.point_lights.get(index)?;
+            return Some((Vector3::from(light.position), UnitQuaternion::identity(), Vector3::new(1.0, 1.0, 1.0)));
+        }
+
+        // trigger volumes and anything else that's only a body
+        let body = self.world.rigid_body_set.get(self.world.component_body(component_id)?)?;
+        Some((*body.translation(), *body.rotation(), Vector3::new(1.0, 1.0, 1.0)))
+    }
+
+    // Applies what a script asked of a component. Colliders keep their shape when scaled.
+    pub fn apply_script_change(&mut self, queue: &wgpu::Queue, change: &ComponentChanges) {
+        let model_id = self.component_model_id(&change.component_id);
+        let body = self.component_body(&change.component_id);
+        let rotation = change
+            .new_rotation
+            .map(|r| UnitQuaternion::from_euler_angles(r.x.to_radians(), r.y.to_radians(), r.z.to_radians()));
+
+        if let Some(model) = self.models.iter_mut().find(|m| m.id == model_id) {
+            for mesh in &mut model.meshes {
+                if let Some(position) = change.new_position {
+                    mesh.transform.update_position([position.x, position.y, position.z]);
+                }
+                if let Some(rotation) = rotation {
+                    mesh.transform.rotation = rotation;
+                }
+                if let Some(scale) = change.new_scale {
+                    mesh.transform.update_scale([scale.x, scale.y, scale.z]);
+                }
+            }
+        }
+
+        if let Some(rb) = body.and_then(|handle| self.world.rigid_body_set.get_mut(handle)) {
+            if let Some(position) = change.new_position {
+                rb.set_translation(position, true);
+            }
+            if let Some(rotation) = rotation {
+                rb.set_rotation(rotation, true);
+            }
+            if let Some(impulse) = change.impulse {
+                rb.apply_impulse(impulse, true);
+            }
+        }
+
+        if let Some(index) = self.point_light_ids.iter().position(|id| *id == change.component_id) {
+            if let Some(light) = self.point_lights.get_mut(index) {
+                if let Some(position) = change.new_position {
+                    light.position = [position.x, position.y, position.z];
+                }
+                if let Some(intensity) = change.light_intensity {
+                    light.intensity = intensity;
+                }
+                if let Some(color) = change.light_color {
+                    light.color = color;
+                }
+            }
+        }
+
+        if let Some(config) = change.water_config {
+            if let Some(index) = self.water_plane_ids.iter().position(|id| *id == change.component_id) {
+                self.water_planes[index].update_config(queue, config);
+            }
+        }
+    }
+
+    // Takes a component out of the running level, the saved level still has it
+    pub fn remove_component(&mut self, component_id: &str) {
+        let model_id = self.component_model_id(component_id);
+        let mut bodies: Vec<RigidBodyHandle> = Vec::new();
+
+        if let Some(index) = self.world.npcs.iter().position(|n| n.id == component_id) {
+            bodies.push(self.world.npcs.remove(index).rigid_body_handle);
+        }
+        if let Some(index) = self.world.collectables.iter().position(|c| c.id == component_id) {
+            bodies.push(self.world.collectables.remove(index).rigid_body_handle);
+        }
+
+        // other collectables of the same asset share its model
+        if !self.world.collectables.iter().any(|c| c.model_id == model_id) {
+            if let Some(index) = self.models.iter().position(|m| m.id == model_id) {
+                let model = self.models.remove(index);
+                bodies.extend(model.meshes.iter().filter_map(|m| m.rigid_body_handle));
+            }
+        }
+
+        if let Some(index) = self.point_light_ids.iter().position(|id| id == component_id) {
+            self.point_light_ids.remove(index);
+            self.point_lights.remove(index);
+        }
+
+        if let Some(index) = self.water_plane_ids.iter().position(|id| id == component_id) {
+            self.water_plane_ids.remove(index);
+            self.water_planes.remove(index);
+        }
+
+        bodies.dedup();
+        for handle in bodies {
+            self.world.remove_body(handle);
+        }
+        self.world.update_query_pipeline();
+        self.script_states.remove(component_id);
+    }
+
+    pub fn add_debug_ray(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, camera: &SimpleCamera, start: Point3<f32>, end: Point3<f32>) {
+        let mut debug_cube = Cube::new(
+            &device,
+            &queue,
+            &self.model_bind_group_layout,
+            &self.group_bind_group_layout,
+            &self.texture_render_mode_buffer,
+            camera,
+        );
+
+        let dir = (end - start).normalize();
+        let length = nalgebra::distance(&start, &end);
+
+        debug_cube.transform.update_position([start.x, start.y, start.z]);
+        debug_cube.transform.update_scale([0.02, 0.02, length]);
+
+        let rotation = UnitQuaternion::rotation_between(&Vector3::z(), &dir).unwrap_or_default();
+        debug_cube.transform.update_rotation_quat([
+            rotation.coords.x,
+            rotation.coords.y,
+            rotation.coords.z,
+            rotation.coords.w,
+        ]);
+
+        debug_cube.transform.update_uniform_buffer(&queue);
+
+        self.debug_rays.push(DebugRay {
+            cube: debug_cube,
+            expires_at: Instant::now() + Duration::from_millis(500),
+        });
+    }
+
     // Usage in your main update/render loop:
     pub fn update_rays(
         &mut self,
@@ -835,18 +904,18 @@ impl RendererState {
         // Create ray from mouse position
         let ray = create_ray_from_mouse(mouse_pos, camera, screen_width, screen_height);
 
-        // println!("collider set {:?}", self.collider_set.len());
+        // println!("collider set {:?}", self.world.collider_set.len());
 
         // Cast ray and check for intersection
         if let Some((collider_handle, toi)) = cast_ray_at_components(
             &ray,
-            &self.query_pipeline,
-            &self.rigid_body_set,
-            &self.collider_set,
+            &self.world.query_pipeline,
+            &self.world.rigid_body_set,
+            &self.world.collider_set,
         ) {
             // println!("Colliding!");
             // Get the collider
-            let collider = &self.collider_set[collider_handle];
+            let collider = &self.world.collider_set[collider_handle];
 
             // Get intersection point in world space
             let intersection_point = ray.point_at(toi);
@@ -872,13 +941,13 @@ impl RendererState {
     // }
 
     pub fn update_rapier(&mut self) {
-        self.query_pipeline.update(&self.collider_set);
+        self.world.query_pipeline.update(&self.world.collider_set);
     }
 
     pub fn add_arrow_colliders(&mut self) {
         // self.translation_gizmo.arrows.iter_mut().for_each(|arrow| {
         //     println!("adding arrow collider");
-        //     let collider_handle = self.collider_set.insert(arrow.rapier_collider.clone());
+        //     let collider_handle = self.world.collider_set.insert(arrow.rapier_collider.clone());
         //     arrow.collider_handle = Some(collider_handle);
         // });
     }
@@ -913,7 +982,7 @@ impl RendererState {
     }
 
     pub fn update_player_character_position(&mut self, translation: Vector3<f32>, delta_time: f32, camera: &mut SimpleCamera) {
-        if let Some(player_character) = &mut self.player_character {
+        if let Some(player_character) = &mut self.world.player_character {
             // let mut camera = get_camera();
             // Collision filter (typically you want to collide with everything except other characters)
             let filter = QueryFilter::default()
@@ -938,9 +1007,9 @@ impl RendererState {
 
             player_character.character_controller.move_shape(
                 delta_time,
-                &self.rigid_body_set,
-                &self.collider_set,
-                &self.query_pipeline,
+                &self.world.rigid_body_set,
+                &self.world.collider_set,
+                &self.world.query_pipeline,
                 player_character.movement_shape.shape(),
                 &character_pos,
                 translation,
@@ -965,7 +1034,7 @@ impl RendererState {
         //arrows: &[AxisArrow; 3],
         position: [f32; 3],
     ) {
-        if let Some(player_character) = &mut self.player_character {
+        if let Some(player_character) = &mut self.world.player_character {
 
             // Create translation vector based on the arrow's axis
             let translation = vector![position[0], position[1], position[2]];
@@ -973,7 +1042,7 @@ impl RendererState {
             let isometry =
                 nalgebra::Isometry3::translation(translation.x, translation.y, translation.z);
 
-            if let Some(collider) = self.collider_set.get_mut(
+            if let Some(collider) = self.world.collider_set.get_mut(
                 player_character
                     .collider_handle
                     .expect("Couldn't get mesh collider handle"),
@@ -996,7 +1065,7 @@ impl RendererState {
                 let isometry =
                     nalgebra::Isometry3::translation(translation.x, translation.y, translation.z);
 
-                if let Some(collider) = self.collider_set.get_mut(
+                if let Some(collider) = self.world.collider_set.get_mut(
                     mesh.collider_handle
                         .expect("Couldn't get mesh collider handle"),
                 ) {
@@ -1007,9 +1076,9 @@ impl RendererState {
     }
 
     pub fn apply_player_movement(&mut self, direction: Vector3<f32>) {
-        if let Some(player_character) = &mut self.player_character {
+        if let Some(player_character) = &mut self.world.player_character {
 
-            if let Some(rigidbody) = self.rigid_body_set.get_mut(
+            if let Some(rigidbody) = self.world.rigid_body_set.get_mut(
                 player_character
                     .movement_rigid_body_handle
                     .expect("Couldn't get mesh rigidbody handle"),
@@ -1033,9 +1102,9 @@ impl RendererState {
     }
 
     pub fn apply_jump_impulse(&mut self) {
-        if let Some(player_character) = &mut self.player_character {
+        if let Some(player_character) = &mut self.world.player_character {
 
-            if let Some(rigidbody) = self.rigid_body_set.get_mut(
+            if let Some(rigidbody) = self.world.rigid_body_set.get_mut(
                 player_character
                     .movement_rigid_body_handle
                     .expect("Couldn't get mesh rigidbody handle"),
@@ -1055,7 +1124,7 @@ impl RendererState {
         //arrows: &[AxisArrow; 3],
         position: [f32; 3],
     ) {
-        if let Some(player_character) = &mut self.player_character {
+        if let Some(player_character) = &mut self.world.player_character {
 
             // Create translation vector based on the arrow's axis
             let translation = vector![position[0], position[1], position[2]];
@@ -1063,7 +1132,7 @@ impl RendererState {
             let isometry =
                 nalgebra::Isometry3::translation(translation.x, translation.y, translation.z);
 
-            if let Some(rigidbody) = self.rigid_body_set.get_mut(
+            if let Some(rigidbody) = self.world.rigid_body_set.get_mut(
                 player_character
                     .movement_rigid_body_handle
                     .expect("Couldn't get mesh rigidbody handle"),
@@ -1086,7 +1155,7 @@ impl RendererState {
                 let isometry =
                     nalgebra::Isometry3::translation(translation.x, translation.y, translation.z);
 
-                if let Some(rigidbody) = self.rigid_body_set.get_mut(
+                if let Some(rigidbody) = self.world.rigid_body_set.get_mut(
                     mesh.rigid_body_handle
                         .expect("Couldn't get mesh collider handle"),
                 ) {
@@ -1108,7 +1177,7 @@ impl RendererState {
             let isometry =
                 nalgebra::Isometry3::translation(translation.x, translation.y, translation.z);
 
-            // if let Some(collider) = self.collider_set.get_mut(
+            // if let Some(collider) = self.world.collider_set.get_mut(
             //     landscape
             //         .collider_handle
             //         .expect("Couldn't get landscape collider handle"),
@@ -1121,104 +1190,40 @@ impl RendererState {
         });
     }
 
-    pub fn add_collider(&mut self, component_id: String, component_kind: ComponentKind) {
-        match component_kind {
-            ComponentKind::Landscape => {
-                println!("Adding landscape collider");
+    // Points every mesh of a model at a body World placed for it (NPCs, collectables)
+    pub fn attach_body(&mut self, model_id: &str, rigid_body_handle: RigidBodyHandle) {
+        let collider_handle = self
+            .world
+            .rigid_body_set
+            .get(rigid_body_handle)
+            .and_then(|body| body.colliders().first().copied());
 
-                // should be added as part of terrain manager
-                let landscape = self
-                    .landscapes
-                    .iter_mut()
-                    .find(|l| l.id == component_id.clone())
-                    .expect("Couldn't get Renderer Landscape");
-
-                let rigid_body_handle = self
-                    .rigid_body_set
-                    .insert(landscape.rapier_rigidbody.clone());
-                landscape.rigid_body_handle = Some(rigid_body_handle);
-
-                // now associate rigidbody with collider
-                let collider_handle = self.collider_set.insert_with_parent(
-                    landscape.rapier_heightfield.clone(),
-                    rigid_body_handle,
-                    &mut self.rigid_body_set,
-                );
-                landscape.collider_handle = Some(collider_handle);
+        if let Some(model) = self.models.iter_mut().find(|m| m.id == model_id) {
+            for mesh in &mut model.meshes {
+                mesh.rigid_body_handle = Some(rigid_body_handle);
+                mesh.collider_handle = collider_handle;
             }
-            ComponentKind::Model => {
-                let renderer_model = self
-                    .models
-                    .iter_mut()
-                    .find(|l| l.id == component_id.clone())
-                    .expect("Couldn't get Renderer Model");
-
-                renderer_model.meshes.iter_mut().for_each(|mesh| {
-                    let rigid_body_handle =
-                        self.rigid_body_set.insert(mesh.rapier_rigidbody.clone());
-                    mesh.rigid_body_handle = Some(rigid_body_handle);
+        }
+    }
 
-                    // now associate rigidbody with collider
-                    let collider_handle = self.collider_set.insert_with_parent(
-                        mesh.rapier_collider.clone(),
-                        rigid_body_handle,
-                        &mut self.rigid_body_set,
-                    );
-                    mesh.collider_handle = Some(collider_handle);
-                });
-            },
-            ComponentKind::Collectable => {
-                 let renderer_model = self
-                    .models
-                    .iter_mut()
-                    .find(|l| l.id == component_id.clone())
-                    .expect("Couldn't get Renderer Model");
+    pub fn attach_landscape_body(&mut self, landscape_id: &str, data: &LandscapePixelData, position: [f32; 3]) {
+        let (rigid_body_handle, collider_handle) = match self.world.place_landscape(landscape_id, data, position) {
+            Ok(handles) => handles,
+            Err(e) => {
+                println!("Skipping landscape collider {}: {}", landscape_id, e);
+                return;
+            }
+        };
 
-                renderer_model.meshes.iter_mut().for_each(|mesh| {
-                    let existing_iso = mesh.rapier_rigidbody.position().clone();
-
-                    let rapier_collider = ColliderBuilder::ball(0.5)
-                        // .expect("Couldn't create trimesh")
-                        .sensor(true)
-                        .friction(0.7)
-                        .restitution(0.0)
-                        .density(1.0)
-                        .user_data(
-                            Uuid::from_str(&component_id.clone())
-                                .expect("Couldn't extract uuid")
-                                .as_u128(),
-                        )
-                        .build();
-
-                    let dynamic_body = RigidBodyBuilder::fixed()
-                        .additional_mass(70.0) // Explicitly set mass (e.g., 70kg for a person)
-                        .linear_damping(0.1)
-                        .position(existing_iso)
-                        .locked_axes(LockedAxes::ROTATION_LOCKED_X | LockedAxes::ROTATION_LOCKED_Z)
-                        .user_data(
-                            Uuid::from_str(&component_id.clone())
-                                .expect("Couldn't extract uuid")
-                                .as_u128(),
-                        )
-                        .build();
-
-                    mesh.rapier_collider = rapier_collider;
-                    mesh.rapier_rigidbody = dynamic_body;
-
-                    let rigid_body_handle =
-                        self.rigid_body_set.insert(mesh.rapier_rigidbody.clone());
-                    mesh.rigid_body_handle = Some(rigid_body_handle);
+        if let Some(landscape) = self.landscapes.iter_mut().find(|l| l.id == landscape_id) {
+            landscape.rigid_body_handle = Some(rigid_body_handle);
+            landscape.collider_handle = Some(collider_handle);
+        }
+    }
 
-                    // now associate rigidbody with collider
-                    let collider_handle = self.collider_set.insert_with_parent(
-                        mesh.rapier_collider.clone(),
-                        rigid_body_handle,
-                        &mut self.rigid_body_set,
-                    );
-                    mesh.collider_handle = Some(collider_handle);
-                });
-            },
-            ComponentKind::NPC => {
+    pub fn add_collider(&mut self, component_id: String, component_kind: ComponentKind) {
+        match component_kind {
+            ComponentKind::Model => {
                 let renderer_model = self
                     .models
                     .iter_mut()
@@ -1226,45 +1231,10 @@ impl RendererState {
                     .expect("Couldn't get Renderer Model");
 
                 renderer_model.meshes.iter_mut().for_each(|mesh| {
-                    let existing_iso = mesh.rapier_rigidbody.position().clone();
-
-                    let rapier_collider = ColliderBuilder::capsule_y(1.0, 0.5)
-                        // .expect("Couldn't create trimesh")
-                        .friction(0.7)
-                        .restitution(0.0)
-                        .density(1.0)
-                        .user_data(
-                            Uuid::from_str(&component_id.clone())
-                                .expect("Couldn't extract uuid")
-                                .as_u128(),
-                        )
-                        .build();
-
-                    let dynamic_body = RigidBodyBuilder::dynamic()
-                        .additional_mass(70.0) // Explicitly set mass (e.g., 70kg for a person)
-                        .linear_damping(0.1)
-                        .position(existing_iso)
-                        .locked_axes(LockedAxes::ROTATION_LOCKED_X | LockedAxes::ROTATION_LOCKED_Z)
-                        .user_data(
-                            Uuid::from_str(&component_id.clone())
-                                .expect("Couldn't extract uuid")
-                                .as_u128(),
-                        )
-                        .build();
-
-                    mesh.rapier_collider = rapier_collider;
-                    mesh.rapier_rigidbody = dynamic_body;
-
-                    let rigid_body_handle =
-                        self.rigid_body_set.insert(mesh.rapier_rigidbody.clone());
+                    let (rigid_body_handle, collider_handle) = self
+                        .world
+                        .insert_body(mesh.rapier_rigidbody.clone(), mesh.rapier_collider.clone());
                     mesh.rigid_body_handle = Some(rigid_body_handle);
-
-                    // now associate rigidbody with collider
-                    let collider_handle = self.collider_set.insert_with_parent(
-                        mesh.rapier_collider.clone(),
-                        rigid_body_handle,
-                        &mut self.rigid_body_set,
-                    );
                     mesh.collider_handle = Some(collider_handle);
                 });
             },
@@ -1287,7 +1257,7 @@ impl RendererState {
         scale: Vector3<f32>,
         camera: &SimpleCamera,
         hide_in_world: bool,
-        script_state: Option<HashMap<String, String>>,
+        script_state: Option<ScriptState>,
     ) {
         let mut model = Model::from_glb(
             model_component_id,
@@ -1357,13 +1327,13 @@ impl RendererState {
         );
 
         for mesh in &mut house.meshes {
-            let rigid_body_handle = self.rigid_body_set.insert(mesh.rigid_body.clone());
+            let rigid_body_handle = self.world.rigid_body_set.insert(mesh.rigid_body.clone());
             mesh.rigid_body_handle = Some(rigid_body_handle);
 
-            let collider_handle = self.collider_set.insert_with_parent(
+            let collider_handle = self.world.collider_set.insert_with_parent(
                 mesh.collider.clone(),
                 rigid_body_handle,
-                &mut self.rigid_body_set,
+                &mut self.world.rigid_body_set,
             );
             mesh.collider_handle = Some(collider_handle);
         }
@@ -1408,8 +1378,8 @@ impl RendererState {
                 .expect("Couldn't get first terrain manager");
 
             // keep for debugging:
-            // if let Some(rb_handle) = self.player_character.movement_rigid_body_handle {
-            //     if let Some(rb) = self.rigid_body_set.get(rb_handle) {
+            // if let Some(rb_handle) = self.world.player_character.movement_rigid_body_handle {
+            //     if let Some(rb) = self.world.rigid_body_set.get(rb_handle) {
             //         let character_pos = rb.position();
 
             //         // let camera = get_camera();
@@ -1429,9 +1399,9 @@ impl RendererState {
 
             //         if let Some(handle) = collider_handle {
             //             // Use QueryPipeline for ray casting
-            //             let hit = self.query_pipeline.cast_ray(
-            //                 &self.rigid_body_set,
-            //                 &self.collider_set,
+            //             let hit = self.world.query_pipeline.cast_ray(
+            //                 &self.world.rigid_body_set,
+            //                 &self.world.collider_set,
             //                 &Ray::new(ray_start, ray_dir),
             //                 f32::MAX,
             //                 true,
@@ -1454,14 +1424,14 @@ impl RendererState {
             terrain_manager.update(
                 [camera.position.x, camera.position.y, camera.position.z],
                 device,
-                &mut self.rigid_body_set,
-                &mut self.collider_set,
-                &mut self.island_manager,
-                &mut self.impulse_joint_set,
-                &mut self.multibody_joint_set, // terrain_manager.terrain_position,
+                &mut self.world.rigid_body_set,
+                &mut self.world.collider_set,
+                &mut self.world.island_manager,
+                &mut self.world.impulse_joint_set,
+                &mut self.world.multibody_joint_set, // terrain_manager.terrain_position,
                 // terrain_manager.id.clone(),
                 dt,
-                // &mut self.query_pipeline,
+                // &mut self.world.query_pipeline,
                 camera,
                 self.game_mode
             );
@@ -0,0 +1,311 @@
+use std::collections::{HashMap, HashSet};
+
+use nalgebra::{Isometry3, Translation3, UnitQuaternion, Vector3};
+use rapier3d::prelude::{RigidBodyHandle, RigidBodySet};
+use uuid::Uuid;
+
+use crate::helpers::saved_data::{ComponentData, GenericProperties};
+
+// World-space placement of a component. Scale is baked into vertices when a model loads,
+// so only the isometry changes at runtime.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    pub isometry: Isometry3<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Placement {
+    // Saved rotations are euler angles in degrees
+    pub fn from_generic(generic: &GenericProperties) -> Self {
+        let rotation = UnitQuaternion::from_euler_angles(
+            generic.rotation[0].to_radians(),
+            generic.rotation[1].to_radians(),
+            generic.rotation[2].to_radians(),
+        );
+
+        Placement {
+            isometry: Isometry3::from_parts(Translation3::from(Vector3::from(generic.position)), rotation),
+            scale: Vector3::from(generic.scale),
+        }
+    }
+
+    pub fn position(&self) -> [f32; 3] {
+        let t = self.isometry.translation.vector;
+        [t.x, t.y, t.z]
+    }
+
+    // Where a child with these parent-relative properties ends up
+    pub fn child(&self, local: &GenericProperties) -> Placement {
+        let local = Placement::from_generic(local);
+        Placement {
+            isometry: self.isometry * scaled_local(&local.isometry, &self.scale),
+            scale: self.scale.component_mul(&local.scale),
+        }
+    }
+
+    // The parent-relative position and rotation that put a child at `world`
+    pub fn local_generic(&self, world: &Placement, generic: &GenericProperties) -> GenericProperties {
+        let local = self.isometry.inverse() * world.isometry;
+        GenericProperties {
+            position: unscaled_position(&local, &self.scale),
+            rotation: degrees(&local.rotation),
+            scale: world.scale.component_div(&self.scale).into(),
+            ..generic.clone()
+        }
+    }
+}
+
+// Local offsets are in the parent's scaled space
+fn scaled_local(local: &Isometry3<f32>, parent_scale: &Vector3<f32>) -> Isometry3<f32> {
+    Isometry3::from_parts(
+        Translation3::from(parent_scale.component_mul(&local.translation.vector)),
+        local.rotation,
+    )
+}
+
+fn unscaled_position(local: &Isometry3<f32>, parent_scale: &Vector3<f32>) -> [f32; 3] {
+    local.translation.vector.component_div(parent_scale).into()
+}
+
+fn degrees(rotation: &UnitQuaternion<f32>) -> [f32; 3] {
+    let (x, y, z) = rotation.euler_angles();
+    [x.to_degrees(), y.to_degrees(), z.to_degrees()]
+}
+
+// How many parents sit above a component, or None if the chain loops or hits a missing component
+pub fn parent_depth(components: &[ComponentData], component: &ComponentData) -> Option<usize> {
+    let mut seen = HashSet::new();
+    let mut current = component;
+    let mut depth = 0;
+
+    seen.insert(current.id.as_str());
+
+    while let Some(parent_id) = &current.parent_id {
+        current = components.iter().find(|c| &c.id == parent_id)?;
+        if !seen.insert(current.id.as_str()) {
+            return None;
+        }
+        depth += 1;
+    }
+
+    Some(depth)
+}
+
+// Whether `ancestor_id` appears anywhere up `component_id`'s parent chain
+pub fn is_descendant(components: &[ComponentData], component_id: &str, ancestor_id: &str) -> bool {
+    let mut current = components.iter().find(|c| c.id == component_id);
+
+    for _ in 0..components.len() {
+        let parent_id = match current.and_then(|c| c.parent_id.as_ref()) {
+            Some(parent_id) => parent_id,
+            None => return false,
+        };
+        if parent_id == ancestor_id {
+            return true;
+        }
+        current = components.iter().find(|c| &c.id == parent_id);
+    }
+
+    false
+}
+
+// World placement of every component in a level. Components whose parent is missing
+// or loops back on itself are placed as if they had no parent.
+pub fn component_placements(components: &[ComponentData]) -> HashMap<String, Placement> {
+    let mut ordered: Vec<(Option<usize>, &ComponentData)> = components
+        .iter()
+        .map(|c| (parent_depth(components, c), c))
+        .collect();
+    ordered.sort_by_key(|(depth, _)| depth.unwrap_or(0));
+
+    let mut placements: HashMap<String, Placement> = HashMap::new();
+
+    for (depth, component) in ordered {
+        let parent = match depth {
+            Some(depth) if depth > 0 => component.parent_id.as_ref().and_then(|id| placements.get(id)).copied(),
+            _ => None,
+        };
+
+        let placement = match parent {
+            Some(parent) => parent.child(&component.generic_properties),
+            None => Placement::from_generic(&component.generic_properties),
+        };
+
+        placements.insert(component.id.clone(), placement);
+    }
+
+    placements
+}
+
+pub struct HierarchyNode {
+    pub component_id: String,
+    pub parent_id: String,
+    pub local: Isometry3<f32>, // relative to the parent, with the parent's scale already applied
+    pub parent_scale: Vector3<f32>,
+    pub parent_world: Isometry3<f32>, // as of the last update
+    pub applied: Isometry3<f32>, // where the child's meshes and bodies currently sit
+    bodies: Vec<RigidBodyHandle>,
+}
+
+// Where a parent was at load, and the body that moves it (if any) so its motion can be followed
+struct ParentAnchor {
+    rest: Isometry3<f32>,
+    body: Option<(RigidBodyHandle, Isometry3<f32>)>,
+}
+
+// Keeps parented components attached to their parents while the game runs.
+// Rebuilt whenever a level is placed.
+#[derive(Default)]
+pub struct SceneHierarchy {
+    nodes: Vec<HierarchyNode>, // parents before children
+    anchors: HashMap<String, ParentAnchor>,
+}
+
+impl SceneHierarchy {
+    // Call after the level's bodies are in the world, so children and parents can find theirs
+    pub fn new(components: &[ComponentData], rigid_body_set: &RigidBodySet) -> Self {
+        let placements = component_placements(components);
+
+        let mut bodies: HashMap<u128, Vec<RigidBodyHandle>> = HashMap::new();
+        for (handle, body) in rigid_body_set.iter() {
+            bodies.entry(body.user_data).or_default().push(handle);
+        }
+        let bodies_for = |component_id: &str| -> Vec<RigidBodyHandle> {
+            Uuid::parse_str(component_id)
+                .ok()
+                .and_then(|id| bodies.get(&id.as_u128()))
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        let mut children: Vec<(usize, &ComponentData)> = components
+            .iter()
+            .filter_map(|c| match parent_depth(components, c) {
+                Some(depth) if depth > 0 => Some((depth, c)),
+                _ => None,
+            })
+            .collect();
+        children.sort_by_key(|(depth, _)| *depth);
+
+        let mut hierarchy = SceneHierarchy::default();
+
+        for (_, component) in children {
+            let parent_id = component.parent_id.clone().expect("Couldn't get parent id");
+            let (parent, world) = match (placements.get(&parent_id), placements.get(&component.id)) {
+                (Some(parent), Some(world)) => (*parent, *world),
+                _ => continue,
+            };
+
+            hierarchy.anchors.entry(parent_id.clone()).or_insert_with(|| ParentAnchor {
+                rest: parent.isometry,
+                body: bodies_for(&parent_id)
+                    .first()
+                    .and_then(|handle| rigid_body_set.get(*handle).map(|rb| (*handle, *rb.position()))),
+            });
+
+            hierarchy.nodes.push(HierarchyNode {
+                component_id: component.id.clone(),
+                parent_id,
+                local: parent.isometry.inverse() * world.isometry,
+                parent_scale: parent.scale,
+                parent_world: parent.isometry,
+                applied: world.isometry,
+                bodies: bodies_for(&component.id),
+            });
+        }
+
+        hierarchy
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, component_id: &str) -> Option<&HierarchyNode> {
+        self.nodes.iter().find(|n| n.component_id == component_id)
+    }
+
+    // Moves children after their parents, carrying any fixed or kinematic bodies along.
+    // Returns each moved child and the change to apply to its render-side transforms.
+    pub fn update(&mut self, rigid_body_set: &mut RigidBodySet) -> Vec<(String, Isometry3<f32>)> {
+        let mut current: HashMap<String, Isometry3<f32>> = self
+            .anchors
+            .iter()
+            .map(|(id, anchor)| {
+                // bodies can sit offset from the component (mesh nodes, capsules), so follow how far the body moved
+                let world = anchor
+                    .body
+                    .and_then(|(handle, rest)| {
+                        rigid_body_set
+                            .get(handle)
+                            .map(|rb| rb.position() * rest.inverse() * anchor.rest)
+                    })
+                    .unwrap_or(anchor.rest);
+                (id.clone(), world)
+            })
+            .collect();
+
+        let mut moved = Vec::new();
+
+        for node in &mut self.nodes {
+            let parent_world = match current.get(&node.parent_id) {
+                Some(parent_world) => *parent_world,
+                None => continue,
+            };
+            let world = parent_world * node.local;
+            node.parent_world = parent_world;
+
+            let distance = (world.translation.vector - node.applied.translation.vector).norm();
+            let angle = world.rotation.angle_to(&node.applied.rotation);
+
+            if distance > 1e-5 || angle > 1e-5 {
+                let delta = world * node.applied.inverse();
+
+                for handle in &node.bodies {
+                    if let Some(rb) = rigid_body_set.get_mut(*handle) {
+                        // dynamic children answer to physics, not their parent
+                        if !rb.is_dynamic() {
+                            let position = delta * rb.position();
+                            rb.set_position(position, true);
+                        }
+                    }
+                }
+
+                node.applied = world;
+                moved.push((node.component_id.clone(), delta));
+            }
+
+            current.insert(node.component_id.clone(), world);
+        }
+
+        moved
+    }
+
+    // Re-attaches a child that was moved directly (gizmo, properties panel) so it stays
+    // where it was put. Returns the parent-relative properties to save.
+    pub fn set_child_world(
+        &mut self,
+        component_id: &str,
+        world: Isometry3<f32>,
+        generic: &GenericProperties,
+    ) -> Option<GenericProperties> {
+        let node = self.nodes.iter_mut().find(|n| n.component_id == component_id)?;
+
+        node.local = node.parent_world.inverse() * world;
+        node.applied = world;
+
+        Some(GenericProperties {
+            position: unscaled_position(&node.local, &node.parent_scale),
+            rotation: degrees(&node.local.rotation),
+            ..generic.clone()
+        })
+    }
+
+    // Changes a child's parent-relative placement, it moves there on the next update
+    pub fn set_child_local(&mut self, component_id: &str, generic: &GenericProperties) {
+        if let Some(node) = self.nodes.iter_mut().find(|n| n.component_id == component_id) {
+            let local = Placement::from_generic(generic);
+            node.local = scaled_local(&local.isometry, &node.parent_scale);
+        }
+    }
+}
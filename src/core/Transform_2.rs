@@ -1,4 +1,4 @@
-use nalgebra::{Matrix4, Point3, Quaternion, UnitQuaternion, Vector3};
+use nalgebra::{Isometry3, Matrix4, Point3, Quaternion, UnitQuaternion, Vector3};
 use wgpu::util::DeviceExt;
 
 use crate::core::vertex::Vertex;
@@ -86,6 +86,12 @@ impl Transform {
         );
     }
 
+    // Moves the transform along with a parent that moved by `delta` (in world space)
+    pub fn apply_isometry(&mut self, delta: &Isometry3<f32>) {
+        self.position = delta.transform_point(&Point3::from(self.position)).coords;
+        self.rotation = delta.rotation * self.rotation;
+    }
+
     pub fn translate(&mut self, translation: Vector3<f32>) {
         self.position += translation;
     }
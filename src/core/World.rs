@@ -0,0 +1,583 @@
+use std::sync::Mutex;
+
+use nalgebra::{Isometry3, Point3, Vector3};
+use rapier3d::prelude::*;
+use uuid::Uuid;
+
+use crate::core::SceneHierarchy::{component_placements, SceneHierarchy};
+use crate::game_behaviors::behavior_tree::ScriptLeafRequest;
+use crate::game_behaviors::navmesh::NavMesh;
+use crate::helpers::landscapes::LandscapePixelData;
+use crate::helpers::saved_data::{CollectableType, ComponentData, ComponentKind, PortalProperties, StatData, TriggerProperties};
+use crate::heightfield_landscapes::Landscape::Landscape;
+use crate::model_components::{Collectable::Collectable, PlayerCharacter::PlayerCharacter, TriggerVolume, NPC::{NPCArchetype, NPC}};
+
+// Gameplay simulation state: physics, the player, NPCs and collectables.
+// Holds no wgpu resources so it can be stepped headless or on a server.
+// RendererState keeps a render-side mirror (models, meshes, debug cubes) and syncs transforms from it each frame.
+pub struct World {
+    // physics
+    pub gravity: Vector<f32>,
+    pub integration_parameters: IntegrationParameters,
+    pub physics_pipeline: PhysicsPipeline,
+    pub island_manager: IslandManager,
+    pub broad_phase: BroadPhaseMultiSap,
+    pub narrow_phase: NarrowPhase,
+    pub impulse_joint_set: ImpulseJointSet,
+    pub multibody_joint_set: MultibodyJointSet,
+    pub ccd_solver: CCDSolver,
+    pub query_pipeline: QueryPipeline,
+    pub rigid_body_set: RigidBodySet,
+    pub collider_set: ColliderSet,
+
+    // model components
+    pub player_character: Option<PlayerCharacter>,
+    pub npcs: Vec<NPC>,
+    pub collectables: Vec<Collectable>,
+
+    // parent links between the level's components
+    pub hierarchy: SceneHierarchy,
+
+    // where NPCs can walk, kept in step with the fixed colliders
+    pub navmesh: NavMesh,
+
+    // player has to step out of a portal before another one can fire
+    pub portal_armed: bool,
+
+    pub elapsed: f32,
+    pub dt: f32, // length of the last step
+}
+
+// Physics transform of a body, keyed by the component id stored in its user_data
+pub struct BodyTransform {
+    pub component_id: Uuid,
+    pub position: Vector3<f32>,
+    pub euler: (f32, f32, f32),
+}
+
+// What happened during a step that the render side (or a report) may care about
+#[derive(Default)]
+pub struct WorldStepEvents {
+    pub damage_to_player: f32,
+    pub debug_lines: Vec<(Point3<f32>, Point3<f32>)>,
+    // children carried by their parents this step, with how far they moved
+    pub moved_children: Vec<(String, Isometry3<f32>)>,
+    pub collisions: Vec<ComponentCollision>,
+    // behavior tree leaves waiting on a Rhai function, see BehaviorTree::resolve
+    pub script_leaves: Vec<ScriptLeafRequest>,
+}
+
+// Two components that started or stopped touching, by the ids stored in collider user_data
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComponentCollision {
+    pub a: String,
+    pub b: String,
+    pub started: bool, // false when they stopped touching
+    pub sensor: bool, // one of them is a trigger volume, collectable or other sensor
+}
+
+impl ComponentCollision {
+    // The Rhai hook either side gets, see RhaiEngine::execute_collision_hook
+    pub fn hook_name(&self) -> &'static str {
+        match (self.sensor, self.started) {
+            (true, true) => "on_trigger_enter",
+            (true, false) => "on_trigger_exit",
+            (false, true) => "on_collision_enter",
+            (false, false) => "on_collision_exit",
+        }
+    }
+}
+
+// Rapier wants a Send + Sync handler, so events wait behind a mutex until the step is done
+#[derive(Default)]
+struct CollisionCollector {
+    events: Mutex<Vec<CollisionEvent>>,
+}
+
+impl EventHandler for CollisionCollector {
+    fn handle_collision_event(
+        &self,
+        _bodies: &RigidBodySet,
+        _colliders: &ColliderSet,
+        event: CollisionEvent,
+        _contact_pair: Option<&ContactPair>,
+    ) {
+        self.events.lock().expect("Couldn't lock collision events").push(event);
+    }
+
+    fn handle_contact_force_event(
+        &self,
+        _dt: Real,
+        _bodies: &RigidBodySet,
+        _colliders: &ColliderSet,
+        _contact_pair: &ContactPair,
+        _total_force_magnitude: Real,
+    ) {
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        World {
+            gravity: vector![0.0, -9.81, 0.0],
+            integration_parameters: IntegrationParameters::default(),
+            physics_pipeline: PhysicsPipeline::new(),
+            island_manager: IslandManager::new(),
+            broad_phase: DefaultBroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            impulse_joint_set: ImpulseJointSet::new(),
+            multibody_joint_set: MultibodyJointSet::new(),
+            ccd_solver: CCDSolver::new(),
+            query_pipeline: QueryPipeline::new(),
+            rigid_body_set: RigidBodySet::new(),
+            collider_set: ColliderSet::new(),
+
+            player_character: None,
+            npcs: Vec::new(),
+            collectables: Vec::new(),
+
+            hierarchy: SceneHierarchy::default(),
+
+            navmesh: NavMesh::default(),
+
+            portal_armed: false,
+
+            elapsed: 0.0,
+            dt: 0.0,
+        }
+    }
+
+    pub fn insert_body(&mut self, rigid_body: RigidBody, mut collider: Collider) -> (RigidBodyHandle, ColliderHandle) {
+        // components report their contacts, see step_physics
+        collider.set_active_events(collider.active_events() | ActiveEvents::COLLISION_EVENTS);
+
+        let rigid_body_handle = self.rigid_body_set.insert(rigid_body);
+        let collider_handle = self
+            .collider_set
+            .insert_with_parent(collider, rigid_body_handle, &mut self.rigid_body_set);
+
+        (rigid_body_handle, collider_handle)
+    }
+
+    // Removes a body along with its colliders and joints
+    pub fn remove_body(&mut self, handle: RigidBodyHandle) {
+        self.rigid_body_set.remove(
+            handle,
+            &mut self.island_manager,
+            &mut self.collider_set,
+            &mut self.impulse_joint_set,
+            &mut self.multibody_joint_set,
+            true,
+        );
+    }
+
+    pub fn update_query_pipeline(&mut self) {
+        self.query_pipeline.update(&self.collider_set);
+    }
+
+    // The body whose user_data is the component's id
+    pub fn component_body(&self, component_id: &str) -> Option<RigidBodyHandle> {
+        let user_data = Uuid::parse_str(component_id).ok()?.as_u128();
+
+        self.rigid_body_set
+            .iter()
+            .find(|(_, rb)| rb.user_data == user_data)
+            .map(|(handle, _)| handle)
+    }
+
+    pub fn player_handle(&self) -> Option<RigidBodyHandle> {
+        self.player_character
+            .as_ref()
+            .and_then(|p| p.movement_rigid_body_handle)
+    }
+
+    pub fn body_position(&self, handle: RigidBodyHandle) -> Option<Vector3<f32>> {
+        self.rigid_body_set.get(handle).map(|rb| *rb.translation())
+    }
+
+    pub fn is_player_grounded(&self, player_handle: RigidBodyHandle) -> bool {
+        const GROUND_CHECK_DISTANCE: f32 = 10.0; // Small distance to check below the player
+
+        let player_pos = match self.rigid_body_set.get(player_handle) {
+            Some(rb) => rb.translation(),
+            None => return false,
+        };
+
+        // Create a ray from the player's position downward
+        let ray = Ray::new(
+            point![player_pos.x, player_pos.y, player_pos.z],
+            vector![0.0, -1.0, 0.0],
+        );
+
+        self.query_pipeline
+            .cast_ray(
+                &self.rigid_body_set,
+                &self.collider_set,
+                &ray,
+                GROUND_CHECK_DISTANCE,
+                true,
+                QueryFilter::default().exclude_rigid_body(player_handle),
+            )
+            .is_some()
+    }
+
+    // Steps physics, carries children along with their parents, then the player and NPC behaviors
+    pub fn step(&mut self, dt: f32) -> WorldStepEvents {
+        let collisions = self.step_physics();
+        let moved_children = self.hierarchy.update(&mut self.rigid_body_set);
+        self.navmesh.sync(&self.rigid_body_set, &self.collider_set);
+        self.update_player();
+
+        let mut events = self.update_npcs(dt);
+        events.moved_children = moved_children;
+        events.collisions = collisions;
+
+        self.elapsed += dt;
+        self.dt = dt;
+
+        events
+    }
+
+    // Returns the contacts and sensor overlaps between components that began or ended this step
+    pub fn step_physics(&mut self) -> Vec<ComponentCollision> {
+        let physics_hooks = ();
+        let event_handler = CollisionCollector::default();
+
+        self.physics_pipeline.step(
+            &self.gravity,
+            &self.integration_parameters,
+            &mut self.island_manager,
+            &mut self.broad_phase,
+            &mut self.narrow_phase,
+            &mut self.rigid_body_set,
+            &mut self.collider_set,
+            &mut self.impulse_joint_set,
+            &mut self.multibody_joint_set,
+            &mut self.ccd_solver,
+            Some(&mut self.query_pipeline),
+            &physics_hooks,
+            &event_handler,
+        );
+
+        let events = event_handler.events.into_inner().unwrap_or_default();
+        let mut collisions: Vec<ComponentCollision> = Vec::new();
+
+        for event in events {
+            // models with several meshes touch with each of them, so pairs are reported once
+            if let Some(collision) = self.component_collision(event) {
+                if !collisions.contains(&collision) {
+                    collisions.push(collision);
+                }
+            }
+        }
+
+        collisions
+    }
+
+    // Colliders without a component, removed colliders and a model touching itself are left out
+    fn component_collision(&self, event: CollisionEvent) -> Option<ComponentCollision> {
+        let component_id = |handle: ColliderHandle| {
+            self.collider_set
+                .get(handle)
+                .filter(|c| c.user_data != 0)
+                .map(|c| Uuid::from_u128(c.user_data).to_string())
+        };
+
+        let a = component_id(event.collider1())?;
+        let b = component_id(event.collider2())?;
+        if a == b {
+            return None;
+        }
+
+        Some(ComponentCollision {
+            a,
+            b,
+            started: event.started(),
+            sensor: event.sensor(),
+        })
+    }
+
+    fn update_player(&mut self) {
+        if let Some(character) = &mut self.player_character {
+            // Update is_moving based on velocity
+            if let Some(rb) = character
+                .movement_rigid_body_handle
+                .and_then(|h| self.rigid_body_set.get(h))
+            {
+                let velocity = rb.linvel();
+                let horizontal_speed = (velocity.x * velocity.x + velocity.z * velocity.z).sqrt();
+                character.is_moving = horizontal_speed > 0.1;
+            }
+        }
+    }
+
+    fn update_npcs(&mut self, dt: f32) -> WorldStepEvents {
+        let mut events = WorldStepEvents::default();
+
+        // behaviors need a target
+        let player_character = match &mut self.player_character {
+            Some(player_character) => player_character,
+            None => return events,
+        };
+        let player_handle = match player_character.movement_rigid_body_handle {
+            Some(handle) => handle,
+            None => return events,
+        };
+
+        for npc in self.npcs.iter_mut() {
+            if npc.is_talking || !npc.is_alive() {
+                continue;
+            }
+
+            let (current_pos, collider_handle) = match self.rigid_body_set.get(npc.rigid_body_handle) {
+                Some(rb) => (*rb.translation(), rb.colliders().first().copied()),
+                None => continue,
+            };
+            let collider = match collider_handle.and_then(|h| self.collider_set.get(h)) {
+                Some(collider) => collider,
+                None => continue,
+            };
+
+            let result = npc.test_behavior.update(
+                &mut self.rigid_body_set,
+                &self.collider_set,
+                &self.query_pipeline,
+                &mut self.navmesh,
+                npc.rigid_body_handle,
+                player_handle,
+                collider,
+                current_pos,
+                &npc.stats,
+                dt,
+            );
+
+            if let Some(tree) = npc.test_behavior.tree_mut() {
+                events.script_leaves.extend(tree.take_script_requests(&npc.id));
+            }
+
+            if let Some((damage, debug_line)) = result {
+                if damage > 0.0 {
+                    player_character.handle_incoming_damage(damage);
+                    events.damage_to_player += damage;
+                }
+
+                if let Some(line) = debug_line {
+                    events.debug_lines.push(line);
+                }
+            }
+        }
+
+        events
+    }
+
+    // Component placement, shared by the renderer's handlers and headless runs so both simulate the same bodies.
+    // Bodies carry the component id in user_data, which is how collisions and transforms find their component.
+
+    pub fn place_landscape(&mut self, component_id: &str, data: &LandscapePixelData, position: [f32; 3]) -> Result<(RigidBodyHandle, ColliderHandle), String> {
+        component_user_data(component_id)?;
+        let (rigid_body, collider) = Landscape::create_physics(&component_id.to_string(), data, position);
+        Ok(self.insert_body(rigid_body, collider))
+    }
+
+    // Upright capsule the NPC's behaviors push around, rotation is locked so it never tips over
+    pub fn place_npc(&mut self, component_id: &str, isometry: Isometry3<f32>, archetype: &NPCArchetype) -> Result<RigidBodyHandle, String> {
+        let user_data = component_user_data(component_id)?;
+
+        let collider = ColliderBuilder::capsule_y(1.0, 0.5)
+            .friction(0.7)
+            .restitution(0.0)
+            .density(1.0)
+            .user_data(user_data)
+            .build();
+
+        let rigid_body = RigidBodyBuilder::dynamic()
+            .additional_mass(70.0) // e.g. 70kg for a person
+            .linear_damping(0.1)
+            .position(isometry)
+            .locked_axes(LockedAxes::ROTATION_LOCKED_X | LockedAxes::ROTATION_LOCKED_Z)
+            .user_data(user_data)
+            .build();
+
+        let (handle, _) = self.insert_body(rigid_body, collider);
+        let mut npc = NPC::new(component_id.to_string(), component_id.to_string(), handle);
+        npc.apply_archetype(archetype);
+        self.npcs.push(npc);
+
+        Ok(handle)
+    }
+
+    // Fixed sensor the player picks the collectable up through. model_id is the render model it's drawn with,
+    // collectables of the same asset share one
+    pub fn place_collectable(
+        &mut self,
+        component_id: &str,
+        model_id: &str,
+        isometry: Isometry3<f32>,
+        collectable_type: CollectableType,
+        stats: StatData,
+    ) -> Result<RigidBodyHandle, String> {
+        let user_data = component_user_data(component_id)?;
+
+        let collider = ColliderBuilder::ball(0.5)
+            .sensor(true)
+            .user_data(user_data)
+            .build();
+
+        let rigid_body = RigidBodyBuilder::fixed()
+            .position(isometry)
+            .user_data(user_data)
+            .build();
+
+        let (handle, _) = self.insert_body(rigid_body, collider);
+        self.collectables.push(Collectable::new(component_id.to_string(), model_id.to_string(), collectable_type, stats, handle));
+
+        Ok(handle)
+    }
+
+    pub fn place_trigger_volume(
+        &mut self,
+        component_id: &str,
+        properties: &TriggerProperties,
+        isometry: Isometry3<f32>,
+        scale: Vector3<f32>,
+    ) -> Result<RigidBodyHandle, String> {
+        let user_data = component_user_data(component_id)?;
+        let (rigid_body, collider) = TriggerVolume::create_physics(user_data, properties, isometry, scale);
+        Ok(self.insert_body(rigid_body, collider).0)
+    }
+
+    pub fn place_player(
+        &mut self,
+        component_id: &str,
+        isometry: Isometry3<f32>,
+        scale: Vector3<f32>,
+        default_weapon: Option<ComponentData>,
+    ) -> Option<RigidBodyHandle> {
+        let mut player_character = PlayerCharacter::new(
+            component_id.to_string(),
+            &mut self.rigid_body_set,
+            &mut self.collider_set,
+            isometry,
+            scale,
+            default_weapon,
+        );
+        player_character.model_id = Some(component_id.to_string()); // may want to be an optional model later
+
+        let handle = player_character.movement_rigid_body_handle;
+        self.player_character = Some(player_character);
+        handle
+    }
+
+    // Tears down everything placed for the current level, except the player and what they carry
+    pub fn unload_level(&mut self) {
+        let mut keep_bodies: Vec<RigidBodyHandle> = Vec::new();
+        let mut keep_collectable_ids: Vec<String> = Vec::new();
+
+        if let Some(player) = &self.player_character {
+            keep_bodies.extend(player.movement_rigid_body_handle);
+            if let Some(weapon) = &player.inventory.equipped_weapon {
+                keep_collectable_ids.push(weapon.id.clone());
+            }
+        }
+
+        for collectable in &self.collectables {
+            if keep_collectable_ids.contains(&collectable.id) {
+                keep_bodies.push(collectable.rigid_body_handle);
+            }
+        }
+
+        let bodies: Vec<RigidBodyHandle> = self
+            .rigid_body_set
+            .iter()
+            .map(|(handle, _)| handle)
+            .filter(|handle| !keep_bodies.contains(handle))
+            .collect();
+
+        for handle in bodies {
+            self.remove_body(handle);
+        }
+
+        // colliders without a parent body (terrain chunks and the like)
+        let loose_colliders: Vec<ColliderHandle> = self
+            .collider_set
+            .iter()
+            .filter(|(_, collider)| collider.parent().is_none())
+            .map(|(handle, _)| handle)
+            .collect();
+
+        for handle in loose_colliders {
+            self.collider_set
+                .remove(handle, &mut self.island_manager, &mut self.rigid_body_set, true);
+        }
+
+        self.npcs.clear();
+        self.collectables.retain(|c| keep_collectable_ids.contains(&c.id));
+        self.hierarchy = SceneHierarchy::default();
+        self.portal_armed = false;
+        self.update_query_pipeline();
+    }
+
+    pub fn teleport_player(&mut self, position: [f32; 3]) {
+        if let Some(rb) = self.player_handle().and_then(|handle| self.rigid_body_set.get_mut(handle)) {
+            rb.set_translation(vector![position[0], position[1], position[2]], true);
+            rb.set_linvel(vector![0.0, 0.0, 0.0], true);
+        }
+    }
+
+    // The portal the player just walked into. Portals fire once the player has stepped out of the last one,
+    // so arriving on top of a portal doesn't bounce them straight back.
+    pub fn enter_portal(&mut self, components: &[ComponentData]) -> Option<PortalProperties> {
+        match self.player_portal(components).and_then(|c| c.portal_properties.clone()) {
+            Some(portal) if self.portal_armed => {
+                self.portal_armed = false;
+                Some(portal)
+            }
+            Some(_) => None,
+            None => {
+                self.portal_armed = true;
+                None
+            }
+        }
+    }
+
+    // The portal component the player is standing in, if any
+    pub fn player_portal<'a>(&self, components: &'a [ComponentData]) -> Option<&'a ComponentData> {
+        let player_pos = self.body_position(self.player_handle()?)?;
+        // parented portals are positioned relative to their parent
+        let placements = component_placements(components);
+
+        components.iter().find(|c| {
+            if c.kind != Some(ComponentKind::Portal) {
+                return false;
+            }
+
+            let radius = c.portal_properties.as_ref().map(|p| p.radius).unwrap_or(0.0);
+
+            placements
+                .get(&c.id)
+                .map(|p| (p.isometry.translation.vector - player_pos).norm() <= radius)
+                .unwrap_or(false)
+        })
+    }
+
+    pub fn body_transforms(&self) -> Vec<BodyTransform> {
+        self.rigid_body_set
+            .iter()
+            .map(|(_, rigid_body)| {
+                let physics_position = rigid_body.position();
+
+                BodyTransform {
+                    component_id: Uuid::from_u128(rigid_body.user_data),
+                    position: physics_position.translation.vector,
+                    euler: physics_position.rotation.euler_angles(),
+                }
+            })
+            .collect()
+    }
+}
+
+fn component_user_data(component_id: &str) -> Result<u128, String> {
+    Uuid::parse_str(component_id)
+        .map(|uuid| uuid.as_u128())
+        .map_err(|_| "id is not a uuid".to_string())
+}
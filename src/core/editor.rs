@@ -2,8 +2,10 @@ use std::fs;
 use std::path::Path;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
+use std::rc::Rc;
+use std::cell::RefCell;
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
@@ -17,7 +19,9 @@ use crate::core::RendererState::RendererState;
 use crate::core::SimpleCamera::SimpleCamera as Camera;
 use crate::core::camera::CameraBinding;
 use crate::core::gpu_resources::{self, GpuResources};
-use crate::helpers::saved_data::SavedState;
+use crate::helpers::load_project::LevelChange;
+use crate::helpers::project_error::ProjectError;
+use crate::helpers::saved_data::{ComponentData, SavedState};
 use crate::helpers::timelines::SavedTimelineStateConfig;
 use crate::renderer_images::st_image::StImage;
 use crate::renderer_text::fonts::FontManager;
@@ -27,8 +31,10 @@ use crate::core::HealthBar::HealthBar;
 use crate::shape_primitives::polygon::Polygon;
 use crate::vector_animations::animations::{AnimationProperty, EasingType, KeyType, KeyframeValue, ObjectType, Sequence, UIKeyframe};
 use crate::shape_primitives::Cube::Cube;
-use crate::rhai_engine::RhaiEngine;
+use crate::rhai_engine::{ComponentChanges, RhaiEngine};
+use crate::game_behaviors::quests::QuestLog;
 use crate::game_behaviors::dialogue_state::DialogueState;
+use crate::game_behaviors::dialogue_graph::DialogueLibrary;
 
 use cgmath::SquareMatrix;
 
@@ -323,6 +329,7 @@ pub struct Editor {
 
     // Dialogue State
     pub dialogue_state: DialogueState,
+    pub dialogues: DialogueLibrary, // the project's dialogue graphs
 
     // pub dragging_image: Option<Uuid>,
     // pub font_manager: FontManager,
@@ -334,7 +341,15 @@ pub struct Editor {
     // pub video_items: Vec<StVideo>,
     // pub dragging_video: Option<Uuid>,
     pub saved_state: Option<SavedState>,
+    pub current_level_id: Option<String>,
+    pub pending_level: Option<LevelChange>, // set by portals and Rhai, loaded at the start of the next frame
+    pub pending_save_load: Option<String>, // save slot requested by Rhai, loaded at the start of the next frame
+    pub load_errors: Vec<ProjectError>, // problems from the last project or level load, shown in the editor
     pub rhai_engine: RhaiEngine,
+    pub quest_log: Rc<RefCell<QuestLog>>, // shared with the Rhai `quests` api
+    pub pending_script_changes: Vec<ComponentChanges>, // from lifecycle hooks run during loads, applied next frame
+    pub pending_spawns: Vec<ComponentData>, // requested by Rhai, placed at the start of the next frame
+    pub spawned_components: Vec<ComponentData>, // placed by Rhai this level, never saved to the project
     
     // resize handles system
     pub selected_object: Option<SelectedObject>,
@@ -410,7 +425,7 @@ pub struct Editor {
 }
 
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 pub fn init_editor_with_model(viewport: Arc<Mutex<Viewport>>, project_id: String) -> Editor {
     // let inference = load_common_motion_2d();
 
@@ -459,11 +474,22 @@ impl Editor {
         // Initialize StCapture - this handles the non-Send+Sync Windows capture types
         // let st_capture = StCapture::new(project_path);
 
+        let quest_log = Rc::new(RefCell::new(QuestLog::default()));
+        let mut rhai_engine = RhaiEngine::new();
+        rhai_engine.bind_quests(quest_log.clone());
 
         Editor {
             renderer_state: None,
             saved_state: None,
-            rhai_engine: RhaiEngine::new(),
+            current_level_id: None,
+            pending_level: None,
+            pending_save_load: None,
+            load_errors: Vec::new(),
+            rhai_engine,
+            quest_log,
+            pending_script_changes: Vec::new(),
+            pending_spawns: Vec::new(),
+            spawned_components: Vec::new(),
             // st_capture,
             // exporter: None,
             // font_manager,
@@ -515,6 +541,7 @@ impl Editor {
             inventory_ui_ids: Vec::new(),
             font_manager,
             dialogue_state: DialogueState::default(),
+            dialogues: DialogueLibrary::default(),
             // dragging_image: None,
             video_is_playing: false,
             video_start_playing_time: None,
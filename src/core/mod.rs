@@ -17,4 +17,6 @@ pub mod ui_pipeline;
 pub mod HealthBar;
 pub mod animation_system;
 pub mod AnimationState;
-pub mod skinned_pipeline;
\ No newline at end of file
+pub mod skinned_pipeline;
+pub mod World;
+pub mod SceneHierarchy;
\ No newline at end of file
@@ -2,7 +2,7 @@ use crate::core::skinned_pipeline::SkinnedPipeline;
 use crate::{
     core::{Grid::{Grid, GridConfig}, RendererState::RendererState, SimpleCamera::SimpleCamera as Camera, Texture::pack_pbr_textures, camera::{self, CameraBinding}, editor::{
         Editor, PointLight, Viewport, WindowSize, WindowSizeShader
-    }, gpu_resources::{self, GpuResources}, vertex::Vertex}, handlers::{EntropySize}, heightfield_landscapes::Landscape::{PBRMaterialType, PBRTextureKind}, helpers::{landscapes::{read_landscape_heightmap_as_texture, read_texture_bytes}, saved_data::{ComponentKind, LandscapeTextureKinds, LevelData, PBRTextureData, ProceduralSkyConfig, SavedState}, timelines::SavedTimelineStateConfig, utilities}, procedural_trees::trees::DrawTrees, vector_animations::animations::Sequence, video_export::frame_buffer::FrameCaptureBuffer, water_plane::water::DrawWater
+    }, gpu_resources::{self, GpuResources}, vertex::Vertex}, handlers::{EntropySize}, heightfield_landscapes::Landscape::{PBRMaterialType, PBRTextureKind}, helpers::{landscapes::{read_landscape_heightmap_as_texture, read_texture_bytes}, saved_data::{ComponentKind, LandscapeTextureKinds, LevelData, NPCBehaviorKind, PBRTextureData, ProceduralSkyConfig, SavedState, ScriptState}, timelines::SavedTimelineStateConfig, utilities}, procedural_trees::trees::DrawTrees, vector_animations::animations::Sequence, video_export::frame_buffer::FrameCaptureBuffer, water_plane::water::DrawWater
 };
 use crate::core::Texture::Texture;
 use crate::core::shadow_pipeline::ShadowPipelineData;
@@ -22,25 +22,33 @@ use web_sys::HtmlCanvasElement;
 use wgpu::{Limits, RenderPipeline, util::DeviceExt};
 use bytemuck::{Pod, Zeroable}; // For procedural sky uniform
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use winit::window::Window;
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use egui;
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use crate::startup::Gui;
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_timer::Instant;
 
 use crate::shape_primitives::Cube::Cube;
-use crate::helpers::load_project::load_project;
-use crate::rhai_engine::{ComponentChanges, RhaiEngine};
+use crate::helpers::load_project::{load_level, load_project, load_save_game, spawn_components, LevelChange};
+use crate::helpers::save_game;
+use crate::helpers::prefabs;
+use crate::core::SceneHierarchy::{component_placements, is_descendant, Placement};
+use crate::rhai_engine::{ComponentChanges, RhaiEngine, ScriptErrorKind, ScriptScene};
+use crate::game_behaviors::blackboard::Blackboard;
 use crate::game_behaviors::dialogue_ui;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::game_behaviors::behavior_tree_ui;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::game_behaviors::navmesh_ui;
 use crate::procedural_particles::particle_system::{ParticleSystem, ParticleUniforms};
 
 // use super::chat::Chat;
@@ -176,7 +184,7 @@ impl ExportPipeline {
     pub async fn initialize(
         &mut self,
         
-        #[cfg(target_os = "windows")]
+        #[cfg(not(target_arch = "wasm32"))]
         window: Option<&Window>,
 
         #[cfg(target_arch = "wasm32")]
@@ -1077,8 +1085,7 @@ impl ExportPipeline {
         let procedural_sky_config_from_level = export_editor
             .saved_state
             .as_ref()
-            .and_then(|state| state.levels.as_ref())
-            .and_then(|levels| levels.get(0)) // Assuming we always work with the first level
+            .and_then(|state| state.level(export_editor.current_level_id.as_deref()))
             .and_then(|level| level.procedural_sky.clone())
             .unwrap_or_default(); // Get from saved_data, or use defaults
 
@@ -1316,7 +1323,7 @@ impl ExportPipeline {
         //         // &gpu_resources.queue,
         //     );
         // });
-        // #[cfg(target_os = "windows")]
+        // #[cfg(not(target_arch = "wasm32"))]
         let now = Instant::now();
         
         // #[cfg(target_arch = "wasm32")]
@@ -1569,6 +1576,27 @@ impl ExportPipeline {
 
     pub fn render_frame(&mut self, target_view: Option<&wgpu::TextureView>, current_time: f64, game_mode: bool) {
         let editor = self.export_editor.as_mut().expect("Couldn't get editor");
+
+        // Switch levels requested by portals or scripts during the last frame
+        if let Some(level_change) = editor.pending_level.take() {
+            if let Some(project_id) = editor.saved_state.as_ref().and_then(|s| s.id.clone()) {
+                pollster::block_on(load_level(editor, &project_id, &level_change.level_id, level_change.spawn_position));
+            }
+        }
+
+        if let Some(slot) = editor.pending_save_load.take() {
+            if let Some(project_id) = editor.saved_state.as_ref().and_then(|s| s.id.clone()) {
+                pollster::block_on(load_save_game(editor, &project_id, &slot));
+            }
+        }
+
+        // Place whatever scripts spawned during the last frame
+        if !editor.pending_spawns.is_empty() {
+            if let Some(project_id) = editor.saved_state.as_ref().and_then(|s| s.id.clone()) {
+                pollster::block_on(spawn_components(editor, &project_id));
+            }
+        }
+
         let renderer_state = editor.renderer_state.as_mut().expect("Couldn't get RendererState");
         let gpu_resources = self
             .gpu_resources
@@ -1615,7 +1643,7 @@ impl ExportPipeline {
         let texture = self.texture.as_ref().expect("Couldn't get texture");
         
         // Sync player health to UI
-        if let Some(player) = &renderer_state.player_character {
+        if let Some(player) = &renderer_state.world.player_character {
             if let Some(health_bar) = &mut editor.health_bar {
                 health_bar.update_health(queue, player.stats.health);
             }
@@ -1623,7 +1651,7 @@ impl ExportPipeline {
 
         // Sync enemy health to UI
         if let Some(target_id) = &editor.current_enemy_target {
-            if let Some(npc) = renderer_state.npcs.iter().find(|n| &n.id == target_id) {
+            if let Some(npc) = renderer_state.world.npcs.iter().find(|n| &n.id == target_id) {
                  if let Some(health_bar) = &mut editor.enemy_health_bar {
                     health_bar.update_health(queue, npc.stats.health);
                 }
@@ -1636,8 +1664,7 @@ impl ExportPipeline {
             let current_procedural_sky_config = editor
                 .saved_state
                 .as_ref()
-                .and_then(|state| state.levels.as_ref())
-                .and_then(|levels| levels.get(0))
+                .and_then(|state| state.level(editor.current_level_id.as_deref()))
                 .and_then(|level| level.procedural_sky.clone());
 
             if let Some(config) = current_procedural_sky_config {
@@ -1695,49 +1722,189 @@ impl ExportPipeline {
                     camera_binding,
                     camera
                 );
+
+                // Portals fire when the player walks in, once they've stepped out of the last one
+                let portal = editor
+                    .saved_state
+                    .as_ref()
+                    .and_then(|s| s.level_components(editor.current_level_id.as_deref()))
+                    .and_then(|components| renderer_state.world.enter_portal(components));
+
+                if let Some(portal) = portal {
+                    editor.pending_level = Some(LevelChange {
+                        level_id: portal.target_level_id,
+                        spawn_position: portal.spawn_position,
+                    });
+                }
+
+                // pickups, kills and areas reached this frame count towards quest objectives
+                if let Some(components) = editor.saved_state.as_ref().and_then(|s| s.level_components(editor.current_level_id.as_deref())) {
+                    editor.quest_log.borrow_mut().observe(&renderer_state.world, components);
+                }
+            } else {
+                // keep children attached while parents are edited
+                renderer_state.update_hierarchy();
+            }
+
+            // Execute Rhai component scripts, after anything the load-time lifecycle hooks asked for
+            let mut changes: Vec<ComponentChanges> = editor.pending_script_changes.drain(..).collect();
+            let collisions: Vec<_> = renderer_state.collisions.drain(..).collect();
+            let script_leaves: Vec<_> = renderer_state.script_leaves.drain(..).collect();
+
+            // scripts edited on disk are recompiled in place, a broken edit keeps the last good version
+            editor.rhai_engine.reload_changed_scripts(self.start_time.elapsed().as_secs_f32());
+
+            // script time only runs while playing
+            if game_mode {
+                editor.rhai_engine.timers.borrow_mut().advance(renderer_state.world.dt);
             }
 
-            // Execute Rhai component scripts
-            let mut changes: Vec<ComponentChanges> = Vec::new();
             if let Some(saved_state) = editor.saved_state.as_ref() {
-                if let Some(levels) = saved_state.levels.as_ref() {
-                    if let Some(components) = levels.get(0).and_then(|l| l.components.as_ref()) {
-                        for component in components.iter() {
-                            if let Some(script_path) = &component.rhai_script_path {
-                                if let Some(change) = editor.rhai_engine.execute_component_script(
-                                    renderer_state,
-                                    component,
-                                    script_path,
-                                    "on_update",
-                                ) {
-                                    changes.push(change);
+                if let Some(components) = saved_state.level_components(editor.current_level_id.as_deref()) {
+                    let level_id = editor.current_level_id.clone().unwrap_or_default();
+                    let scripted = components
+                        .iter()
+                        .chain(editor.spawned_components.iter())
+                        .any(|c| c.rhai_script_path.is_some());
+
+                    // every hook this frame sees the level as it was before any of them ran
+                    if scripted || !script_leaves.is_empty() || saved_state.global_rhai_scripts.as_ref().map_or(false, |s| !s.is_empty()) {
+                        editor.rhai_engine.set_scene(ScriptScene::from_level(renderer_state, &level_id, components, &editor.spawned_components));
+                    }
+
+                    // every hook from here to the global scripts shares one time budget
+                    editor.rhai_engine.begin_frame();
+
+                    // contacts and trigger overlaps from this frame's physics step, both sides hear about them
+                    for collision in &collisions {
+                        for (id, other_id) in [(&collision.a, &collision.b), (&collision.b, &collision.a)] {
+                            let component = components.iter().chain(editor.spawned_components.iter()).find(|c| &c.id == id);
+
+                            if let Some(component) = component {
+                                if let Some(script_path) = &component.rhai_script_path {
+                                    changes.extend(editor.rhai_engine.execute_collision_hook(
+                                        renderer_state,
+                                        component,
+                                        script_path,
+                                        collision.hook_name(),
+                                        other_id,
+                                    ));
                                 }
                             }
                         }
                     }
+
+                    // system.after and system.every calls that came due, on whoever scheduled them
+                    while let Some(timer) = editor.rhai_engine.next_timer() {
+                        if timer.is_global() {
+                            changes.extend(editor.rhai_engine.execute_global_timer(&timer));
+                        } else if let Some(component) = components.iter().chain(editor.spawned_components.iter()).find(|c| c.id == timer.owner_id) {
+                            changes.extend(editor.rhai_engine.execute_timer(renderer_state, component, &timer));
+                        }
+                    }
+
+                    // behavior tree leaves that call into scripts, the trees see the answers next step
+                    for request in &script_leaves {
+                        let script_path = request.script.clone().or_else(|| {
+                            components
+                                .iter()
+                                .chain(editor.spawned_components.iter())
+                                .find(|c| c.id == request.npc_id)
+                                .and_then(|c| c.rhai_script_path.clone())
+                        });
+                        changes.extend(editor.rhai_engine.execute_behavior_leaf(renderer_state, request, script_path.as_deref()));
+                    }
+
+                    for component in components.iter().chain(editor.spawned_components.iter()) {
+                        if let Some(script_path) = &component.rhai_script_path {
+                            changes.extend(editor.rhai_engine.execute_component_script(
+                                renderer_state,
+                                component,
+                                script_path,
+                                "on_update",
+                            ));
+                        }
+                    }
+
+                    // then the level-wide scripts, which see every component
+                    for script_path in saved_state.global_rhai_scripts.iter().flatten() {
+                        changes.extend(editor.rhai_engine.execute_global_hook(script_path, "on_update"));
+                    }
+
+                    editor.rhai_engine.end_frame();
                 }
             }
 
             // Apply collected changes
             for change in changes {
-                if let Some(model) = renderer_state.models.iter_mut().find(|m| m.id == change.component_id) {
-                    if let Some(new_pos) = change.new_position {
-                        let pos_array = [new_pos.x, new_pos.y, new_pos.z];
-                        
-                        // Update model's transform for rendering
-                        for mesh in &mut model.meshes {
-                            mesh.transform.update_position(pos_array);
+                if let Some(level_id) = &change.load_level {
+                    editor.pending_level = Some(LevelChange {
+                        level_id: level_id.clone(),
+                        spawn_position: None,
+                    });
+                }
+
+                if let Some(slot) = &change.save_slot {
+                    let saved_state = editor.saved_state.as_ref();
+                    let project_id = saved_state.and_then(|s| s.id.clone());
+                    let level_id = editor.current_level_id.clone();
+                    let components = saved_state.and_then(|s| s.level_components(level_id.as_deref()));
+
+                    if let (Some(project_id), Some(level_id), Some(components)) = (project_id, level_id, components) {
+                        let mut save = save_game::capture_game(&project_id, &level_id, renderer_state, components, &editor.dialogue_state, &editor.quest_log.borrow());
+                        save.script_states.extend(editor.rhai_engine.global_states.clone());
+                        save.timers = editor.rhai_engine.timers.borrow().clone();
+                        save.blackboard = editor.rhai_engine.blackboard.borrow().clone();
+                        match save_game::write_save(slot, &save) {
+                            Ok(path) => println!("Game saved to {:?}", path),
+                            Err(e) => println!("Couldn't save game: {}", e),
                         }
-                        
-                        // Update rigidbody for physics
-                        if let Some(rb_handle) = model.meshes[0].rigid_body_handle {
-                            if let Some(rb) = renderer_state.rigid_body_set.get_mut(rb_handle) {
-                                let new_isometry = nalgebra::Isometry3::translation(new_pos.x, new_pos.y, new_pos.z);
-                                rb.set_position(new_isometry, true);
+                    }
+                }
+
+                if let Some(slot) = &change.load_slot {
+                    editor.pending_save_load = Some(slot.clone());
+                }
+
+                // spawning and destroying only happen while playing, the editor keeps the level as saved
+                if let Some(source_id) = &change.spawn_from {
+                    if game_mode {
+                        let position = change.new_position.unwrap_or_else(Vector3::zeros);
+                        match editor.saved_state.as_ref().and_then(|s| prefabs::spawn_template(s, source_id)) {
+                            Some(mut component) => {
+                                component.id = change.component_id.clone();
+                                component.generic_properties.position = [position.x, position.y, position.z];
+                                editor.pending_spawns.push(component);
+                            }
+                            None => println!("Couldn't spawn {}, no prefab or model has that id", source_id),
+                        }
+                    }
+                    continue;
+                }
+
+                if change.destroy {
+                    if game_mode {
+                        let component = editor
+                            .saved_state
+                            .as_ref()
+                            .and_then(|s| s.level_components(editor.current_level_id.as_deref()))
+                            .and_then(|components| components.iter().find(|c| c.id == change.component_id))
+                            .or_else(|| editor.spawned_components.iter().find(|c| c.id == change.component_id));
+
+                        if let Some(component) = component {
+                            if let Some(script_path) = &component.rhai_script_path {
+                                editor.pending_script_changes.extend(editor.rhai_engine.execute_component_script(renderer_state, component, script_path, "on_destroy"));
                             }
                         }
+
+                        renderer_state.remove_component(&change.component_id);
+                        editor.spawned_components.retain(|c| c.id != change.component_id);
+                        editor.rhai_engine.timers.borrow_mut().cancel_owner(&change.component_id);
                     }
+                    continue;
                 }
+
+                renderer_state.apply_script_change(queue, &change);
             }
 
             let time = self.start_time.elapsed().as_secs_f32();
@@ -1930,15 +2097,15 @@ impl ExportPipeline {
             // draw grass
 
             for grass in &renderer_state.grasses {
-                if let Some(player_character) = &renderer_state.player_character {
+                if let Some(player_character) = &renderer_state.world.player_character {
                     if let Some(model_id) = &player_character.model_id {
                         let player_model = renderer_state.models.iter().find(|m| m.id == model_id.clone());
                         let player_model = player_model.as_ref().expect("Couldn't find related model");
                         let model_mesh = player_model.meshes.get(0);
                         let model_mesh = model_mesh.as_ref().expect("Couldn't get first mesh");
                         grass.update_uniforms(&queue, time as f32, Point3::new(model_mesh.transform.position.x, model_mesh.transform.position.y, model_mesh.transform.position.z));
-                    } else if let Some(sphere) = &player_character.sphere {
-                        grass.update_uniforms(&queue, time as f32, Point3::new(sphere.transform.position.x, sphere.transform.position.y, sphere.transform.position.z));
+                    } else if let Some(pos) = player_character.movement_rigid_body_handle.and_then(|h| renderer_state.world.body_position(h)) {
+                        grass.update_uniforms(&queue, time as f32, Point3::new(pos.x, pos.y, pos.z));
                     } else {
                         grass.update_uniforms(&queue, time as f32, camera.position);
                     }
@@ -1972,7 +2139,7 @@ impl ExportPipeline {
 
             // draw water
             for water_plane in &mut renderer_state.water_planes {
-                if let Some(player_character) = &renderer_state.player_character {
+                if let Some(player_character) = &renderer_state.world.player_character {
                     if let Some(model_id) = &player_character.model_id {
                         let player_model = renderer_state.models.iter().find(|m| m.id == model_id.clone());
                         let player_model = player_model.as_ref().expect("Couldn't find related model");
@@ -1980,8 +2147,7 @@ impl ExportPipeline {
                         let model_mesh = model_mesh.as_ref().expect("Couldn't get first mesh");
                         water_plane.update_uniforms(queue, time as f32, [model_mesh.transform.position.x, model_mesh.transform.position.y, model_mesh.transform.position.z]);
                         render_pass.draw_water(water_plane, &camera_binding.bind_group, &water_plane.time_bind_group, &water_plane.landscape_bind_group, &water_plane.config_bind_group);
-                    } else if let Some(sphere) = &player_character.sphere {
-                        let player_pos = sphere.transform.position;
+                    } else if let Some(player_pos) = player_character.movement_rigid_body_handle.and_then(|h| renderer_state.world.body_position(h)) {
                         water_plane.update_uniforms(queue, time as f32, [player_pos.x, player_pos.y, player_pos.z]);
                         render_pass.draw_water(water_plane, &camera_binding.bind_group, &water_plane.time_bind_group, &water_plane.landscape_bind_group, &water_plane.config_bind_group);
                     }
@@ -2312,7 +2478,7 @@ impl ExportPipeline {
     #[cfg(target_arch = "wasm32")]
     pub fn render_display_frame(&mut self, game_mode: bool) {}
 
-    #[cfg(target_os = "windows")]
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn render_display_frame(&mut self, gui: &mut Gui, window: &Window, game_mode: bool) {
         let gpu_resources = self.gpu_resources.as_ref().expect("Couldn't get GPU Resources").clone();
     
@@ -2376,7 +2542,7 @@ impl ExportPipeline {
         output.present();
     }
     
-    #[cfg(target_os = "windows")]
+    #[cfg(not(target_arch = "wasm32"))]
     fn ui(&mut self, ctx: &egui::Context) {
         let editor = self.export_editor.as_mut().unwrap();
         if editor.saved_state.is_none() {
@@ -2415,7 +2581,6 @@ impl ExportPipeline {
 
                         // load_project(editor, project_id); // await needed?
                         pollster::block_on(load_project(editor, project_id));
-                        // editor.rhai_engine.load_global_scripts(&editor.saved_state.as_ref().unwrap().global_rhai_scripts);
                     }
                 }
             });
@@ -2488,10 +2653,129 @@ impl ExportPipeline {
         //     // TODO: need to display textures and models (assets) available in the saved_data library
         // });
 
+        let current_level_id = editor.current_level_id.clone();
+
+        if !editor.load_errors.is_empty() {
+            egui::Window::new("Load Problems").show(ctx, |ui| {
+                ui.label("Skipped while loading:");
+                for error in &editor.load_errors {
+                    ui.label(error.to_string());
+                }
+                if ui.button("Dismiss").clicked() {
+                    editor.load_errors.clear();
+                }
+            });
+        }
+
+        if !editor.rhai_engine.errors.is_empty() {
+            egui::Window::new("Script Errors").show(ctx, |ui| {
+                let mut enable = None;
+                for error in &editor.rhai_engine.errors {
+                    ui.horizontal(|ui| {
+                        ui.label(error.to_string());
+                        if error.kind == ScriptErrorKind::Disabled && ui.button("Re-enable").clicked() {
+                            enable = Some(error.script_path.clone());
+                        }
+                    });
+                }
+                if let Some(script_path) = enable {
+                    editor.rhai_engine.enable_script(&script_path);
+                    editor.rhai_engine.errors.retain(|e| e.script_path != script_path);
+                }
+                if ui.button("Clear").clicked() {
+                    editor.rhai_engine.errors.clear();
+                }
+            });
+        }
+
+        egui::Window::new("Blackboard").default_open(false).show(ctx, |ui| {
+            let mut blackboard = editor.rhai_engine.blackboard.borrow_mut();
+            let sorted = |vars: &ScriptState| {
+                let mut lines: Vec<String> = vars.iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+                lines.sort();
+                lines
+            };
+
+            ui.label("Globals:");
+            for line in sorted(&blackboard.globals) {
+                ui.label(line);
+            }
+
+            let mut npc_ids: Vec<&String> = blackboard.npcs.keys().collect();
+            npc_ids.sort();
+            for npc_id in npc_ids {
+                let memory = &blackboard.npcs[npc_id];
+                let name = editor
+                    .saved_state
+                    .as_ref()
+                    .and_then(|s| s.level_components(current_level_id.as_deref()))
+                    .and_then(|components| components.iter().find(|c| &c.id == npc_id))
+                    .map(|c| format!("{} ({})", c.generic_properties.name, npc_id))
+                    .unwrap_or_else(|| npc_id.clone());
+
+                ui.collapsing(name, |ui| {
+                    for line in sorted(&memory.vars) {
+                        ui.label(line);
+                    }
+                    let mut visits: Vec<String> = memory.visits.iter().map(|(node, count)| format!("visited {} x{}", node, count)).collect();
+                    visits.sort();
+                    for line in visits {
+                        ui.label(line);
+                    }
+                });
+            }
+
+            if ui.button("Clear").clicked() {
+                *blackboard = Blackboard::default();
+            }
+        });
+
+        if let Some(renderer_state) = editor.renderer_state.as_mut() {
+            egui::Window::new("Navigation").default_open(false).show(ctx, |ui| {
+                ui.checkbox(&mut renderer_state.game_settings.show_navmesh, "Show navmesh");
+                if renderer_state.game_settings.show_navmesh {
+                    let navmesh = &renderer_state.world.navmesh;
+                    ui.label(format!("{} tiles built, revision {}", navmesh.built_tiles(), navmesh.revision));
+                    navmesh_ui::navmesh_overlay(ui, &renderer_state.world, 40.0);
+                }
+            });
+
+            egui::Window::new("Behavior Trees").default_open(false).show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    behavior_tree_ui::behavior_tree_view(ui, &renderer_state.world);
+                });
+            });
+        }
+
+        egui::Window::new("Levels").show(ctx, |ui| {
+            if let Some(saved_state) = &editor.saved_state {
+                let start_level_id = saved_state.start_level_id();
+                for level in saved_state.levels.iter().flatten() {
+                    ui.horizontal(|ui| {
+                        let mut label = level.id.clone();
+                        if Some(&level.id) == start_level_id.as_ref() {
+                            label.push_str(" (start)");
+                        }
+                        if Some(&level.id) == current_level_id.as_ref() {
+                            ui.strong(label);
+                        } else {
+                            ui.label(label);
+                            if ui.button("Load").clicked() {
+                                editor.pending_level = Some(LevelChange {
+                                    level_id: level.id.clone(),
+                                    spawn_position: None,
+                                });
+                            }
+                        }
+                    });
+                }
+            }
+        });
+
         egui::Window::new("Components").show(ctx, |ui| {
             if let Some(saved_state) = &mut editor.saved_state {
-                if let Some(levels) = &mut saved_state.levels.clone() {
-                    if let Some(components) = &mut levels[0].components {
+                if let Some(level) = saved_state.level(current_level_id.as_deref()).cloned() {
+                    if let Some(components) = &level.components {
                         for component in components {
                             ui.horizontal(|ui| {
                                 ui.label(&component.generic_properties.name);
@@ -2520,17 +2804,126 @@ impl ExportPipeline {
                 }
             }
         });
+
+        egui::Window::new("Prefabs").show(ctx, |ui| {
+            if let Some(saved_state) = &mut editor.saved_state {
+                for prefab in saved_state.prefabs.iter().flatten() {
+                    let instances = saved_state
+                        .levels
+                        .iter()
+                        .flatten()
+                        .filter_map(|l| l.components.as_ref())
+                        .flatten()
+                        .filter(|c| c.prefab.as_ref().map(|p| p.prefab_id == prefab.id).unwrap_or(false))
+                        .count();
+                    ui.label(format!("{} ({} instances)", prefab.name, instances));
+                }
+
+                if let Some(selected_component_id) = &self.selected_component_id {
+                    let project_id = saved_state.id.clone().expect("Couldn't get project id");
+                    let selected = saved_state
+                        .find_component_mut(selected_component_id)
+                        .map(|c| (c.generic_properties.name.clone(), c.prefab.clone()));
+
+                    match selected {
+                        Some((name, Some(instance))) => {
+                            ui.separator();
+                            let prefab_name = saved_state.prefab(&instance.prefab_id).map(|p| p.name.clone()).unwrap_or(instance.prefab_id.clone());
+                            ui.label(format!("{} is an instance of {}", name, prefab_name));
+                            if ui.button("Apply to Prefab").clicked() {
+                                let updated = prefabs::apply_to_prefab(saved_state, selected_component_id);
+                                utilities::update_project_state(&project_id, saved_state).expect("Failed to update project state");
+
+                                // reload so the other placed instances pick up the change
+                                if !updated.is_empty() {
+                                    if let Some(level_id) = current_level_id.clone() {
+                                        editor.pending_level = Some(LevelChange {
+                                            level_id,
+                                            spawn_position: None,
+                                        });
+                                    }
+                                }
+                            }
+                        }
+                        Some((name, None)) => {
+                            ui.separator();
+                            if ui.button(format!("Make Prefab from {}", name)).clicked() {
+                                prefabs::make_prefab(saved_state, selected_component_id, &name);
+                                utilities::update_project_state(&project_id, saved_state).expect("Failed to update project state");
+                            }
+                        }
+                        None => {}
+                    }
+                }
+            }
+        });
     
         if let Some(selected_component_id) = &self.selected_component_id {
             
             egui::Window::new("Properties").show(ctx, |ui| {
                 if let Some(saved_state) = &mut editor.saved_state {
-                    let project_id = saved_state.id.as_ref().expect("Couldn't get project id");
-                    if let Some(levels) = &mut saved_state.levels {
-                        if let Some(components) = &mut levels[0].components {
+                    let project_id = saved_state.id.clone().expect("Couldn't get project id");
+                    let stat_options: Vec<(String, String)> = saved_state.stats.iter().flatten().map(|s| (s.id.clone(), s.name.clone())).collect();
+                    let tree_ids: Vec<String> = saved_state.behavior_trees.iter().flatten().map(|t| t.id.clone()).collect();
+                    if let Some(level) = saved_state.level_mut(current_level_id.as_deref()) {
+                        if let Some(components) = &mut level.components {
                             let light_components: Vec<_> = components.clone();
                             let light_components: Vec<_> = light_components.iter().filter(|c| matches!(c.kind, Some(ComponentKind::PointLight))).collect();
+                            let placements = component_placements(components);
+                            // anything that wouldn't make a loop can be a parent
+                            let parent_options: Vec<(String, String)> = components
+                                .iter()
+                                .filter(|c| &c.id != selected_component_id && !is_descendant(components, &c.id, selected_component_id))
+                                .map(|c| (c.id.clone(), c.generic_properties.name.clone()))
+                                .collect();
+                            let item_options: Vec<(String, String)> = components
+                                .iter()
+                                .filter(|c| c.kind == Some(ComponentKind::Collectable))
+                                .map(|c| (c.id.clone(), c.generic_properties.name.clone()))
+                                .collect();
                             if let Some(component) = components.iter_mut().find(|c| &c.id == selected_component_id) {
+                                let mut parent_id = component.parent_id.clone();
+                                let parent_name = |id: &Option<String>| match id {
+                                    Some(id) => parent_options.iter().find(|(option_id, _)| option_id == id).map(|(_, name)| name.clone()).unwrap_or(id.clone()),
+                                    None => "None".to_string(),
+                                };
+                                egui::ComboBox::from_label("Parent")
+                                    .selected_text(parent_name(&parent_id))
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(&mut parent_id, None, "None");
+                                        for (id, name) in &parent_options {
+                                            ui.selectable_value(&mut parent_id, Some(id.clone()), name);
+                                        }
+                                    });
+
+                                if parent_id != component.parent_id {
+                                    // keep it where it is in the world, only its saved transform changes frame
+                                    let world = placements.get(&component.id).copied().unwrap_or_else(|| Placement::from_generic(&component.generic_properties));
+                                    let new_parent = parent_id
+                                        .as_ref()
+                                        .and_then(|id| placements.get(id).copied())
+                                        .unwrap_or(Placement { isometry: Isometry3::identity(), scale: Vector3::new(1.0, 1.0, 1.0) });
+
+                                    component.generic_properties = new_parent.local_generic(&world, &component.generic_properties);
+                                    component.parent_id = parent_id;
+                                    utilities::update_project_state_component(&project_id, component).expect("Failed to update project state");
+
+                                    // the hierarchy is built at placement, so reload to pick it up
+                                    if let Some(level_id) = current_level_id.clone() {
+                                        editor.pending_level = Some(LevelChange {
+                                            level_id,
+                                            spawn_position: None,
+                                        });
+                                    }
+                                }
+
+                                // children are moved by the hierarchy, relative to their parent
+                                let is_child = editor
+                                    .renderer_state
+                                    .as_ref()
+                                    .map(|r| r.world.hierarchy.node(&component.id).is_some())
+                                    .unwrap_or(false);
+
                                 match component.kind {
                                     Some(ComponentKind::Model) => {
                                         ui.label("Position");
@@ -2540,7 +2933,9 @@ impl ExportPipeline {
                                             ui.add(egui::DragValue::new(&mut component.generic_properties.position[2]).speed(0.1)).changed()
                                         }).inner {
                                             if let Some(renderer_state) = &mut editor.renderer_state {
-                                                if let Some(model) = renderer_state.models.iter_mut().find(|m| &m.id == selected_component_id) {
+                                                if is_child {
+                                                    renderer_state.world.hierarchy.set_child_local(&component.id, &component.generic_properties);
+                                                } else if let Some(model) = renderer_state.models.iter_mut().find(|m| &m.id == selected_component_id) {
                                                     for mesh in &mut model.meshes {
                                                         mesh.transform.update_position(component.generic_properties.position);
                                                     }
@@ -2556,7 +2951,9 @@ impl ExportPipeline {
                                             ui.add(egui::DragValue::new(&mut component.generic_properties.rotation[2]).speed(0.1)).changed()
                                         }).inner {
                                             if let Some(renderer_state) = &mut editor.renderer_state {
-                                                if let Some(model) = renderer_state.models.iter_mut().find(|m| &m.id == selected_component_id) {
+                                                if is_child {
+                                                    renderer_state.world.hierarchy.set_child_local(&component.id, &component.generic_properties);
+                                                } else if let Some(model) = renderer_state.models.iter_mut().find(|m| &m.id == selected_component_id) {
                                                     for mesh in &mut model.meshes {
                                                         mesh.transform.update_rotation([component.generic_properties.rotation[0].to_radians(), component.generic_properties.rotation[1].to_radians(), component.generic_properties.rotation[2].to_radians()]);
                                                     }
@@ -2575,8 +2972,9 @@ impl ExportPipeline {
                                             ui.add(egui::DragValue::new(&mut component.generic_properties.position[2]).speed(0.1)).changed()
                                         }).inner {
                                             if let Some(renderer_state) = &mut editor.renderer_state {
-                                                
-                                                if let Some(index) = light_components.iter().position(|c| &c.id == selected_component_id) {
+                                                if is_child {
+                                                    renderer_state.world.hierarchy.set_child_local(&component.id, &component.generic_properties);
+                                                } else if let Some(index) = light_components.iter().position(|c| &c.id == selected_component_id) {
                                                     if let Some(light) = renderer_state.point_lights.get_mut(index) {
                                                         light.position = component.generic_properties.position;
                                                     }
@@ -2611,6 +3009,104 @@ impl ExportPipeline {
                                             // }
                                         }
                                     },
+                                    Some(ComponentKind::NPC) => {
+                                        if let Some(npc_props) = &mut component.npc_properties {
+                                            let mut changed = false;
+                                            let option_name = |options: &[(String, String)], id: &Option<String>| match id {
+                                                Some(id) => options.iter().find(|(option_id, _)| option_id == id).map(|(_, name)| name.clone()).unwrap_or(id.clone()),
+                                                None => "None".to_string(),
+                                            };
+
+                                            ui.label("Name");
+                                            let mut name = npc_props.name.clone().unwrap_or_default();
+                                            if ui.text_edit_singleline(&mut name).changed() {
+                                                npc_props.name = if name.is_empty() { None } else { Some(name) };
+                                                changed = true;
+                                            }
+
+                                            egui::ComboBox::from_label("Behavior")
+                                                .selected_text(format!("{:?}", npc_props.behavior))
+                                                .show_ui(ui, |ui| {
+                                                    for kind in [NPCBehaviorKind::Wander, NPCBehaviorKind::Melee, NPCBehaviorKind::Ranged] {
+                                                        changed |= ui.selectable_value(&mut npc_props.behavior, kind, format!("{:?}", kind)).changed();
+                                                    }
+                                                });
+
+                                            egui::ComboBox::from_label("Behavior tree")
+                                                .selected_text(npc_props.behavior_tree_id.clone().unwrap_or("None".to_string()))
+                                                .show_ui(ui, |ui| {
+                                                    changed |= ui.selectable_value(&mut npc_props.behavior_tree_id, None, "None").changed();
+                                                    for id in &tree_ids {
+                                                        changed |= ui.selectable_value(&mut npc_props.behavior_tree_id, Some(id.clone()), id).changed();
+                                                    }
+                                                });
+
+                                            egui::ComboBox::from_label("Attack and defense stat")
+                                                .selected_text(option_name(&stat_options, &npc_props.stat_id))
+                                                .show_ui(ui, |ui| {
+                                                    changed |= ui.selectable_value(&mut npc_props.stat_id, None, "None").changed();
+                                                    for (id, name) in &stat_options {
+                                                        changed |= ui.selectable_value(&mut npc_props.stat_id, Some(id.clone()), name).changed();
+                                                    }
+                                                });
+
+                                            for (label, value) in [
+                                                ("Health", &mut npc_props.health),
+                                                ("Stamina", &mut npc_props.stamina),
+                                                ("Chase speed", &mut npc_props.chase_speed),
+                                                ("Detection radius", &mut npc_props.detection_radius),
+                                                ("Evade speed", &mut npc_props.evade_speed),
+                                                ("Wander radius", &mut npc_props.wander_radius),
+                                                ("Wander speed", &mut npc_props.wander_speed),
+                                            ] {
+                                                ui.horizontal(|ui| {
+                                                    ui.label(label);
+                                                    changed |= ui.add(egui::DragValue::new(value).speed(0.5)).changed();
+                                                });
+                                            }
+
+                                            ui.label("Inventory");
+                                            let mut removed = None;
+                                            for (index, item_id) in npc_props.inventory.iter().enumerate() {
+                                                ui.horizontal(|ui| {
+                                                    ui.label(option_name(&item_options, &Some(item_id.clone())));
+                                                    if ui.small_button("Remove").clicked() {
+                                                        removed = Some(index);
+                                                    }
+                                                });
+                                            }
+                                            if let Some(index) = removed {
+                                                npc_props.inventory.remove(index);
+                                                changed = true;
+                                            }
+                                            egui::ComboBox::from_label("Add item")
+                                                .selected_text("Choose...")
+                                                .show_ui(ui, |ui| {
+                                                    for (id, name) in &item_options {
+                                                        if ui.selectable_label(false, name).clicked() {
+                                                            npc_props.inventory.push(id.clone());
+                                                            changed = true;
+                                                        }
+                                                    }
+                                                });
+
+                                            if changed {
+                                                utilities::update_project_state_component(&project_id, component).expect("Failed to update project state");
+                                            }
+
+                                            // NPCs are set up from these when they're placed
+                                            ui.separator();
+                                            ui.label("Changes apply when the level loads");
+                                            if ui.button("Reload Level").clicked() {
+                                                if let Some(level_id) = current_level_id.clone() {
+                                                    editor.pending_level = Some(LevelChange {
+                                                        level_id,
+                                                        spawn_position: None,
+                                                    });
+                                                }
+                                            }
+                                        }
+                                    },
                                     _ => {
                                         ui.label("This component type is not editable.");
                                     }
@@ -3,13 +3,12 @@ use nalgebra_glm::Vec3;
 use rand::Rng;
 use rapier3d::{parry::query::ShapeCastOptions, prelude::*};
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_timer::Instant;
 
-use crate::core::Transform_2::Transform;
 use crate::helpers::saved_data::AttackStats;
 use crate::model_components::{PlayerCharacter::PlayerCharacter, NPC::NPC};
 
@@ -48,11 +47,9 @@ impl MeleeAttackBehavior {
         query_pipeline: &QueryPipeline,
         attacker_handle: RigidBodyHandle,
         target_handle: RigidBodyHandle,
-        transform: &Transform,
+        current_pos: Vector3<f32>,
     ) -> Option<f32> {
         // Returns damage dealt if attack lands
-        let current_pos = transform.position;
-
         // Get target position
         let target_pos = if let Some(target_body) = rigid_body_set.get(target_handle) {
             Vec3::new(
@@ -125,11 +122,9 @@ impl RangedAttackBehavior {
         query_pipeline: &QueryPipeline,
         attacker_handle: RigidBodyHandle,
         target_handle: RigidBodyHandle,
-        transform: &Transform,
+        current_pos: Vector3<f32>,
     ) -> Option<(f32, Option<(Point3<f32>, Point3<f32>)>)> {
         // Returns (damage, debug_line)
-        let current_pos = transform.position;
-
         // Get target position for distance check (optimization)
         let target_pos = if let Some(target_body) = rigid_body_set.get(target_handle) {
             Vec3::new(
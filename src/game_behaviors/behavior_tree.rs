@@ -0,0 +1,648 @@
+use nalgebra::{Point3, Vector3};
+use rapier3d::prelude::*;
+
+use crate::game_behaviors::attack::{MeleeAttackBehavior, RangedAttackBehavior};
+use crate::game_behaviors::chase::ChaseBehavior;
+use crate::game_behaviors::defense::DefenseBehavior;
+use crate::game_behaviors::evade::EvadeBehavior;
+use crate::game_behaviors::navmesh::NavMesh;
+use crate::game_behaviors::wander::WanderBehavior;
+use crate::helpers::saved_data::{BehaviorNodeData, BehaviorTreeData, CharacterStats, ParallelPolicy, ScriptState};
+
+// Behavior trees built from BehaviorTreeData. Each tick walks the tree from the root: composites and
+// decorators pick which children run, leaves check something or drive one of the game behaviors.
+// Times are game time (the step's dt), so a tree runs the same headless as in the editor.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeStatus {
+    Success,
+    Failure,
+    Running,
+}
+
+// A script leaf waiting on its function. World::step hands these out with the step's events and
+// whoever owns the Rhai engine answers with BehaviorTree::resolve before the next step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptLeafRequest {
+    pub npc_id: String,
+    pub node: usize,
+    pub function: String,
+    pub script: Option<String>, // the NPC's own script when not set
+}
+
+// What a tick gets to see and move
+pub struct TickContext<'a> {
+    pub rigid_body_set: &'a mut RigidBodySet,
+    pub collider_set: &'a ColliderSet,
+    pub query_pipeline: &'a QueryPipeline,
+    pub navmesh: &'a mut NavMesh,
+    pub entity_handle: RigidBodyHandle,
+    pub target_handle: RigidBodyHandle,
+    pub collider: &'a Collider,
+    pub current_pos: Vector3<f32>,
+    pub stats: &'a CharacterStats,
+    pub dt: f32,
+}
+
+pub struct BehaviorTree {
+    pub id: String,
+    pub root: BehaviorNode,
+    pub blackboard: ScriptState, // shared by every node and the script leaves
+    pub elapsed: f32,
+    pub ticks: u64,
+    pub target_distance: f32, // as of the last tick
+    last_hit: Option<f32>,
+    blocking: Option<usize>, // the Defend node running, it gets to block incoming hits
+    animation: &'static str,
+    requests: Vec<(usize, String, Option<String>)>,
+}
+
+pub struct BehaviorNode {
+    pub index: usize, // depth first from the root, script results come back by it
+    pub label: String,
+    pub children: Vec<BehaviorNode>,
+    pub status: Option<NodeStatus>, // from the last tick that reached it
+    pub last_tick: u64,
+    kind: NodeKind,
+    current: usize, // running child of sequences and selectors, successes of repeats, whether a task started
+    timer: f32, // how long a timed task has run, or when a cooldown is over
+    finished: Vec<Option<NodeStatus>>, // parallel children that are done
+}
+
+enum NodeKind {
+    Sequence { reactive: bool },
+    Selector { reactive: bool },
+    Parallel { policy: ParallelPolicy },
+    Inverter,
+    Succeeder,
+    Repeat { times: Option<u32> },
+    Cooldown { seconds: f32 },
+    TimeLimit { seconds: f32 },
+    TargetWithin { distance: f32 },
+    HealthBelow { value: f32 },
+    StaminaBelow { value: f32 },
+    RecentlyHit { seconds: f32 },
+    Check { key: String, value: serde_json::Value },
+    Chase { behavior: ChaseBehavior, stop_distance: f32 },
+    Wander(WanderBehavior),
+    Evade { behavior: EvadeBehavior, seconds: f32 },
+    MeleeAttack(MeleeAttackBehavior),
+    RangedAttack(RangedAttackBehavior),
+    Defend { behavior: DefenseBehavior, seconds: f32 },
+    Wait { seconds: f32 },
+    Set { key: String, value: serde_json::Value },
+    Script { function: String, script: Option<String>, result: Option<NodeStatus> },
+}
+
+// Per-tick state the nodes share
+struct Tick<'a, 'b> {
+    context: &'a mut TickContext<'b>,
+    number: u64,
+    elapsed: f32,
+    target_distance: f32,
+    blackboard: &'a mut ScriptState,
+    last_hit: Option<f32>,
+    blocking: Option<usize>,
+    animation: &'static str,
+    requests: &'a mut Vec<(usize, String, Option<String>)>,
+    damage: f32,
+    debug_line: Option<(Point3<f32>, Point3<f32>)>,
+}
+
+impl BehaviorTree {
+    pub fn new(data: &BehaviorTreeData) -> Self {
+        let mut next_index = 0;
+
+        BehaviorTree {
+            id: data.id.clone(),
+            root: BehaviorNode::build(&data.root, &mut next_index),
+            blackboard: data.blackboard.clone(),
+            elapsed: 0.0,
+            ticks: 0,
+            target_distance: f32::INFINITY,
+            last_hit: None,
+            blocking: None,
+            animation: "Idle",
+            requests: Vec::new(),
+        }
+    }
+
+    // Ticks the tree once. Returns damage dealt to the target, with a debug line for ranged attacks.
+    pub fn update(&mut self, mut context: TickContext) -> Option<(f32, Option<(Point3<f32>, Point3<f32>)>)> {
+        self.elapsed += context.dt;
+        self.ticks += 1;
+        self.target_distance = context
+            .rigid_body_set
+            .get(context.target_handle)
+            .map(|target| context.current_pos.metric_distance(target.translation()))
+            .unwrap_or(f32::INFINITY);
+
+        let mut tick = Tick {
+            context: &mut context,
+            number: self.ticks,
+            elapsed: self.elapsed,
+            target_distance: self.target_distance,
+            blackboard: &mut self.blackboard,
+            last_hit: self.last_hit,
+            blocking: None,
+            animation: "Idle",
+            requests: &mut self.requests,
+            damage: 0.0,
+            debug_line: None,
+        };
+        self.root.tick(&mut tick);
+
+        let (damage, debug_line) = (tick.damage, tick.debug_line);
+        self.blocking = tick.blocking;
+        self.animation = tick.animation;
+
+        if damage > 0.0 || debug_line.is_some() {
+            Some((damage, debug_line))
+        } else {
+            None
+        }
+    }
+
+    // Blocked by a running Defend task, taken in full otherwise. Trees see it with RecentlyHit.
+    pub fn handle_incoming_damage(&mut self, damage: f32, stats: &mut CharacterStats) {
+        self.last_hit = Some(self.elapsed);
+
+        let defense = self.blocking.and_then(|index| self.root.find_mut(index)).and_then(|node| match &mut node.kind {
+            NodeKind::Defend { behavior, .. } => Some(behavior),
+            _ => None,
+        });
+        let (damage_taken, stamina_used) = match defense {
+            Some(defense) => defense.try_block(damage, stats.stamina),
+            None => (damage, 0.0),
+        };
+
+        stats.health = (stats.health - damage_taken).max(0.0);
+        stats.stamina = (stats.stamina - stamina_used).max(0.0);
+
+        println!("NPC Health: {:.2}, Stamina: {:.2}", stats.health, stats.stamina);
+    }
+
+    // Script leaves that ran this tick and want their function called
+    pub fn take_script_requests(&mut self, npc_id: &str) -> Vec<ScriptLeafRequest> {
+        self.requests
+            .drain(..)
+            .map(|(node, function, script)| ScriptLeafRequest {
+                npc_id: npc_id.to_string(),
+                node,
+                function,
+                script,
+            })
+            .collect()
+    }
+
+    // What a script leaf's function decided, the leaf picks it up next tick. Ignored when the leaf
+    // was interrupted in the meantime.
+    pub fn resolve(&mut self, node: usize, status: NodeStatus) {
+        if let Some(node) = self.root.find_mut(node) {
+            if node.status == Some(NodeStatus::Running) {
+                if let NodeKind::Script { result, .. } = &mut node.kind {
+                    *result = Some(status);
+                }
+            }
+        }
+    }
+
+    pub fn get_animation_name(&self) -> &str {
+        self.animation
+    }
+
+    // The navmesh waypoints of whichever movement task ran last
+    pub fn path(&self) -> &[Vector3<f32>] {
+        self.root.path(self.ticks).unwrap_or(&[])
+    }
+}
+
+impl BehaviorNode {
+    fn build(data: &BehaviorNodeData, next_index: &mut usize) -> Self {
+        let index = *next_index;
+        *next_index += 1;
+
+        let (label, kind, children) = match data {
+            BehaviorNodeData::Sequence { children, reactive } => {
+                (if *reactive { "Sequence (reactive)" } else { "Sequence" }.to_string(), NodeKind::Sequence { reactive: *reactive }, Self::build_all(children, next_index))
+            }
+            BehaviorNodeData::Selector { children, reactive } => {
+                (if *reactive { "Selector (reactive)" } else { "Selector" }.to_string(), NodeKind::Selector { reactive: *reactive }, Self::build_all(children, next_index))
+            }
+            BehaviorNodeData::Parallel { children, policy } => {
+                (format!("Parallel ({:?})", policy), NodeKind::Parallel { policy: *policy }, Self::build_all(children, next_index))
+            }
+            BehaviorNodeData::Inverter { child } => ("Inverter".to_string(), NodeKind::Inverter, vec![BehaviorNode::build(child, next_index)]),
+            BehaviorNodeData::Succeeder { child } => ("Succeeder".to_string(), NodeKind::Succeeder, vec![BehaviorNode::build(child, next_index)]),
+            BehaviorNodeData::Repeat { child, times } => {
+                let label = match times {
+                    Some(times) => format!("Repeat x{}", times),
+                    None => "Repeat".to_string(),
+                };
+                (label, NodeKind::Repeat { times: *times }, vec![BehaviorNode::build(child, next_index)])
+            }
+            BehaviorNodeData::Cooldown { child, seconds } => {
+                (format!("Cooldown {}s", seconds), NodeKind::Cooldown { seconds: *seconds }, vec![BehaviorNode::build(child, next_index)])
+            }
+            BehaviorNodeData::TimeLimit { child, seconds } => {
+                (format!("Time limit {}s", seconds), NodeKind::TimeLimit { seconds: *seconds }, vec![BehaviorNode::build(child, next_index)])
+            }
+            BehaviorNodeData::TargetWithin { distance } => {
+                (format!("Target within {}", distance), NodeKind::TargetWithin { distance: *distance }, Vec::new())
+            }
+            BehaviorNodeData::HealthBelow { value } => (format!("Health below {}", value), NodeKind::HealthBelow { value: *value }, Vec::new()),
+            BehaviorNodeData::StaminaBelow { value } => (format!("Stamina below {}", value), NodeKind::StaminaBelow { value: *value }, Vec::new()),
+            BehaviorNodeData::RecentlyHit { seconds } => {
+                (format!("Hit in the last {}s", seconds), NodeKind::RecentlyHit { seconds: *seconds }, Vec::new())
+            }
+            BehaviorNodeData::Check { key, value } => {
+                (format!("{} is {}", key, value), NodeKind::Check { key: key.clone(), value: value.clone() }, Vec::new())
+            }
+            BehaviorNodeData::Chase { speed, detection_radius, stop_distance } => {
+                let mut behavior = ChaseBehavior::new(*speed, *detection_radius);
+                behavior.min_distance = *stop_distance;
+                ("Chase".to_string(), NodeKind::Chase { behavior, stop_distance: *stop_distance }, Vec::new())
+            }
+            BehaviorNodeData::Wander { radius, speed } => ("Wander".to_string(), NodeKind::Wander(WanderBehavior::new(*radius, *speed)), Vec::new()),
+            BehaviorNodeData::Evade { speed, distance, seconds } => {
+                ("Evade".to_string(), NodeKind::Evade { behavior: EvadeBehavior::new(*speed, *distance), seconds: *seconds }, Vec::new())
+            }
+            BehaviorNodeData::Attack { stats, ranged: false } => {
+                ("Melee attack".to_string(), NodeKind::MeleeAttack(MeleeAttackBehavior::new(stats.clone())), Vec::new())
+            }
+            BehaviorNodeData::Attack { stats, ranged: true } => {
+                ("Ranged attack".to_string(), NodeKind::RangedAttack(RangedAttackBehavior::new(stats.clone())), Vec::new())
+            }
+            BehaviorNodeData::Defend { block_chance, seconds } => {
+                ("Defend".to_string(), NodeKind::Defend { behavior: DefenseBehavior::new(*block_chance), seconds: *seconds }, Vec::new())
+            }
+            BehaviorNodeData::Wait { seconds } => (format!("Wait {}s", seconds), NodeKind::Wait { seconds: *seconds }, Vec::new()),
+            BehaviorNodeData::Set { key, value } => {
+                (format!("Set {} to {}", key, value), NodeKind::Set { key: key.clone(), value: value.clone() }, Vec::new())
+            }
+            BehaviorNodeData::Script { function, script } => (
+                format!("Script {}", function),
+                NodeKind::Script { function: function.clone(), script: script.clone(), result: None },
+                Vec::new(),
+            ),
+        };
+
+        BehaviorNode {
+            index,
+            label,
+            children,
+            status: None,
+            last_tick: 0,
+            kind,
+            current: 0,
+            timer: 0.0,
+            finished: Vec::new(),
+        }
+    }
+
+    fn build_all(children: &[BehaviorNodeData], next_index: &mut usize) -> Vec<BehaviorNode> {
+        children.iter().map(|child| BehaviorNode::build(child, next_index)).collect()
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    fn find_mut(&mut self, index: usize) -> Option<&mut BehaviorNode> {
+        if self.index == index {
+            return Some(self);
+        }
+        self.children.iter_mut().find_map(|child| child.find_mut(index))
+    }
+
+    fn path(&self, tick: u64) -> Option<&[Vector3<f32>]> {
+        if self.last_tick != tick {
+            return None;
+        }
+        match &self.kind {
+            NodeKind::Chase { behavior, .. } => Some(behavior.path()),
+            NodeKind::Wander(behavior) => Some(behavior.path()),
+            _ => self.children.iter().find_map(|child| child.path(tick)),
+        }
+    }
+
+    // Forgets a run that was cut short, so the node starts over next time
+    fn reset(&mut self) {
+        if self.status == Some(NodeStatus::Running) {
+            for child in self.children.iter_mut() {
+                child.reset();
+            }
+        }
+
+        self.status = None;
+        self.current = 0;
+        self.finished.clear();
+        match &mut self.kind {
+            NodeKind::Cooldown { .. } => {} // still cooling down
+            NodeKind::Script { result, .. } => *result = None,
+            _ => self.timer = 0.0,
+        }
+    }
+
+    fn tick(&mut self, tick: &mut Tick) -> NodeStatus {
+        self.last_tick = tick.number;
+
+        let status = match self.kind {
+            NodeKind::Sequence { reactive } => self.tick_composite(tick, reactive, NodeStatus::Success),
+            NodeKind::Selector { reactive } => self.tick_composite(tick, reactive, NodeStatus::Failure),
+            NodeKind::Parallel { policy } => self.tick_parallel(tick, policy),
+            NodeKind::Inverter => match self.children[0].tick(tick) {
+                NodeStatus::Success => NodeStatus::Failure,
+                NodeStatus::Failure => NodeStatus::Success,
+                NodeStatus::Running => NodeStatus::Running,
+            },
+            NodeKind::Succeeder => match self.children[0].tick(tick) {
+                NodeStatus::Running => NodeStatus::Running,
+                _ => NodeStatus::Success,
+            },
+            NodeKind::Repeat { times } => match self.children[0].tick(tick) {
+                NodeStatus::Success => {
+                    self.current += 1;
+                    if times.map_or(false, |times| self.current as u32 >= times) {
+                        self.current = 0;
+                        NodeStatus::Success
+                    } else {
+                        NodeStatus::Running
+                    }
+                }
+                NodeStatus::Failure => {
+                    self.current = 0;
+                    NodeStatus::Failure
+                }
+                NodeStatus::Running => NodeStatus::Running,
+            },
+            NodeKind::Cooldown { seconds } => {
+                if self.current == 0 && tick.elapsed < self.timer {
+                    NodeStatus::Failure
+                } else {
+                    let status = self.children[0].tick(tick);
+                    if status == NodeStatus::Running {
+                        self.current = 1;
+                    } else {
+                        self.current = 0;
+                        self.timer = tick.elapsed + seconds;
+                    }
+                    status
+                }
+            }
+            NodeKind::TimeLimit { seconds } => {
+                self.timer += tick.context.dt;
+                if self.timer > seconds {
+                    self.children[0].reset();
+                    self.timer = 0.0;
+                    NodeStatus::Failure
+                } else {
+                    let status = self.children[0].tick(tick);
+                    if status != NodeStatus::Running {
+                        self.timer = 0.0;
+                    }
+                    status
+                }
+            }
+            _ => self.tick_leaf(tick),
+        };
+
+        self.status = Some(status);
+        status
+    }
+
+    // Sequences carry on through successes and selectors through failures, anything else ends them
+    fn tick_composite(&mut self, tick: &mut Tick, reactive: bool, carry_on: NodeStatus) -> NodeStatus {
+        let start = if reactive { 0 } else { self.current };
+
+        for index in start..self.children.len() {
+            let status = self.children[index].tick(tick);
+            if status == carry_on {
+                continue;
+            }
+
+            // an earlier child took over, the one that was running stops
+            if index < self.current {
+                self.children[self.current].reset();
+            }
+            self.current = if status == NodeStatus::Running { index } else { 0 };
+            return status;
+        }
+
+        self.current = 0;
+        carry_on
+    }
+
+    fn tick_parallel(&mut self, tick: &mut Tick, policy: ParallelPolicy) -> NodeStatus {
+        let count = self.children.len();
+        if self.finished.len() != count {
+            self.finished = vec![None; count];
+        }
+
+        for (child, finished) in self.children.iter_mut().zip(self.finished.iter_mut()) {
+            if finished.is_none() {
+                let status = child.tick(tick);
+                if status != NodeStatus::Running {
+                    *finished = Some(status);
+                }
+            }
+        }
+
+        let successes = self.finished.iter().filter(|s| **s == Some(NodeStatus::Success)).count();
+        let failures = self.finished.iter().filter(|s| **s == Some(NodeStatus::Failure)).count();
+        let status = match policy {
+            ParallelPolicy::RequireOne if successes > 0 => NodeStatus::Success,
+            ParallelPolicy::RequireOne if failures == count => NodeStatus::Failure,
+            ParallelPolicy::RequireAll if failures > 0 => NodeStatus::Failure,
+            ParallelPolicy::RequireAll if successes == count => NodeStatus::Success,
+            _ => NodeStatus::Running,
+        };
+
+        if status != NodeStatus::Running {
+            for (child, finished) in self.children.iter_mut().zip(self.finished.iter()) {
+                if finished.is_none() {
+                    child.reset();
+                }
+            }
+            self.finished.clear();
+        }
+        status
+    }
+
+    fn tick_leaf(&mut self, tick: &mut Tick) -> NodeStatus {
+        let success_if = |condition: bool| if condition { NodeStatus::Success } else { NodeStatus::Failure };
+        let dt = tick.context.dt;
+
+        match &mut self.kind {
+            NodeKind::TargetWithin { distance } => success_if(tick.target_distance <= *distance),
+            NodeKind::HealthBelow { value } => success_if(tick.context.stats.health < *value),
+            NodeKind::StaminaBelow { value } => success_if(tick.context.stats.stamina < *value),
+            NodeKind::RecentlyHit { seconds } => success_if(tick.last_hit.map_or(false, |hit| tick.elapsed - hit <= *seconds)),
+            NodeKind::Check { key, value } => success_if(tick.blackboard.get(key.as_str()).unwrap_or(&serde_json::Value::Null) == value),
+            NodeKind::Chase { behavior, stop_distance } => {
+                if tick.target_distance > behavior.detection_radius {
+                    stop(tick.context);
+                    return NodeStatus::Failure;
+                }
+                if tick.target_distance <= *stop_distance {
+                    stop(tick.context);
+                    return NodeStatus::Success;
+                }
+
+                let context = &mut *tick.context;
+                behavior.update(
+                    context.rigid_body_set,
+                    context.collider_set,
+                    context.query_pipeline,
+                    context.navmesh,
+                    context.entity_handle,
+                    context.target_handle,
+                    context.collider,
+                    context.current_pos,
+                    dt,
+                );
+                tick.animation = "Walking";
+                NodeStatus::Running
+            }
+            NodeKind::Wander(behavior) => {
+                let context = &mut *tick.context;
+                behavior.update(
+                    context.rigid_body_set,
+                    context.collider_set,
+                    context.query_pipeline,
+                    context.navmesh,
+                    context.entity_handle,
+                    context.collider,
+                    context.current_pos,
+                    dt,
+                );
+                tick.animation = "Walking";
+                NodeStatus::Running
+            }
+            NodeKind::Evade { behavior, seconds } => {
+                // one dodge, then give it time to carry the NPC clear
+                if self.current == 0 {
+                    let context = &mut *tick.context;
+                    let evading = behavior.update(
+                        context.rigid_body_set,
+                        context.collider_set,
+                        context.query_pipeline,
+                        context.navmesh,
+                        context.entity_handle,
+                        context.target_handle,
+                        context.current_pos,
+                        dt,
+                    );
+                    if !evading {
+                        return NodeStatus::Failure;
+                    }
+                    self.current = 1;
+                }
+
+                tick.animation = "Evade";
+                self.timer += dt;
+                if self.timer >= *seconds {
+                    self.timer = 0.0;
+                    self.current = 0;
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Running
+                }
+            }
+            NodeKind::MeleeAttack(behavior) => {
+                if tick.target_distance > behavior.stats.range {
+                    return NodeStatus::Failure;
+                }
+
+                stop(tick.context);
+                tick.animation = "Attack";
+                let context = &mut *tick.context;
+                let damage = behavior.update(
+                    context.rigid_body_set,
+                    context.collider_set,
+                    context.query_pipeline,
+                    context.entity_handle,
+                    context.target_handle,
+                    context.current_pos,
+                );
+
+                match damage {
+                    Some(damage) => {
+                        tick.damage += damage;
+                        NodeStatus::Success
+                    }
+                    None => NodeStatus::Running,
+                }
+            }
+            NodeKind::RangedAttack(behavior) => {
+                if tick.target_distance > behavior.stats.range {
+                    return NodeStatus::Failure;
+                }
+
+                stop(tick.context);
+                tick.animation = "Shoot";
+                let context = &mut *tick.context;
+                let shot = behavior.update(
+                    context.rigid_body_set,
+                    context.collider_set,
+                    context.query_pipeline,
+                    context.entity_handle,
+                    context.target_handle,
+                    context.current_pos,
+                );
+
+                match shot {
+                    Some((damage, debug_line)) => {
+                        tick.damage += damage;
+                        tick.debug_line = debug_line.or(tick.debug_line);
+                        NodeStatus::Success
+                    }
+                    None => NodeStatus::Running,
+                }
+            }
+            NodeKind::Defend { seconds, .. } => {
+                stop(tick.context);
+                tick.animation = "Defend";
+                self.timer += dt;
+                if self.timer >= *seconds {
+                    self.timer = 0.0;
+                    NodeStatus::Success
+                } else {
+                    tick.blocking = Some(self.index);
+                    NodeStatus::Running
+                }
+            }
+            NodeKind::Wait { seconds } => {
+                self.timer += dt;
+                if self.timer >= *seconds {
+                    self.timer = 0.0;
+                    NodeStatus::Success
+                } else {
+                    NodeStatus::Running
+                }
+            }
+            NodeKind::Set { key, value } => {
+                tick.blackboard.insert(key.clone(), value.clone());
+                NodeStatus::Success
+            }
+            NodeKind::Script { function, script, result } => match result.take() {
+                Some(status) if status != NodeStatus::Running => status,
+                _ => {
+                    tick.requests.push((self.index, function.clone(), script.clone()));
+                    NodeStatus::Running
+                }
+            },
+            _ => NodeStatus::Failure,
+        }
+    }
+}
+
+// Stands still, keeping whatever gravity is doing
+fn stop(context: &mut TickContext) {
+    if let Some(rigid_body) = context.rigid_body_set.get_mut(context.entity_handle) {
+        let mut linvel = *rigid_body.linvel();
+        linvel.x = 0.0;
+        linvel.z = 0.0;
+        rigid_body.set_linvel(linvel, true);
+    }
+}
@@ -0,0 +1,64 @@
+use egui::{Color32, RichText};
+
+use crate::core::World::World;
+use crate::game_behaviors::behavior_tree::{BehaviorNode, BehaviorTree, NodeStatus};
+
+// Every NPC running a behavior tree, with its blackboard and the nodes the last tick reached
+// colored by how they came out: green succeeded, red failed, yellow still running.
+pub fn behavior_tree_view(ui: &mut egui::Ui, world: &World) {
+    let mut any = false;
+    for npc in &world.npcs {
+        let tree = match npc.test_behavior.tree() {
+            Some(tree) => tree,
+            None => continue,
+        };
+        any = true;
+
+        egui::CollapsingHeader::new(format!("{} ({})", npc.name, tree.id))
+            .id_salt(&npc.id)
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.label(format!("tick {}, target {:.1} away", tree.ticks, tree.target_distance));
+
+                if !tree.blackboard.is_empty() {
+                    let mut keys: Vec<_> = tree.blackboard.keys().collect();
+                    keys.sort();
+                    egui::CollapsingHeader::new("Blackboard").id_salt((&npc.id, "blackboard")).show(ui, |ui| {
+                        for key in keys {
+                            ui.monospace(format!("{} = {}", key, tree.blackboard[key]));
+                        }
+                    });
+                }
+
+                node_view(ui, tree, &tree.root);
+            });
+    }
+
+    if !any {
+        ui.label("No NPCs are running behavior trees");
+    }
+}
+
+fn node_view(ui: &mut egui::Ui, tree: &BehaviorTree, node: &BehaviorNode) {
+    let color = match node.status {
+        _ if node.last_tick != tree.ticks => Color32::GRAY,
+        Some(NodeStatus::Success) => Color32::from_rgb(90, 200, 90),
+        Some(NodeStatus::Failure) => Color32::from_rgb(220, 80, 80),
+        Some(NodeStatus::Running) => Color32::YELLOW,
+        None => Color32::GRAY,
+    };
+    let text = RichText::new(&node.label).color(color).monospace();
+
+    if node.children.is_empty() {
+        ui.label(text);
+    } else {
+        egui::CollapsingHeader::new(text)
+            .id_salt(node.index)
+            .default_open(true)
+            .show(ui, |ui| {
+                for child in &node.children {
+                    node_view(ui, tree, child);
+                }
+            });
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::helpers::saved_data::ScriptState;
+
+// What an NPC remembers about the player's conversations with it
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NpcMemory {
+    pub vars: ScriptState,
+    pub visits: HashMap<String, u32>, // dialogue node -> times the conversation was there
+}
+
+// Variables dialogue reads and writes, world-wide and per NPC. Kept for the whole playthrough
+// and saved with the game, see helpers::save_game.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Blackboard {
+    pub globals: ScriptState,
+    pub npcs: HashMap<String, NpcMemory>, // keyed by NPC component id
+}
+
+impl Blackboard {
+    pub fn global(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.globals.insert(name.to_string(), value);
+    }
+
+    pub fn var(&self, npc_id: &str, name: &str) -> Option<&Value> {
+        self.npcs.get(npc_id).and_then(|memory| memory.vars.get(name))
+    }
+
+    pub fn set_var(&mut self, npc_id: &str, name: &str, value: Value) {
+        self.npcs.entry(npc_id.to_string()).or_default().vars.insert(name.to_string(), value);
+    }
+
+    // An NPC's variable, or a global one when npc_id is None
+    pub fn get(&self, npc_id: Option<&str>, name: &str) -> Option<&Value> {
+        match npc_id {
+            Some(npc_id) => self.var(npc_id, name),
+            None => self.global(name),
+        }
+    }
+
+    pub fn set(&mut self, npc_id: Option<&str>, name: &str, value: Value) {
+        match npc_id {
+            Some(npc_id) => self.set_var(npc_id, name, value),
+            None => self.set_global(name, value),
+        }
+    }
+
+    pub fn visit(&mut self, npc_id: &str, node: &str) {
+        if node.is_empty() {
+            return;
+        }
+        *self.npcs.entry(npc_id.to_string()).or_default().visits.entry(node.to_string()).or_default() += 1;
+    }
+
+    pub fn times_visited(&self, npc_id: &str, node: &str) -> u32 {
+        self.npcs
+            .get(npc_id)
+            .and_then(|memory| memory.visits.get(node))
+            .copied()
+            .unwrap_or(0)
+    }
+}
@@ -2,7 +2,7 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
@@ -12,7 +12,7 @@ use nalgebra::{vector, ComplexField, Vector3};
 use nalgebra_glm::Vec3;
 use rapier3d::{parry::query::ShapeCastOptions, prelude::*};
 
-use crate::core::Transform_2::Transform;
+use crate::game_behaviors::navmesh::{NavMesh, NavSource, PathFollower};
 
 pub struct ChaseBehavior {
     // Configuration
@@ -25,6 +25,7 @@ pub struct ChaseBehavior {
     // Internal state
     target_handle: Option<RigidBodyHandle>,
     last_update: Instant,
+    path: PathFollower,
 }
 
 impl ChaseBehavior {
@@ -37,6 +38,7 @@ impl ChaseBehavior {
             prediction_time: 0.5,
             target_handle: None,
             last_update: Instant::now(),
+            path: PathFollower::default(),
         }
     }
 
@@ -49,14 +51,13 @@ impl ChaseBehavior {
         rigid_body_set: &mut RigidBodySet,
         collider_set: &ColliderSet,
         query_pipeline: &QueryPipeline,
+        navmesh: &mut NavMesh,
         rigid_body_handle: RigidBodyHandle,
         target_handle: RigidBodyHandle,
         collider: &Collider,
-        transform: &mut Transform,
+        current_pos: Vector3<f32>,
         dt: f32,
     ) {
-        let current_pos = transform.position;
-
         // Get target position and velocity
         let (target_pos, target_vel) = if let Some(target_body) = rigid_body_set.get(target_handle)
         {
@@ -79,6 +80,19 @@ impl ChaseBehavior {
                 target_vel.z * self.prediction_time,
             );
 
+        // Follow the navmesh around walls and houses, straight at the target when there's no path
+        let target_distance = current_pos.metric_distance(&target_pos);
+        let source = NavSource {
+            bodies: rigid_body_set,
+            colliders: collider_set,
+            query_pipeline,
+        };
+        if target_distance <= self.detection_radius && self.path.seek(navmesh, &source, current_pos, predicted_pos, self.max_slope) {
+            let direction = self.path.direction(current_pos).unwrap_or_else(|| (predicted_pos - current_pos).normalize());
+            self.steer(rigid_body_set, rigid_body_handle, direction, target_distance, dt);
+            return;
+        }
+
         // Calculate direction to predicted position
         let direction = (predicted_pos - current_pos).normalize();
 
@@ -125,6 +139,14 @@ impl ChaseBehavior {
 
         // println!("Chase update distance to target: {:?}", distance);
 
+        self.steer(rigid_body_set, rigid_body_handle, direction, distance, dt);
+    }
+
+    pub fn path(&self) -> &[Vector3<f32>] {
+        self.path.remaining()
+    }
+
+    fn steer(&self, rigid_body_set: &mut RigidBodySet, rigid_body_handle: RigidBodyHandle, direction: Vector3<f32>, distance: f32, dt: f32) {
         // Only move if we're outside the minimum distance
         if distance > self.min_distance {
             let speed_factor = if distance > self.detection_radius {
@@ -3,7 +3,7 @@ use nalgebra_glm::Vec3;
 use rand::Rng;
 use rapier3d::{parry::query::ShapeCastOptions, prelude::*};
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
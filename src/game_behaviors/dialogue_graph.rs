@@ -0,0 +1,323 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::game_behaviors::blackboard::Blackboard;
+use crate::game_behaviors::dialogue_state::{DialogueOption, DialogueState};
+use crate::game_behaviors::inventory::Inventory;
+use crate::game_behaviors::quests::{matches_item, QuestLog};
+use crate::helpers::pak;
+use crate::helpers::project_error::ProjectError;
+use crate::helpers::saved_data::{
+    ComponentData, ComponentKind, DialogueAction, DialogueCondition, DialogueData, SavedState,
+};
+
+// Nodes a graph links to but doesn't have are left to the NPC's script, like script-only dialogue
+pub const INTERACT_HOOK: &str = "interact";
+
+// Every dialogue graph of the project by id, from SavedState::dialogues and dialogue_files
+#[derive(Default)]
+pub struct DialogueLibrary {
+    pub dialogues: HashMap<String, DialogueData>,
+}
+
+impl DialogueLibrary {
+    pub fn new(dialogues: Vec<DialogueData>) -> Self {
+        DialogueLibrary {
+            dialogues: dialogues.into_iter().map(|d| (d.id.clone(), d)).collect(),
+        }
+    }
+
+    // Files that can't be read or parsed come back as errors, everything else still loads
+    pub fn load(project_id: &str, saved_state: &SavedState) -> (Self, Vec<ProjectError>) {
+        let mut dialogues = saved_state.dialogues.clone().unwrap_or_default();
+        let mut errors = Vec::new();
+
+        for path in saved_state.dialogue_files.iter().flatten() {
+            let loaded = pak::read_project_file(project_id, path)
+                .map_err(|reason| ProjectError::MissingFile { path: path.clone(), reason })
+                .and_then(|bytes| parse_dialogue(&String::from_utf8_lossy(&bytes), path));
+
+            match loaded {
+                Ok(dialogue) => dialogues.push(dialogue),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        (DialogueLibrary::new(dialogues), errors)
+    }
+
+    pub fn get(&self, dialogue_id: &str) -> Option<&DialogueData> {
+        self.dialogues.get(dialogue_id)
+    }
+}
+
+pub fn parse_dialogue(json: &str, path: &str) -> Result<DialogueData, ProjectError> {
+    serde_json::from_str(json).map_err(|e| ProjectError::Parse {
+        path: path.to_string(),
+        message: e.to_string(),
+        line: e.line(),
+        column: e.column(),
+    })
+}
+
+// What conditions and actions can see and change
+pub struct DialogueContext<'a> {
+    pub npc_id: &'a str, // whose memory Var conditions and actions use
+    pub npc_name: &'a str, // who's speaking when a node doesn't say
+    pub blackboard: &'a mut Blackboard,
+    pub quests: &'a mut QuestLog,
+    pub inventory: Option<&'a mut Inventory>,
+    pub items: &'a [ComponentData], // what GiveItem can hand out, the current level's components
+}
+
+// What the caller has to do once the graph has moved
+#[derive(Clone, Debug, PartialEq)]
+pub enum DialogueStep {
+    Shown, // the node's text and options are in the DialogueState
+    Script(String), // call this function of the NPC's script with the dialogue
+    Closed,
+}
+
+// Opens a conversation at the graph's start node
+pub fn start(dialogue: &DialogueData, state: &mut DialogueState, context: &mut DialogueContext) -> DialogueStep {
+    state.dialogue_id = Some(dialogue.id.clone());
+
+    match dialogue.start_node.clone().or_else(|| dialogue.nodes.first().map(|n| n.id.clone())) {
+        Some(node_id) => enter(dialogue, &node_id, state, context),
+        None => close(state),
+    }
+}
+
+// Runs the option at `index` of state.options: its actions, then the node it leads to
+pub fn choose(dialogue: &DialogueData, state: &mut DialogueState, index: usize, context: &mut DialogueContext) -> DialogueStep {
+    let option = match state.options.get(index) {
+        Some(option) => option.clone(),
+        None => return close(state),
+    };
+
+    let choice = option.choice.and_then(|choice| {
+        dialogue
+            .nodes
+            .iter()
+            .find(|n| n.id == state.current_node)
+            .and_then(|n| n.options.get(choice))
+    });
+    if let Some(choice) = choice {
+        run_actions(&choice.actions, context);
+    }
+
+    if option.next_node.is_empty() {
+        return close(state);
+    }
+    enter(dialogue, &option.next_node, state, context)
+}
+
+pub fn close(state: &mut DialogueState) -> DialogueStep {
+    state.is_open = false;
+    state.options.clear();
+    state.dialogue_id = None;
+    state.selected_option_index = 0;
+    state.ui_dirty = true;
+    DialogueStep::Closed
+}
+
+fn enter(dialogue: &DialogueData, node_id: &str, state: &mut DialogueState, context: &mut DialogueContext) -> DialogueStep {
+    state.current_node = node_id.to_string();
+    state.selected_option_index = 0;
+    state.ui_dirty = true;
+
+    let node = match dialogue.nodes.iter().find(|n| n.id == node_id) {
+        Some(node) => node,
+        None => return DialogueStep::Script(INTERACT_HOOK.to_string()),
+    };
+
+    run_actions(&node.actions, context);
+    if let Some(hook) = &node.script_hook {
+        return DialogueStep::Script(hook.clone());
+    }
+
+    state.is_open = true;
+    state.current_text = node.text.clone();
+    state.npc_name = node.speaker.clone().unwrap_or_else(|| context.npc_name.to_string());
+    let shown: &DialogueContext = context;
+    let options: Vec<DialogueOption> = node
+        .options
+        .iter()
+        .enumerate()
+        .filter(|(_, option)| option.conditions.iter().all(|c| holds(c, shown)))
+        .map(|(index, option)| DialogueOption {
+            text: option.text.clone(),
+            next_node: option.next_node.clone().unwrap_or_default(),
+            choice: Some(index),
+        })
+        .collect();
+    state.options = options;
+    // after the options, so Visited conditions look at earlier conversations
+    context.blackboard.visit(context.npc_id, node_id);
+
+    DialogueStep::Shown
+}
+
+// None for global variables
+fn owner<'a>(context: &DialogueContext<'a>, global: bool) -> Option<&'a str> {
+    if global { None } else { Some(context.npc_id) }
+}
+
+fn holds(condition: &DialogueCondition, context: &DialogueContext) -> bool {
+    let var = move |name: &str, global: bool| context.blackboard.get(owner(context, global), name);
+
+    match condition {
+        DialogueCondition::VarEquals { name, value, global } => var(name, *global).map_or(false, |v| values_equal(v, value)),
+        DialogueCondition::VarNotEquals { name, value, global } => !var(name, *global).map_or(false, |v| values_equal(v, value)),
+        DialogueCondition::VarAtLeast { name, value, global } => {
+            var(name, *global).and_then(Value::as_f64).map_or(false, |v| v >= *value)
+        }
+        DialogueCondition::Visited { node } => context.blackboard.times_visited(context.npc_id, node) > 0,
+        DialogueCondition::NotVisited { node } => context.blackboard.times_visited(context.npc_id, node) == 0,
+        DialogueCondition::QuestNotStarted { quest_id } => context.quests.progress(quest_id).is_none(),
+        DialogueCondition::QuestActive { quest_id } => context.quests.is_active(quest_id),
+        DialogueCondition::QuestComplete { quest_id } => context.quests.is_complete(quest_id),
+        DialogueCondition::HasItem { item_id } => context.inventory.as_ref().map_or(false, |inventory| {
+            inventory
+                .items
+                .iter()
+                .chain(inventory.equipped_weapon.iter())
+                .chain(inventory.equipped_armor.iter())
+                .any(|item| matches_item(item, item_id))
+        }),
+    }
+}
+
+fn run_actions(actions: &[DialogueAction], context: &mut DialogueContext) {
+    for action in actions {
+        match action {
+            DialogueAction::SetVar { name, value, global } => {
+                let npc_id = owner(context, *global);
+                context.blackboard.set(npc_id, name, value.clone());
+            }
+            DialogueAction::AddVar { name, amount, global } => {
+                let npc_id = owner(context, *global);
+                let current = context.blackboard.get(npc_id, name).and_then(Value::as_f64).unwrap_or(0.0);
+                context.blackboard.set(npc_id, name, number(current + amount));
+            }
+            DialogueAction::StartQuest { quest_id } => {
+                context.quests.start(quest_id);
+            }
+            DialogueAction::AdvanceQuest { quest_id } => {
+                context.quests.advance(quest_id);
+            }
+            DialogueAction::GiveItem { item_id } => {
+                let item = context
+                    .items
+                    .iter()
+                    .find(|c| c.kind == Some(ComponentKind::Collectable) && matches_item(c, item_id));
+
+                match (item, context.inventory.as_deref_mut()) {
+                    (Some(item), Some(inventory)) => inventory.add_item(item),
+                    _ => println!("Couldn't give item {}", item_id),
+                }
+            }
+        }
+    }
+}
+
+// Whole numbers stay ints, so counters read back the way writers typed them
+fn number(value: f64) -> Value {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        Value::from(value as i64)
+    } else {
+        serde_json::Number::from_f64(value).map(Value::Number).unwrap_or(Value::Null)
+    }
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// Mistakes a writer can make in a graph. Unreachable nodes still work, the rest break conversations.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DialogueIssue {
+    NoNodes,
+    DuplicateNode(String),
+    MissingStart(String),
+    DanglingLink { node_id: String, target: String },
+    Unreachable(String),
+}
+
+impl DialogueIssue {
+    pub fn is_error(&self) -> bool {
+        !matches!(self, DialogueIssue::Unreachable(_))
+    }
+}
+
+impl fmt::Display for DialogueIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DialogueIssue::NoNodes => write!(f, "has no nodes"),
+            DialogueIssue::DuplicateNode(node_id) => write!(f, "has more than one node {}", node_id),
+            DialogueIssue::MissingStart(node_id) => write!(f, "starts at node {}, which doesn't exist", node_id),
+            DialogueIssue::DanglingLink { node_id, target } => write!(f, "node {} leads to node {}, which doesn't exist", node_id, target),
+            DialogueIssue::Unreachable(node_id) => write!(f, "node {} can't be reached from the start", node_id),
+        }
+    }
+}
+
+// Links are option next_nodes and the script_exits of custom nodes
+pub fn dialogue_issues(dialogue: &DialogueData) -> Vec<DialogueIssue> {
+    let mut issues = Vec::new();
+    if dialogue.nodes.is_empty() {
+        issues.push(DialogueIssue::NoNodes);
+        return issues;
+    }
+
+    let mut ids = HashSet::new();
+    for node in &dialogue.nodes {
+        if !ids.insert(node.id.as_str()) {
+            issues.push(DialogueIssue::DuplicateNode(node.id.clone()));
+        }
+    }
+
+    let links = |node_id: &str| -> Vec<String> {
+        dialogue
+            .nodes
+            .iter()
+            .filter(|n| n.id == node_id)
+            .flat_map(|n| n.options.iter().filter_map(|o| o.next_node.clone()).chain(n.script_exits.iter().cloned()))
+            .collect()
+    };
+
+    for node in &dialogue.nodes {
+        for target in links(&node.id) {
+            if !ids.contains(target.as_str()) {
+                issues.push(DialogueIssue::DanglingLink { node_id: node.id.clone(), target });
+            }
+        }
+    }
+
+    let start = dialogue.start_node.clone().unwrap_or_else(|| dialogue.nodes[0].id.clone());
+    if !ids.contains(start.as_str()) {
+        issues.push(DialogueIssue::MissingStart(start));
+        return issues;
+    }
+
+    let mut reached = HashSet::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(node_id) = queue.pop_front() {
+        if reached.insert(node_id.clone()) {
+            queue.extend(links(&node_id).into_iter().filter(|t| ids.contains(t.as_str())));
+        }
+    }
+
+    for node in &dialogue.nodes {
+        if !reached.contains(&node.id) && !issues.contains(&DialogueIssue::Unreachable(node.id.clone())) {
+            issues.push(DialogueIssue::Unreachable(node.id.clone()));
+        }
+    }
+
+    issues
+}
@@ -5,6 +5,8 @@ use serde::{Deserialize, Serialize};
 pub struct DialogueOption {
     pub text: String,
     pub next_node: String,
+    #[serde(default)]
+    pub choice: Option<usize>, // index into the graph node's options, for options shown from a DialogueData
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -16,6 +18,8 @@ pub struct DialogueState {
     pub current_node: String,
     pub current_npc_id: String,
     pub selected_option_index: usize,
+    #[serde(default)]
+    pub dialogue_id: Option<String>, // the DialogueData being run, None for script-only conversations
     #[serde(skip)]
     pub ui_ids: Vec<Uuid>,
     #[serde(skip)]
@@ -3,21 +3,22 @@ use nalgebra_glm::Vec3;
 use rand::Rng;
 use rapier3d::{parry::query::ShapeCastOptions, prelude::*};
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_timer::Instant;
 
-use crate::core::Transform_2::Transform;
 use crate::model_components::{PlayerCharacter::PlayerCharacter, NPC::NPC};
 
 use super::chase::ChaseBehavior;
+use super::navmesh::{NavMesh, NavSource};
 
 pub struct EvadeBehavior {
     pub speed: f32,
     pub evade_distance: f32,
     pub cooldown: f32,
+    pub max_slope: f32, // Maximum traversable slope angle
     last_evade: Instant,
     // rng: rand::rngs::ThreadRng,
 }
@@ -28,6 +29,7 @@ impl EvadeBehavior {
             speed,
             evade_distance,
             cooldown: 1.0,
+            max_slope: 45.0,
             last_evade: Instant::now(),
             // rng: rand::thread_rng(),
         }
@@ -38,9 +40,10 @@ impl EvadeBehavior {
         rigid_body_set: &mut RigidBodySet,
         collider_set: &ColliderSet,
         query_pipeline: &QueryPipeline,
+        navmesh: &mut NavMesh,
         evader_handle: RigidBodyHandle,
         threat_handle: RigidBodyHandle,
-        transform: &Transform,
+        current_pos: Vector3<f32>,
         dt: f32,
     ) -> bool {
         // Returns true if currently evading
@@ -50,8 +53,6 @@ impl EvadeBehavior {
 
         let mut rng = rand::thread_rng();
 
-        let current_pos = transform.position;
-
         // Get threat position
         let threat_pos = if let Some(threat_body) = rigid_body_set.get(threat_handle) {
             Vec3::new(
@@ -97,7 +98,16 @@ impl EvadeBehavior {
             )
             .is_some();
 
-        if !obstacle_detected {
+        // don't dodge off a ledge or into a corner the navmesh can't walk out of
+        let source = NavSource {
+            bodies: rigid_body_set,
+            colliders: collider_set,
+            query_pipeline,
+        };
+        let landing = current_pos + evade_direction * self.evade_distance;
+        let walkable = navmesh.line_walkable(&source, current_pos, landing, self.max_slope);
+
+        if !obstacle_detected && walkable {
             // Apply evade movement
             if let Some(rigid_body) = rigid_body_set.get_mut(evader_handle) {
                 let movement = evade_direction * self.speed * dt;
@@ -117,7 +117,7 @@ fn open_inventory(editor: &mut Editor, device: &wgpu::Device, queue: &wgpu::Queu
     
     // Items
     if let Some(state) = &editor.renderer_state {
-        if let Some(player) = &state.player_character {
+        if let Some(player) = &state.world.player_character {
              for (i, item) in player.inventory.items.iter().enumerate() {
                 let item_id = item.id.clone();
                 let item_ui_id = Uuid::new_v4();
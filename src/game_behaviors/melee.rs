@@ -3,18 +3,18 @@ use nalgebra_glm::Vec3;
 use rand::Rng;
 use rapier3d::{parry::query::ShapeCastOptions, prelude::*};
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_timer::Instant;
 
-use crate::core::Transform_2::Transform;
 use crate::helpers::saved_data::{AttackStats, CharacterStats};
 use crate::model_components::{PlayerCharacter::PlayerCharacter, NPC::NPC};
 use crate::game_behaviors::attack::{MeleeAttackBehavior};
 use crate::game_behaviors::defense::DefenseBehavior;
 use crate::game_behaviors::evade::EvadeBehavior;
+use crate::game_behaviors::navmesh::NavMesh;
 
 use super::chase::ChaseBehavior;
 
@@ -59,10 +59,11 @@ impl MeleeCombatBehavior {
         rigid_body_set: &mut RigidBodySet,
         collider_set: &ColliderSet,
         query_pipeline: &QueryPipeline,
+        navmesh: &mut NavMesh,
         entity_handle: RigidBodyHandle,
         target_handle: RigidBodyHandle,
         collider: &Collider,
-        transform: &mut Transform,
+        current_pos: Vector3<f32>,
         current_stamina: f32,
         dt: f32,
     ) -> Option<f32> {
@@ -78,17 +79,18 @@ impl MeleeCombatBehavior {
                     rigid_body_set,
                     collider_set,
                     query_pipeline,
+                    navmesh,
                     entity_handle,
                     target_handle,
                     collider,
-                    transform,
+                    current_pos,
                     dt,
                 );
 
                 // Transition to attacking if in range
                 if state_duration >= min_state_duration {
                     let target_pos = rigid_body_set.get(target_handle)?.translation();
-                    let distance = transform.position.metric_distance(&Vec3::new(
+                    let distance = current_pos.metric_distance(&Vec3::new(
                         target_pos.x,
                         target_pos.y,
                         target_pos.z,
@@ -109,13 +111,13 @@ impl MeleeCombatBehavior {
                     query_pipeline,
                     entity_handle,
                     target_handle,
-                    transform,
+                    current_pos,
                 );
 
                 // Transition to evading after attack or if too close
                 if state_duration >= min_state_duration {
                     let target_pos = rigid_body_set.get(target_handle)?.translation();
-                    let distance = transform.position.metric_distance(&Vec3::new(
+                    let distance = current_pos.metric_distance(&Vec3::new(
                         target_pos.x,
                         target_pos.y,
                         target_pos.z,
@@ -134,9 +136,10 @@ impl MeleeCombatBehavior {
                     rigid_body_set,
                     collider_set,
                     query_pipeline,
+                    navmesh,
                     entity_handle,
                     target_handle,
-                    transform,
+                    current_pos,
                     dt,
                 );
 
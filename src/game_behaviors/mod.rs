@@ -5,7 +5,17 @@ pub mod wander;
 pub mod attack;
 pub mod defense;
 pub mod evade;
+pub mod behavior_tree;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod behavior_tree_ui;
+pub mod navmesh;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod navmesh_ui;
 pub mod inventory;
 pub mod inventory_ui;
 pub mod dialogue_state;
+pub mod dialogue_graph;
+pub mod blackboard;
+pub mod quests;
+pub mod timers;
 pub mod dialogue_ui;
\ No newline at end of file
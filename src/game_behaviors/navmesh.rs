@@ -0,0 +1,566 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use nalgebra::{point, vector, Isometry3, Vector2, Vector3};
+use rapier3d::parry::bounding_volume::{Aabb, BoundingVolume};
+use rapier3d::parry::shape::Capsule;
+use rapier3d::prelude::{ColliderHandle, ColliderSet, QueryFilter, QueryPipeline, Ray, RigidBodySet};
+
+// Where agents fit, sampled from the level's fixed colliders: landscape heightfields, model
+// trimeshes and procedural houses. The level is split into square cells, grouped in tiles that are
+// sampled the first time a path needs them and dropped again when a collider over them changes.
+// Cells should be at most twice the agent radius wide, or thin walls can fall between them.
+#[derive(Clone, Debug)]
+pub struct NavMeshSettings {
+    pub cell_size: f32,
+    pub tile_cells: i32, // cells along a tile's side
+    pub agent_radius: f32,
+    pub agent_height: f32,
+    pub step_height: f32, // climbable whatever the slope, like curbs and stairs
+    pub max_search_nodes: usize, // a path query gives up after expanding this many cells
+}
+
+impl Default for NavMeshSettings {
+    fn default() -> Self {
+        NavMeshSettings {
+            cell_size: 0.5,
+            tile_cells: 32,
+            agent_radius: 0.5,
+            agent_height: 2.0,
+            step_height: 0.4,
+            max_search_nodes: 40_000,
+        }
+    }
+}
+
+pub type CellCoord = (i32, i32);
+pub type TileCoord = (i32, i32);
+
+// The physics a navmesh is sampled from. Only fixed colliders that aren't sensors count, so
+// NPCs, the player, pickups and trigger volumes never block a path.
+pub struct NavSource<'a> {
+    pub bodies: &'a RigidBodySet,
+    pub colliders: &'a ColliderSet,
+    pub query_pipeline: &'a QueryPipeline,
+}
+
+fn static_filter() -> QueryFilter<'static> {
+    QueryFilter::only_fixed().exclude_sensors()
+}
+
+struct NavTile {
+    heights: Vec<Option<f32>>, // ground height per cell, row by row along x, None where an agent doesn't fit
+}
+
+pub struct NavMesh {
+    pub settings: NavMeshSettings,
+    tiles: HashMap<TileCoord, NavTile>,
+    statics: HashMap<ColliderHandle, Aabb>, // the colliders the tiles were sampled from
+    bounds: Option<Aabb>,
+    pub revision: u64, // bumped when tiles are dropped, paths planned before may cross something new
+}
+
+impl Default for NavMesh {
+    fn default() -> Self {
+        NavMesh::new(NavMeshSettings::default())
+    }
+}
+
+impl NavMesh {
+    pub fn new(settings: NavMeshSettings) -> Self {
+        NavMesh {
+            settings,
+            tiles: HashMap::new(),
+            statics: HashMap::new(),
+            bounds: None,
+            revision: 0,
+        }
+    }
+
+    // Compares the fixed colliders with the ones the tiles were sampled from and drops every tile an
+    // added, removed or moved collider overlaps. They're sampled again when a path next needs them.
+    pub fn sync(&mut self, bodies: &RigidBodySet, colliders: &ColliderSet) {
+        let mut current = HashMap::new();
+        for (handle, collider) in colliders.iter() {
+            let fixed = collider.parent().and_then(|p| bodies.get(p)).map_or(true, |b| b.is_fixed());
+            if fixed && !collider.is_sensor() && collider.is_enabled() {
+                current.insert(handle, collider.compute_aabb());
+            }
+        }
+
+        let mut changed = Vec::new();
+        for (handle, aabb) in &current {
+            match self.statics.get(handle) {
+                Some(old) if same_aabb(old, aabb) => {}
+                Some(old) => changed.extend([*old, *aabb]),
+                None => changed.push(*aabb),
+            }
+        }
+        for (handle, aabb) in &self.statics {
+            if !current.contains_key(handle) {
+                changed.push(*aabb);
+            }
+        }
+
+        if changed.is_empty() {
+            return;
+        }
+
+        self.bounds = current.values().copied().reduce(|a, b| a.merged(&b));
+        self.statics = current;
+        for aabb in &changed {
+            self.invalidate(aabb);
+        }
+        self.revision += 1;
+    }
+
+    // Drops the tiles within an agent's reach of `aabb`
+    pub fn invalidate(&mut self, aabb: &Aabb) {
+        let margin = self.settings.agent_radius + self.settings.cell_size;
+        let tile_size = self.tile_size();
+        let min = ((aabb.mins.x - margin) / tile_size).floor() as i32;
+        let max = ((aabb.maxs.x + margin) / tile_size).floor() as i32;
+        let min_z = ((aabb.mins.z - margin) / tile_size).floor() as i32;
+        let max_z = ((aabb.maxs.z + margin) / tile_size).floor() as i32;
+
+        self.tiles
+            .retain(|&(x, z), _| x < min || x > max || z < min_z || z > max_z);
+    }
+
+    // Drops everything, for a new level
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+        self.statics.clear();
+        self.bounds = None;
+        self.revision += 1;
+    }
+
+    pub fn built_tiles(&self) -> usize {
+        self.tiles.len()
+    }
+
+    fn tile_size(&self) -> f32 {
+        self.settings.cell_size * self.settings.tile_cells as f32
+    }
+
+    pub fn cell_at(&self, x: f32, z: f32) -> CellCoord {
+        let cell_size = self.settings.cell_size;
+        ((x / cell_size).floor() as i32, (z / cell_size).floor() as i32)
+    }
+
+    pub fn cell_center(&self, cell: CellCoord) -> Vector2<f32> {
+        let cell_size = self.settings.cell_size;
+        Vector2::new((cell.0 as f32 + 0.5) * cell_size, (cell.1 as f32 + 0.5) * cell_size)
+    }
+
+    // Ground height of a cell agents fit in, sampling its tile first if needed
+    pub fn cell_height(&mut self, source: &NavSource, cell: CellCoord) -> Option<f32> {
+        let n = self.settings.tile_cells;
+        let tile = (cell.0.div_euclid(n), cell.1.div_euclid(n));
+        if !self.tiles.contains_key(&tile) {
+            let built = self.build_tile(source, tile);
+            self.tiles.insert(tile, built);
+        }
+
+        let (x, z) = (cell.0.rem_euclid(n), cell.1.rem_euclid(n));
+        self.tiles[&tile].heights[(z * n + x) as usize]
+    }
+
+    // Ground height where an agent can stand at x, z
+    pub fn walkable_height(&mut self, source: &NavSource, x: f32, z: f32) -> Option<f32> {
+        let cell = self.cell_at(x, z);
+        self.cell_height(source, cell)
+    }
+
+    fn build_tile(&self, source: &NavSource, tile: TileCoord) -> NavTile {
+        let n = self.settings.tile_cells;
+        let mut heights = Vec::with_capacity((n * n) as usize);
+        for z in 0..n {
+            for x in 0..n {
+                heights.push(self.sample(source, (tile.0 * n + x, tile.1 * n + z)));
+            }
+        }
+        NavTile { heights }
+    }
+
+    // Casts down from above everything to find the ground, then checks an agent standing there
+    // wouldn't be inside anything above step height
+    fn sample(&self, source: &NavSource, cell: CellCoord) -> Option<f32> {
+        let bounds = self.bounds.as_ref()?;
+        let center = self.cell_center(cell);
+        if center.x < bounds.mins.x || center.x > bounds.maxs.x || center.y < bounds.mins.z || center.y > bounds.maxs.z {
+            return None;
+        }
+
+        let top = bounds.maxs.y + 1.0;
+        let ray = Ray::new(point![center.x, top, center.y], vector![0.0, -1.0, 0.0]);
+        let (_, distance) = source.query_pipeline.cast_ray(
+            source.bodies,
+            source.colliders,
+            &ray,
+            top - bounds.mins.y + 1.0,
+            true,
+            static_filter(),
+        )?;
+        let ground = top - distance;
+
+        let settings = &self.settings;
+        let half_height = ((settings.agent_height - settings.step_height) * 0.5 - settings.agent_radius).max(0.0);
+        let body = Capsule::new_y(half_height, settings.agent_radius);
+        let body_y = ground + settings.step_height + settings.agent_radius + half_height;
+        let body_pos = Isometry3::translation(center.x, body_y, center.y);
+
+        let blocked = source
+            .query_pipeline
+            .intersection_with_shape(source.bodies, source.colliders, &body_pos, &body, static_filter())
+            .is_some();
+
+        if blocked { None } else { Some(ground) }
+    }
+
+    // Whether an agent can walk straight from one cell's ground to its neighbor's
+    fn can_climb(&self, from: f32, to: f32, max_slope: f32) -> bool {
+        let rise = (to - from).abs();
+        rise <= self.settings.step_height || rise <= self.settings.cell_size * max_slope.to_radians().tan()
+    }
+
+    // The walkable cell nearest to a position within a couple of cells, at about its height
+    fn nearest_cell(&mut self, source: &NavSource, position: Vector3<f32>) -> Option<CellCoord> {
+        let origin = self.cell_at(position.x, position.z);
+        let reach = self.settings.agent_height;
+        let mut best: Option<(f32, CellCoord)> = None;
+
+        for dz in -2..=2 {
+            for dx in -2..=2 {
+                let cell = (origin.0 + dx, origin.1 + dz);
+                let height = match self.cell_height(source, cell) {
+                    Some(height) if (position.y - height).abs() <= reach => height,
+                    _ => continue,
+                };
+                let distance = (self.cell_center(cell) - Vector2::new(position.x, position.z)).norm() + (position.y - height).abs() * 0.01;
+                if best.map_or(true, |(d, _)| distance < d) {
+                    best = Some((distance, cell));
+                }
+            }
+        }
+
+        best.map(|(_, cell)| cell)
+    }
+
+    // A* over the cells, then the funnel to pull the corridor taut. The path starts at `start`, ends
+    // at `goal` and its points are on the ground. None when the goal can't be reached.
+    pub fn find_path(&mut self, source: &NavSource, start: Vector3<f32>, goal: Vector3<f32>, max_slope: f32) -> Option<Vec<Vector3<f32>>> {
+        let start_cell = self.nearest_cell(source, start)?;
+        let goal_cell = self.nearest_cell(source, goal)?;
+        let cells = self.search(source, start_cell, goal_cell, max_slope)?;
+
+        let start_height = self.cell_height(source, start_cell)?;
+        let goal_height = self.cell_height(source, goal_cell)?;
+        let mut portals = vec![Portal::point(Vector2::new(start.x, start.z), start_height)];
+        for pair in cells.windows(2) {
+            let y = (self.cell_height(source, pair[0])? + self.cell_height(source, pair[1])?) * 0.5;
+            portals.push(self.portal(pair[0], pair[1], y));
+        }
+        portals.push(Portal::point(Vector2::new(goal.x, goal.z), goal_height));
+
+        let path = funnel(&portals).into_iter().map(|(p, y)| Vector3::new(p.x, y, p.y)).collect();
+        Some(self.pull_taut(source, path, max_slope))
+    }
+
+    // The funnel only sees through the corridor A* picked, and on open ground that's a staircase of
+    // cells the straight line leaves. Skips every waypoint the agent can walk straight past.
+    fn pull_taut(&mut self, source: &NavSource, path: Vec<Vector3<f32>>, max_slope: f32) -> Vec<Vector3<f32>> {
+        let mut taut = vec![path[0]];
+        let mut from = 0;
+        while from + 1 < path.len() {
+            let mut to = path.len() - 1;
+            while to > from + 1 && !self.sees(source, path[from], path[to], max_slope) {
+                to -= 1;
+            }
+            taut.push(path[to]);
+            from = to;
+        }
+        taut
+    }
+
+    // line_walkable between two waypoints. Funnel corners sit on the edge of the cell they turn around,
+    // so the ends are moved a hair along the line into the cells it actually crosses.
+    fn sees(&mut self, source: &NavSource, from: Vector3<f32>, to: Vector3<f32>, max_slope: f32) -> bool {
+        let offset = Vector3::new(to.x - from.x, 0.0, to.z - from.z);
+        let length = offset.norm();
+        if length < 1.0e-4 {
+            return true;
+        }
+        let nudge = offset * ((self.settings.cell_size * 0.01).min(length * 0.5) / length);
+        self.line_walkable(source, from + nudge, to - nudge, max_slope)
+    }
+
+    fn search(&mut self, source: &NavSource, start: CellCoord, goal: CellCoord, max_slope: f32) -> Option<Vec<CellCoord>> {
+        let goal_center = self.cell_center(goal);
+        let heuristic = |mesh: &NavMesh, cell: CellCoord| (mesh.cell_center(cell) - goal_center).norm();
+
+        let mut open = BinaryHeap::new();
+        let mut costs: HashMap<CellCoord, f32> = HashMap::from([(start, 0.0)]);
+        let mut came_from: HashMap<CellCoord, CellCoord> = HashMap::new();
+        open.push(OpenCell { estimate: heuristic(self, start), cell: start });
+
+        let mut expanded = 0;
+        while let Some(OpenCell { cell, estimate }) = open.pop() {
+            if cell == goal {
+                let mut path = vec![goal];
+                let mut current = goal;
+                while let Some(&previous) = came_from.get(&current) {
+                    path.push(previous);
+                    current = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            // stale entry, the cell was reached cheaper since
+            let cost = costs[&cell];
+            if estimate > cost + heuristic(self, cell) + 1.0e-4 {
+                continue;
+            }
+
+            expanded += 1;
+            if expanded > self.settings.max_search_nodes {
+                return None;
+            }
+
+            let height = self.cell_height(source, cell)?;
+            for (dx, dz) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let next = (cell.0 + dx, cell.1 + dz);
+                let next_height = match self.cell_height(source, next) {
+                    Some(h) if self.can_climb(height, h, max_slope) => h,
+                    _ => continue,
+                };
+
+                let step = (self.settings.cell_size.powi(2) + (next_height - height).powi(2)).sqrt();
+                let next_cost = cost + step;
+                if costs.get(&next).map_or(true, |&c| next_cost < c) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, cell);
+                    open.push(OpenCell { estimate: next_cost + heuristic(self, next), cell: next });
+                }
+            }
+        }
+
+        None
+    }
+
+    // The edge two neighboring cells share, with left and right as seen walking from `from` into `to`
+    fn portal(&self, from: CellCoord, to: CellCoord, y: f32) -> Portal {
+        let s = self.settings.cell_size;
+        let (x0, x1) = (from.0 as f32 * s, (from.0 + 1) as f32 * s);
+        let (z0, z1) = (from.1 as f32 * s, (from.1 + 1) as f32 * s);
+
+        let (left, right) = match (to.0 - from.0, to.1 - from.1) {
+            (1, _) => (Vector2::new(x1, z1), Vector2::new(x1, z0)),
+            (-1, _) => (Vector2::new(x0, z0), Vector2::new(x0, z1)),
+            (_, 1) => (Vector2::new(x0, z1), Vector2::new(x1, z1)),
+            _ => (Vector2::new(x1, z0), Vector2::new(x0, z0)),
+        };
+        Portal { left, right, y }
+    }
+
+    // Whether an agent can walk the straight line between two points without leaving the navmesh
+    pub fn line_walkable(&mut self, source: &NavSource, from: Vector3<f32>, to: Vector3<f32>, max_slope: f32) -> bool {
+        let mut previous = match self.nearest_cell(source, from) {
+            Some(cell) => cell,
+            None => return false,
+        };
+
+        let length = Vector2::new(to.x - from.x, to.z - from.z).norm();
+        let steps = (length / (self.settings.cell_size * 0.5)).ceil().max(1.0) as i32;
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            let cell = self.cell_at(from.x + (to.x - from.x) * t, from.z + (to.z - from.z) * t);
+            if cell == previous {
+                continue;
+            }
+
+            let walkable = match (self.cell_height(source, previous), self.cell_height(source, cell)) {
+                (Some(a), Some(b)) => self.can_climb(a, b, max_slope),
+                _ => false,
+            };
+            if !walkable {
+                return false;
+            }
+            previous = cell;
+        }
+        true
+    }
+
+    // Built cells with their ground height, None where agents don't fit. For the debug overlay.
+    pub fn debug_cells(&self) -> Vec<(Vector2<f32>, Option<f32>)> {
+        let n = self.settings.tile_cells;
+        let mut cells = Vec::new();
+        for (&(tx, tz), tile) in &self.tiles {
+            for (index, height) in tile.heights.iter().enumerate() {
+                let cell = (tx * n + index as i32 % n, tz * n + index as i32 / n);
+                cells.push((self.cell_center(cell), *height));
+            }
+        }
+        cells
+    }
+}
+
+fn same_aabb(a: &Aabb, b: &Aabb) -> bool {
+    (a.mins - b.mins).norm() < 1.0e-3 && (a.maxs - b.maxs).norm() < 1.0e-3
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Portal {
+    left: Vector2<f32>,
+    right: Vector2<f32>,
+    y: f32,
+}
+
+impl Portal {
+    fn point(p: Vector2<f32>, y: f32) -> Self {
+        Portal { left: p, right: p, y }
+    }
+}
+
+// Positive when b is left of a
+fn cross(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+// The simple stupid funnel algorithm: walks the portals keeping the widest funnel that still sees
+// through all of them, and turns a corner wherever one side would cross the other
+fn funnel(portals: &[Portal]) -> Vec<(Vector2<f32>, f32)> {
+    let mut points = vec![(portals[0].left, portals[0].y)];
+    let (mut apex, mut left, mut right) = (portals[0].left, portals[0].left, portals[0].right);
+    let (mut apex_index, mut left_index, mut right_index) = (0, 0, 0);
+
+    let mut i = 1;
+    while i < portals.len() {
+        let Portal { left: next_left, right: next_right, .. } = portals[i];
+
+        if cross(right - apex, next_right - apex) >= 0.0 {
+            if apex == right || cross(left - apex, next_right - apex) < 0.0 {
+                right = next_right;
+                right_index = i;
+            } else {
+                // the right side crossed the left, so the path turns at the left corner
+                apex = left;
+                apex_index = left_index;
+                push_corner(&mut points, apex, portals[apex_index].y);
+                right = apex;
+                right_index = apex_index;
+                i = apex_index + 1;
+                continue;
+            }
+        }
+
+        if cross(left - apex, next_left - apex) <= 0.0 {
+            if apex == left || cross(right - apex, next_left - apex) > 0.0 {
+                left = next_left;
+                left_index = i;
+            } else {
+                apex = right;
+                apex_index = right_index;
+                push_corner(&mut points, apex, portals[apex_index].y);
+                left = apex;
+                left_index = apex_index;
+                i = apex_index + 1;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    let end = portals[portals.len() - 1];
+    push_corner(&mut points, end.left, end.y);
+    points
+}
+
+// Portals that touch the apex turn at the same corner again, which only needs to be there once
+fn push_corner(points: &mut Vec<(Vector2<f32>, f32)>, corner: Vector2<f32>, y: f32) {
+    if points.last().map_or(true, |(p, _)| (p - corner).norm() > 1.0e-4) {
+        points.push((corner, y));
+    }
+}
+
+// Lowest estimate first
+struct OpenCell {
+    estimate: f32,
+    cell: CellCoord,
+}
+
+impl PartialEq for OpenCell {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for OpenCell {}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.partial_cmp(&self.estimate).unwrap_or(Ordering::Equal)
+    }
+}
+
+// How close to a waypoint counts as there
+const ARRIVE_DISTANCE: f32 = 0.35;
+
+// Walks an agent along a path, planning again when the goal moves or the navmesh changes
+#[derive(Clone, Debug, Default)]
+pub struct PathFollower {
+    pub path: Vec<Vector3<f32>>,
+    next: usize,
+    goal: Option<Vector3<f32>>,
+    revision: u64,
+}
+
+impl PathFollower {
+    // Plans a path to `goal` unless the current one already leads there. False when there's no way there,
+    // which isn't tried again until the goal moves or the navmesh changes.
+    pub fn seek(&mut self, navmesh: &mut NavMesh, source: &NavSource, position: Vector3<f32>, goal: Vector3<f32>, max_slope: f32) -> bool {
+        let moved = |current: Vector3<f32>| Vector2::new(current.x - goal.x, current.z - goal.z).norm() > navmesh.settings.cell_size * 2.0;
+        let replan = match self.goal {
+            Some(current) => self.revision != navmesh.revision || moved(current),
+            None => true,
+        };
+        if !replan {
+            return !self.path.is_empty();
+        }
+
+        self.goal = Some(goal);
+        self.revision = navmesh.revision;
+        self.next = 0;
+        self.path = navmesh.find_path(source, position, goal, max_slope).unwrap_or_default();
+        !self.path.is_empty()
+    }
+
+    // Horizontal direction to the next waypoint, None once the end is reached
+    pub fn direction(&mut self, position: Vector3<f32>) -> Option<Vector3<f32>> {
+        while let Some(waypoint) = self.path.get(self.next) {
+            let offset = Vector3::new(waypoint.x - position.x, 0.0, waypoint.z - position.z);
+            if offset.norm() > ARRIVE_DISTANCE {
+                return Some(offset.normalize());
+            }
+            self.next += 1;
+        }
+        None
+    }
+
+    // The waypoints still ahead
+    pub fn remaining(&self) -> &[Vector3<f32>] {
+        &self.path[self.next.min(self.path.len())..]
+    }
+
+    pub fn clear(&mut self) {
+        *self = PathFollower::default();
+    }
+}
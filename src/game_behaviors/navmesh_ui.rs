@@ -0,0 +1,45 @@
+use egui::{Color32, Rect, Sense, Shape, Stroke};
+use nalgebra::Vector3;
+
+use crate::core::World::World;
+
+// Top-down map of the navmesh around the player, `span` world units across: walkable cells green,
+// cells agents don't fit in red and each NPC's path ahead in yellow. Tiles no path has needed yet
+// aren't built, so they stay blank.
+pub fn navmesh_overlay(ui: &mut egui::Ui, world: &World, span: f32) {
+    let (response, painter) = ui.allocate_painter(egui::vec2(320.0, 320.0), Sense::hover());
+    let rect = response.rect;
+    let painter = painter.with_clip_rect(rect);
+    painter.rect_filled(rect, 0.0, Color32::from_gray(20));
+
+    let center = world
+        .player_handle()
+        .and_then(|handle| world.body_position(handle))
+        .unwrap_or_else(Vector3::zeros);
+    let scale = rect.width() / span;
+    let to_screen = |x: f32, z: f32| rect.center() + egui::vec2((x - center.x) * scale, (z - center.z) * scale);
+
+    let cell_size = (world.navmesh.settings.cell_size * scale).max(1.0);
+    for (cell, height) in world.navmesh.debug_cells() {
+        let position = to_screen(cell.x, cell.y);
+        if !rect.expand(cell_size).contains(position) {
+            continue;
+        }
+        let color = match height {
+            Some(_) => Color32::from_rgb(40, 130, 60),
+            None => Color32::from_rgb(130, 40, 40),
+        };
+        painter.rect_filled(Rect::from_center_size(position, egui::vec2(cell_size, cell_size)), 0.0, color);
+    }
+
+    for npc in &world.npcs {
+        if let Some(position) = world.body_position(npc.rigid_body_handle) {
+            let mut points = vec![to_screen(position.x, position.z)];
+            points.extend(npc.test_behavior.path().iter().map(|p| to_screen(p.x, p.z)));
+            painter.circle_filled(points[0], 3.0, Color32::WHITE);
+            painter.add(Shape::line(points, Stroke::new(1.5, Color32::YELLOW)));
+        }
+    }
+
+    painter.circle_filled(rect.center(), 4.0, Color32::LIGHT_BLUE);
+}
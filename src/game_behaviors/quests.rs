@@ -0,0 +1,305 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::SceneHierarchy::component_placements;
+use crate::core::World::World;
+use crate::helpers::saved_data::{ComponentData, ObjectiveKind, QuestData, QuestObjective};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuestStatus {
+    Active,
+    Complete,
+}
+
+// Where the player is in one quest. Saved with the game, see helpers::save_game.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuestProgress {
+    pub quest_id: String,
+    pub stage: usize,
+    pub status: QuestStatus,
+    #[serde(default)]
+    pub counts: HashMap<String, u32>, // objective id -> matching events so far, this stage only
+}
+
+// Things that happened in the game that objectives can be waiting on
+#[derive(Clone, Debug)]
+pub enum QuestEvent {
+    Collected(ComponentData),
+    Killed(String),
+    Talked { npc_id: String, node: String },
+    Reached(String),
+}
+
+// One line of the journal: a started quest, its current stage and how far along each objective is
+#[derive(Clone, Debug, PartialEq)]
+pub struct JournalEntry {
+    pub quest_id: String,
+    pub name: String,
+    pub stage_description: String,
+    pub objectives: Vec<(String, u32, u32)>, // description, progress, required
+    pub complete: bool,
+}
+
+#[derive(Default)]
+pub struct QuestLog {
+    pub quests: Vec<QuestData>, // definitions from SavedState
+    pub progress: Vec<QuestProgress>,
+    // what observe has already turned into events, so nothing counts twice
+    seen_items: HashSet<String>,
+    seen_deaths: HashSet<String>,
+    primed: bool,
+}
+
+// Items are named by component id, model asset id or prefab id, so one id can cover every copy
+pub fn matches_item(item: &ComponentData, item_id: &str) -> bool {
+    item.id == item_id
+        || item.asset_id == item_id
+        || item.prefab.as_ref().map(|p| p.prefab_id == item_id).unwrap_or(false)
+}
+
+fn objective_matches(objective: &QuestObjective, event: &QuestEvent) -> bool {
+    match (&objective.kind, event) {
+        (ObjectiveKind::Collect { item_id, .. }, QuestEvent::Collected(item)) => matches_item(item, item_id),
+        (ObjectiveKind::Kill { npc_id }, QuestEvent::Killed(id)) => npc_id == id,
+        (ObjectiveKind::Talk { npc_id, node }, QuestEvent::Talked { npc_id: id, node: talked }) => {
+            npc_id == id && node.as_ref().map(|n| n == talked).unwrap_or(true)
+        }
+        (ObjectiveKind::Reach { component_id, .. }, QuestEvent::Reached(id)) => component_id == id,
+        _ => false,
+    }
+}
+
+impl QuestLog {
+    pub fn new(quests: Vec<QuestData>) -> Self {
+        QuestLog {
+            quests,
+            ..Default::default()
+        }
+    }
+
+    pub fn quest(&self, quest_id: &str) -> Option<&QuestData> {
+        self.quests.iter().find(|q| q.id == quest_id)
+    }
+
+    pub fn progress(&self, quest_id: &str) -> Option<&QuestProgress> {
+        self.progress.iter().find(|p| p.quest_id == quest_id)
+    }
+
+    pub fn is_active(&self, quest_id: &str) -> bool {
+        self.progress(quest_id).map(|p| p.status == QuestStatus::Active).unwrap_or(false)
+    }
+
+    pub fn is_complete(&self, quest_id: &str) -> bool {
+        self.progress(quest_id).map(|p| p.status == QuestStatus::Complete).unwrap_or(false)
+    }
+
+    // Id of the stage the quest is on, empty when it hasn't started or is complete
+    pub fn stage_id(&self, quest_id: &str) -> String {
+        match (self.quest(quest_id), self.progress(quest_id)) {
+            (Some(quest), Some(progress)) if progress.status == QuestStatus::Active => quest
+                .stages
+                .get(progress.stage)
+                .map(|s| s.id.clone())
+                .unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    // Starts a quest at its first stage. False if it doesn't exist or was already started.
+    pub fn start(&mut self, quest_id: &str) -> bool {
+        let stage_count = match self.quest(quest_id) {
+            Some(quest) => quest.stages.len(),
+            None => {
+                println!("Quest {} doesn't exist", quest_id);
+                return false;
+            }
+        };
+        if self.progress(quest_id).is_some() {
+            return false;
+        }
+
+        self.progress.push(QuestProgress {
+            quest_id: quest_id.to_string(),
+            stage: 0,
+            status: if stage_count == 0 { QuestStatus::Complete } else { QuestStatus::Active },
+            counts: HashMap::new(),
+        });
+        println!("Quest {} started", quest_id);
+
+        true
+    }
+
+    // Moves an active quest to its next stage, completing it after the last one
+    pub fn advance(&mut self, quest_id: &str) -> bool {
+        let stage_count = match self.quest(quest_id) {
+            Some(quest) => quest.stages.len(),
+            None => return false,
+        };
+        let progress = match self.progress.iter_mut().find(|p| p.quest_id == quest_id) {
+            Some(progress) if progress.status == QuestStatus::Active => progress,
+            _ => return false,
+        };
+
+        progress.stage += 1;
+        progress.counts.clear();
+
+        if progress.stage >= stage_count {
+            progress.status = QuestStatus::Complete;
+            println!("Quest {} complete", quest_id);
+        }
+
+        true
+    }
+
+    // Counts an event against every active objective it matches, advancing stages that are now done.
+    // Returns the ids of quests that advanced.
+    pub fn notify(&mut self, event: &QuestEvent) -> Vec<String> {
+        let mut finished = Vec::new();
+
+        for progress in self.progress.iter_mut().filter(|p| p.status == QuestStatus::Active) {
+            let stage = match self
+                .quests
+                .iter()
+                .find(|q| q.id == progress.quest_id)
+                .and_then(|q| q.stages.get(progress.stage))
+            {
+                Some(stage) => stage,
+                None => continue,
+            };
+
+            for objective in &stage.objectives {
+                if objective_matches(objective, event) {
+                    let count = progress.counts.entry(objective.id.clone()).or_insert(0);
+                    *count = (*count + 1).min(objective.kind.required());
+                }
+            }
+
+            let done = !stage.objectives.is_empty()
+                && stage
+                    .objectives
+                    .iter()
+                    .all(|o| progress.counts.get(&o.id).copied().unwrap_or(0) >= o.kind.required());
+
+            if done {
+                finished.push(progress.quest_id.clone());
+            }
+        }
+
+        for quest_id in &finished {
+            self.advance(quest_id);
+        }
+
+        finished
+    }
+
+    // Forget what observe has seen, call after placing a level or loading a save
+    // so the restored world doesn't count as new pickups and kills
+    pub fn reset_observed(&mut self) {
+        self.seen_items.clear();
+        self.seen_deaths.clear();
+        self.primed = false;
+    }
+
+    // Turns changes in the world since the last call into events: new inventory items,
+    // NPCs that died and areas the player is standing in
+    pub fn observe(&mut self, world: &World, level_components: &[ComponentData]) -> Vec<String> {
+        let mut events = Vec::new();
+
+        if let Some(player) = &world.player_character {
+            let inventory = &player.inventory;
+            let carried = inventory
+                .items
+                .iter()
+                .chain(inventory.equipped_weapon.iter())
+                .chain(inventory.equipped_armor.iter());
+
+            for item in carried {
+                if self.seen_items.insert(item.id.clone()) {
+                    events.push(QuestEvent::Collected(item.clone()));
+                }
+            }
+        }
+
+        for npc in world.npcs.iter().filter(|n| !n.is_alive()) {
+            if self.seen_deaths.insert(npc.id.clone()) {
+                events.push(QuestEvent::Killed(npc.id.clone()));
+            }
+        }
+
+        // the first look only records what's already there
+        if !self.primed {
+            self.primed = true;
+            events.clear();
+        }
+
+        events.extend(self.reached_areas(world, level_components).into_iter().map(QuestEvent::Reached));
+
+        events.iter().flat_map(|event| self.notify(event)).collect()
+    }
+
+    // Reach objectives of active stages that the player is inside of
+    fn reached_areas(&self, world: &World, level_components: &[ComponentData]) -> Vec<String> {
+        let areas: Vec<(&String, f32)> = self
+            .progress
+            .iter()
+            .filter(|p| p.status == QuestStatus::Active)
+            .filter_map(|p| self.quest(&p.quest_id).and_then(|q| q.stages.get(p.stage)))
+            .flat_map(|stage| stage.objectives.iter())
+            .filter_map(|o| match &o.kind {
+                ObjectiveKind::Reach { component_id, radius } => Some((component_id, *radius)),
+                _ => None,
+            })
+            .collect();
+
+        if areas.is_empty() {
+            return Vec::new();
+        }
+
+        let player_pos = match world.player_handle().and_then(|h| world.body_position(h)) {
+            Some(position) => position,
+            None => return Vec::new(),
+        };
+        let placements = component_placements(level_components);
+
+        areas
+            .into_iter()
+            .filter(|(component_id, radius)| {
+                placements
+                    .get(*component_id)
+                    .map(|p| (p.isometry.translation.vector - player_pos).norm() <= *radius)
+                    .unwrap_or(false)
+            })
+            .map(|(component_id, _)| component_id.clone())
+            .collect()
+    }
+
+    // Started quests in the order they were started, for a journal screen
+    pub fn journal(&self) -> Vec<JournalEntry> {
+        self.progress
+            .iter()
+            .filter_map(|progress| {
+                let quest = self.quest(&progress.quest_id)?;
+                let stage = quest.stages.get(progress.stage);
+
+                Some(JournalEntry {
+                    quest_id: quest.id.clone(),
+                    name: quest.name.clone(),
+                    stage_description: stage.map(|s| s.description.clone()).unwrap_or_default(),
+                    objectives: stage
+                        .map(|s| {
+                            s.objectives
+                                .iter()
+                                .map(|o| {
+                                    let count = progress.counts.get(&o.id).copied().unwrap_or(0);
+                                    (o.description.clone(), count, o.kind.required())
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    complete: progress.status == QuestStatus::Complete,
+                })
+            })
+            .collect()
+    }
+}
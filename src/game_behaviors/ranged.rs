@@ -3,18 +3,18 @@ use nalgebra_glm::Vec3;
 use rand::Rng;
 use rapier3d::{parry::query::ShapeCastOptions, prelude::*};
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_timer::Instant;
 
-use crate::core::Transform_2::Transform;
 use crate::helpers::saved_data::{AttackStats, CharacterStats};
 use crate::model_components::{PlayerCharacter::PlayerCharacter, NPC::NPC};
 use crate::game_behaviors::attack::{RangedAttackBehavior};
 use crate::game_behaviors::defense::DefenseBehavior;
 use crate::game_behaviors::evade::EvadeBehavior;
+use crate::game_behaviors::navmesh::NavMesh;
 
 use super::chase::ChaseBehavior;
 
@@ -59,10 +59,11 @@ impl RangedCombatBehavior {
         rigid_body_set: &mut RigidBodySet,
         collider_set: &ColliderSet,
         query_pipeline: &QueryPipeline,
+        navmesh: &mut NavMesh,
         entity_handle: RigidBodyHandle,
         target_handle: RigidBodyHandle,
         collider: &Collider,
-        transform: &mut Transform,
+        current_pos: Vector3<f32>,
         current_stamina: f32,
         dt: f32,
     ) -> Option<(f32, Option<(Point3<f32>, Point3<f32>)>)> {
@@ -80,10 +81,11 @@ impl RangedCombatBehavior {
                     rigid_body_set,
                     collider_set,
                     query_pipeline,
+                    navmesh,
                     entity_handle,
                     target_handle,
                     collider,
-                    transform,
+                    current_pos,
                     dt,
                 );
 
@@ -91,7 +93,7 @@ impl RangedCombatBehavior {
                 // Check more frequently than melee
                 if state_duration >= 2.0 { 
                     let target_pos = rigid_body_set.get(target_handle)?.translation();
-                    let distance = transform.position.metric_distance(&Vec3::new(
+                    let distance = current_pos.metric_distance(&Vec3::new(
                         target_pos.x,
                         target_pos.y,
                         target_pos.z,
@@ -111,14 +113,14 @@ impl RangedCombatBehavior {
                     query_pipeline,
                     entity_handle,
                     target_handle,
-                    transform,
+                    current_pos,
                 );
 
                 // For ranged, we want to keep attacking as long as we have line of sight and range.
                 // But for simple behavior, we can switch states occasionally.
                 if state_duration >= min_state_duration {
                     let target_pos = rigid_body_set.get(target_handle)?.translation();
-                    let distance = transform.position.metric_distance(&Vec3::new(
+                    let distance = current_pos.metric_distance(&Vec3::new(
                         target_pos.x,
                         target_pos.y,
                         target_pos.z,
@@ -141,9 +143,10 @@ impl RangedCombatBehavior {
                     rigid_body_set,
                     collider_set,
                     query_pipeline,
+                    navmesh,
                     entity_handle,
                     target_handle,
-                    transform,
+                    current_pos,
                     dt,
                 );
 
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+
+// A script function to call later, from system.after or system.every. Saved with the game.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScriptTimer {
+    pub id: i64,
+    pub owner_id: String, // component id, or the script path for global scripts
+    pub script_path: String,
+    pub function: String,
+    #[serde(default)]
+    pub args: Option<serde_json::Value>, // passed after script_state when set
+    pub due: f32, // game time in seconds
+    #[serde(default)]
+    pub interval: Option<f32>, // repeating timers fire again this many seconds later
+}
+
+impl ScriptTimer {
+    // global scripts file their requests under their own path
+    pub fn is_global(&self) -> bool {
+        self.owner_id == self.script_path
+    }
+}
+
+// Game time for scripts and the calls they scheduled on it. Only advances while the game runs,
+// so timers wait through pauses and don't depend on the frame rate.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timers {
+    pub elapsed: f32,
+    #[serde(skip)]
+    pub dt: f32, // length of the current frame
+    pub pending: Vec<ScriptTimer>,
+    #[serde(skip)]
+    due: Vec<ScriptTimer>, // came due this frame and not called yet, see next_due
+    next_id: i64,
+}
+
+impl Timers {
+    pub fn after(
+        &mut self,
+        owner_id: &str,
+        script_path: &str,
+        seconds: f32,
+        function: &str,
+        args: Option<serde_json::Value>,
+    ) -> i64 {
+        self.schedule(owner_id, script_path, seconds, function, args, None)
+    }
+
+    pub fn every(
+        &mut self,
+        owner_id: &str,
+        script_path: &str,
+        seconds: f32,
+        function: &str,
+        args: Option<serde_json::Value>,
+    ) -> i64 {
+        self.schedule(owner_id, script_path, seconds, function, args, Some(seconds.max(0.0)))
+    }
+
+    fn schedule(
+        &mut self,
+        owner_id: &str,
+        script_path: &str,
+        seconds: f32,
+        function: &str,
+        args: Option<serde_json::Value>,
+        interval: Option<f32>,
+    ) -> i64 {
+        self.next_id += 1;
+        self.pending.push(ScriptTimer {
+            id: self.next_id,
+            owner_id: owner_id.to_string(),
+            script_path: script_path.to_string(),
+            function: function.to_string(),
+            args,
+            due: self.elapsed + seconds.max(0.0),
+            interval,
+        });
+        self.next_id
+    }
+
+    // Returns false when the timer already fired or was cancelled. Works on timers that came due
+    // this frame too, so a callback can stop one that would have run after it.
+    pub fn cancel(&mut self, id: i64) -> bool {
+        let count = self.pending.len() + self.due.len();
+        self.retain(|t| t.id != id);
+        self.pending.len() + self.due.len() != count
+    }
+
+    // For destroyed components
+    pub fn cancel_owner(&mut self, owner_id: &str) {
+        self.retain(|t| t.owner_id != owner_id);
+    }
+
+    // Components go with their level, global scripts keep their timers
+    pub fn unload_level(&mut self) {
+        self.retain(|t| t.is_global());
+    }
+
+    fn retain(&mut self, keep: impl Fn(&ScriptTimer) -> bool) {
+        self.pending.retain(|t| keep(t));
+        self.due.retain(|t| keep(t));
+    }
+
+    // Moves game time on and lines up the timers that came due, earliest first. Repeating timers
+    // fire at most once per frame and are rescheduled, the rest are done.
+    pub fn advance(&mut self, dt: f32) {
+        self.dt = dt;
+        self.elapsed += dt;
+
+        let elapsed = self.elapsed;
+        let (mut due, waiting): (Vec<_>, Vec<_>) = self.pending.drain(..).partition(|t| t.due <= elapsed);
+        self.pending = waiting;
+        due.sort_by(|a, b| a.due.total_cmp(&b.due).then(a.id.cmp(&b.id)));
+
+        for timer in &due {
+            if let Some(interval) = timer.interval {
+                let mut repeat = timer.clone();
+                if interval > 0.0 {
+                    // after a long frame, skip the calls it missed rather than catching up
+                    while repeat.due <= elapsed {
+                        repeat.due += interval;
+                    }
+                } else {
+                    repeat.due = elapsed; // every frame
+                }
+                self.pending.push(repeat);
+            }
+        }
+
+        self.due = due;
+    }
+
+    // The next timer to call this frame. Taken one at a time since each call can cancel others.
+    pub fn next_due(&mut self) -> Option<ScriptTimer> {
+        if self.due.is_empty() {
+            None
+        } else {
+            Some(self.due.remove(0))
+        }
+    }
+}
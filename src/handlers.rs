@@ -3,7 +3,6 @@ use mint::{Quaternion, Vector3 as MintVector3};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use crate::core::RendererState::DebugRay;
-use crate::model_components::Collectable::Collectable;
 use crate::procedural_models::House::HouseConfig;
 // use tokio::spawn;
 use transform_gizmo::math::Transform;
@@ -19,13 +18,16 @@ use std::{cell::RefCell, collections::HashMap};
 use noise::{Fbm, NoiseFn, Perlin, Worley};
 use noise::MultiFractal;
 
-use crate::model_components::{PlayerCharacter::PlayerCharacter, NPC::NPC};
+use crate::model_components::NPC::NPCArchetype;
 use crate::core::SimpleCamera::to_row_major_f64;
 use crate::core::editor::{self, Editor};
 use crate::core::gpu_resources;
 use crate::helpers::utilities;
-use crate::helpers::landscapes::{TextureData, read_landscape_heightmap_as_texture};
-use crate::helpers::saved_data::{CollectableProperties, CollectableType, ComponentData, ComponentKind, StatData};
+use crate::game_behaviors::quests::QuestEvent;
+use crate::game_behaviors::dialogue_graph::{self, DialogueContext, DialogueStep, INTERACT_HOOK};
+use crate::helpers::project_error::ProjectError;
+use crate::helpers::landscapes::{mask_folder, TextureData, read_landscape_heightmap_as_texture};
+use crate::helpers::saved_data::{CollectableProperties, CollectableType, ComponentData, ComponentKind, ScriptState, StatData};
 #[cfg(target_arch = "wasm32")]
 use crate::helpers::wasm_loaders::{get_landscape_pixels_wasm, read_landscape_mask_wasm, read_landscape_texture_wasm, read_model_wasm};
 use crate::procedural_trees::trees::{ProceduralTrees, TreeInstance};
@@ -58,7 +60,7 @@ use crate::{
 };
 use crate::{art_assets::Model::read_model, shape_primitives::Pyramid::Pyramid};
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
@@ -95,7 +97,7 @@ pub enum EntropyMouseButton {
 #[derive(Serialize)]
 pub struct ReadModelParams {
     pub projectId: String,
-    pub modelFilename: String,
+    pub modelPath: String,
 }
 
 #[derive(Serialize)]
@@ -134,42 +136,34 @@ pub async fn handle_add_player(
     projectId: String,
     modelAssetId: String, // model is added to stored library as an asset
     modelComponentId: String, // model is added from library to scene as an active component
-    modelFilename: String,
+    modelPath: String,
     isometry: Isometry3<f32>,
     scale: Vector3<f32>,
     camera: &SimpleCamera,
     default_weapon: Option<ComponentData>,
-    script_state: Option<HashMap<String, String>>,
-) {
-    #[cfg(target_os = "windows")]
-    let bytes = read_model(projectId, modelFilename).expect("Couldn't get model bytes");
-
-    #[cfg(target_arch = "wasm32")]
-    let bytes = read_model_wasm(projectId, modelFilename).await.expect("Couldn't get model bytes");
+    script_state: Option<ScriptState>,
+) -> Result<(), ProjectError> {
+    let bytes = load_model_bytes(projectId, modelPath).await?;
 
     state.add_model(device, queue, &modelComponentId, &bytes, isometry, scale, camera, false, script_state);
 
-    state.add_collider(modelComponentId.clone(), ComponentKind::PlayerCharacter);
-
-    // TODO: provide model info for Player model and isometry for player position
-    let mut player_character = PlayerCharacter::new(
-        modelComponentId.clone(),
-        &mut state.rigid_body_set,
-        &mut state.collider_set,
-        &device,
-        &queue,
-        &state.model_bind_group_layout,
-        &state.group_bind_group_layout,
-        &state.texture_render_mode_buffer,
-        camera,
-        isometry,
-        scale,
-        default_weapon
-    );
+    state.world.place_player(&modelComponentId, isometry, scale, default_weapon);
+
+    Ok(())
+}
+
+// Reads a model file, modelPath is relative to the project root (see File::load_path)
+async fn load_model_bytes(projectId: String, modelPath: String) -> Result<Vec<u8>, ProjectError> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let bytes = read_model(projectId, modelPath.clone());
 
-    player_character.model_id = Some(modelComponentId); // may want to be an optional model later
+    #[cfg(target_arch = "wasm32")]
+    let bytes = read_model_wasm(projectId, modelPath.clone()).await;
 
-    state.player_character = Some(player_character);
+    bytes.map_err(|reason| ProjectError::MissingFile {
+        path: modelPath,
+        reason,
+    })
 }
 
 pub fn handle_key_press(state: &mut Editor, key_code: &str, is_pressed: bool) {
@@ -220,59 +214,12 @@ pub fn handle_key_press(state: &mut Editor, key_code: &str, is_pressed: bool) {
                 return;
             },
             "Enter" => {
-                 // Trigger option
-                 if !state.dialogue_state.options.is_empty() {
-                     let next_node = state.dialogue_state.options[state.dialogue_state.selected_option_index].next_node.clone();
-                     state.dialogue_state.current_node = next_node;
-                     
-                     // Find script again - TODO: cache script path in dialogue_state
-                     let mut script_path = String::new();
-                     if let Some(saved_state) = &state.saved_state {
-                         if let Some(levels) = &saved_state.levels {
-                             if let Some(level) = levels.get(0) {
-                                 if let Some(components) = &level.components {
-                                     for comp in components {
-                                         if comp.id == state.dialogue_state.current_npc_id {
-                                             if let Some(script) = &comp.rhai_script_path {
-                                                 script_path = script.clone();
-                                             }
-                                         }
-                                     }
-                                 }
-                             }
-                         }
-                     }
-                     
-                     if !script_path.is_empty() {
-                        if let Some(renderer_state) = state.renderer_state.as_mut() {
-                            state.rhai_engine.execute_interaction_script(
-                                renderer_state,
-                                &mut state.dialogue_state,
-                                &script_path,
-                                "interact"
-                            );
-                        }
-                     } else {
-                         // Close if no script found? or just close
-                         state.dialogue_state.is_open = false;
-                         state.dialogue_state.ui_dirty = true;
-                         // Handle cleanup of is_talking manually if script fails? 
-                         // Ideally execute_interaction_script handles it, but if we don't call it...
-                         if let Some(renderer_state) = state.renderer_state.as_mut() {
-                             if let Some(npc) = renderer_state.npcs.iter_mut().find(|n| n.model_id == state.dialogue_state.current_npc_id) {
-                                 npc.is_talking = false;
-                             }
-                         }
-                     }
-                 } else {
+                 if state.dialogue_state.options.is_empty() {
                      // No options, just close on enter
-                     state.dialogue_state.is_open = false;
-                     state.dialogue_state.ui_dirty = true;
-                     if let Some(renderer_state) = state.renderer_state.as_mut() {
-                         if let Some(npc) = renderer_state.npcs.iter_mut().find(|n| n.model_id == state.dialogue_state.current_npc_id) {
-                             npc.is_talking = false;
-                         }
-                     }
+                     close_dialogue(state);
+                 } else {
+                     let index = state.dialogue_state.selected_option_index;
+                     advance_dialogue(state, Some(index));
                  }
                  return;
             }
@@ -378,7 +325,7 @@ pub fn handle_mouse_input(state: &mut Editor, button: EntropyMouseButton, elemen
                             for model in &renderer_state.models {
                                 if model.id == hit_uuid {
                                     // Don't select the player character for now
-                                    if let Some(pc) = &renderer_state.player_character {
+                                    if let Some(pc) = &renderer_state.world.player_character {
                                         if pc.model_id.as_ref() == Some(&model.id) {
                                             continue;
                                         }
@@ -387,14 +334,12 @@ pub fn handle_mouse_input(state: &mut Editor, button: EntropyMouseButton, elemen
 
                                     // NOW FIND THE MATCHING COMPONENT ID
                                     if let Some(saved_state) = &state.saved_state {
-                                        if let Some(levels) = &saved_state.levels {
-                                            if let Some(level) = levels.get(0) {
-                                                if let Some(components) = &level.components {
-                                                    // Find component where asset_id matches the model id
-                                                    if let Some(component) = components.iter().find(|c| c.asset_id == model.id) {
-                                                        renderer_state.selected_component_id = Some(component.id.clone());
-                                                        println!("Selected model: {:?}, component: {:?}", model.id, component.id);
-                                                    }
+                                        if let Some(level) = saved_state.level(state.current_level_id.as_deref()) {
+                                            if let Some(components) = &level.components {
+                                                // Find component where asset_id matches the model id
+                                                if let Some(component) = components.iter().find(|c| c.asset_id == model.id) {
+                                                    renderer_state.selected_component_id = Some(component.id.clone());
+                                                    println!("Selected model: {:?}, component: {:?}", model.id, component.id);
                                                 }
                                             }
                                         }
@@ -424,13 +369,13 @@ pub fn handle_mouse_input(state: &mut Editor, button: EntropyMouseButton, elemen
     } else if renderer_state.game_mode && element_state == EntropyElementState::Pressed {
         match button {
             EntropyMouseButton::Left => {
-                if let Some(player_character) = &mut renderer_state.player_character {
+                if let Some(player_character) = &mut renderer_state.world.player_character {
                     if let Some(camera) = &state.camera {
                         let (attacked_npc_id, debug_line) = player_character.attack(
-                            &renderer_state.rigid_body_set,
-                            &renderer_state.collider_set,
-                            &mut renderer_state.query_pipeline,
-                            &mut renderer_state.npcs,
+                            &renderer_state.world.rigid_body_set,
+                            &renderer_state.world.collider_set,
+                            &mut renderer_state.world.query_pipeline,
+                            &mut renderer_state.world.npcs,
                             camera,
                         );
                         
@@ -442,21 +387,17 @@ pub fn handle_mouse_input(state: &mut Editor, button: EntropyMouseButton, elemen
                         // Execute Rhai on_attack scripts for the player
                         let mut script_changes = Vec::new();
                         if let Some(saved_state) = &state.saved_state {
-                            if let Some(levels) = &saved_state.levels {
-                                if let Some(components) = levels.get(0).and_then(|l| l.components.as_ref()) {
-                                    for component in components.iter() {
-                                        if component.kind == Some(ComponentKind::PlayerCharacter) {
-                                            if let Some(script_path) = &component.rhai_script_path {
-                                                println!("execute_component_script on_attack");
-                                                if let Some(change) = state.rhai_engine.execute_component_script(
-                                                    renderer_state,
-                                                    component,
-                                                    script_path,
-                                                    "on_attack",
-                                                ) {
-                                                    script_changes.push(change);
-                                                }
-                                            }
+                            if let Some(components) = saved_state.level_components(state.current_level_id.as_deref()) {
+                                for component in components.iter() {
+                                    if component.kind == Some(ComponentKind::PlayerCharacter) {
+                                        if let Some(script_path) = &component.rhai_script_path {
+                                            println!("execute_component_script on_attack");
+                                            script_changes.extend(state.rhai_engine.execute_component_script(
+                                                renderer_state,
+                                                component,
+                                                script_path,
+                                                "on_attack",
+                                            ));
                                         }
                                     }
                                 }
@@ -465,9 +406,10 @@ pub fn handle_mouse_input(state: &mut Editor, button: EntropyMouseButton, elemen
 
                         println!("script_changes {:?}", script_changes.len());
 
-                        // Handle particle spawns from on_attack
-                        for change in script_changes {
-                            if let Some(spawns) = change.particle_spawns {
+                        // Handle particle spawns from on_attack here, they aim along the hitscan line.
+                        // Everything else is applied with the next frame's script changes.
+                        for mut change in script_changes {
+                            if let Some(spawns) = change.particle_spawns.take() {
                                 let gpu_resources = state.gpu_resources.as_ref().expect("GPU resources missing");
                                 for spawn in spawns {
                                     if let Some((start, end)) = debug_line {
@@ -508,6 +450,8 @@ pub fn handle_mouse_input(state: &mut Editor, button: EntropyMouseButton, elemen
                                     };
                                 }
                             }
+
+                            state.pending_script_changes.push(change);
                         }
 
                         println!("particle_systems {:?}", renderer_state.particle_systems.len());
@@ -567,7 +511,7 @@ pub fn handle_mouse_input(state: &mut Editor, button: EntropyMouseButton, elemen
                 }
             }
             EntropyMouseButton::Right => {
-                if let Some(player_character) = &mut renderer_state.player_character {
+                if let Some(player_character) = &mut renderer_state.world.player_character {
                     player_character.defend();
                     println!("Right mouse button pressed - Player Defend!");
                 }
@@ -631,7 +575,7 @@ pub fn handle_mouse_move(mousePressed: bool, currentPosition: EntropyPosition, d
 
                             // also update rigidbody position
                             if let Some(rb_handle) = mesh.rigid_body_handle {
-                                if let Some(rb) = renderer_state.rigid_body_set.get_mut(rb_handle) {
+                                if let Some(rb) = renderer_state.world.rigid_body_set.get_mut(rb_handle) {
                                     let new_iso = Isometry3::from_parts(
                                         nalgebra::Translation3::new(new_transform.translation.x as f32, new_transform.translation.y as f32, new_transform.translation.z as f32),
                                         nalgebra::UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(new_transform.rotation.s as f32, new_transform.rotation.v.x as f32, new_transform.rotation.v.y as f32, new_transform.rotation.v.z as f32))
@@ -650,7 +594,7 @@ pub fn handle_mouse_move(mousePressed: bool, currentPosition: EntropyPosition, d
                             if let Some(project_id) = &saved_state.id {
                                 let mut component_updated = false;
                                 if let Some(levels) = saved_state.levels.as_mut() {
-                                    if let Some(level) = levels.get_mut(0) {
+                                    if let Some(level) = levels.iter_mut().find(|l| Some(&l.id) == state.current_level_id.as_ref()) {
                                         if let Some(components) = level.components.as_mut() {
                                             // if let Some(component) = components.iter_mut().find(|c| c.id == selected_id) {
                                             if let Some(component) = components.iter_mut().find(|c| c.id == component_id.clone()) {
@@ -662,9 +606,21 @@ pub fn handle_mouse_move(mousePressed: bool, currentPosition: EntropyPosition, d
                                                 
                                                 let new_scale = [mesh.transform.scale.x as f32, mesh.transform.scale.y as f32, mesh.transform.scale.z as f32];
 
-                                                component.generic_properties.position = new_pos;
-                                                component.generic_properties.rotation = new_rot;
                                                 component.generic_properties.scale = new_scale;
+
+                                                // children save their placement relative to the parent
+                                                let new_iso = Isometry3::from_parts(nalgebra::Translation3::from(Vector3::from(new_pos)), new_rot_quat);
+                                                let local = component.parent_id.as_ref().and_then(|_| {
+                                                    renderer_state.world.hierarchy.set_child_world(&component.id, new_iso, &component.generic_properties)
+                                                });
+
+                                                match local {
+                                                    Some(local) => component.generic_properties = local,
+                                                    None => {
+                                                        component.generic_properties.position = new_pos;
+                                                        component.generic_properties.rotation = new_rot;
+                                                    }
+                                                }
                                                 component_updated = true;
                                             }
                                         }
@@ -751,20 +707,18 @@ pub async fn handle_add_model(
     projectId: String,
     modelAssetId: String, // model is added to stored library as an asset
     modelComponentId: String, // model is added from library to scene as an active component
-    modelFilename: String,
+    modelPath: String,
     isometry: Isometry3<f32>,
     scale: Vector3<f32>,
     camera: &SimpleCamera,
-    script_state: Option<HashMap<String, String>>,
-) {
-    #[cfg(target_os = "windows")]
-    let bytes = read_model(projectId, modelFilename).expect("Couldn't get model bytes");
-
-    #[cfg(target_arch = "wasm32")]
-    let bytes = read_model_wasm(projectId, modelFilename).await.expect("Couldn't get model bytes");
+    script_state: Option<ScriptState>,
+) -> Result<(), ProjectError> {
+    let bytes = load_model_bytes(projectId, modelPath).await?;
 
     state.add_model(device, queue, &modelComponentId, &bytes, isometry, scale, camera, false, script_state);
     state.add_collider(modelComponentId, ComponentKind::Model);
+
+    Ok(())
 }
 
 pub async fn handle_add_npc(
@@ -774,32 +728,24 @@ pub async fn handle_add_npc(
     projectId: String,
     modelAssetId: String, // model is added to stored library as an asset
     npcComponentId: String, // model is added from library to scene as an active component
-    modelFilename: String,
+    modelPath: String,
     isometry: Isometry3<f32>,
     scale: Vector3<f32>,
     camera: &SimpleCamera,
-    script_state: Option<HashMap<String, String>>,
-) {
-    #[cfg(target_os = "windows")]
-    let bytes = read_model(projectId, modelFilename).expect("Couldn't get model bytes");
-
-    #[cfg(target_arch = "wasm32")]
-    let bytes = read_model_wasm(projectId, modelFilename).await.expect("Couldn't get model bytes");
+    script_state: Option<ScriptState>,
+    archetype: &NPCArchetype,
+) -> Result<(), ProjectError> {
+    let bytes = load_model_bytes(projectId, modelPath).await?;
 
     state.add_model(device, queue, &npcComponentId, &bytes, isometry, scale, camera, false, script_state);
 
-    state.add_collider(npcComponentId.clone(), ComponentKind::NPC);
+    let handle = state
+        .world
+        .place_npc(&npcComponentId, isometry, archetype)
+        .map_err(|reason| ProjectError::InvalidComponent { component_id: npcComponentId.clone(), reason })?;
+    state.attach_body(&npcComponentId, handle);
 
-    // Retrieve the rigid_body_handle after the collider has been added
-    let npc_rigid_body_handle = state
-        .models
-        .iter()
-        .find(|m| m.id == npcComponentId)
-        .and_then(|m| m.meshes.get(0))
-        .and_then(|mesh| mesh.rigid_body_handle)
-        .expect("Couldn't retrieve rigid body handle for NPC after adding collider");
-
-    state.npcs.push(NPC::new(npcComponentId.clone(), npcComponentId.clone(), npc_rigid_body_handle));
+    Ok(())
 }
 
 pub async fn handle_add_collectable(
@@ -809,37 +755,31 @@ pub async fn handle_add_collectable(
     projectId: String,
     modelAssetId: String, // model is added to stored library as an asset
     modelComponentId: String, // model is added from library to scene as an active component
-    modelFilename: String,
+    modelPath: String,
     isometry: Isometry3<f32>,
     scale: Vector3<f32>,
     camera: &SimpleCamera,
     collectable_properties: &CollectableProperties,
     related_stat: &StatData,
     hide_in_world: bool,
-    script_state: Option<HashMap<String, String>>,
-) {
-    #[cfg(target_os = "windows")]
-    let bytes = read_model(projectId, modelFilename).expect("Couldn't get model bytes");
+    script_state: Option<ScriptState>,
+) -> Result<(), ProjectError> {
+    let collectable_type = collectable_properties.collectable_type.clone().ok_or_else(|| ProjectError::MissingProperty {
+        component_id: modelComponentId.clone(),
+        property: "collectable_type".to_string(),
+    })?;
 
-    #[cfg(target_arch = "wasm32")]
-    let bytes = read_model_wasm(projectId, modelFilename).await.expect("Couldn't get model bytes");
+    let bytes = load_model_bytes(projectId, modelPath).await?;
 
     state.add_model(device, queue, &modelAssetId, &bytes, isometry, scale, camera, hide_in_world, script_state);
 
-    state.add_collider(modelAssetId.clone(), ComponentKind::Collectable);
-
-    // Retrieve the rigid_body_handle after the collider has been added
-    let npc_rigid_body_handle = state
-        .models
-        .iter()
-        .find(|m| m.id == modelAssetId)
-        .and_then(|m| m.meshes.get(0))
-        .and_then(|mesh| mesh.rigid_body_handle)
-        .expect("Couldn't retrieve rigid body handle for NPC after adding collider");
-
-    let collectable_type = collectable_properties.collectable_type.as_ref().expect("Couldn't get collectable type");
+    let handle = state
+        .world
+        .place_collectable(&modelComponentId, &modelAssetId, isometry, collectable_type, related_stat.clone())
+        .map_err(|reason| ProjectError::InvalidComponent { component_id: modelComponentId.clone(), reason })?;
+    state.attach_body(&modelAssetId, handle);
 
-    state.collectables.push(Collectable::new(modelComponentId.clone(), modelAssetId.clone(), collectable_type.clone(), related_stat.clone(), npc_rigid_body_handle));
+    Ok(())
 }
 
 #[derive(Serialize, Deserialize)]
@@ -863,19 +803,21 @@ pub async fn handle_add_landscape(
     projectId: String,
     landscapeAssetId: String,
     landscapeComponentId: String,
-    landscapeFilename: String,
+    heightmapPath: String, // relative to the project root, see File::load_path
     position: [f32; 3],
     camera: &mut SimpleCamera
-) {
+) -> Result<(), ProjectError> {
     // w/o quadtree
-    #[cfg(target_os = "windows")]
-    let data = get_landscape_pixels(projectId, landscapeAssetId, landscapeFilename);
+    #[cfg(not(target_arch = "wasm32"))]
+    let data = get_landscape_pixels(projectId, landscapeAssetId, heightmapPath.clone());
 
     #[cfg(target_arch = "wasm32")]
-    let data = get_landscape_pixels_wasm(projectId, landscapeAssetId, landscapeFilename).await;
+    let data = get_landscape_pixels_wasm(projectId, landscapeAssetId, heightmapPath.clone()).await;
+
+    let data = data.map_err(|reason| ProjectError::MissingFile { path: heightmapPath, reason })?;
 
     state.add_landscape(device, queue, &landscapeComponentId, &data, position, camera);
-    state.add_collider(landscapeComponentId, ComponentKind::Landscape);
+    state.attach_landscape_body(&landscapeComponentId, &data, position);
 
     // with quadtree
     // state.add_terrain_manager(
@@ -888,6 +830,8 @@ pub async fn handle_add_landscape(
     //     position,
     //     camera
     // );
+
+    Ok(())
 }
 
 pub async fn handle_add_landscape_texture(
@@ -919,10 +863,17 @@ pub async fn handle_add_landscape_texture(
     let mask = fetch_mask_data(
         project_id.clone(),
         landscape_asset_id.clone(),
-        mask_filename,
-        texture_kind.clone(),
+        format!("{}/{}", mask_folder(&landscape_asset_id, &texture_kind), mask_filename),
     ).await;
 
+    let (texture, mask) = match (texture, mask) {
+        (Ok(texture), Ok(mask)) => (texture, mask),
+        (Err(e), _) | (_, Err(e)) => {
+            println!("Skipping landscape texture: {}", e);
+            return;
+        }
+    };
+
     let maskKind = match texture_kind_clone {
         LandscapeTextureKinds::Primary => LandscapeTextureKinds::PrimaryMask,
         LandscapeTextureKinds::Rockmap => LandscapeTextureKinds::RockmapMask,
@@ -947,35 +898,34 @@ pub async fn fetch_texture_data(
     project_id: String,
     landscape_id: String,
     texture_filename: String,
-) -> Texture {
-    #[cfg(target_os = "windows")]
-    let texture_data =
-            read_landscape_texture(project_id, landscape_id, texture_filename)
-                .expect("Couldn't get texture data");
+) -> Result<Texture, ProjectError> {
+    let texture_path = format!("textures/{}.png", texture_filename);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let texture_data = read_landscape_texture(project_id, landscape_id, texture_filename);
 
     #[cfg(target_arch = "wasm32")]
-    let texture_data =
-        read_landscape_texture_wasm(project_id, landscape_id, texture_filename).await
-            .expect("Couldn't get texture data");
+    let texture_data = read_landscape_texture_wasm(project_id, landscape_id, texture_filename).await;
 
-    Texture::new(texture_data.bytes, texture_data.width, texture_data.height)
+    let texture_data = texture_data.map_err(|reason| ProjectError::MissingFile { path: texture_path, reason })?;
+
+    Ok(Texture::new(texture_data.bytes, texture_data.width, texture_data.height))
 }
 
 pub async fn fetch_mask_data(
     project_id: String,
     landscape_id: String,
-    mask_filename: String,
-    mask_kind: LandscapeTextureKinds,
-) -> Texture {
-    #[cfg(target_os = "windows")]
-    let mask_data = read_landscape_mask(project_id, landscape_id, mask_filename, mask_kind)
-        .expect("Couldn't get mask data");
+    mask_path: String, // relative to the project root, see File::load_path
+) -> Result<Texture, ProjectError> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let mask_data = read_landscape_mask(project_id, landscape_id, mask_path.clone());
 
     #[cfg(target_arch = "wasm32")]
-    let mask_data = read_landscape_mask_wasm(project_id, landscape_id, mask_filename, mask_kind).await
-        .expect("Couldn't get mask data");
+    let mask_data = read_landscape_mask_wasm(project_id, landscape_id, mask_path.clone()).await;
 
-    Texture::new(mask_data.bytes, mask_data.width, mask_data.height)
+    let mask_data = mask_data.map_err(|reason| ProjectError::MissingFile { path: mask_path, reason })?;
+
+    Ok(Texture::new(mask_data.bytes, mask_data.width, mask_data.height))
 }
 
 pub fn handle_add_grass(
@@ -1022,6 +972,7 @@ pub fn handle_add_water_plane(
         let config = WaterConfig::default();
         let water_plane = WaterPlane::new(device, camera_bind_group_layout, texture_format, landscape_obj, config);
         state.water_planes.push(water_plane);
+        state.water_plane_ids.push(component_id);
     }
 }
 
@@ -1080,13 +1031,13 @@ fn handle_npc_interaction(state: &mut Editor) {
         None => return,
     };
     
-    let player = match &renderer_state.player_character {
+    let player = match &renderer_state.world.player_character {
         Some(p) => p,
         None => return,
     };
     
     let player_handle = player.movement_rigid_body_handle.as_ref().expect("Couldn't get player rigidbody");
-    let player_pos = if let Some(rb) = renderer_state.rigid_body_set.get(*player_handle) {
+    let player_pos = if let Some(rb) = renderer_state.world.rigid_body_set.get(*player_handle) {
         rb.translation().clone()
     } else {
         return;
@@ -1094,8 +1045,8 @@ fn handle_npc_interaction(state: &mut Editor) {
 
     let mut target_id = String::new();
     
-    for npc in &renderer_state.npcs {
-        if let Some(rb) = renderer_state.rigid_body_set.get(npc.rigid_body_handle) {
+    for npc in &renderer_state.world.npcs {
+        if let Some(rb) = renderer_state.world.rigid_body_set.get(npc.rigid_body_handle) {
             let npc_pos = rb.translation();
             let dist = (npc_pos - player_pos).magnitude();
             // Using 50.0 as interaction range
@@ -1111,43 +1062,140 @@ fn handle_npc_interaction(state: &mut Editor) {
     }
 
     // println!("Running interact... {:?}", target_id);
-    
-    let mut target_script_path = None;
-    let mut target_npc_name = String::new();
-    
-    if let Some(saved_state) = &state.saved_state {
-        if let Some(levels) = &saved_state.levels {
-             if let Some(level) = levels.get(0) {
-                 if let Some(components) = &level.components {
-                     for comp in components {
-                         if let Some(kind) = &comp.kind {
-                             if let ComponentKind::NPC = kind {
-                                //  if let Some(props) = &comp.npc_properties {
-                                     if comp.id == target_id {
-                                         if let Some(script) = &comp.rhai_script_path {
-                                             target_script_path = Some(script.clone());
-                                             target_npc_name = comp.generic_properties.name.clone();
-                                         }
-                                     }
-                                //  }
-                             }
-                         }
-                     }
-                 }
-             }
+
+    let (script_path, npc_name, dialogue_id) = npc_dialogue_source(state, &target_id);
+
+    println!("target_npc_name... {:?} {:?} {:?}", target_id, npc_name, script_path);
+
+    if script_path.is_some() || dialogue_id.is_some() {
+        state.dialogue_state.npc_name = npc_name;
+        state.dialogue_state.current_npc_id = target_id;
+        advance_dialogue(state, None);
+    }
+}
+
+// The NPC's script, name and dialogue graph, from the current level
+fn npc_dialogue_source(state: &Editor, npc_id: &str) -> (Option<String>, String, Option<String>) {
+    let component = state
+        .saved_state
+        .as_ref()
+        .and_then(|s| s.level_components(state.current_level_id.as_deref()))
+        .and_then(|components| components.iter().find(|c| c.id == npc_id && c.kind == Some(ComponentKind::NPC)));
+
+    match component {
+        Some(component) => (
+            component.rhai_script_path.clone(),
+            component
+                .npc_properties
+                .as_ref()
+                .and_then(|p| p.name.clone())
+                .unwrap_or_else(|| component.generic_properties.name.clone()),
+            component.npc_properties.as_ref().and_then(|p| p.dialogue_id.clone()),
+        ),
+        None => (None, String::new(), None),
+    }
+}
+
+// Starts the conversation with the current NPC when `choice` is None, otherwise picks that option.
+// Dialogue graphs run through dialogue_graph, script conversations and a graph's custom nodes
+// through the NPC's script.
+fn advance_dialogue(state: &mut Editor, choice: Option<usize>) {
+    let npc_id = state.dialogue_state.current_npc_id.clone();
+    let (script_path, npc_name, dialogue_id) = npc_dialogue_source(state, &npc_id);
+
+    let graph_id = match choice {
+        None => dialogue_id,
+        Some(_) => state.dialogue_state.dialogue_id.clone(),
+    };
+
+    let step = match graph_id.as_ref().and_then(|id| state.dialogues.get(id)) {
+        Some(dialogue) => {
+            let items = state
+                .saved_state
+                .as_ref()
+                .and_then(|s| s.level_components(state.current_level_id.as_deref()))
+                .map(|c| c.as_slice())
+                .unwrap_or(&[]);
+            let mut quests = state.quest_log.borrow_mut();
+            let inventory = state
+                .renderer_state
+                .as_mut()
+                .and_then(|r| r.world.player_character.as_mut())
+                .map(|p| &mut p.inventory);
+            let mut blackboard = state.rhai_engine.blackboard.borrow_mut();
+            let mut context = DialogueContext {
+                npc_id: &npc_id,
+                npc_name: &npc_name,
+                blackboard: &mut blackboard,
+                quests: &mut quests,
+                inventory,
+                items,
+            };
+
+            match choice {
+                None => dialogue_graph::start(dialogue, &mut state.dialogue_state, &mut context),
+                Some(index) => dialogue_graph::choose(dialogue, &mut state.dialogue_state, index, &mut context),
+            }
+        }
+        None => {
+            // the option's node becomes current and the script decides what it shows,
+            // a new conversation starts over rather than where the last one stopped
+            let next_node = match choice {
+                None => Some(String::new()),
+                Some(index) => state.dialogue_state.options.get(index).map(|o| o.next_node.clone()),
+            };
+            if let Some(next_node) = next_node {
+                state.dialogue_state.current_node = next_node;
+            }
+            DialogueStep::Script(INTERACT_HOOK.to_string())
+        }
+    };
+
+    match step {
+        DialogueStep::Shown => set_npc_talking(state, true),
+        DialogueStep::Closed => set_npc_talking(state, false),
+        DialogueStep::Script(hook) => {
+            let ran = match (&script_path, state.renderer_state.as_mut()) {
+                (Some(script), Some(renderer_state)) => {
+                    state.rhai_engine.execute_interaction_script(renderer_state, &mut state.dialogue_state, script, &hook);
+                    true
+                }
+                _ => false,
+            };
+
+            if !ran {
+                // nothing to run the node, so the conversation ends here
+                close_dialogue(state);
+                return;
+            }
         }
     }
 
-    println!("target_npc_name... {:?} {:?} {:?}", target_id, target_npc_name, target_script_path);
-    
-    if let Some(script) = target_script_path {
-        state.dialogue_state.npc_name = target_npc_name;
-        state.dialogue_state.current_npc_id = target_id;
-        state.rhai_engine.execute_interaction_script(
-            state.renderer_state.as_mut().unwrap(), // Need to pass renderer_state
-            &mut state.dialogue_state,
-            &script,
-            "interact"
-        );
+    notify_dialogue_node(state);
+}
+
+fn close_dialogue(state: &mut Editor) {
+    dialogue_graph::close(&mut state.dialogue_state);
+    set_npc_talking(state, false);
+}
+
+fn set_npc_talking(state: &mut Editor, talking: bool) {
+    if let Some(renderer_state) = state.renderer_state.as_mut() {
+        if let Some(npc) = renderer_state.world.npcs.iter_mut().find(|n| n.model_id == state.dialogue_state.current_npc_id) {
+            npc.is_talking = talking;
+        }
     }
+}
+
+// Talk objectives count the dialogue node the NPC's script left the conversation on
+fn notify_dialogue_node(state: &mut Editor) {
+    let dialogue = &state.dialogue_state;
+    if !dialogue.is_open && dialogue.current_node.is_empty() {
+        return;
+    }
+
+    state.quest_log.borrow_mut().notify(&QuestEvent::Talked {
+        npc_id: dialogue.current_npc_id.clone(),
+        node: dialogue.current_node.clone(),
+    });
 }
\ No newline at end of file
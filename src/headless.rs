@@ -0,0 +1,812 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use nalgebra::{Isometry3, UnitQuaternion, Vector3};
+use rapier3d::prelude::*;
+use rhai::Dynamic;
+use serde::Serialize;
+
+use crate::art_assets::Model::{read_model, read_model_physics};
+use crate::core::SceneHierarchy::{component_placements, Placement, SceneHierarchy};
+use crate::core::World::{ComponentCollision, World};
+use crate::game_behaviors::behavior_tree::ScriptLeafRequest;
+use crate::game_behaviors::quests::QuestLog;
+use crate::helpers::landscapes::get_landscape_pixels;
+use crate::helpers::load_project::LevelChange;
+use crate::helpers::project_error::{check_component, ProjectError};
+use crate::helpers::saved_data::{CharacterStats, ComponentData, ComponentKind, SavedState, ScriptState};
+use crate::helpers::utilities;
+use crate::model_components::NPC::NPCArchetype;
+use crate::rhai_engine::{euler_degrees, json_to_dynamic, ComponentChanges, PlayerWrapper, RhaiEngine, SceneComponent, ScriptScene, WrapperFields};
+use crate::water_plane::config::WaterConfig;
+
+// Headless simulation: loads a SavedState into a World and steps physics, NPC behaviors and Rhai hooks
+// at a fixed rate without a window or GPU device. Useful for servers, CI and soak tests.
+
+pub struct HeadlessConfig {
+    pub frames: u32,
+    pub tick_rate: f32, // steps per second
+    // behaviors time their states with Instant, so by default we sleep to keep wall clock and sim time in step
+    pub realtime: bool,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        HeadlessConfig {
+            frames: 600,
+            tick_rate: 60.0,
+            realtime: true,
+        }
+    }
+}
+
+pub struct ScriptedComponent {
+    pub id: String,
+    pub kind: ComponentKind,
+    pub script_path: String,
+    pub script_state: Option<ScriptState>,
+    pub rigid_body_handle: Option<RigidBodyHandle>,
+    pub placed: Isometry3<f32>, // for kinds without a body, like lights
+    pub scale: Vector3<f32>, // bodies don't carry scale, so it stays as placed
+    pub fields: WrapperFields, // what the script sees besides the transform, NPCs read theirs when called
+}
+
+#[derive(Serialize, Debug)]
+pub struct BodyReport {
+    pub id: String,
+    pub kind: Option<ComponentKind>,
+    pub position: [f32; 3],
+}
+
+#[derive(Serialize, Debug)]
+pub struct NPCReport {
+    pub id: String,
+    pub health: f32,
+    pub stamina: f32,
+    pub position: [f32; 3],
+}
+
+#[derive(Serialize, Debug)]
+pub struct HeadlessReport {
+    pub project_id: String,
+    pub frames: u32,
+    pub simulated_seconds: f32,
+    pub wall_seconds: f32,
+    pub rigid_bodies: usize,
+    pub colliders: usize,
+    pub script_calls: u32,
+    pub script_changes: u32,
+    pub particle_spawns: u32,
+    pub collision_events: u32,
+    pub damage_to_player: f32,
+    pub player: Option<BodyReport>,
+    pub player_stats: Option<CharacterStats>,
+    pub npcs: Vec<NPCReport>,
+    pub bodies: Vec<BodyReport>,
+    pub skipped_components: Vec<String>,
+}
+
+impl HeadlessReport {
+    pub fn print(&self) {
+        println!("Headless run for project {}", self.project_id);
+        println!(
+            "  frames: {} ({:.2}s simulated, {:.2}s wall)",
+            self.frames, self.simulated_seconds, self.wall_seconds
+        );
+        println!("  rigid bodies: {}, colliders: {}", self.rigid_bodies, self.colliders);
+        println!(
+            "  script calls: {}, changes: {}, particle spawns: {}, collisions: {}",
+            self.script_calls, self.script_changes, self.particle_spawns, self.collision_events
+        );
+
+        if let (Some(player), Some(stats)) = (&self.player, &self.player_stats) {
+            println!(
+                "  player {} at {:?}, health {:.1}, stamina {:.1} (took {:.1} damage)",
+                player.id, player.position, stats.health, stats.stamina, self.damage_to_player
+            );
+        }
+
+        for npc in &self.npcs {
+            println!(
+                "  npc {} at {:?}, health {:.1}, stamina {:.1}",
+                npc.id, npc.position, npc.health, npc.stamina
+            );
+        }
+
+        for body in &self.bodies {
+            println!("  {:?} {} at {:?}", body.kind, body.id, body.position);
+        }
+
+        for skipped in &self.skipped_components {
+            println!("  skipped: {}", skipped);
+        }
+    }
+}
+
+pub struct HeadlessSim {
+    pub project_id: String,
+    pub saved_state: SavedState,
+    pub current_level_id: Option<String>,
+    pub pending_level: Option<LevelChange>, // set by portals and scripts, loaded at the start of the next step
+
+    pub world: World,
+
+    pub player_weapon: Option<ComponentData>,
+    pub scripted_components: Vec<ScriptedComponent>,
+    pub component_bodies: Vec<(String, ComponentKind, RigidBodyHandle)>,
+    scene_components: Vec<(ComponentData, Placement)>, // what passed check_component, for capture_scene
+    pub skipped_components: Vec<String>,
+
+    pub rhai_engine: RhaiEngine,
+    pub quest_log: Rc<RefCell<QuestLog>>,
+
+    frames: u32,
+    script_calls: u32,
+    script_changes: u32,
+    particle_spawns: u32,
+    collision_events: u32,
+    damage_to_player: f32,
+}
+
+impl HeadlessSim {
+    pub async fn load(project_id: &str) -> Result<Self, Box<dyn Error>> {
+        let saved_state = utilities::load_project_state(project_id).await?;
+
+        let mut sim = HeadlessSim::new(project_id, saved_state);
+        sim.place_components();
+        sim.start();
+
+        Ok(sim)
+    }
+
+    pub fn new(project_id: &str, saved_state: SavedState) -> Self {
+        let quest_log = Rc::new(RefCell::new(QuestLog::new(saved_state.quests.clone().unwrap_or_default())));
+        let mut rhai_engine = RhaiEngine::new();
+        rhai_engine.bind_quests(quest_log.clone());
+
+        HeadlessSim {
+            project_id: project_id.to_string(),
+            current_level_id: saved_state.start_level_id(),
+            pending_level: None,
+            saved_state,
+
+            world: World::new(),
+
+            player_weapon: None,
+            scripted_components: Vec::new(),
+            component_bodies: Vec::new(),
+            scene_components: Vec::new(),
+            skipped_components: Vec::new(),
+
+            rhai_engine,
+            quest_log,
+
+            frames: 0,
+            script_calls: 0,
+            script_changes: 0,
+            particle_spawns: 0,
+            collision_events: 0,
+            damage_to_player: 0.0,
+        }
+    }
+
+    // mirrors place_project, but only builds what the simulation needs
+    pub fn place_components(&mut self) {
+        let components = match self.saved_state.level_components(self.current_level_id.as_deref()).cloned() {
+            Some(components) => components,
+            None => {
+                println!("No level components to simulate");
+                return;
+            }
+        };
+
+        let placements = component_placements(&components);
+
+        for component in &components {
+            if let Err(e) = check_component(&self.saved_state, &components, component) {
+                self.skipped_components.push(e.to_string());
+                continue;
+            }
+
+            let kind = match &component.kind {
+                Some(kind) => kind.clone(),
+                None => continue,
+            };
+
+            let placement = placements
+                .get(&component.id)
+                .copied()
+                .unwrap_or_else(|| Placement::from_generic(&component.generic_properties));
+            self.scene_components.push((component.clone(), placement));
+            let position = placement.position();
+            let model_iso = placement.isometry;
+            let model_scale = placement.scale;
+
+            // bodies are placed by the same World methods the renderer's handlers use
+            let placed = match kind {
+                ComponentKind::Landscape => {
+                    let landscape_data = self
+                        .saved_state
+                        .landscapes
+                        .as_ref()
+                        .and_then(|landscapes| landscapes.iter().find(|l| l.id == component.asset_id));
+
+                    match landscape_data.and_then(|l| l.heightmap.as_ref()) {
+                        Some(heightmap) => {
+                            let heightmap_path = heightmap
+                                .load_path(&self.project_id, &format!("landscapes/{}/heightmaps", component.asset_id));
+
+                            get_landscape_pixels(self.project_id.clone(), component.asset_id.clone(), heightmap_path.clone())
+                                .map_err(|reason| ProjectError::MissingFile { path: heightmap_path, reason }.to_string())
+                                .and_then(|data| self.world.place_landscape(&component.id, &data, position))
+                                .map(|(handle, _)| Some(handle))
+                        }
+                        None => Err("landscape has no heightmap".to_string()),
+                    }
+                }
+                ComponentKind::Model => {
+                    let asset = self.saved_state.models.iter().find(|m| m.id == component.asset_id).cloned();
+
+                    asset
+                        .ok_or_else(|| "missing model asset".to_string())
+                        .and_then(|asset| read_model(self.project_id.clone(), asset.load_path(&self.project_id, "models")))
+                        .and_then(|bytes| read_model_physics(&component.id, &bytes, model_iso, model_scale))
+                        .map(|bodies| {
+                            let mut first_handle = None;
+                            for (rigid_body, collider) in bodies {
+                                let (handle, _) = self.world.insert_body(rigid_body, collider);
+                                first_handle.get_or_insert(handle);
+                            }
+                            first_handle
+                        })
+                }
+                ComponentKind::NPC => {
+                    let archetype = NPCArchetype::new(&self.saved_state, component, &components);
+                    self.world.place_npc(&component.id, model_iso, &archetype).map(Some)
+                }
+                ComponentKind::PlayerCharacter if self.world.player_character.is_some() => {
+                    // carried over from the previous level, move them to this level's start
+                    self.world.teleport_player(position);
+                    continue;
+                }
+                ComponentKind::PlayerCharacter => {
+                    if let Some(default_weapon_id) = component
+                        .player_properties
+                        .as_ref()
+                        .and_then(|p| p.default_weapon_id.clone())
+                    {
+                        self.player_weapon = components.iter().find(|c| c.id == default_weapon_id).cloned();
+                    }
+
+                    Ok(self.world.place_player(&component.id, model_iso, model_scale, self.player_weapon.clone()))
+                }
+                ComponentKind::Collectable => {
+                    let properties = component.collectable_properties.as_ref();
+                    let collectable_type = properties.and_then(|p| p.collectable_type.clone());
+                    let stats = properties
+                        .and_then(|p| p.stat_id.as_ref())
+                        .and_then(|stat_id| self.saved_state.stats.as_ref()?.iter().find(|s| &s.id == stat_id))
+                        .cloned();
+
+                    match (collectable_type, stats) {
+                        (Some(collectable_type), Some(stats)) => self
+                            .world
+                            .place_collectable(&component.id, &component.asset_id, model_iso, collectable_type, stats)
+                            .map(Some),
+                        _ => Err("collectable has no type or stat".to_string()),
+                    }
+                }
+                ComponentKind::TriggerVolume => {
+                    let properties = component.trigger_properties.clone().unwrap_or_default();
+                    self.world.place_trigger_volume(&component.id, &properties, model_iso, model_scale).map(Some)
+                }
+                _ => Ok(None),
+            };
+
+            let rigid_body_handle = match placed {
+                Ok(handle) => handle,
+                Err(e) => {
+                    self.skipped_components.push(format!("{} ({})", component.id, e));
+                    None
+                }
+            };
+
+            if let Some(handle) = rigid_body_handle {
+                self.component_bodies.push((component.id.clone(), kind.clone(), handle));
+            }
+
+            if let Some(script_path) = &component.rhai_script_path {
+                let fields = match kind {
+                    ComponentKind::NPC => WrapperFields::NPC { health: 0.0, stamina: 0.0 },
+                    ComponentKind::Collectable => WrapperFields::Collectable {
+                        collectable_type: component
+                            .collectable_properties
+                            .as_ref()
+                            .and_then(|p| p.collectable_type.as_ref())
+                            .map(|t| format!("{:?}", t))
+                            .unwrap_or_default(),
+                    },
+                    ComponentKind::PointLight => {
+                        let light = component.light_properties.clone().unwrap_or_default();
+                        WrapperFields::PointLight { intensity: light.intensity, color: [light.color[0], light.color[1], light.color[2]] }
+                    }
+                    ComponentKind::WaterPlane => WrapperFields::WaterPlane { config: WaterConfig::default() },
+                    _ => WrapperFields::Model,
+                };
+
+                self.scripted_components.push(ScriptedComponent {
+                    id: component.id.clone(),
+                    kind,
+                    script_path: script_path.clone(),
+                    script_state: component.script_state.clone(),
+                    rigid_body_handle,
+                    placed: model_iso,
+                    scale: model_scale,
+                    fields,
+                });
+            }
+        }
+
+        self.world.hierarchy = SceneHierarchy::new(&components, &self.world.rigid_body_set);
+        self.world.update_query_pipeline();
+
+        println!(
+            "Headless level placed: {} bodies, {} npcs, {} scripts",
+            self.world.rigid_body_set.len(),
+            self.world.npcs.len(),
+            self.scripted_components.len()
+        );
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        // switch levels requested by portals or scripts during the last step
+        if let Some(level_change) = self.pending_level.take() {
+            self.load_level(&level_change.level_id, level_change.spawn_position);
+        }
+
+        self.world.integration_parameters.dt = dt;
+
+        let events = self.world.step(dt);
+        self.damage_to_player += events.damage_to_player;
+
+        let portal = self
+            .saved_state
+            .level_components(self.current_level_id.as_deref())
+            .and_then(|components| self.world.enter_portal(components));
+        if let Some(portal) = portal {
+            self.pending_level = Some(LevelChange {
+                level_id: portal.target_level_id,
+                spawn_position: portal.spawn_position,
+            });
+        }
+
+        // every hook this frame sees the level as it was before any of them ran
+        self.capture_scene();
+        self.rhai_engine.begin_frame();
+        self.run_collision_scripts(&events.collisions);
+        self.run_timers(dt);
+        self.run_behavior_scripts(&events.script_leaves);
+
+        if let Some(components) = self.saved_state.level_components(self.current_level_id.as_deref()) {
+            self.quest_log.borrow_mut().observe(&self.world, components);
+        }
+
+        self.run_scripts("on_update");
+        self.run_global_scripts("on_update");
+        self.rhai_engine.end_frame();
+
+        self.frames += 1;
+    }
+
+    // Script lifecycle for the placed level, same order the editor uses
+    pub fn start(&mut self) {
+        self.rhai_engine.configure(&self.saved_state.script_settings);
+        self.rhai_engine.load_global_scripts(&self.saved_state.global_rhai_scripts);
+        self.capture_scene();
+        self.run_scripts("on_start");
+        self.run_global_scripts("on_level_load");
+    }
+
+    pub fn stop(&mut self) {
+        self.capture_scene();
+        self.run_global_scripts("on_level_unload");
+        self.run_scripts("on_destroy");
+    }
+
+    // Swaps the current level for another one, like load_project::load_level. The player, their stats
+    // and what they carry over.
+    pub fn load_level(&mut self, level_id: &str, spawn_position: Option<[f32; 3]>) {
+        if self.saved_state.level(Some(level_id)).is_none() {
+            println!("Level {} not found, staying put", level_id);
+            return;
+        }
+
+        println!("Loading level {}", level_id);
+
+        self.capture_scene();
+        self.run_global_scripts("on_level_unload");
+        self.run_level_scripts("on_destroy");
+        self.rhai_engine.timers.borrow_mut().unload_level();
+
+        self.world.unload_level();
+        let kept_bodies = &self.world.rigid_body_set;
+        self.component_bodies.retain(|(_, _, handle)| kept_bodies.contains(*handle));
+        self.scene_components.clear();
+        self.scripted_components.retain(|c| c.kind == ComponentKind::PlayerCharacter);
+
+        self.current_level_id = Some(level_id.to_string());
+        self.place_components();
+        self.quest_log.borrow_mut().reset_observed();
+
+        self.capture_scene();
+        self.run_level_scripts("on_start");
+        self.run_global_scripts("on_level_load");
+
+        if let Some(position) = spawn_position {
+            self.world.teleport_player(position);
+        }
+    }
+
+    // The player only spawns once, so their on_start/on_destroy are left out when they carry over between levels
+    fn run_level_scripts(&mut self, hook_name: &str) {
+        let (player, level): (Vec<_>, Vec<_>) = std::mem::take(&mut self.scripted_components)
+            .into_iter()
+            .partition(|c| c.kind == ComponentKind::PlayerCharacter);
+
+        self.scripted_components = level;
+        self.run_scripts(hook_name);
+        self.scripted_components.extend(player);
+    }
+
+    // What lookups and raycasts see during the hooks about to run: components at their
+    // current body transforms, or where they were placed when they have no body
+    fn capture_scene(&mut self) {
+        let level_id = self.current_level_id.clone().unwrap_or_default();
+
+        let components = self
+            .scene_components
+            .iter()
+            .map(|(c, placement)| {
+                let body = self
+                    .component_bodies
+                    .iter()
+                    .find(|(id, _, _)| *id == c.id)
+                    .and_then(|(_, _, handle)| self.world.rigid_body_set.get(*handle));
+                let isometry = body.map(|rb| *rb.position()).unwrap_or(placement.isometry);
+
+                SceneComponent {
+                    id: c.id.clone(),
+                    name: c.generic_properties.name.clone(),
+                    kind: c.kind.as_ref().map(|k| format!("{:?}", k)).unwrap_or_default(),
+                    position: isometry.translation.vector,
+                    rotation: euler_degrees(&isometry.rotation),
+                    scale: placement.scale,
+                }
+            })
+            .collect();
+
+        self.rhai_engine.set_scene(ScriptScene::new(&level_id, components).with_world(&self.world));
+    }
+
+    // Runs a hook of every global script, with components at their current body positions
+    pub fn run_global_scripts(&mut self, hook_name: &str) {
+        let scripts = self.saved_state.global_rhai_scripts.clone().unwrap_or_default();
+        if scripts.is_empty() {
+            return;
+        }
+
+        for script_path in &scripts {
+            self.script_calls += 1;
+            for change in self.rhai_engine.execute_global_hook(script_path, hook_name) {
+                self.apply_change(change);
+            }
+        }
+    }
+
+    fn apply_change(&mut self, change: ComponentChanges) {
+        self.script_changes += 1;
+
+        if let Some(spawns) = &change.particle_spawns {
+            self.particle_spawns += spawns.len() as u32;
+        }
+
+        if let Some(level_id) = &change.load_level {
+            self.pending_level = Some(LevelChange {
+                level_id: level_id.clone(),
+                spawn_position: None,
+            });
+        }
+
+        if let Some(source_id) = &change.spawn_from {
+            println!("Script spawned {}, headless runs don't place spawned components", source_id);
+            return;
+        }
+
+        let handle = self
+            .component_bodies
+            .iter()
+            .find(|(id, _, _)| *id == change.component_id)
+            .map(|(_, _, handle)| *handle);
+
+        if change.destroy {
+            if let Some(handle) = handle {
+                self.world.remove_body(handle);
+                self.world.update_query_pipeline();
+            }
+            self.world.npcs.retain(|n| n.id != change.component_id);
+            self.component_bodies.retain(|(id, _, _)| *id != change.component_id);
+            self.scene_components.retain(|(c, _)| c.id != change.component_id);
+            self.scripted_components.retain(|c| c.id != change.component_id);
+            self.rhai_engine.timers.borrow_mut().cancel_owner(&change.component_id);
+            return;
+        }
+
+        if let Some(rb) = handle.and_then(|h| self.world.rigid_body_set.get_mut(h)) {
+            if let Some(new_pos) = change.new_position {
+                rb.set_translation(new_pos, true);
+            }
+            if let Some(rotation) = change.new_rotation {
+                let rotation = UnitQuaternion::from_euler_angles(
+                    rotation.x.to_radians(),
+                    rotation.y.to_radians(),
+                    rotation.z.to_radians(),
+                );
+                rb.set_rotation(rotation, true);
+            }
+            if let Some(impulse) = change.impulse {
+                rb.apply_impulse(impulse, true);
+            }
+        }
+
+        let component = match self.scripted_components.iter_mut().find(|c| c.id == change.component_id) {
+            Some(component) => component,
+            None => return,
+        };
+        if let Some(new_scale) = change.new_scale {
+            component.scale = new_scale;
+        }
+        if handle.is_none() {
+            if let Some(new_pos) = change.new_position {
+                component.placed.translation.vector = new_pos;
+            }
+        }
+
+        // so the next hook sees what the last one set
+        match &mut component.fields {
+            WrapperFields::PointLight { intensity, color } => {
+                *intensity = change.light_intensity.unwrap_or(*intensity);
+                *color = change.light_color.unwrap_or(*color);
+            }
+            WrapperFields::WaterPlane { config } => *config = change.water_config.unwrap_or(*config),
+            _ => {}
+        }
+    }
+
+    pub fn run_scripts(&mut self, hook_name: &str) {
+        self.run_component_scripts(hook_name, None, None);
+    }
+
+    // Collision and trigger hooks for both sides of each pair
+    fn run_collision_scripts(&mut self, collisions: &[ComponentCollision]) {
+        for collision in collisions {
+            self.collision_events += 1;
+
+            for (id, other_id) in [(&collision.a, &collision.b), (&collision.b, &collision.a)] {
+                self.run_component_scripts(collision.hook_name(), Some(id.as_str()), Some(other_id.as_str().into()));
+            }
+        }
+    }
+
+    // Moves script time on and calls whatever system.after and system.every scheduled for now
+    fn run_timers(&mut self, dt: f32) {
+        self.rhai_engine.timers.borrow_mut().advance(dt);
+
+        while let Some(timer) = self.rhai_engine.next_timer() {
+            let args = timer.args.as_ref().map(json_to_dynamic);
+
+            if timer.is_global() {
+                self.script_calls += 1;
+                for change in self.rhai_engine.execute_global_timer(&timer) {
+                    self.apply_change(change);
+                }
+            } else {
+                self.run_component_scripts(&timer.function, Some(timer.owner_id.as_str()), args);
+            }
+        }
+    }
+
+    // Behavior tree leaves that call into scripts, the trees see the answers next step
+    fn run_behavior_scripts(&mut self, requests: &[ScriptLeafRequest]) {
+        if requests.is_empty() {
+            return;
+        }
+
+        let mut changes = Vec::new();
+        for request in requests {
+            let component = self.scripted_components.iter_mut().find(|c| c.id == request.npc_id);
+            let script_path = request.script.clone().or_else(|| component.as_ref().map(|c| c.script_path.clone()));
+            let mut no_state = None;
+            let (script_state, scale) = match component {
+                Some(component) => (&mut component.script_state, component.scale),
+                None => (&mut no_state, Vector3::new(1.0, 1.0, 1.0)),
+            };
+
+            let npc = match self.world.npcs.iter_mut().find(|n| n.id == request.npc_id) {
+                Some(npc) => npc,
+                None => continue,
+            };
+            let isometry = self
+                .world
+                .rigid_body_set
+                .get(npc.rigid_body_handle)
+                .map(|rb| *rb.position())
+                .unwrap_or_else(Isometry3::identity);
+
+            self.script_calls += 1;
+            changes.extend(self.rhai_engine.call_behavior_leaf(
+                npc,
+                isometry.translation.vector,
+                euler_degrees(&isometry.rotation),
+                scale,
+                script_state,
+                script_path.as_deref(),
+                request,
+            ));
+        }
+
+        for change in changes {
+            self.apply_change(change);
+        }
+    }
+
+    // Runs a hook on every scripted component, or only on `only_id` when given, with an extra
+    // argument after script_state for collision hooks and timers
+    fn run_component_scripts(&mut self, hook_name: &str, only_id: Option<&str>, extra: Option<Dynamic>) {
+        let mut changes = Vec::new();
+
+        if only_id.map_or(false, |id| !self.scripted_components.iter().any(|c| c.id == id)) {
+            return;
+        }
+
+        for component in self.scripted_components.iter_mut() {
+            if only_id.map_or(false, |id| id != component.id) {
+                continue;
+            }
+
+            let isometry = component
+                .rigid_body_handle
+                .and_then(|h| self.world.rigid_body_set.get(h))
+                .map(|rb| *rb.position())
+                .unwrap_or(component.placed);
+            let position = isometry.translation.vector;
+
+            if let WrapperFields::NPC { health, stamina } = &mut component.fields {
+                match self.world.npcs.iter().find(|n| n.id == component.id) {
+                    Some(npc) => (*health, *stamina) = (npc.stats.health, npc.stats.stamina),
+                    None => continue,
+                }
+            }
+
+            self.script_calls += 1;
+
+            let change = match component.kind {
+                ComponentKind::PlayerCharacter => {
+                    let wrapper = PlayerWrapper {
+                        id: component.id.clone(),
+                        equipped_weapon_id: self.player_weapon.as_ref().map(|w| w.id.clone()).unwrap_or_default(),
+                        equipped_weapon_name: self
+                            .player_weapon
+                            .as_ref()
+                            .map(|w| w.generic_properties.name.clone())
+                            .unwrap_or_default(),
+                        position,
+                    };
+                    self.rhai_engine.execute_player_hook(wrapper, &mut component.script_state, &component.script_path, hook_name, extra.clone())
+                }
+                _ => self.rhai_engine.execute_wrapped_hook(
+                    &component.id,
+                    component.fields.clone(),
+                    position,
+                    euler_degrees(&isometry.rotation),
+                    component.scale,
+                    &mut component.script_state,
+                    &component.script_path,
+                    hook_name,
+                    extra.clone(),
+                ),
+            };
+
+            changes.extend(change);
+        }
+
+        for change in changes {
+            self.apply_change(change);
+        }
+    }
+
+    pub fn run(&mut self, config: &HeadlessConfig) -> HeadlessReport {
+        let dt = 1.0 / config.tick_rate.max(1.0);
+        let frame_duration = Duration::from_secs_f32(dt);
+        let started = Instant::now();
+
+        for _ in 0..config.frames {
+            let frame_start = Instant::now();
+
+            self.step(dt);
+
+            if config.realtime {
+                let elapsed = frame_start.elapsed();
+                if elapsed < frame_duration {
+                    std::thread::sleep(frame_duration - elapsed);
+                }
+            }
+        }
+
+        self.stop();
+
+        self.report(started.elapsed(), dt)
+    }
+
+    fn body_position(&self, handle: RigidBodyHandle) -> [f32; 3] {
+        self.world
+            .body_position(handle)
+            .map(|t| [t.x, t.y, t.z])
+            .unwrap_or([0.0, 0.0, 0.0])
+    }
+
+    pub fn report(&self, wall_time: Duration, dt: f32) -> HeadlessReport {
+        let player = self.world.player_character.as_ref().map(|p| BodyReport {
+            id: p.id.clone(),
+            kind: Some(ComponentKind::PlayerCharacter),
+            position: p
+                .movement_rigid_body_handle
+                .map(|h| self.body_position(h))
+                .unwrap_or([0.0, 0.0, 0.0]),
+        });
+
+        let npcs = self
+            .world
+            .npcs
+            .iter()
+            .map(|npc| NPCReport {
+                id: npc.id.clone(),
+                health: npc.stats.health,
+                stamina: npc.stats.stamina,
+                position: self.body_position(npc.rigid_body_handle),
+            })
+            .collect();
+
+        let bodies = self
+            .component_bodies
+            .iter()
+            .filter(|(_, kind, _)| *kind != ComponentKind::NPC && *kind != ComponentKind::PlayerCharacter)
+            .map(|(id, kind, handle)| BodyReport {
+                id: id.clone(),
+                kind: Some(kind.clone()),
+                position: self.body_position(*handle),
+            })
+            .collect();
+
+        HeadlessReport {
+            project_id: self.project_id.clone(),
+            frames: self.frames,
+            simulated_seconds: self.frames as f32 * dt,
+            wall_seconds: wall_time.as_secs_f32(),
+            rigid_bodies: self.world.rigid_body_set.len(),
+            colliders: self.world.collider_set.len(),
+            script_calls: self.script_calls,
+            script_changes: self.script_changes,
+            particle_spawns: self.particle_spawns,
+            collision_events: self.collision_events,
+            damage_to_player: self.damage_to_player,
+            player,
+            player_stats: self.world.player_character.as_ref().map(|p| p.stats.clone()),
+            npcs,
+            bodies,
+            skipped_components: self.skipped_components.clone(),
+        }
+    }
+}
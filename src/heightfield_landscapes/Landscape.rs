@@ -60,6 +60,54 @@ pub struct Landscape {
 }
 
 impl Landscape {
+    // Heightfield collider and fixed body for a landscape, no GPU resources involved
+    pub fn create_physics(
+        landscapeComponentId: &String,
+        data: &LandscapePixelData,
+        position: [f32; 3],
+    ) -> (RigidBody, Collider) {
+        // let square_size = 1024.0 * 100.0;
+        let square_size = 1024.0 * 4.0;
+
+        // Create terrain size that matches your actual terrain dimensions
+        let terrain_size = Vector::new(
+            square_size, // Total width in world units
+            // 250.0,  // Total height in world units
+            1.0,         // already specified when loading
+            square_size, // Total depth in world units
+        );
+
+        let isometry = Isometry3::translation(position[0], position[1], position[2]);
+
+        let terrain_collider =
+            ColliderBuilder::heightfield(data.rapier_heights.clone(), terrain_size)
+                .friction(0.9)
+                .restitution(0.1)
+                // .position(isometry)
+                .user_data(
+                    Uuid::from_str(landscapeComponentId)
+                        .expect("Couldn't extract uuid")
+                        .as_u128(),
+                )
+                .build();
+
+        // Create the ground as a fixed rigid body
+
+        println!("insert landscape position {:?}", position);
+
+        let ground_rigid_body = RigidBodyBuilder::fixed()
+            .position(isometry)
+            .user_data(
+                Uuid::from_str(&landscapeComponentId)
+                    .expect("Couldn't extract uuid")
+                    .as_u128(),
+            )
+            .sleeping(false)
+            .build();
+
+        (ground_rigid_body, terrain_collider)
+    }
+
     pub fn new(
         landscapeComponentId: &String,
         data: &LandscapePixelData,
@@ -96,46 +144,13 @@ impl Landscape {
                 .fold(f32::NEG_INFINITY, |a, &b| a.max(b))
         );
 
-        // let square_size = 1024.0 * 100.0;
         // let square_height = 1858.0;
         let square_size = 1024.0 * 4.0;
         let square_height = 150.0 * 4.0;
 
-        // Create terrain size that matches your actual terrain dimensions
-        let terrain_size = Vector::new(
-            square_size, // Total width in world units
-            // 250.0,  // Total height in world units
-            1.0,         // already specified when loading
-            square_size, // Total depth in world units
-        );
-
         let isometry = Isometry3::translation(position[0], position[1], position[2]);
 
-        let terrain_collider =
-            ColliderBuilder::heightfield(data.rapier_heights.clone(), terrain_size)
-                .friction(0.9)
-                .restitution(0.1)
-                // .position(isometry)
-                .user_data(
-                    Uuid::from_str(landscapeComponentId)
-                        .expect("Couldn't extract uuid")
-                        .as_u128(),
-                )
-                .build();
-
-        // Create the ground as a fixed rigid body
-
-        println!("insert landscape position {:?}", position);
-
-        let ground_rigid_body = RigidBodyBuilder::fixed()
-            .position(isometry)
-            .user_data(
-                Uuid::from_str(&landscapeComponentId)
-                    .expect("Couldn't extract uuid")
-                    .as_u128(),
-            )
-            .sleeping(false)
-            .build();
+        let (ground_rigid_body, terrain_collider) = Self::create_physics(landscapeComponentId, data, position);
 
         // let (vertices, indices) = Self::generate_debug_terrain(&terrain_collider, &device, &isometry);
 
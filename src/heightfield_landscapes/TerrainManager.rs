@@ -6,7 +6,7 @@ use std::num::NonZeroU32;
 use std::str::FromStr;
 use std::sync::mpsc::{channel, Receiver, Sender};
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
@@ -74,7 +74,10 @@ impl TerrainManager {
         // let square_height = 1858.0 * 10.0;
         let square_size = 1024.0 * 4.0;
         // let square_height = 150.0 * 4.0;
-        let data = get_landscape_pixels(projectId, landscapeAssetId, landscapeFilename);
+        // only reached from the quadtree path, which handle_add_landscape has commented out
+        let heightmap_path = format!("landscapes/{}/heightmaps/{}", landscapeAssetId, landscapeFilename);
+        let data = get_landscape_pixels(projectId, landscapeAssetId, heightmap_path)
+            .expect("Couldn't read landscape heightmap");
 
         println!("loaded heights... creating root quad...");
 
@@ -1,6 +1,7 @@
-use std::{fs::File, path::PathBuf};
+use std::{fs, io::Cursor, path::PathBuf};
 
-use exr::prelude::read_first_rgba_layer_from_file;
+use exr::image::read as exr_read;
+use exr::prelude::{ReadChannels, ReadLayers};
 use image::GenericImageView;
 use serde::Serialize;
 use tiff::decoder::{Decoder, DecodingResult};
@@ -10,7 +11,7 @@ use crate::helpers::saved_data::LandscapeTextureKinds;
 #[cfg(target_arch = "wasm32")]
 use crate::helpers::wasm_loaders::read_texture_bytes_wasm;
 
-use super::utilities::get_common_os_dir;
+use super::pak::read_project_file;
 
 pub struct LandscapePixelData {
     pub width: usize,
@@ -149,10 +150,30 @@ pub fn read_heightmap(
     na::DMatrix<f32>,
     Vec<f32>,
     f32,
+) {
+    let bytes = fs::read(landscape_path).expect("Couldn't read heightmap file");
+
+    read_heightmap_bytes(&bytes, landscape_path, target_width, target_length, target_height)
+}
+
+// Same as `read_heightmap` for a file that's already in memory, `file_name` picks the decoder
+pub fn read_heightmap_bytes(
+    bytes: &[u8],
+    file_name: &str,
+    target_width: f32,
+    target_length: f32,
+    target_height: f32,
+) -> (
+    usize,
+    usize,
+    Vec<Vec<PixelData>>,
+    na::DMatrix<f32>,
+    Vec<f32>,
+    f32,
 ) {
     use std::path::Path;
 
-    let path = Path::new(landscape_path);
+    let path = Path::new(file_name);
     let extension = path.extension()
         .and_then(|e| e.to_str())
         .map(|e| e.to_lowercase());
@@ -160,12 +181,10 @@ pub fn read_heightmap(
     let (width, height, image_data) = match extension.as_deref() {
         Some("tif") | Some("tiff") => {
             // Handle TIFF files
-            use std::fs::File;
             // You'll need: tiff = "0.9" in Cargo.toml
             // use tiff::decoder::{Decoder, DecodingResult};
             
-            let file = File::open(landscape_path).expect("Couldn't open TIFF file");
-            let mut decoder = tiff::decoder::Decoder::new(file)
+            let mut decoder = tiff::decoder::Decoder::new(Cursor::new(bytes))
                 .expect("Couldn't decode TIFF file");
 
             let (w, h) = decoder.dimensions().expect("Couldn't get TIFF dimensions");
@@ -189,7 +208,7 @@ pub fn read_heightmap(
             // Handle PNG and other common image formats
             use image::GenericImageView;
 
-            let img = image::open(landscape_path)
+            let img = image::load_from_memory(bytes)
                 .expect("Couldn't open image file");
             let (w, h) = img.dimensions();
             let width = w as usize;
@@ -266,33 +285,29 @@ pub fn get_landscape_pixels(
     // state: tauri::State<'_, AppState>,
     projectId: String,
     landscapeAssetId: String,
-    landscapeFilename: String,
-) -> LandscapePixelData {
+    heightmapPath: String, // relative to the project root, see File::load_path
+) -> Result<LandscapePixelData, String> {
     // let handle = &state.handle;
     // let config = handle.config();
     // let package_info = handle.package_info();
     // let env = handle.env();
 
-    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
-    let landscapes_dir = sync_dir.join(format!(
-        "midpoint/projects/{}/landscapes/{}/heightmaps",
-        projectId, landscapeAssetId
-    ));
-    let landscape_path = landscapes_dir.join(landscapeFilename);
+    let landscape_path = heightmapPath;
     // let landscape_path = landscapes_dir
     //     .join("upscaled")
     //     .join("upscaled_heightmap.tiff");
 
     println!("landscape_path {:?}", landscape_path);
 
+    let bytes = read_project_file(&projectId, &landscape_path)?;
+
     // let square_size = 1024.0 * 100.0;
     // let square_height = 1858.0 * 10.0;
     let square_size = 1024.0 * 4.0;
     let square_height = 150.0 * 4.0;
-    let (width, height, pixel_data, rapier_heights, raw_heights, max_height) = read_heightmap(
-        landscape_path
-            .to_str()
-            .expect("Couldn't form landscape string"),
+    let (width, height, pixel_data, rapier_heights, raw_heights, max_height) = read_heightmap_bytes(
+        &bytes,
+        &landscape_path,
         // battlefield size
         // 2048.0,
         // 2048.0,
@@ -303,7 +318,7 @@ pub fn get_landscape_pixels(
         square_height,
     );
 
-    LandscapePixelData {
+    Ok(LandscapePixelData {
         width,
         height,
         // data: heightmap.to_vec(),
@@ -311,23 +326,20 @@ pub fn get_landscape_pixels(
         rapier_heights,
         raw_heights,
         max_height,
-    }
+    })
 }
 
 pub fn read_landscape_heightmap_as_texture(
     projectId: String,
     landscapeId: String,
-    textureFilename: String,
+    heightmapPath: String,
 ) -> Result<TextureData, String> {
-    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
-    let texture_path = sync_dir.join(format!(
-        "midpoint/projects/{}/landscapes/{}/heightmaps/{}",
-        projectId, landscapeId, textureFilename
-    ));
+    let texture_path = heightmapPath;
 
     println!("texture path {:?}", texture_path);
 
-    let img = image::open(&texture_path)
+    let texture_bytes = read_project_file(&projectId, &texture_path)?;
+    let img = image::load_from_memory(&texture_bytes)
         .map_err(|e| format!("Failed to open landscape texture: {}", e))?;
 
     let (width, height) = img.dimensions();
@@ -380,16 +392,16 @@ pub fn read_landscape_texture(
     // let package_info = handle.package_info();
     // let env = handle.env();
 
-    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
-    let texture_path = sync_dir.join(format!(
-        "midpoint/projects/{}/textures/{}{}",
-        projectId, textureFilename, ".png"
-    ));
+    let texture_path = format!(
+        "textures/{}{}",
+        textureFilename, ".png"
+    );
 
     println!("texture path {:?}", texture_path);
 
     // Read the image file
-    let img = image::open(&texture_path)
+    let texture_bytes = read_project_file(&projectId, &texture_path)?;
+    let img = image::load_from_memory(&texture_bytes)
         .map_err(|e| format!("Failed to open landscape texture: {}", e))?;
 
     // Get dimensions
@@ -406,36 +418,37 @@ pub fn read_landscape_texture(
     })
 }
 
+// Where the editor imports a landscape's masks of each kind, for files without a normalFilePath
+pub fn mask_folder(landscape_id: &str, kind: &LandscapeTextureKinds) -> String {
+    let kind_slug = match kind {
+        LandscapeTextureKinds::Primary => "heightmaps",
+        LandscapeTextureKinds::Rockmap => "rockmaps",
+        LandscapeTextureKinds::Soil => "soils",
+        _ => "",
+    };
+
+    format!("landscapes/{}/{}", landscape_id, kind_slug)
+}
+
 pub fn read_landscape_mask(
     // state: tauri::State<'_, AppState>,
     projectId: String,
     landscapeId: String,
-    maskFilename: String,
-    maskKind: LandscapeTextureKinds,
+    maskPath: String, // relative to the project root, see File::load_path
 ) -> Result<TextureData, String> {
     // let handle = &state.handle;
     // let config = handle.config();
     // let package_info = handle.package_info();
     // let env = handle.env();
 
-    let kind_slug = match maskKind {
-        LandscapeTextureKinds::Primary => "heightmaps",
-        LandscapeTextureKinds::Rockmap => "rockmaps",
-        LandscapeTextureKinds::Soil => "soils",
-        _ => "",
-    };
-
-    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
-    let mask_path = sync_dir.join(format!(
-        "midpoint/projects/{}/landscapes/{}/{}/{}",
-        projectId, landscapeId, kind_slug, maskFilename
-    ));
+    let mask_path = maskPath;
 
     println!("mask_path {:?}", mask_path);
 
     // Read the image file
+    let mask_bytes = read_project_file(&projectId, &mask_path)?;
     let img =
-        image::open(&mask_path).map_err(|e| format!("Failed to open landscape mask: {}", e))?;
+        image::load_from_memory(&mask_bytes).map_err(|e| format!("Failed to open landscape mask: {}", e))?;
 
     // Get dimensions
     let (width, height) = img.dimensions();
@@ -456,7 +469,7 @@ pub async fn read_texture_bytes(
     asset_id: String, // This could be landscapeId or pbr_texture_id
     file_name: String,
 ) -> Result<(Vec<u8>, u32, u32), String> {
-    #[cfg(target_os = "windows")]
+    #[cfg(not(target_arch = "wasm32"))]
     return read_texture_bytes_local(
         project_id,
         asset_id,
@@ -476,19 +489,13 @@ pub fn read_texture_bytes_local(
     asset_id: String, // This could be landscapeId or pbr_texture_id
     file_name: String,
 ) -> Result<(Vec<u8>, u32, u32), String> {
-    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
-
-    // Determine the base directory based on asset_id type
-    let base_path =
-        sync_dir.join(format!(
-            "midpoint/projects/{}/textures/",
-            project_id
-        ));
-
-    let file_path = base_path.join(file_name.clone());
+    // file_name is relative to the project root, see File::load_path
+    let file_path = PathBuf::from(&file_name);
 
     println!("Attempting to read texture from path: {:?}", file_path);
 
+    let file_bytes = read_project_file(&project_id, &file_name)?;
+
     let extension = file_path
         .extension()
         .and_then(|s| s.to_str())
@@ -496,7 +503,7 @@ pub fn read_texture_bytes_local(
 
     let data = match extension {
         "png" | "jpg" | "jpeg" => {
-            let image = image::open(&file_path)
+            let image = image::load_from_memory(&file_bytes)
                 .map_err(|e| format!("Failed to open image file {}: {}", file_name, e))?;
 
             let width = image.width();
@@ -506,9 +513,7 @@ pub fn read_texture_bytes_local(
                 .into_raw(), width, height)
         }
         "tif" | "tiff" => {
-            let file = File::open(&file_path)
-                .map_err(|e| format!("Failed to open TIFF file {}: {}", file_name, e))?;
-            let mut decoder = Decoder::new(file)
+            let mut decoder = Decoder::new(Cursor::new(&file_bytes))
                 .map_err(|e| format!("Failed to decode TIFF file {}: {}", file_name, e))?;
             let (width, height) = decoder.dimensions().map_err(|e| format!("Failed to get TIFF dimensions {}: {}", file_name, e))?;
 
@@ -527,20 +532,25 @@ pub fn read_texture_bytes_local(
         }
         "exr" => {
             // Read EXR file into a nested Vec<Vec<[f32; 4]>> structure
-            let image = read_first_rgba_layer_from_file(
-                &file_path,
-                // Instantiate image type with the size of the image in file
-                |resolution, _| {
-                    let default_pixel = [0.0, 0.0, 0.0, 0.0];
-                    let empty_line = vec![default_pixel; resolution.width()];
-                    let empty_image = vec![empty_line; resolution.height()];
-                    empty_image
-                },
-                // Transfer the colors from the file to your image type
-                |pixel_vector, position, (r, g, b, a): (f32, f32, f32, f32)| {
-                    pixel_vector[position.y()][position.x()] = [r, g, b, a]
-                }
-            )
+            let image = exr_read::read()
+                .no_deep_data()
+                .largest_resolution_level()
+                .rgba_channels(
+                    // Instantiate image type with the size of the image in file
+                    |resolution, _| {
+                        let default_pixel = [0.0, 0.0, 0.0, 0.0];
+                        let empty_line = vec![default_pixel; resolution.width()];
+                        let empty_image = vec![empty_line; resolution.height()];
+                        empty_image
+                    },
+                    // Transfer the colors from the file to your image type
+                    |pixel_vector, position, (r, g, b, a): (f32, f32, f32, f32)| {
+                        pixel_vector[position.y()][position.x()] = [r, g, b, a]
+                    }
+                )
+                .first_valid_layer()
+                .all_attributes()
+                .from_buffered(Cursor::new(&file_bytes))
             .map_err(|e| format!("Failed to read EXR file {}: {:?}", file_name, e)).unwrap();
 
             // println!("exr pixels {:?}", image.layer_data.channel_data.pixels.len());
@@ -3,12 +3,17 @@ use nalgebra::{Isometry3, Translation3, UnitQuaternion, Vector3};
 #[cfg(target_arch = "wasm32")]
 use crate::helpers::wasm_loaders::read_landscape_heightmap_as_texture_wasm;
 use crate::{
-    core::{Texture::{Texture, pack_pbr_textures}, editor::Editor}, 
+    game_behaviors::{blackboard::Blackboard, dialogue_graph::DialogueLibrary, dialogue_state::DialogueState, quests::QuestLog},
+    core::{Texture::{Texture, pack_pbr_textures}, editor::Editor, SceneHierarchy::{component_placements, Placement, SceneHierarchy}}, 
     handlers::{fetch_mask_data, handle_add_collectable, handle_add_grass, handle_add_house, handle_add_landscape, handle_add_model, handle_add_npc, handle_add_player, handle_add_trees, handle_add_water_plane}, 
     heightfield_landscapes::Landscape::{PBRMaterialType, PBRTextureKind}, 
-    helpers::{landscapes::{read_landscape_heightmap_as_texture, read_texture_bytes}, 
+    helpers::{landscapes::{mask_folder, read_landscape_heightmap_as_texture, read_texture_bytes}, 
+    project_error::{check_component, ProjectError},
+    save_game,
     saved_data::{CollectableType, ComponentKind, LandscapeTextureKinds, SavedState}, utilities},
-    procedural_models::House::HouseConfig
+    procedural_models::House::HouseConfig,
+    model_components::NPC::{loadout_item, NPCArchetype},
+    rhai_engine::ScriptScene
 };
 
 pub async fn load_project(editor: &mut Editor, project_id: &str) {
@@ -19,13 +24,328 @@ pub async fn load_project(editor: &mut Editor, project_id: &str) {
             }
         Err(e) => {
             println!("Failed to load project: {}", e);
+            editor.load_errors = vec![e];
         }
     }
 }
 
+// A level switch requested by a portal or a script, applied at the start of the next frame
+#[derive(Clone, Debug)]
+pub struct LevelChange {
+    pub level_id: String,
+    pub spawn_position: Option<[f32; 3]>,
+}
+
 pub async fn place_project(editor: &mut Editor, project_id: &str, loaded_state: SavedState) {
-editor.saved_state = Some(loaded_state);
-            
+    let start_level_id = loaded_state.start_level_id();
+
+    *editor.quest_log.borrow_mut() = QuestLog::new(loaded_state.quests.clone().unwrap_or_default());
+    let (dialogues, dialogue_errors) = DialogueLibrary::load(project_id, &loaded_state);
+    editor.dialogues = dialogues;
+    editor.dialogue_state = DialogueState::default();
+    *editor.rhai_engine.blackboard.borrow_mut() = Blackboard::default();
+    editor.saved_state = Some(loaded_state);
+    editor.current_level_id = start_level_id.clone();
+    editor.pending_level = None;
+    editor.load_errors = dialogue_errors;
+    editor.pending_script_changes.clear();
+    editor.pending_spawns.clear();
+    editor.spawned_components.clear();
+    let script_settings = editor.saved_state.as_ref().and_then(|s| s.script_settings.clone());
+    editor.rhai_engine.configure(&script_settings);
+    let global_scripts = editor.saved_state.as_ref().and_then(|s| s.global_rhai_scripts.clone());
+    editor.rhai_engine.load_global_scripts(&global_scripts);
+
+    match start_level_id {
+        Some(level_id) => {
+            place_level(editor, project_id, &level_id).await;
+            start_level(editor, &level_id, true);
+        }
+        None => println!("Project has no levels to place"),
+    }
+}
+
+// What lookups and raycasts see during the hooks about to run
+fn capture_scene(editor: &mut Editor, level_id: &str) {
+    let components = editor.saved_state.as_ref().and_then(|s| s.level_components(Some(level_id)));
+
+    if let (Some(components), Some(renderer_state)) = (components, editor.renderer_state.as_ref()) {
+        let scene = ScriptScene::from_level(renderer_state, level_id, components, &editor.spawned_components);
+        editor.rhai_engine.set_scene(scene);
+    }
+}
+
+// Runs a lifecycle hook on the level's scripted components, and anything scripts spawned into it.
+// The player only spawns once, so their on_start/on_destroy are left out when they carry over between levels.
+fn run_component_hooks(editor: &mut Editor, level_id: &str, hook_name: &str, include_player: bool) {
+    capture_scene(editor, level_id);
+
+    let components = match editor.saved_state.as_ref().and_then(|s| s.level_components(Some(level_id))) {
+        Some(components) => components,
+        None => return,
+    };
+    let renderer_state = match editor.renderer_state.as_mut() {
+        Some(renderer_state) => renderer_state,
+        None => return,
+    };
+
+    for component in components.iter().chain(editor.spawned_components.iter()) {
+        if component.kind == Some(ComponentKind::PlayerCharacter) && !include_player {
+            continue;
+        }
+
+        // components that failed to place have no model and are skipped here
+        if let Some(script_path) = &component.rhai_script_path {
+            let changes = editor.rhai_engine.execute_component_script(renderer_state, component, script_path, hook_name);
+            editor.pending_script_changes.extend(changes);
+        }
+    }
+}
+
+// Runs a hook of every global script against the level as it stands
+fn run_global_hooks(editor: &mut Editor, level_id: &str, hook_name: &str) {
+    capture_scene(editor, level_id);
+
+    let global_scripts = editor.saved_state.as_ref().and_then(|s| s.global_rhai_scripts.clone()).unwrap_or_default();
+    for script_path in &global_scripts {
+        let changes = editor.rhai_engine.execute_global_hook(script_path, hook_name);
+        editor.pending_script_changes.extend(changes);
+    }
+}
+
+fn start_level(editor: &mut Editor, level_id: &str, include_player: bool) {
+    run_component_hooks(editor, level_id, "on_start", include_player);
+    run_global_hooks(editor, level_id, "on_level_load");
+}
+
+fn end_level(editor: &mut Editor, level_id: &str) {
+    run_global_hooks(editor, level_id, "on_level_unload");
+    run_component_hooks(editor, level_id, "on_destroy", false);
+}
+
+// Places what scripts spawned last frame. Only models and NPCs can be spawned for now,
+// other kinds need more than an asset to place. Their on_start runs straight away.
+pub async fn spawn_components(editor: &mut Editor, project_id: &str) {
+    let spawns: Vec<_> = editor.pending_spawns.drain(..).collect();
+    let level_id = match editor.current_level_id.clone() {
+        Some(level_id) => level_id,
+        None => return,
+    };
+
+    for component in spawns {
+        let asset = editor
+            .saved_state
+            .as_ref()
+            .and_then(|s| s.models.iter().find(|m| m.id == component.asset_id).cloned());
+        let asset = match asset {
+            Some(asset) => asset,
+            None => {
+                println!("Couldn't spawn {}, its model asset is missing", component.id);
+                continue;
+            }
+        };
+
+        let archetype = editor.saved_state.as_ref().map(|s| {
+            let level_components = s.level_components(Some(&level_id)).map(|c| c.as_slice()).unwrap_or(&[]);
+            NPCArchetype::new(s, &component, level_components)
+        });
+        let renderer_state = editor.renderer_state.as_mut().unwrap();
+        let camera = editor.camera.as_mut().unwrap();
+        let gpu_resources = editor.gpu_resources.as_ref().unwrap();
+        let placement = Placement::from_generic(&component.generic_properties);
+
+        let placed = match component.kind {
+            Some(ComponentKind::Model) => handle_add_model(
+                renderer_state,
+                &gpu_resources.device,
+                &gpu_resources.queue,
+                project_id.to_string(),
+                asset.id.clone(),
+                component.id.clone(),
+                asset.load_path(project_id, "models"),
+                placement.isometry,
+                placement.scale,
+                camera,
+                component.script_state.clone()
+            ).await,
+            Some(ComponentKind::NPC) => handle_add_npc(
+                renderer_state,
+                &gpu_resources.device,
+                &gpu_resources.queue,
+                project_id.to_string(),
+                asset.id.clone(),
+                component.id.clone(),
+                asset.load_path(project_id, "models"),
+                placement.isometry,
+                placement.scale,
+                camera,
+                component.script_state.clone(),
+                archetype.as_ref().expect("Couldn't get saved state for NPC")
+            ).await,
+            _ => {
+                println!("Couldn't spawn {}, only models and NPCs can be spawned", component.id);
+                continue;
+            }
+        };
+
+        match placed {
+            Ok(()) => {
+                renderer_state.world.update_query_pipeline();
+                editor.spawned_components.push(component.clone());
+
+                if let Some(script_path) = &component.rhai_script_path {
+                    capture_scene(editor, &level_id);
+                    let renderer_state = editor.renderer_state.as_mut().unwrap();
+                    let changes = editor.rhai_engine.execute_component_script(renderer_state, &component, script_path, "on_start");
+                    editor.pending_script_changes.extend(changes);
+                }
+            }
+            Err(e) => println!("Couldn't spawn {}: {}", component.id, e),
+        }
+    }
+}
+
+// Swaps the current level for another one. The player, their stats and inventory carry over.
+pub async fn load_level(editor: &mut Editor, project_id: &str, level_id: &str, spawn_position: Option<[f32; 3]>) {
+    let level_exists = editor
+        .saved_state
+        .as_ref()
+        .and_then(|s| s.level(Some(level_id)))
+        .is_some();
+
+    if !level_exists {
+        println!("Level {} not found, staying put", level_id);
+        return;
+    }
+
+    println!("Loading level {}", level_id);
+
+    if let Some(current_level_id) = editor.current_level_id.clone() {
+        end_level(editor, &current_level_id);
+    }
+    editor.pending_spawns.clear();
+    editor.spawned_components.clear();
+    editor.rhai_engine.timers.borrow_mut().unload_level();
+
+    let had_player = editor
+        .renderer_state
+        .as_ref()
+        .map(|r| r.world.player_character.is_some())
+        .unwrap_or(false);
+
+    if let Some(renderer_state) = editor.renderer_state.as_mut() {
+        renderer_state.unload_level();
+    }
+
+    editor.current_level_id = Some(level_id.to_string());
+    editor.current_enemy_target = None;
+    editor.load_errors.clear();
+    editor.dialogue_state.is_open = false;
+    editor.dialogue_state.ui_dirty = true;
+
+    place_level(editor, project_id, level_id).await;
+    editor.quest_log.borrow_mut().reset_observed();
+    start_level(editor, level_id, !had_player);
+
+    if let Some(position) = spawn_position {
+        if let Some(renderer_state) = editor.renderer_state.as_mut() {
+            renderer_state.teleport_player(position);
+        }
+    }
+}
+
+// Loads a save slot: places its level fresh, then puts the saved runtime state back on top
+pub async fn load_save_game(editor: &mut Editor, project_id: &str, slot: &str) {
+    let save = match save_game::read_save(project_id, slot) {
+        Ok(save) => save,
+        Err(e) => {
+            println!("Couldn't load save {}: {}", slot, e);
+            return;
+        }
+    };
+
+    load_level(editor, project_id, &save.level_id, None).await;
+
+    if editor.current_level_id.as_deref() != Some(save.level_id.as_str()) {
+        println!("Save {} is for level {}, which this project doesn't have", slot, save.level_id);
+        return;
+    }
+
+    if let Some(renderer_state) = editor.renderer_state.as_mut() {
+        let removed = save.restore(&mut renderer_state.world);
+        renderer_state.models.retain(|m| !removed.contains(&m.id));
+
+        for model in renderer_state.models.iter_mut() {
+            if let Some(state) = save.script_states.get(&model.id) {
+                model.script_state = Some(state.clone());
+            }
+        }
+
+        // scripted lights, water and collectables keep theirs on the renderer
+        let component_ids = editor
+            .saved_state
+            .as_ref()
+            .and_then(|s| s.level_components(Some(save.level_id.as_str())))
+            .map(|components| components.iter().map(|c| c.id.clone()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        for component_id in component_ids {
+            if renderer_state.models.iter().any(|m| m.id == component_id) {
+                continue;
+            }
+            if let Some(state) = save.script_states.get(&component_id) {
+                renderer_state.script_states.insert(component_id, state.clone());
+            }
+        }
+
+        if save.dialogue.is_open {
+            if let Some(npc) = renderer_state.world.npcs.iter_mut().find(|n| n.model_id == save.dialogue.current_npc_id) {
+                npc.is_talking = true;
+            }
+        }
+    }
+
+    // global scripts keep their state under their script path
+    let global_scripts = editor.saved_state.as_ref().and_then(|s| s.global_rhai_scripts.clone()).unwrap_or_default();
+    for script_path in &global_scripts {
+        if let Some(state) = save.script_states.get(script_path) {
+            editor.rhai_engine.global_states.insert(script_path.clone(), state.clone());
+        }
+    }
+
+    // pending timers replace whatever the level's on_start hooks just scheduled
+    *editor.rhai_engine.timers.borrow_mut() = save.timers;
+    *editor.rhai_engine.blackboard.borrow_mut() = save.blackboard;
+
+    editor.dialogue_state = save.dialogue;
+    editor.dialogue_state.ui_dirty = true;
+
+    let mut quest_log = editor.quest_log.borrow_mut();
+    quest_log.progress = save.quests;
+    quest_log.reset_observed();
+
+    println!("Loaded save {} from {}", slot, save.saved_at);
+}
+
+pub async fn place_level(editor: &mut Editor, project_id: &str, level_id: &str) {
+            // Check references up front, broken components are skipped and listed in the editor
+            let mut load_errors = Vec::new();
+            if let Some(saved_state) = &editor.saved_state {
+                if let Some(components) = saved_state.level_components(Some(level_id)) {
+                    for component in components {
+                        if let Err(e) = check_component(saved_state, components, component) {
+                            record_load_error(&mut load_errors, e);
+                        }
+                    }
+                }
+            }
+            let broken_ids: Vec<String> = load_errors.iter().filter_map(|e| e.component_id().map(String::from)).collect();
+            let placements = editor
+                .saved_state
+                .as_ref()
+                .and_then(|s| s.level_components(Some(level_id)))
+                .map(|components| component_placements(components))
+                .unwrap_or_default();
+
             let renderer_state = editor.renderer_state.as_mut().unwrap();
             let camera = editor.camera.as_mut().unwrap();
             let gpu_resources = editor.gpu_resources.as_ref().unwrap();
@@ -33,30 +353,35 @@ editor.saved_state = Some(loaded_state);
             // now load landscapes
             if let Some(saved_state) = &editor.saved_state {
                 if let Some(landscapes) = &saved_state.landscapes {
-                    if let Some(levels) = &saved_state.levels {
-                        let level = &levels[0]; // assume one level for now
+                    if let Some(level) = saved_state.level(Some(level_id)) {
                         for landscape_data in landscapes {
                             if let Some(components) = &level.components {
                                 for component in components {
+                                    if broken_ids.contains(&component.id) {
+                                        continue;
+                                    }
+
                                     if let Some(ComponentKind::Landscape) = component.kind {
                                         if component.asset_id == landscape_data.id {
                                             if let Some(heightmap) = &landscape_data.heightmap {
                                                 
-                                                handle_add_landscape(
+                                                if let Err(e) = handle_add_landscape(
                                                     renderer_state,
                                                     &gpu_resources.device,
                                                     &gpu_resources.queue,
                                                     project_id.to_string(),
                                                     landscape_data.id.clone(),
                                                     component.id.clone(),
-                                                    heightmap.fileName.clone(),
+                                                    heightmap.load_path(project_id, &format!("landscapes/{}/heightmaps", landscape_data.id)),
                                                     component.generic_properties.position,
                                                     camera,
-                                                ).await;
+                                                ).await {
+                                                    record_load_error(&mut load_errors, e);
+                                                    continue;
+                                                }
 
                                                 // Existing texture loading for regular textures (optional, can be removed if fully PBR)
                                                 if let Some(textures) = &saved_state.textures {
-                                                    let landscape_properties = component.landscape_properties.as_ref().expect("Couldn't get landscape properties");
 
                                                     // if let Some(texture_id) = &landscape_properties.rockmap_texture_id {
                                                     //     let rockmap_texture = textures.iter().find(|t| {
@@ -96,46 +421,49 @@ editor.saved_state = Some(loaded_state);
 
                                                 // NEW: Load PBR textures
                                                 if let Some(pbr_textures) = &saved_state.pbr_textures {
-                                                    if let Some(mut landscape_obj) = renderer_state.landscapes.iter_mut().find(|l| l.id == component.id) {
-                                                        let landscape_properties = component.landscape_properties.as_ref().expect("Couldn't get landscape properties");
+                                                    let landscape_obj = renderer_state.landscapes.iter_mut().find(|l| l.id == component.id);
+                                                    if let (Some(mut landscape_obj), Some(landscape_properties)) = (landscape_obj, component.landscape_properties.as_ref()) {
 
                                                         let model_bind_group_layout = editor.model_bind_group_layout.as_ref().unwrap();
                                                         let texture_render_mode_buffer = renderer_state.texture_render_mode_buffer.clone();
                                                         let color_render_mode_buffer = renderer_state.color_render_mode_buffer.clone();
 
                                                         if let Some(rock_mask) = &landscape_data.rockmap {
-                                                            let mask = fetch_mask_data(
+                                                            // a missing mask leaves the layer out, the landscape still loads
+                                                            match fetch_mask_data(
                                                                 project_id.to_string().clone(),
                                                                 component.asset_id.clone(),
-                                                                rock_mask.fileName.clone(),
-                                                                LandscapeTextureKinds::Rockmap,
-                                                            ).await;
-                                                            landscape_obj.update_texture(
-                                                                &gpu_resources.device, 
-                                                                &gpu_resources.queue, 
-                                                                model_bind_group_layout, 
-                                                                &texture_render_mode_buffer, 
-                                                                &color_render_mode_buffer, 
-                                                                LandscapeTextureKinds::RockmapMask, 
-                                                                &mask
-                                                            );
+                                                                rock_mask.load_path(project_id, &mask_folder(&component.asset_id, &LandscapeTextureKinds::Rockmap)),
+                                                            ).await {
+                                                                Ok(mask) => landscape_obj.update_texture(
+                                                                    &gpu_resources.device, 
+                                                                    &gpu_resources.queue, 
+                                                                    model_bind_group_layout, 
+                                                                    &texture_render_mode_buffer, 
+                                                                    &color_render_mode_buffer, 
+                                                                    LandscapeTextureKinds::RockmapMask, 
+                                                                    &mask
+                                                                ),
+                                                                Err(e) => record_load_error(&mut load_errors, e),
+                                                            }
                                                         }
                                                         if let Some(soil_mask) = &landscape_data.soil {
-                                                            let mask = fetch_mask_data(
+                                                            match fetch_mask_data(
                                                                 project_id.to_string().clone(),
                                                                 component.asset_id.clone(),
-                                                                soil_mask.fileName.clone(),
-                                                                LandscapeTextureKinds::Soil,
-                                                            ).await;
-                                                            landscape_obj.update_texture(
-                                                                &gpu_resources.device, 
-                                                                &gpu_resources.queue, 
-                                                                model_bind_group_layout, 
-                                                                &texture_render_mode_buffer, 
-                                                                &color_render_mode_buffer, 
-                                                                LandscapeTextureKinds::SoilMask, 
-                                                                &mask
-                                                            );
+                                                                soil_mask.load_path(project_id, &mask_folder(&component.asset_id, &LandscapeTextureKinds::Soil)),
+                                                            ).await {
+                                                                Ok(mask) => landscape_obj.update_texture(
+                                                                    &gpu_resources.device, 
+                                                                    &gpu_resources.queue, 
+                                                                    model_bind_group_layout, 
+                                                                    &texture_render_mode_buffer, 
+                                                                    &color_render_mode_buffer, 
+                                                                    LandscapeTextureKinds::SoilMask, 
+                                                                    &mask
+                                                                ),
+                                                                Err(e) => record_load_error(&mut load_errors, e),
+                                                            }
                                                         }
 
                                                         // Rockmap PBR Texture (similar logic as primary)
@@ -143,7 +471,7 @@ editor.saved_state = Some(loaded_state);
                                                             if let Some(pbr_data) = pbr_textures.iter().find(|p| &p.id == pbr_texture_id) {
                                                                 // Load diffuse (albedo)
                                                                 if let Some(diff_file) = &pbr_data.diff {
-                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), diff_file.fileName.clone()).await {
+                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), diff_file.load_path(project_id, "textures")).await {
                                                                         if let texture = Texture::new(data.0, data.1, data.2) {
                                                                             landscape_obj.update_texture(&gpu_resources.device, &gpu_resources.queue, model_bind_group_layout, &texture_render_mode_buffer, &color_render_mode_buffer, LandscapeTextureKinds::Rockmap, &texture);
                                                                         } else {
@@ -155,7 +483,7 @@ editor.saved_state = Some(loaded_state);
                                                                 }
                                                                 // Load normal
                                                                 if let Some(nor_gl_file) = &pbr_data.nor_gl {
-                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), nor_gl_file.fileName.clone()).await {
+                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), nor_gl_file.load_path(project_id, "textures")).await {
                                                                         if let texture = Texture::new(data.0, data.1, data.2) {
                                                                             landscape_obj.update_pbr_texture(&gpu_resources.device, &gpu_resources.queue, model_bind_group_layout, &texture_render_mode_buffer, &color_render_mode_buffer, PBRTextureKind::Normal, PBRMaterialType::Rockmap, &texture);
                                                                         } else {
@@ -173,21 +501,21 @@ editor.saved_state = Some(loaded_state);
                                                                 // Load roughness/metallic/AO
                                                                 // let mut pbr_params_data = vec![0u8; 4];
                                                                 if let Some(rough_file) = &pbr_data.rough {
-                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), rough_file.fileName.clone()).await {
+                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), rough_file.load_path(project_id, "textures")).await {
                                                                         rough_tex = Some(Texture::new(data.0, data.1, data.2));
                                                                     } else {
                                                                         println!("Failed to load texture!");
                                                                     }
                                                                 }
                                                                 if let Some(metallic_file) = &pbr_data.metallic {
-                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), metallic_file.fileName.clone()).await {
+                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), metallic_file.load_path(project_id, "textures")).await {
                                                                         metallic_tex = Some(Texture::new(data.0, data.1, data.2));
                                                                     } else {
                                                                         println!("Failed to load texture!");
                                                                     }
                                                                 }
                                                                 if let Some(ao_file) = &pbr_data.ao {
-                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), ao_file.fileName.clone()).await {
+                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), ao_file.load_path(project_id, "textures")).await {
                                                                         ao_tex = Some(Texture::new(data.0, data.1, data.2));
                                                                     } else {
                                                                         println!("Failed to load texture!");
@@ -210,7 +538,7 @@ editor.saved_state = Some(loaded_state);
                                                             if let Some(pbr_data) = pbr_textures.iter().find(|p| &p.id == pbr_texture_id) {
                                                                 // Load diffuse (albedo)
                                                                 if let Some(diff_file) = &pbr_data.diff {
-                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), diff_file.fileName.clone()).await {
+                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), diff_file.load_path(project_id, "textures")).await {
                                                                         if let texture = Texture::new(data.0, data.1, data.2) {
                                                                             landscape_obj.update_texture(&gpu_resources.device, &gpu_resources.queue, model_bind_group_layout, &texture_render_mode_buffer, &color_render_mode_buffer, LandscapeTextureKinds::Soil, &texture);
                                                                         } else {
@@ -222,7 +550,7 @@ editor.saved_state = Some(loaded_state);
                                                                 }
                                                                 // Load normal
                                                                 if let Some(nor_gl_file) = &pbr_data.nor_gl {
-                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), nor_gl_file.fileName.clone()).await {
+                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), nor_gl_file.load_path(project_id, "textures")).await {
                                                                         if let texture = Texture::new(data.0, data.1, data.2) {
                                                                             landscape_obj.update_pbr_texture(&gpu_resources.device, &gpu_resources.queue, model_bind_group_layout, &texture_render_mode_buffer, &color_render_mode_buffer, PBRTextureKind::Normal, PBRMaterialType::Soil, &texture);
                                                                         } else {
@@ -235,7 +563,7 @@ editor.saved_state = Some(loaded_state);
                                                                 // Load roughness/metallic/AO
                                                                 // let mut pbr_params_data = vec![0u8; 4];
                                                                 // if let Some(rough_file) = &pbr_data.rough {
-                                                                //     if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), rough_file.fileName.clone()) {
+                                                                //     if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), rough_file.load_path(project_id, "textures")) {
                                                                 //         if let texture = Texture::new(data.0, data.1, data.2) {
                                                                 //             if !texture.data.is_empty() {
                                                                 //                 pbr_params_data[0] = texture.data[0];
@@ -246,7 +574,7 @@ editor.saved_state = Some(loaded_state);
                                                                 //     }
                                                                 // }
                                                                 // if let Some(metallic_file) = &pbr_data.metallic {
-                                                                //     if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), metallic_file.fileName.clone()) {
+                                                                //     if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), metallic_file.load_path(project_id, "textures")) {
                                                                 //         if let texture = Texture::new(data.0, data.1, data.2) {
                                                                 //             if !texture.data.is_empty() {
                                                                 //                 pbr_params_data[1] = texture.data[0];
@@ -257,7 +585,7 @@ editor.saved_state = Some(loaded_state);
                                                                 //     }
                                                                 // }
                                                                 // if let Some(ao_file) = &pbr_data.ao {
-                                                                //     if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), ao_file.fileName.clone()) {
+                                                                //     if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), ao_file.load_path(project_id, "textures")) {
                                                                 //         if let texture = Texture::new(data.0, data.1, data.2) {
                                                                 //             if !texture.data.is_empty() {
                                                                 //                 pbr_params_data[2] = texture.data[0];
@@ -276,21 +604,21 @@ editor.saved_state = Some(loaded_state);
                                                                 // Load roughness/metallic/AO
                                                                 // let mut pbr_params_data = vec![0u8; 4];
                                                                 if let Some(rough_file) = &pbr_data.rough {
-                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), rough_file.fileName.clone()).await {
+                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), rough_file.load_path(project_id, "textures")).await {
                                                                         rough_tex = Some(Texture::new(data.0, data.1, data.2));
                                                                     } else {
                                                                         println!("Failed to load texture!");
                                                                     }
                                                                 }
                                                                 if let Some(metallic_file) = &pbr_data.metallic {
-                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), metallic_file.fileName.clone()).await {
+                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), metallic_file.load_path(project_id, "textures")).await {
                                                                         metallic_tex = Some(Texture::new(data.0, data.1, data.2));
                                                                     } else {
                                                                         println!("Failed to load texture!");
                                                                     }
                                                                 }
                                                                 if let Some(ao_file) = &pbr_data.ao {
-                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), ao_file.fileName.clone()).await {
+                                                                    if let Ok(data) = read_texture_bytes(project_id.to_string(), pbr_texture_id.clone(), ao_file.load_path(project_id, "textures")).await {
                                                                         ao_tex = Some(Texture::new(data.0, data.1, data.2));
                                                                     } else {
                                                                         println!("Failed to load texture!");
@@ -309,11 +637,11 @@ editor.saved_state = Some(loaded_state);
                                                     }
                                                 }
 
-                                                #[cfg(target_os = "windows")]
-                                                let heightmap_texture = read_landscape_heightmap_as_texture(project_id.to_string(), landscape_data.id.clone(), heightmap.fileName.clone());
+                                                #[cfg(not(target_arch = "wasm32"))]
+                                                let heightmap_texture = read_landscape_heightmap_as_texture(project_id.to_string(), landscape_data.id.clone(), heightmap.load_path(project_id, &format!("landscapes/{}/heightmaps", landscape_data.id)));
 
                                                 #[cfg(target_arch = "wasm32")]
-                                                let heightmap_texture = read_landscape_heightmap_as_texture_wasm(project_id.to_string(), landscape_data.id.clone(), heightmap.fileName.clone()).await;
+                                                let heightmap_texture = read_landscape_heightmap_as_texture_wasm(project_id.to_string(), landscape_data.id.clone(), heightmap.load_path(project_id, &format!("landscapes/{}/heightmaps", landscape_data.id))).await;
 
                                                 if let Some(texture) = heightmap_texture.ok() {
                                                     // TODO: only load in when in saved state / data, and with the desireed configuration (ex. grass color)
@@ -345,97 +673,103 @@ editor.saved_state = Some(loaded_state);
                                     }
                                     if let Some(ComponentKind::Model) = component.kind {
                                         let asset = saved_state.models.iter().find(|m| m.id == component.asset_id);
-                                        let model_position = Translation3::new(component.generic_properties.position[0], component.generic_properties.position[1], component.generic_properties.position[2]);
-                                        let model_rotation = UnitQuaternion::from_euler_angles(component.generic_properties.rotation[0].to_radians(), component.generic_properties.rotation[1].to_radians(), component.generic_properties.rotation[2].to_radians());
-                                        let model_iso = Isometry3::from_parts(model_position, model_rotation);
-                                        let model_scale = Vector3::new(component.generic_properties.scale[0], component.generic_properties.scale[1], component.generic_properties.scale[2]);
+                                        // world-space, so parented components land relative to their parent
+                                        let placement = placements.get(&component.id).copied().unwrap_or_else(|| Placement::from_generic(&component.generic_properties));
+                                        let model_iso = placement.isometry;
+                                        let model_scale = placement.scale;
 
                                         if let Some(asset_item) = asset {
-                                            handle_add_model(
+                                            if let Err(e) = handle_add_model(
                                                 renderer_state,  
                                                 &gpu_resources.device,
                                                 &gpu_resources.queue, 
                                                 project_id.to_string(), 
                                                 asset_item.id.clone(), 
                                                 component.id.clone(), 
-                                                asset_item.fileName.clone(), 
+                                                asset_item.load_path(project_id, "models"), 
                                                 model_iso, 
                                                 model_scale,
                                                 camera,
                                                 component.script_state.clone()
-                                            ).await;
+                                            ).await {
+                                                record_load_error(&mut load_errors, e);
+                                            }
                                         }
                                     }
                                     if let Some(ComponentKind::PlayerCharacter) = component.kind {
                                         let asset = saved_state.models.iter().find(|m| m.id == component.asset_id);
-                                        let model_position = Translation3::new(component.generic_properties.position[0], component.generic_properties.position[1], component.generic_properties.position[2]);
-                                        let model_rotation = UnitQuaternion::from_euler_angles(component.generic_properties.rotation[0].to_radians(), component.generic_properties.rotation[1].to_radians(), component.generic_properties.rotation[2].to_radians());
-                                        let model_iso = Isometry3::from_parts(model_position, model_rotation);
-                                        let model_scale = Vector3::new(component.generic_properties.scale[0], component.generic_properties.scale[1], component.generic_properties.scale[2]);
+                                        let placement = placements.get(&component.id).copied().unwrap_or_else(|| Placement::from_generic(&component.generic_properties));
+                                        let model_iso = placement.isometry;
+                                        let model_scale = placement.scale;
 
                                         let mut default_weapon = None;
 
                                         if let Some(data) = &component.player_properties {
                                             if let Some(default_weapon_id) = data.default_weapon_id.clone() {
                                                 let weapon = components.iter().find(|c| c.id == default_weapon_id);
-                                                let weapon = weapon.as_ref().expect("Couldn't find associated wepaon");
                                                 // let props = weapon.collectable_properties.as_ref().expect("Couldn't find weapon properties");
-                                                default_weapon = Some(weapon.clone().clone());
+                                                default_weapon = weapon.cloned();
                                             }
                                         }
 
-                                        if let Some(asset_item) = asset {
-                                            handle_add_player(
+                                        if renderer_state.world.player_character.is_some() {
+                                            // carried over from the previous level, move them to this level's start
+                                            renderer_state.teleport_player(component.generic_properties.position);
+                                        } else if let Some(asset_item) = asset {
+                                            if let Err(e) = handle_add_player(
                                                 renderer_state,  
                                                 &gpu_resources.device,
                                                 &gpu_resources.queue, 
                                                 project_id.to_string(), 
                                                 asset_item.id.clone(), 
                                                 component.id.clone(), 
-                                                asset_item.fileName.clone(), 
+                                                asset_item.load_path(project_id, "models"), 
                                                 model_iso, 
                                                 model_scale,
                                                 camera,
                                                 default_weapon,
                                                 component.script_state.clone()
-                                            ).await;
+                                            ).await {
+                                                record_load_error(&mut load_errors, e);
+                                            }
                                         }
                                     }
                                     if let Some(ComponentKind::NPC) = component.kind {
                                         let asset = saved_state.models.iter().find(|m| m.id == component.asset_id);
-                                        let model_position = Translation3::new(component.generic_properties.position[0], component.generic_properties.position[1], component.generic_properties.position[2]);
-                                        let model_rotation = UnitQuaternion::from_euler_angles(component.generic_properties.rotation[0].to_radians(), component.generic_properties.rotation[1].to_radians(), component.generic_properties.rotation[2].to_radians());
-                                        let model_iso = Isometry3::from_parts(model_position, model_rotation);
-                                        let model_scale = Vector3::new(component.generic_properties.scale[0], component.generic_properties.scale[1], component.generic_properties.scale[2]);
+                                        let placement = placements.get(&component.id).copied().unwrap_or_else(|| Placement::from_generic(&component.generic_properties));
+                                        let model_iso = placement.isometry;
+                                        let model_scale = placement.scale;
 
                                         if let Some(asset_item) = asset {
-                                            handle_add_npc(
+                                            if let Err(e) = handle_add_npc(
                                                 renderer_state,  
                                                 &gpu_resources.device,
                                                 &gpu_resources.queue, 
                                                 project_id.to_string(), 
                                                 asset_item.id.clone(), 
                                                 component.id.clone(), 
-                                                asset_item.fileName.clone(), 
+                                                asset_item.load_path(project_id, "models"), 
                                                 model_iso, 
                                                 model_scale,
                                                 camera,
-                                                component.script_state.clone()
-                                            ).await;
+                                                component.script_state.clone(),
+                                                &NPCArchetype::new(saved_state, component, components)
+                                            ).await {
+                                                record_load_error(&mut load_errors, e);
+                                            }
                                         }
                                     }
                                     if let Some(ComponentKind::Collectable) = component.kind {
                                         let asset = saved_state.models.iter().find(|m| m.id == component.asset_id);
-                                        let model_position = Translation3::new(component.generic_properties.position[0], component.generic_properties.position[1], component.generic_properties.position[2]);
-                                        let model_rotation = UnitQuaternion::from_euler_angles(component.generic_properties.rotation[0].to_radians(), component.generic_properties.rotation[1].to_radians(), component.generic_properties.rotation[2].to_radians());
-                                        let model_iso = Isometry3::from_parts(model_position, model_rotation);
-                                        let model_scale = Vector3::new(component.generic_properties.scale[0], component.generic_properties.scale[1], component.generic_properties.scale[2]);
+                                        let placement = placements.get(&component.id).copied().unwrap_or_else(|| Placement::from_generic(&component.generic_properties));
+                                        let model_iso = placement.isometry;
+                                        let model_scale = placement.scale;
 
-                                        let collectable_properties = component.collectable_properties.as_ref().expect("Couldn't find collectable properties");
-                                        let stat_id = collectable_properties.stat_id.as_ref().expect("Couldn't get collectable type");
-                                        let stats = saved_state.stats.as_ref().expect("Couldn't find any stats");
-                                        let related_stat = stats.iter().find(|s| s.id == stat_id.clone());
-                                        let related_stat = related_stat.as_ref().expect("Couldn't get related stat");
+                                        // check_component already reported anything missing here
+                                        let Some(collectable_properties) = component.collectable_properties.as_ref() else { continue };
+                                        let Some(stat_id) = collectable_properties.stat_id.as_ref() else { continue };
+                                        let related_stat = saved_state.stats.as_ref().and_then(|stats| stats.iter().find(|s| &s.id == stat_id));
+                                        let Some(related_stat) = related_stat else { continue };
 
                                         let character = components.iter().find(|c| c.kind == Some(ComponentKind::PlayerCharacter));
 
@@ -453,15 +787,27 @@ editor.saved_state = Some(loaded_state);
 
                                         println!("Adding collectale. Hidden in world: {:?}", hide_in_world);
 
-                                        if let Some(asset_item) = asset {
-                                            handle_add_collectable(
+                                        let already_carried = renderer_state.world.collectables.iter().any(|c| c.id == component.id);
+                                        // NPC loadouts resolving to this very component take it out of the world
+                                        let carried_by_npc = components
+                                            .iter()
+                                            .filter_map(|c| c.npc_properties.as_ref())
+                                            .flat_map(|p| p.inventory.iter())
+                                            .any(|item_id| loadout_item(components, item_id).map_or(false, |item| item.id == component.id));
+
+                                        if already_carried {
+                                            println!("Collectable already carried by the player");
+                                        } else if carried_by_npc {
+                                            println!("Collectable carried by an NPC");
+                                        } else if let Some(asset_item) = asset {
+                                            if let Err(e) = handle_add_collectable(
                                                 renderer_state,  
                                                 &gpu_resources.device,
                                                 &gpu_resources.queue, 
                                                 project_id.to_string(), 
                                                 asset_item.id.clone(), 
                                                 component.id.clone(), 
-                                                asset_item.fileName.clone(), 
+                                                asset_item.load_path(project_id, "models"), 
                                                 model_iso, 
                                                 model_scale,
                                                 camera,
@@ -469,14 +815,18 @@ editor.saved_state = Some(loaded_state);
                                                 related_stat,
                                                 hide_in_world,
                                                 component.script_state.clone()
-                                            ).await;
+                                            ).await {
+                                                record_load_error(&mut load_errors, e);
+                                            }
                                         }
                                     }
 
                                     if let Some(crate::helpers::saved_data::ComponentKind::PointLight) = component.kind {
                                         if let Some(light_props) = component.light_properties.as_ref() {
+                                            let placement = placements.get(&component.id).copied().unwrap_or_else(|| Placement::from_generic(&component.generic_properties));
+                                            renderer_state.point_light_ids.push(component.id.clone());
                                             renderer_state.point_lights.push(crate::core::editor::PointLight {
-                                                position: component.generic_properties.position,
+                                                position: placement.position(),
                                                 _padding1: 0,
                                                 color: [light_props.color[0], light_props.color[1], light_props.color[2]],
                                                 _padding2: 0,
@@ -489,6 +839,16 @@ editor.saved_state = Some(loaded_state);
                                             // }
                                         }
                                     }
+
+                                    if let Some(ComponentKind::TriggerVolume) = component.kind {
+                                        if let Some(trigger_props) = component.trigger_properties.as_ref() {
+                                            let placement = placements.get(&component.id).copied().unwrap_or_else(|| Placement::from_generic(&component.generic_properties));
+
+                                            if let Err(e) = renderer_state.world.place_trigger_volume(&component.id, trigger_props, placement.isometry, placement.scale) {
+                                                println!("Skipping trigger volume {}: {}", component.id, e);
+                                            }
+                                        }
+                                    }
                                 }
                             }
                         }
@@ -509,4 +869,19 @@ editor.saved_state = Some(loaded_state);
             //     &house_config,
             //     house_iso,
             // ).await;
+
+            // bodies are in the world now, so children can be attached to their parents
+            if let Some(components) = editor.saved_state.as_ref().and_then(|s| s.level_components(Some(level_id))) {
+                renderer_state.world.hierarchy = SceneHierarchy::new(components, &renderer_state.world.rigid_body_set);
+            }
+
+            editor.load_errors.extend(load_errors);
+}
+
+fn record_load_error(load_errors: &mut Vec<ProjectError>, error: ProjectError) {
+    println!("Skipping component: {}", error);
+
+    if !load_errors.contains(&error) {
+        load_errors.push(error);
+    }
 }
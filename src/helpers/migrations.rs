@@ -0,0 +1,263 @@
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+// Bump this and append to MIGRATIONS whenever midpoint.json changes shape.
+// Documents without a schema_version are treated as version 0.
+pub const CURRENT_SCHEMA_VERSION: u32 = 3;
+
+// Upgrades a document from `from` to `from + 1`, noting what it touched
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(&mut Map<String, Value>, &mut Vec<String>),
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "fill in fields older projects were saved without",
+        apply: migrate_v0_to_v1,
+    },
+    Migration {
+        from: 1,
+        description: "record the start level for multi-level projects",
+        apply: migrate_v1_to_v2,
+    },
+    Migration {
+        from: 2,
+        description: "store script state values with their types instead of as strings",
+        apply: migrate_v2_to_v3,
+    },
+];
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub changes: Vec<String>,
+}
+
+impl MigrationReport {
+    pub fn migrated(&self) -> bool {
+        self.from_version != self.to_version
+    }
+
+    pub fn print(&self) {
+        println!(
+            "Migrated project from schema v{} to v{}",
+            self.from_version, self.to_version
+        );
+        for change in &self.changes {
+            println!("  - {}", change);
+        }
+    }
+}
+
+pub fn schema_version(doc: &Value) -> u32 {
+    doc.get("schema_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u32
+}
+
+// Upgrades a raw midpoint.json document to the current schema
+pub fn migrate(doc: &mut Value) -> Result<MigrationReport, String> {
+    migrate_to(doc, CURRENT_SCHEMA_VERSION)
+}
+
+// Runs migrations one version at a time until the document reaches `target`
+pub fn migrate_to(doc: &mut Value, target: u32) -> Result<MigrationReport, String> {
+    let from_version = schema_version(doc);
+
+    if from_version > CURRENT_SCHEMA_VERSION {
+        return Err(format!(
+            "Project schema v{} is newer than this engine supports (v{})",
+            from_version, CURRENT_SCHEMA_VERSION
+        ));
+    }
+
+    let root = doc
+        .as_object_mut()
+        .ok_or_else(|| "Project file is not a JSON object".to_string())?;
+
+    let mut report = MigrationReport {
+        from_version,
+        to_version: from_version,
+        changes: Vec::new(),
+    };
+
+    while report.to_version < target {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.from == report.to_version)
+            .ok_or_else(|| format!("No migration from schema v{}", report.to_version))?;
+
+        let mut changes = Vec::new();
+        (migration.apply)(root, &mut changes);
+
+        report.to_version += 1;
+        report.changes.push(format!(
+            "v{} -> v{}: {}",
+            migration.from, report.to_version, migration.description
+        ));
+        report.changes.extend(changes.into_iter().map(|c| format!("    {}", c)));
+
+        root.insert("schema_version".to_string(), json!(report.to_version));
+    }
+
+    Ok(report)
+}
+
+// Inserts `value` under `key` if it's missing or null
+fn fill(obj: &mut Map<String, Value>, key: &str, value: Value, changes: &mut Vec<String>, context: &str) {
+    if obj.get(key).map(|v| v.is_null()).unwrap_or(true) {
+        obj.insert(key.to_string(), value);
+        changes.push(format!("added {}.{}", context, key));
+    }
+}
+
+fn migrate_v0_to_v1(root: &mut Map<String, Value>, changes: &mut Vec<String>) {
+    let project_name = root
+        .get("id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("Untitled")
+        .to_string();
+
+    fill(root, "project_name", json!(project_name), changes, "project");
+    fill(root, "concepts", json!([]), changes, "project");
+    fill(root, "models", json!([]), changes, "project");
+
+    // the earliest projects had no levels at all
+    fill(
+        root,
+        "levels",
+        json!([{ "id": Uuid::new_v4().to_string(), "components": [] }]),
+        changes,
+        "project",
+    );
+
+    let levels = match root.get_mut("levels").and_then(|v| v.as_array_mut()) {
+        Some(levels) => levels,
+        None => return,
+    };
+
+    for (level_index, level) in levels.iter_mut().enumerate() {
+        let level = match level.as_object_mut() {
+            Some(level) => level,
+            None => continue,
+        };
+        let level_context = format!("levels[{}]", level_index);
+
+        fill(level, "id", json!(Uuid::new_v4().to_string()), changes, &level_context);
+        fill(level, "components", json!([]), changes, &level_context);
+
+        let components = match level.get_mut("components").and_then(|v| v.as_array_mut()) {
+            Some(components) => components,
+            None => continue,
+        };
+
+        for (component_index, component) in components.iter_mut().enumerate() {
+            let component = match component.as_object_mut() {
+                Some(component) => component,
+                None => continue,
+            };
+            let context = format!("{}.components[{}]", level_context, component_index);
+
+            fill(component, "id", json!(Uuid::new_v4().to_string()), changes, &context);
+            fill(component, "asset_id", json!(""), changes, &context);
+            fill(
+                component,
+                "generic_properties",
+                json!({
+                    "name": "",
+                    "position": [0.0, 0.0, 0.0],
+                    "rotation": [0.0, 0.0, 0.0],
+                    "scale": [1.0, 1.0, 1.0],
+                }),
+                changes,
+                &context,
+            );
+
+            if let Some(generic) = component.get_mut("generic_properties").and_then(|v| v.as_object_mut()) {
+                let generic_context = format!("{}.generic_properties", context);
+                fill(generic, "name", json!(""), changes, &generic_context);
+                fill(generic, "position", json!([0.0, 0.0, 0.0]), changes, &generic_context);
+                fill(generic, "rotation", json!([0.0, 0.0, 0.0]), changes, &generic_context);
+                fill(generic, "scale", json!([1.0, 1.0, 1.0]), changes, &generic_context);
+            }
+        }
+    }
+}
+
+fn migrate_v1_to_v2(root: &mut Map<String, Value>, changes: &mut Vec<String>) {
+    // projects were single-level before, and that level was always the first
+    let first_level_id = root
+        .get("levels")
+        .and_then(|v| v.as_array())
+        .and_then(|levels| levels.first())
+        .and_then(|level| level.get("id"))
+        .cloned();
+
+    if let Some(first_level_id) = first_level_id {
+        fill(root, "start_level_id", first_level_id, changes, "project");
+    }
+}
+
+// Script state used to be flattened to strings. Anything that reads as a bool or number, and
+// writes back as the same string, was one. "007", "1e5" or "+3" were strings all along.
+pub fn typed_script_value(value: &str) -> Value {
+    let typed = match value {
+        "true" | "false" => json!(value == "true"),
+        _ => match (value.parse::<i64>(), value.parse::<f64>()) {
+            (Ok(i), _) => json!(i),
+            (_, Ok(f)) if f.is_finite() => json!(f),
+            _ => return json!(value),
+        },
+    };
+
+    if typed.to_string() == value { typed } else { json!(value) }
+}
+
+// Converts the string values of a script_state object in place, returns whether anything changed
+pub fn type_script_state(state: &mut Value) -> bool {
+    let mut changed = false;
+
+    if let Some(state) = state.as_object_mut() {
+        for value in state.values_mut() {
+            if let Some(s) = value.as_str() {
+                let typed = typed_script_value(s);
+                if !typed.is_string() {
+                    *value = typed;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    changed
+}
+
+fn migrate_v2_to_v3(root: &mut Map<String, Value>, changes: &mut Vec<String>) {
+    // components live in levels and in prefabs
+    for list in ["levels", "prefabs"] {
+        let entries = match root.get_mut(list).and_then(|v| v.as_array_mut()) {
+            Some(entries) => entries,
+            None => continue,
+        };
+
+        for (entry_index, entry) in entries.iter_mut().enumerate() {
+            let components = match entry.get_mut("components").and_then(|v| v.as_array_mut()) {
+                Some(components) => components,
+                None => continue,
+            };
+
+            for (component_index, component) in components.iter_mut().enumerate() {
+                if let Some(state) = component.get_mut("script_state") {
+                    if type_script_state(state) {
+                        changes.push(format!("typed {}[{}].components[{}].script_state", list, entry_index, component_index));
+                    }
+                }
+            }
+        }
+    }
+}
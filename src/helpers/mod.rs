@@ -3,4 +3,9 @@ pub mod saved_data;
 pub mod utilities;
 pub mod timelines;
 pub mod load_project;
+pub mod migrations;
+pub mod project_error;
+pub mod pak;
+pub mod prefabs;
+pub mod save_game;
 pub mod wasm_loaders;
\ No newline at end of file
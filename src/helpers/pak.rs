@@ -0,0 +1,389 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, RwLock};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::utilities::{find_project_json, get_project_root, parse_project_state, serialize_project_state};
+
+// Layout: magic, u32 format version, u64 manifest length, manifest json, then file blobs back to back.
+// Entry offsets are relative to the first byte after the manifest.
+pub const PAK_MAGIC: &[u8; 4] = b"EPAK";
+pub const PAK_FORMAT_VERSION: u32 = 1;
+pub const PAK_PROJECT_FILE: &str = "midpoint.json";
+
+// Project folders that get bundled, everything else (backups, concepts) stays behind
+const PAK_ASSET_DIRS: &[&str] = &["models", "landscapes", "textures", "fonts"];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PakEntry {
+    pub path: String,
+    pub offset: u64,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PakManifest {
+    pub format_version: u32,
+    pub project_id: String,
+    pub project_name: String,
+    pub schema_version: u32,
+    pub created_at: String,
+    pub entries: Vec<PakEntry>,
+}
+
+impl PakManifest {
+    pub fn entry(&self, path: &str) -> Option<&PakEntry> {
+        let path = normalize(path);
+        self.entries.iter().find(|e| e.path == path)
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|e| e.size).sum()
+    }
+}
+
+pub fn is_pak_path(target: &str) -> bool {
+    Path::new(target)
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("pak"))
+        .unwrap_or(false)
+}
+
+fn normalize(path: &str) -> String {
+    path.replace('\\', "/").trim_start_matches("./").to_string()
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+// Recursively lists files under `dir` as (path relative to `root`, path on disk)
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Couldn't read directory {:?}: {}", dir, e))?;
+
+    for entry in entries {
+        let path = entry.map_err(|e| format!("Couldn't read directory {:?}: {}", dir, e))?.path();
+
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push((normalize(&relative.to_string_lossy()), path.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+// Bundles a project into a single archive. `target` is anything `find_project_json` accepts,
+// scripts are read relative to `script_root` and stored under their rhai_script_path.
+pub fn pack_project(target: &str, script_root: &Path, output: &Path) -> Result<PakManifest, String> {
+    let json_path = find_project_json(target);
+    let path_label = json_path.to_string_lossy().to_string();
+    let project_dir = json_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let json_content = fs::read_to_string(&json_path).map_err(|e| format!("Couldn't read {}: {}", path_label, e))?;
+    let (mut state, _) = parse_project_state(&json_content, &path_label).map_err(|e| e.to_string())?;
+
+    let project_id = state.id.clone().unwrap_or_else(|| {
+        project_dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+
+    // the archive always holds a current-schema project with relative paths
+    state.relativize_paths(&project_id);
+    let project_json = serialize_project_state(&state)
+        .map_err(|e| format!("Couldn't serialize project: {}", e))?
+        .into_bytes();
+
+    let mut files = Vec::new();
+    for dir in PAK_ASSET_DIRS {
+        let asset_dir = project_dir.join(dir);
+        if asset_dir.is_dir() {
+            collect_files(&project_dir, &asset_dir, &mut files)?;
+        }
+    }
+
+    let mut scripts: Vec<String> = state.global_rhai_scripts.clone().unwrap_or_default();
+    let level_members = state.levels.iter().flatten().flat_map(|l| l.components.iter().flatten());
+    let prefab_members = state.prefabs.iter().flatten().flat_map(|p| p.components.iter());
+    for component in level_members.chain(prefab_members) {
+        if let Some(script) = &component.rhai_script_path {
+            scripts.push(script.clone());
+        }
+    }
+    for tree in state.behavior_trees.iter().flatten() {
+        let mut leaves = Vec::new();
+        tree.root.script_leaves(&mut leaves);
+        scripts.extend(leaves.into_iter().filter_map(|(_, script)| script.map(String::from)));
+    }
+
+    let mut seen: HashSet<String> = files.iter().map(|(path, _)| path.clone()).collect();
+    for script in scripts {
+        let path = normalize(&script);
+        if seen.insert(path.clone()) {
+            let source = script_root.join(&script);
+            if !source.is_file() {
+                return Err(format!("Couldn't find script {} in {:?}", script, script_root));
+            }
+            files.push((path, source));
+        }
+    }
+
+    // assets kept outside the usual folders are loaded from their normalFilePath, see File::load_path
+    for file in state.files_mut() {
+        let path = normalize(&file.normalFilePath);
+        if path.is_empty() || path.starts_with("..") || seen.contains(&path) {
+            continue;
+        }
+
+        let source = project_dir.join(&path);
+        if source.is_file() {
+            seen.insert(path.clone());
+            files.push((path, source));
+        }
+    }
+
+    for dialogue in state.dialogue_files.iter().flatten() {
+        let path = normalize(dialogue);
+        if seen.insert(path.clone()) {
+            let source = project_dir.join(dialogue);
+            if !source.is_file() {
+                return Err(format!("Couldn't find dialogue file {} in {:?}", dialogue, project_dir));
+            }
+            files.push((path, source));
+        }
+    }
+
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    // hash everything up front so the manifest can be written before the blobs, the same bytes
+    // are written below so a file changing mid-pack can't leave a hash that doesn't match
+    let mut entries = vec![PakEntry {
+        path: PAK_PROJECT_FILE.to_string(),
+        offset: 0,
+        size: project_json.len() as u64,
+        sha256: sha256_hex(&project_json),
+    }];
+    let mut offset = project_json.len() as u64;
+
+    let mut blobs = Vec::with_capacity(files.len());
+
+    for (path, source) in &files {
+        let bytes = fs::read(source).map_err(|e| format!("Couldn't read {:?}: {}", source, e))?;
+        entries.push(PakEntry {
+            path: path.clone(),
+            offset,
+            size: bytes.len() as u64,
+            sha256: sha256_hex(&bytes),
+        });
+        offset += bytes.len() as u64;
+        blobs.push(bytes);
+    }
+
+    let manifest = PakManifest {
+        format_version: PAK_FORMAT_VERSION,
+        project_id,
+        project_name: state.project_name.clone(),
+        schema_version: state.schema_version,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        entries,
+    };
+    let manifest_json = serde_json::to_vec(&manifest).map_err(|e| format!("Couldn't serialize manifest: {}", e))?;
+
+    let write_error = |e: std::io::Error| format!("Couldn't write {:?}: {}", output, e);
+    let mut out = std::io::BufWriter::new(fs::File::create(output).map_err(write_error)?);
+
+    out.write_all(PAK_MAGIC).map_err(write_error)?;
+    out.write_all(&PAK_FORMAT_VERSION.to_le_bytes()).map_err(write_error)?;
+    out.write_all(&(manifest_json.len() as u64).to_le_bytes()).map_err(write_error)?;
+    out.write_all(&manifest_json).map_err(write_error)?;
+    out.write_all(&project_json).map_err(write_error)?;
+
+    for bytes in &blobs {
+        out.write_all(bytes).map_err(write_error)?;
+    }
+    out.flush().map_err(write_error)?;
+
+    Ok(manifest)
+}
+
+pub struct PakArchive {
+    pub path: PathBuf,
+    pub manifest: PakManifest,
+    data_start: u64,
+}
+
+impl PakArchive {
+    // Reads the header and manifest, file contents are read on demand
+    pub fn open(path: &Path) -> Result<PakArchive, String> {
+        let mut file = fs::File::open(path).map_err(|e| format!("Couldn't open {:?}: {}", path, e))?;
+        let read_error = |e: std::io::Error| format!("Couldn't read {:?} header: {}", path, e);
+
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic).map_err(read_error)?;
+        if &magic != PAK_MAGIC {
+            return Err(format!("{:?} is not a game package", path));
+        }
+
+        let mut version = [0u8; 4];
+        file.read_exact(&mut version).map_err(read_error)?;
+        let version = u32::from_le_bytes(version);
+        if version != PAK_FORMAT_VERSION {
+            return Err(format!(
+                "{:?} is package format v{}, this engine reads v{}",
+                path, version, PAK_FORMAT_VERSION
+            ));
+        }
+
+        let mut manifest_len = [0u8; 8];
+        file.read_exact(&mut manifest_len).map_err(read_error)?;
+        let manifest_len = u64::from_le_bytes(manifest_len);
+
+        // a corrupt length would have us allocate whatever it says
+        let file_len = file.metadata().map_err(read_error)?.len();
+        if manifest_len > file_len.saturating_sub(4 + 4 + 8) {
+            return Err(format!(
+                "{:?} says its manifest is {} bytes, but the file is only {} bytes",
+                path, manifest_len, file_len
+            ));
+        }
+
+        let mut manifest_json = vec![0u8; manifest_len as usize];
+        file.read_exact(&mut manifest_json).map_err(read_error)?;
+        let manifest: PakManifest = serde_json::from_slice(&manifest_json)
+            .map_err(|e| format!("Couldn't parse {:?} manifest: {}", path, e))?;
+
+        Ok(PakArchive {
+            path: path.to_path_buf(),
+            manifest,
+            data_start: 4 + 4 + 8 + manifest_len,
+        })
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.manifest.entry(path).is_some()
+    }
+
+    // Reads one file and checks it against the manifest hash
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, String> {
+        let entry = self
+            .manifest
+            .entry(path)
+            .ok_or_else(|| format!("{} is not in {:?}", path, self.path))?;
+
+        let mut file = fs::File::open(&self.path).map_err(|e| format!("Couldn't open {:?}: {}", self.path, e))?;
+
+        // same as the manifest length in open, a corrupt entry shouldn't decide how much we allocate
+        let file_len = file
+            .metadata()
+            .map_err(|e| format!("Couldn't read {:?}: {}", self.path, e))?
+            .len();
+        let end = self.data_start.checked_add(entry.offset).and_then(|start| start.checked_add(entry.size));
+        if end.map(|end| end > file_len).unwrap_or(true) {
+            return Err(format!(
+                "{} in {:?} runs past the end of the file, the package is corrupt",
+                path, self.path
+            ));
+        }
+
+        file.seek(SeekFrom::Start(self.data_start + entry.offset))
+            .map_err(|e| format!("Couldn't seek to {} in {:?}: {}", path, self.path, e))?;
+
+        let mut bytes = vec![0u8; entry.size as usize];
+        file.read_exact(&mut bytes)
+            .map_err(|e| format!("Couldn't read {} from {:?}: {}", path, self.path, e))?;
+
+        if sha256_hex(&bytes) != entry.sha256 {
+            return Err(format!("{} in {:?} doesn't match its hash, the package is corrupt", path, self.path));
+        }
+
+        Ok(bytes)
+    }
+
+    // Paths of every entry that fails to read or doesn't match its hash
+    pub fn verify(&self) -> Vec<String> {
+        self.manifest
+            .entries
+            .iter()
+            .filter(|entry| self.read(&entry.path).is_err())
+            .map(|entry| entry.path.clone())
+            .collect()
+    }
+}
+
+static MOUNTED_PAKS: LazyLock<RwLock<Vec<Arc<PakArchive>>>> = LazyLock::new(|| RwLock::new(Vec::new()));
+
+// Makes an archive's files visible to the project loaders, returns its project id
+pub fn mount_pak(path: &Path) -> Result<String, String> {
+    let archive = PakArchive::open(path)?;
+    let project_id = archive.manifest.project_id.clone();
+
+    println!(
+        "Mounted {:?}: {} ({} files)",
+        path,
+        archive.manifest.project_name,
+        archive.manifest.entries.len()
+    );
+
+    let mut mounted = MOUNTED_PAKS.write().expect("Couldn't lock mounted packages");
+    mounted.retain(|a| a.manifest.project_id != project_id);
+    mounted.push(Arc::new(archive));
+
+    Ok(project_id)
+}
+
+pub fn mounted_pak(project_id: &str) -> Option<Arc<PakArchive>> {
+    MOUNTED_PAKS
+        .read()
+        .expect("Couldn't lock mounted packages")
+        .iter()
+        .find(|a| a.manifest.project_id == project_id)
+        .cloned()
+}
+
+// Accepts a project id or a .pak path, mounting the package if given one
+pub fn resolve_project(target: &str) -> Result<String, String> {
+    if is_pak_path(target) {
+        mount_pak(Path::new(target))
+    } else {
+        Ok(target.to_string())
+    }
+}
+
+// Reads a file relative to the project root, from the mounted package if there is one
+pub fn read_project_file(project_id: &str, relative: &str) -> Result<Vec<u8>, String> {
+    if let Some(archive) = mounted_pak(project_id) {
+        return archive.read(relative);
+    }
+
+    let path = get_project_root(project_id).join(relative);
+    fs::read(&path).map_err(|e| format!("Couldn't read {:?}: {}", path, e))
+}
+
+// Scripts are keyed by their rhai_script_path, which is relative to the working directory on disk
+pub fn read_script(path: &str) -> Result<String, String> {
+    let archive = MOUNTED_PAKS
+        .read()
+        .expect("Couldn't lock mounted packages")
+        .iter()
+        .find(|a| a.contains(path))
+        .cloned();
+
+    match archive {
+        Some(archive) => {
+            let bytes = archive.read(path)?;
+            String::from_utf8(bytes).map_err(|e| format!("Script {} isn't valid UTF-8: {}", path, e))
+        }
+        None => fs::read_to_string(path).map_err(|e| format!("Couldn't read script {}: {}", path, e)),
+    }
+}
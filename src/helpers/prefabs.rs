@@ -0,0 +1,297 @@
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::helpers::saved_data::{ComponentData, ComponentKind, GenericProperties, PrefabData, PrefabInstance, SavedState};
+
+// Prefab instances are stored sparse in midpoint.json: an id, a `prefab` link and only
+// the fields that differ from the prefab. They're resolved into full components when the
+// project is parsed and collapsed back down when it's saved, so prefab edits reach every instance.
+
+// Kept on every instance on disk, whatever the prefab says
+const INSTANCE_KEYS: &[&str] = &["id", "prefab"];
+
+// Overlays `overrides` onto `base`. Objects merge key by key, anything else is replaced.
+pub fn merge(base: &mut Value, overrides: &Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(key) {
+                    Some(existing) if existing.is_object() && value.is_object() => merge(existing, value),
+                    _ => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides.clone(),
+    }
+}
+
+// The parts of `value` that differ from `base`, or None when there's nothing to override
+pub fn diff(base: &Value, value: &Value) -> Option<Value> {
+    if base == value {
+        return None;
+    }
+
+    match (base, value) {
+        (Value::Object(base), Value::Object(value)) => {
+            let mut changed = Map::new();
+
+            for (key, v) in value {
+                match base.get(key) {
+                    Some(b) => {
+                        if let Some(d) = diff(b, v) {
+                            changed.insert(key.clone(), d);
+                        }
+                    }
+                    None if !v.is_null() => {
+                        changed.insert(key.clone(), v.clone());
+                    }
+                    None => {}
+                }
+            }
+
+            if changed.is_empty() {
+                None
+            } else {
+                Some(Value::Object(changed))
+            }
+        }
+        _ => Some(value.clone()),
+    }
+}
+
+// Finds the prefab component a raw `prefab` link points at
+fn prefab_member(prefabs: &Value, link: &Value) -> Option<Value> {
+    let prefab_id = link.get("prefab_id")?.as_str()?;
+    let prefab = prefabs
+        .as_array()?
+        .iter()
+        .find(|p| p.get("id").and_then(Value::as_str) == Some(prefab_id))?;
+    let components = prefab.get("components")?.as_array()?;
+
+    match link.get("component_id").and_then(Value::as_str) {
+        Some(component_id) => components
+            .iter()
+            .find(|c| c.get("id").and_then(Value::as_str) == Some(component_id))
+            .cloned(),
+        None => components.first().cloned(),
+    }
+}
+
+fn instances_mut(doc: &mut Value) -> Vec<&mut Value> {
+    doc.get_mut("levels")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(|level| level.get_mut("components").and_then(Value::as_array_mut))
+        .flatten()
+        .filter(|c| c.get("prefab").map(|p| !p.is_null()).unwrap_or(false))
+        .collect()
+}
+
+// Expands sparse instances in a raw midpoint.json document into full components
+pub fn resolve_instances(doc: &mut Value) {
+    let prefabs = doc.get("prefabs").cloned().unwrap_or(Value::Null);
+
+    for component in instances_mut(doc) {
+        let link = component["prefab"].clone();
+
+        match prefab_member(&prefabs, &link) {
+            Some(mut resolved) => {
+                merge(&mut resolved, component);
+                *component = resolved;
+            }
+            None => {
+                // leave it parseable, check_component reports the missing prefab
+                if let Some(component) = component.as_object_mut() {
+                    component.entry("asset_id").or_insert(json!(""));
+                    component.entry("generic_properties").or_insert(json!({
+                        "name": "",
+                        "position": [0.0, 0.0, 0.0],
+                        "rotation": [0.0, 0.0, 0.0],
+                        "scale": [1.0, 1.0, 1.0],
+                    }));
+                }
+            }
+        }
+    }
+}
+
+// Strips instances in a serialized project back down to their overrides
+pub fn collapse_instances(doc: &mut Value) {
+    let prefabs = doc.get("prefabs").cloned().unwrap_or(Value::Null);
+
+    for component in instances_mut(doc) {
+        let base = match prefab_member(&prefabs, &component["prefab"]) {
+            Some(base) => base,
+            None => continue,
+        };
+
+        let mut sparse = diff(&base, component).unwrap_or_else(|| json!({}));
+        for key in INSTANCE_KEYS {
+            if let Some(value) = component.get(*key) {
+                sparse[*key] = value.clone();
+            }
+        }
+
+        *component = sparse;
+    }
+}
+
+// Replaces (or adds) a prefab and re-resolves its instances, keeping each one's overrides.
+// Returns the ids of the instances that changed.
+pub fn update_prefab(state: &mut SavedState, prefab: PrefabData) -> Vec<String> {
+    let old_prefab = state.prefab(&prefab.id).cloned();
+    let mut updated = Vec::new();
+
+    let components = state
+        .levels
+        .iter_mut()
+        .flatten()
+        .filter_map(|l| l.components.as_mut())
+        .flatten();
+
+    for component in components {
+        let link = match &component.prefab {
+            Some(link) if link.prefab_id == prefab.id => link.clone(),
+            _ => continue,
+        };
+        let new_base = match prefab.member(link.component_id.as_deref()) {
+            Some(base) => base,
+            None => continue,
+        };
+
+        let current = serde_json::to_value(&*component).expect("Couldn't serialize component");
+        let overrides = match old_prefab.as_ref().and_then(|p| p.member(link.component_id.as_deref())) {
+            Some(old_base) => {
+                let old_base = serde_json::to_value(old_base).expect("Couldn't serialize prefab");
+                diff(&old_base, &current).unwrap_or_else(|| json!({}))
+            }
+            // nothing to compare against, so everything counts as an override
+            None => current,
+        };
+
+        let mut resolved = serde_json::to_value(new_base).expect("Couldn't serialize prefab");
+        merge(&mut resolved, &overrides);
+        resolved["id"] = json!(component.id);
+        resolved["prefab"] = serde_json::to_value(&link).expect("Couldn't serialize prefab link");
+
+        match serde_json::from_value::<ComponentData>(resolved) {
+            Ok(resolved) if resolved != *component => {
+                *component = resolved;
+                updated.push(component.id.clone());
+            }
+            Ok(_) => {}
+            Err(e) => println!("Couldn't apply prefab {} to {}: {}", prefab.id, component.id, e),
+        }
+    }
+
+    let prefabs = state.prefabs.get_or_insert_with(Vec::new);
+    match prefabs.iter_mut().find(|p| p.id == prefab.id) {
+        Some(existing) => *existing = prefab,
+        None => prefabs.push(prefab),
+    }
+
+    updated
+}
+
+// Turns a placed component into a new prefab, and the component into its first instance
+pub fn make_prefab(state: &mut SavedState, component_id: &str, name: &str) -> Option<String> {
+    let component = state.find_component_mut(component_id)?;
+    if component.prefab.is_some() {
+        return None;
+    }
+
+    let prefab_id = Uuid::new_v4().to_string();
+
+    let mut member = component.clone();
+    member.id = Uuid::new_v4().to_string();
+
+    component.prefab = Some(PrefabInstance {
+        prefab_id: prefab_id.clone(),
+        component_id: None,
+    });
+
+    state.prefabs.get_or_insert_with(Vec::new).push(PrefabData {
+        id: prefab_id.clone(),
+        name: name.to_string(),
+        components: vec![member],
+    });
+
+    Some(prefab_id)
+}
+
+// Pushes an instance's current values into its prefab, so every other instance picks them up
+pub fn apply_to_prefab(state: &mut SavedState, component_id: &str) -> Vec<String> {
+    let component = state
+        .levels
+        .iter()
+        .flatten()
+        .filter_map(|l| l.components.as_ref())
+        .flatten()
+        .find(|c| c.id == component_id)
+        .cloned();
+
+    let (link, component) = match component {
+        Some(component) => match component.prefab.clone() {
+            Some(link) => (link, component),
+            None => return Vec::new(),
+        },
+        None => return Vec::new(),
+    };
+
+    let mut prefab = match state.prefab(&link.prefab_id) {
+        Some(prefab) => prefab.clone(),
+        None => return Vec::new(),
+    };
+
+    let member = match link.component_id.as_deref() {
+        Some(id) => prefab.components.iter_mut().find(|c| c.id == id),
+        None => prefab.components.first_mut(),
+    };
+    let member = match member {
+        Some(member) => member,
+        None => return Vec::new(),
+    };
+
+    // placement stays per-instance, everything else becomes the prefab's
+    let member_id = member.id.clone();
+    let position = member.generic_properties.position;
+    let rotation = member.generic_properties.rotation;
+
+    *member = component;
+    member.id = member_id;
+    member.prefab = None;
+    member.generic_properties.position = position;
+    member.generic_properties.rotation = rotation;
+
+    update_prefab(state, prefab)
+}
+
+// What a script spawn places: an instance of the prefab's first member, or a bare Model of a
+// model asset. The caller gives it an id and a position.
+pub fn spawn_template(state: &SavedState, source_id: &str) -> Option<ComponentData> {
+    if let Some(prefab) = state.prefab(source_id) {
+        let mut component = prefab.components.first()?.clone();
+        component.prefab = Some(PrefabInstance {
+            prefab_id: prefab.id.clone(),
+            component_id: None,
+        });
+        component.parent_id = None;
+        return Some(component);
+    }
+
+    let asset = state.models.iter().find(|m| m.id == source_id)?;
+
+    Some(ComponentData {
+        kind: Some(ComponentKind::Model),
+        asset_id: asset.id.clone(),
+        generic_properties: GenericProperties {
+            name: asset.fileName.clone(),
+            scale: [1.0, 1.0, 1.0],
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
@@ -0,0 +1,281 @@
+use std::fmt;
+
+use crate::helpers::saved_data::{ComponentData, ComponentKind, SavedState};
+
+// Something wrong with a project on disk. Whole-project errors stop the load,
+// component errors skip that component and get listed in the editor.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProjectError {
+    MissingFile {
+        path: String,
+        reason: String,
+    },
+    Parse {
+        path: String,
+        message: String,
+        line: usize,
+        column: usize,
+    },
+    Migration(String),
+    DanglingReference {
+        component_id: String,
+        field: String,
+        target_id: String,
+    },
+    MissingProperty {
+        component_id: String,
+        property: String,
+    },
+    UnsupportedKind {
+        component_id: String,
+        kind: String,
+    },
+    ParentCycle {
+        component_id: String,
+    },
+    // the component's data is fine but World couldn't place its body
+    InvalidComponent {
+        component_id: String,
+        reason: String,
+    },
+}
+
+impl ProjectError {
+    pub fn parse(path: &str, error: &serde_json::Error) -> Self {
+        ProjectError::Parse {
+            path: path.to_string(),
+            message: error.to_string(),
+            line: error.line(),
+            column: error.column(),
+        }
+    }
+
+    pub fn dangling(component: &ComponentData, field: &str, target_id: &str) -> Self {
+        ProjectError::DanglingReference {
+            component_id: component.id.clone(),
+            field: field.to_string(),
+            target_id: target_id.to_string(),
+        }
+    }
+
+    pub fn missing_property(component: &ComponentData, property: &str) -> Self {
+        ProjectError::MissingProperty {
+            component_id: component.id.clone(),
+            property: property.to_string(),
+        }
+    }
+
+    // The component this error belongs to, if it's not a whole-project error
+    pub fn component_id(&self) -> Option<&str> {
+        match self {
+            ProjectError::DanglingReference { component_id, .. }
+            | ProjectError::MissingProperty { component_id, .. }
+            | ProjectError::UnsupportedKind { component_id, .. }
+            | ProjectError::ParentCycle { component_id }
+            | ProjectError::InvalidComponent { component_id, .. } => Some(component_id),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProjectError::MissingFile { path, reason } => write!(f, "Missing file {}: {}", path, reason),
+            // line is 0 when serde couldn't tell where the error is
+            ProjectError::Parse { path, message, line: 0, .. } => write!(f, "Couldn't parse {}: {}", path, message),
+            ProjectError::Parse { path, message, line, column } => {
+                write!(f, "Couldn't parse {} at line {}, column {}: {}", path, line, column, message)
+            }
+            ProjectError::Migration(message) => write!(f, "Couldn't migrate project: {}", message),
+            ProjectError::DanglingReference { component_id, field, target_id } => write!(
+                f,
+                "Component {} references missing {} {}",
+                component_id, field, target_id
+            ),
+            ProjectError::MissingProperty { component_id, property } => {
+                write!(f, "Component {} is missing {}", component_id, property)
+            }
+            ProjectError::UnsupportedKind { component_id, kind } => {
+                write!(f, "Component {} has unsupported kind {}", component_id, kind)
+            }
+            ProjectError::ParentCycle { component_id } => {
+                write!(f, "Component {} is its own ancestor through parent_id", component_id)
+            }
+            ProjectError::InvalidComponent { component_id, reason } => {
+                write!(f, "Couldn't place component {}: {}", component_id, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+// Checks the references a component needs to be placed. `level_components` is the level it lives in.
+pub fn check_component(
+    saved_state: &SavedState,
+    level_components: &[ComponentData],
+    component: &ComponentData,
+) -> Result<(), ProjectError> {
+    match component_errors(saved_state, level_components, component).into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+// Every broken reference on a component, for tools that want the full list
+pub fn component_errors(
+    saved_state: &SavedState,
+    level_components: &[ComponentData],
+    component: &ComponentData,
+) -> Vec<ProjectError> {
+    let mut errors = Vec::new();
+
+    // an instance of a missing prefab has nothing to resolve its fields from
+    if let Some(instance) = &component.prefab {
+        if saved_state.prefab_member(instance).is_none() {
+            errors.push(ProjectError::dangling(component, "prefab", &instance.prefab_id));
+            return errors;
+        }
+    }
+
+    // without a reachable root there's nothing for the local transform to be relative to
+    if let Some(parent_id) = &component.parent_id {
+        if !level_components.iter().any(|c| &c.id == parent_id) {
+            errors.push(ProjectError::dangling(component, "parent", parent_id));
+        } else if parent_loops(level_components, component) {
+            errors.push(ProjectError::ParentCycle {
+                component_id: component.id.clone(),
+            });
+        }
+    }
+
+    let kind = match &component.kind {
+        Some(kind) => kind,
+        None => {
+            errors.push(ProjectError::UnsupportedKind {
+                component_id: component.id.clone(),
+                kind: "none".to_string(),
+            });
+            return errors;
+        }
+    };
+
+    let has_model = |id: &str| saved_state.models.iter().any(|m| m.id == id);
+
+    match kind {
+        ComponentKind::Model | ComponentKind::NPC | ComponentKind::PlayerCharacter | ComponentKind::Collectable => {
+            if !has_model(&component.asset_id) {
+                errors.push(ProjectError::dangling(component, "model", &component.asset_id));
+            }
+        }
+        ComponentKind::Landscape => {
+            let landscape = saved_state
+                .landscapes
+                .as_ref()
+                .and_then(|landscapes| landscapes.iter().find(|l| l.id == component.asset_id));
+
+            match landscape {
+                Some(landscape) if landscape.heightmap.is_none() => {
+                    errors.push(ProjectError::missing_property(component, "landscape heightmap"));
+                }
+                Some(_) => {}
+                None => errors.push(ProjectError::dangling(component, "landscape", &component.asset_id)),
+            }
+
+            if component.landscape_properties.is_none() {
+                errors.push(ProjectError::missing_property(component, "landscape_properties"));
+            }
+        }
+        _ => {}
+    }
+
+    match kind {
+        ComponentKind::Collectable => match &component.collectable_properties {
+            Some(properties) => {
+                if properties.collectable_type.is_none() {
+                    errors.push(ProjectError::missing_property(component, "collectable_type"));
+                }
+
+                match &properties.stat_id {
+                    Some(stat_id) => {
+                        let stat_exists = saved_state
+                            .stats
+                            .as_ref()
+                            .map(|stats| stats.iter().any(|s| &s.id == stat_id))
+                            .unwrap_or(false);
+
+                        if !stat_exists {
+                            errors.push(ProjectError::dangling(component, "stat", stat_id));
+                        }
+                    }
+                    None => errors.push(ProjectError::missing_property(component, "stat_id")),
+                }
+            }
+            None => errors.push(ProjectError::missing_property(component, "collectable_properties")),
+        },
+        ComponentKind::NPC => {
+            let stat_id = component.npc_properties.as_ref().and_then(|p| p.stat_id.as_ref());
+
+            if let Some(stat_id) = stat_id {
+                if saved_state.stat(stat_id).is_none() {
+                    errors.push(ProjectError::dangling(component, "stat", stat_id));
+                }
+            }
+        }
+        ComponentKind::PlayerCharacter => {
+            let weapon_id = component
+                .player_properties
+                .as_ref()
+                .and_then(|p| p.default_weapon_id.as_ref());
+
+            if let Some(weapon_id) = weapon_id {
+                if !level_components.iter().any(|c| &c.id == weapon_id) {
+                    errors.push(ProjectError::dangling(component, "default weapon", weapon_id));
+                }
+            }
+        }
+        ComponentKind::PointLight => {
+            if component.light_properties.is_none() {
+                errors.push(ProjectError::missing_property(component, "light_properties"));
+            }
+        }
+        ComponentKind::Portal => match &component.portal_properties {
+            Some(properties) => {
+                if saved_state.level(Some(&properties.target_level_id)).is_none() {
+                    errors.push(ProjectError::dangling(component, "level", &properties.target_level_id));
+                }
+            }
+            None => errors.push(ProjectError::missing_property(component, "portal_properties")),
+        },
+        ComponentKind::TriggerVolume => {
+            if component.trigger_properties.is_none() {
+                errors.push(ProjectError::missing_property(component, "trigger_properties"));
+            }
+        }
+        _ => {}
+    }
+
+    errors
+}
+
+// Whether following parent_id from `component` ever comes back around to it
+fn parent_loops(level_components: &[ComponentData], component: &ComponentData) -> bool {
+    let mut current = component;
+
+    // any loop that doesn't include `component` is caught within one pass over the level
+    for _ in 0..level_components.len() {
+        let parent = current
+            .parent_id
+            .as_ref()
+            .and_then(|id| level_components.iter().find(|c| &c.id == id));
+
+        match parent {
+            Some(parent) if parent.id == component.id => return true,
+            Some(parent) => current = parent,
+            None => return false,
+        }
+    }
+
+    false
+}
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::RendererState::RendererState;
+use crate::core::World::World;
+use crate::game_behaviors::blackboard::Blackboard;
+use crate::game_behaviors::dialogue_state::DialogueState;
+use crate::game_behaviors::inventory::Inventory;
+use crate::game_behaviors::quests::{QuestLog, QuestProgress};
+use crate::game_behaviors::timers::Timers;
+use crate::helpers::saved_data::{CharacterStats, ComponentData, ComponentKind, ScriptState};
+
+use super::migrations::type_script_state;
+use super::utilities::get_common_os_dir;
+
+// Save games hold a playthrough's runtime state, they never touch midpoint.json.
+// Components scripts spawned aren't saved, a loaded game starts without them like a reloaded level.
+// Bump this when the layout changes and teach read_save how to bring older files forward.
+// v2: script state values keep their types
+pub const SAVE_FORMAT_VERSION: u32 = 2;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct PlayerSave {
+    pub position: [f32; 3],
+    pub stats: CharacterStats,
+    pub inventory: Inventory,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct NpcSave {
+    pub id: String,
+    pub position: [f32; 3],
+    pub stats: CharacterStats,
+    pub alive: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct SaveGame {
+    pub format_version: u32,
+    pub project_id: String,
+    pub level_id: String,
+    pub saved_at: String,
+    pub player: Option<PlayerSave>,
+    pub npcs: Vec<NpcSave>,
+    pub collected: Vec<String>, // collectable component ids that have been picked up
+    #[serde(default)]
+    pub destroyed: Vec<String>, // NPC component ids the level placed that scripts have since destroyed
+    pub script_states: HashMap<String, ScriptState>, // keyed by component id, or script path for global scripts
+    pub dialogue: DialogueState,
+    #[serde(default)]
+    pub quests: Vec<QuestProgress>,
+    #[serde(default)]
+    pub timers: Timers, // game time and pending system.after / system.every calls
+    #[serde(default)]
+    pub blackboard: Blackboard, // dialogue variables and what each NPC remembers
+}
+
+impl SaveGame {
+    // Snapshots the world. Script states and dialogue live outside it, the caller fills those in.
+    pub fn capture(project_id: &str, level_id: &str, world: &World, level_components: &[ComponentData]) -> Self {
+        let body_position = |handle: RigidBodyHandle| {
+            world
+                .body_position(handle)
+                .map(|p| [p.x, p.y, p.z])
+                .unwrap_or_default()
+        };
+
+        let player = world.player_character.as_ref().map(|player| PlayerSave {
+            position: player.movement_rigid_body_handle.map(body_position).unwrap_or_default(),
+            stats: player.stats.clone(),
+            inventory: player.inventory.clone(),
+        });
+
+        let npcs = world
+            .npcs
+            .iter()
+            .map(|npc| NpcSave {
+                id: npc.id.clone(),
+                position: body_position(npc.rigid_body_handle),
+                stats: npc.stats.clone(),
+                alive: npc.is_alive(),
+            })
+            .collect();
+
+        // anything the level placed that's no longer in the world, plus whatever the player is carrying
+        let mut collected: Vec<String> = level_components
+            .iter()
+            .filter(|c| c.kind == Some(ComponentKind::Collectable))
+            .filter(|c| !world.collectables.iter().any(|placed| placed.id == c.id))
+            .map(|c| c.id.clone())
+            .collect();
+
+        // destroyed NPCs are gone from the world entirely, unlike killed ones
+        let destroyed = level_components
+            .iter()
+            .filter(|c| c.kind == Some(ComponentKind::NPC))
+            .filter(|c| !world.npcs.iter().any(|placed| placed.id == c.id))
+            .map(|c| c.id.clone())
+            .collect();
+
+        if let Some(player) = &world.player_character {
+            let inventory = &player.inventory;
+            let carried = inventory
+                .items
+                .iter()
+                .chain(inventory.equipped_weapon.iter())
+                .chain(inventory.equipped_armor.iter());
+
+            for item in carried {
+                if !collected.contains(&item.id) {
+                    collected.push(item.id.clone());
+                }
+            }
+        }
+
+        let mut script_states = HashMap::new();
+        if let Some(player) = &world.player_character {
+            if let Some(state) = &player.script_state {
+                script_states.insert(player.id.clone(), state.clone());
+            }
+        }
+
+        SaveGame {
+            format_version: SAVE_FORMAT_VERSION,
+            project_id: project_id.to_string(),
+            level_id: level_id.to_string(),
+            saved_at: chrono::Utc::now().to_rfc3339(),
+            player,
+            npcs,
+            collected,
+            destroyed,
+            script_states,
+            dialogue: DialogueState::default(),
+            quests: Vec::new(),
+            timers: Timers::default(),
+            blackboard: Blackboard::default(),
+        }
+    }
+
+    // Puts a freshly placed level back the way it was saved. Returns the model ids of collectables
+    // and NPCs that were removed, so the render side can drop them too.
+    pub fn restore(&self, world: &mut World) -> Vec<String> {
+        if let (Some(saved), Some(player)) = (&self.player, world.player_character.as_mut()) {
+            player.stats = saved.stats.clone();
+            player.inventory = saved.inventory.clone();
+            if let Some(state) = self.script_states.get(&player.id) {
+                player.script_state = Some(state.clone());
+            }
+
+            if let Some(rb) = player.movement_rigid_body_handle.and_then(|h| world.rigid_body_set.get_mut(h)) {
+                rb.set_translation(vector![saved.position[0], saved.position[1], saved.position[2]], true);
+                rb.set_linvel(vector![0.0, 0.0, 0.0], true);
+            }
+        }
+
+        let (destroyed, npcs): (Vec<_>, Vec<_>) = world.npcs.drain(..).partition(|n| self.destroyed.contains(&n.id));
+        world.npcs = npcs;
+
+        for npc in world.npcs.iter_mut() {
+            let saved = match self.npcs.iter().find(|n| n.id == npc.id) {
+                Some(saved) => saved,
+                None => continue,
+            };
+
+            npc.stats = saved.stats.clone();
+            if !saved.alive {
+                npc.stats.health = 0.0;
+            }
+
+            if let Some(rb) = world.rigid_body_set.get_mut(npc.rigid_body_handle) {
+                rb.set_translation(vector![saved.position[0], saved.position[1], saved.position[2]], true);
+                rb.set_linvel(vector![0.0, 0.0, 0.0], true);
+            }
+        }
+
+        let (removed, kept): (Vec<_>, Vec<_>) = world
+            .collectables
+            .drain(..)
+            .partition(|c| self.collected.contains(&c.id));
+        world.collectables = kept;
+
+        for collectable in &removed {
+            world.remove_body(collectable.rigid_body_handle);
+        }
+        for npc in &destroyed {
+            world.remove_body(npc.rigid_body_handle);
+        }
+        world.update_query_pipeline();
+
+        // an NPC's model is keyed by its component id
+        removed
+            .into_iter()
+            .map(|c| c.model_id)
+            .chain(destroyed.into_iter().map(|n| n.id))
+            .collect()
+    }
+}
+
+// Everything a running game needs saved, including the per-model script states kept render-side
+pub fn capture_game(
+    project_id: &str,
+    level_id: &str,
+    renderer_state: &RendererState,
+    level_components: &[ComponentData],
+    dialogue: &DialogueState,
+    quest_log: &QuestLog,
+) -> SaveGame {
+    let mut save = SaveGame::capture(project_id, level_id, &renderer_state.world, level_components);
+
+    for model in &renderer_state.models {
+        if let Some(state) = &model.script_state {
+            save.script_states.insert(model.id.clone(), state.clone());
+        }
+    }
+    save.script_states.extend(renderer_state.script_states.clone());
+    save.dialogue = dialogue.clone();
+    save.quests = quest_log.progress.clone();
+
+    save
+}
+
+// Saves live beside the projects rather than in them, so packaged games can save too.
+// Set ENTROPY_SAVES_DIR to keep them somewhere else.
+pub fn get_saves_dir(project_id: &str) -> PathBuf {
+    let root = match std::env::var("ENTROPY_SAVES_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => get_common_os_dir()
+            .expect("Couldn't get CommonOS directory")
+            .join("midpoint")
+            .join("saves"),
+    };
+
+    root.join(project_id)
+}
+
+// Slot names become file names, so keep them to something safe everywhere
+pub fn is_valid_slot(slot: &str) -> bool {
+    !slot.is_empty() && slot.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn slot_path(project_id: &str, slot: &str) -> Result<PathBuf, String> {
+    if !is_valid_slot(slot) {
+        return Err(format!("Invalid save slot {:?}, use letters, numbers, - and _", slot));
+    }
+
+    Ok(get_saves_dir(project_id).join(format!("{}.json", slot)))
+}
+
+pub fn write_save(slot: &str, save: &SaveGame) -> Result<PathBuf, String> {
+    let path = slot_path(&save.project_id, slot)?;
+    let dir = get_saves_dir(&save.project_id);
+    fs::create_dir_all(&dir).map_err(|e| format!("Couldn't create {:?}: {}", dir, e))?;
+
+    let json = serde_json::to_string_pretty(save).map_err(|e| format!("Couldn't serialize save: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Couldn't write {:?}: {}", path, e))?;
+
+    Ok(path)
+}
+
+pub fn read_save(project_id: &str, slot: &str) -> Result<SaveGame, String> {
+    let path = slot_path(project_id, slot)?;
+    let content = fs::read_to_string(&path).map_err(|e| format!("Couldn't read {:?}: {}", path, e))?;
+    let mut doc: serde_json::Value = serde_json::from_str(&content).map_err(|e| format!("Couldn't parse {:?}: {}", path, e))?;
+
+    let format_version = doc.get("format_version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    if format_version > SAVE_FORMAT_VERSION {
+        return Err(format!(
+            "{:?} is save format v{}, this engine reads up to v{}",
+            path, format_version, SAVE_FORMAT_VERSION
+        ));
+    }
+
+    if format_version < 2 {
+        if let Some(states) = doc.get_mut("script_states").and_then(|v| v.as_object_mut()) {
+            for state in states.values_mut() {
+                type_script_state(state);
+            }
+        }
+    }
+
+    let mut save: SaveGame = serde_json::from_value(doc).map_err(|e| format!("Couldn't parse {:?}: {}", path, e))?;
+    save.format_version = SAVE_FORMAT_VERSION;
+
+    Ok(save)
+}
+
+// Slot names with a save on disk, sorted
+pub fn list_saves(project_id: &str) -> Vec<String> {
+    let mut slots: Vec<String> = fs::read_dir(get_saves_dir(project_id))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|e| e == "json").unwrap_or(false))
+        .filter_map(|path| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .collect();
+
+    slots.sort();
+    slots
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use crate::{helpers::timelines::SavedTimelineStateConfig, vector_animations::animations::Sequence, water_plane::config::WaterConfig};
 
@@ -18,6 +19,53 @@ pub struct File {
     pub normalFilePath: String,
 }
 
+impl File {
+    // normalFilePath relative to the project root, with forward slashes.
+    // Older projects stored absolute Windows paths, those get cut down to the part after the project folder.
+    pub fn relative_path(&self, project_id: &str) -> Option<String> {
+        relative_asset_path(&self.normalFilePath, project_id)
+    }
+
+    // Where the file is on this machine, for a project stored at `project_root`
+    pub fn resolve(&self, project_root: &Path, project_id: &str) -> Option<PathBuf> {
+        let relative = self.relative_path(project_id)?;
+
+        Some(relative.split('/').fold(project_root.to_path_buf(), |path, segment| path.join(segment)))
+    }
+
+    // What the loaders read, relative to the project root or package. normalFilePath wins when it
+    // resolves, otherwise the file is fileName in the folder the editor imports it to.
+    pub fn load_path(&self, project_id: &str, default_folder: &str) -> String {
+        match self.relative_path(project_id) {
+            Some(relative) if !relative.is_empty() => relative,
+            _ => format!("{}/{}", default_folder, self.fileName),
+        }
+    }
+}
+
+pub fn relative_asset_path(path: &str, project_id: &str) -> Option<String> {
+    let normalized = path.replace('\\', "/");
+
+    let is_absolute = normalized.starts_with('/')
+        || (normalized.len() > 2 && normalized.as_bytes()[1] == b':' && normalized.as_bytes()[2] == b'/');
+
+    if !is_absolute {
+        return Some(normalized.trim_start_matches("./").to_string());
+    }
+
+    let project_segment = format!("/{}/", project_id);
+    if let Some(index) = normalized.find(&project_segment) {
+        return Some(normalized[index + project_segment.len()..].to_string());
+    }
+
+    // project folder was renamed, skip whatever folder sits under midpoint/projects
+    let projects_segment = "/midpoint/projects/";
+    let index = normalized.find(projects_segment)?;
+    let rest = &normalized[index + projects_segment.len()..];
+
+    rest.split_once('/').map(|(_, relative)| relative.to_string())
+}
+
 #[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
 pub struct LandscapeData {
     pub id: String,
@@ -34,7 +82,9 @@ pub enum ComponentKind {
     PointLight,
     WaterPlane,
     Collectable,
-    PlayerCharacter
+    PlayerCharacter,
+    Portal, // switches level when the player walks into it
+    TriggerVolume, // invisible sensor, fires on_trigger_enter/exit on scripts
 }
 
 #[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
@@ -90,9 +140,93 @@ pub struct CollectableProperties {
     pub stat_id: Option<String>, 
 }
 
-#[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
+// An NPC's archetype, applied when it's placed
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
 pub struct NPCProperties {
     pub model_id: String,
+    #[serde(default)]
+    pub dialogue_id: Option<String>, // a DialogueData id, talking to the NPC runs it instead of the script's interact
+    #[serde(default)]
+    pub behavior_tree_id: Option<String>, // a BehaviorTreeData id, runs instead of `behavior`
+    #[serde(default)]
+    pub name: Option<String>, // shown in dialogue, the component's name when not set
+    #[serde(default)]
+    pub behavior: NPCBehaviorKind,
+    #[serde(default = "default_chase_speed")]
+    pub chase_speed: f32,
+    #[serde(default = "default_detection_radius")]
+    pub detection_radius: f32,
+    #[serde(default = "default_evade_speed")]
+    pub evade_speed: f32,
+    #[serde(default = "default_wander_radius")]
+    pub wander_radius: f32,
+    #[serde(default = "default_wander_speed")]
+    pub wander_speed: f32,
+    #[serde(default)]
+    pub stat_id: Option<String>, // a StatData whose attack and defense the NPC fights with
+    #[serde(default = "default_npc_health")]
+    pub health: f32,
+    #[serde(default = "default_npc_stamina")]
+    pub stamina: f32,
+    #[serde(default)]
+    pub inventory: Vec<String>, // collectables it carries, by component, asset or prefab id. Weapons and armor get equipped
+}
+
+impl Default for NPCProperties {
+    fn default() -> Self {
+        NPCProperties {
+            model_id: String::new(),
+            dialogue_id: None,
+            behavior_tree_id: None,
+            name: None,
+            behavior: NPCBehaviorKind::default(),
+            chase_speed: default_chase_speed(),
+            detection_radius: default_detection_radius(),
+            evade_speed: default_evade_speed(),
+            wander_radius: default_wander_radius(),
+            wander_speed: default_wander_speed(),
+            stat_id: None,
+            health: default_npc_health(),
+            stamina: default_npc_stamina(),
+            inventory: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+pub enum NPCBehaviorKind {
+    #[default]
+    Wander,
+    Melee, // chases, attacks up close, evades and blocks
+    Ranged, // chases into range and shoots
+}
+
+fn default_chase_speed() -> f32 {
+    200.0
+}
+
+fn default_detection_radius() -> f32 {
+    50.0
+}
+
+fn default_evade_speed() -> f32 {
+    75.0
+}
+
+fn default_wander_radius() -> f32 {
+    50.0
+}
+
+fn default_wander_speed() -> f32 {
+    100.0
+}
+
+fn default_npc_health() -> f32 {
+    100.0
+}
+
+fn default_npc_stamina() -> f32 {
+    100.0
 }
 
 #[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
@@ -103,12 +237,92 @@ pub struct PlayerProperties {
     pub default_weapon_id: Option<String>, // Component id of the Collectable (Weapon type)
 }
 
+#[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
+pub struct PortalProperties {
+    pub target_level_id: String,
+    pub radius: f32,
+    // where the player lands in the target level, otherwise the PlayerCharacter component's position
+    pub spawn_position: Option<[f32; 3]>,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
+pub enum TriggerShape {
+    #[default]
+    Box,
+    Sphere,
+    Capsule, // upright, along y
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
+pub struct TriggerProperties {
+    pub shape: TriggerShape,
+    #[serde(default)]
+    pub half_extents: [f32; 3], // Box
+    #[serde(default)]
+    pub radius: f32, // Sphere and Capsule
+    #[serde(default)]
+    pub half_height: f32, // Capsule
+}
+
 #[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
 pub struct LightProperties {
     pub intensity: f32,
     pub color: [f32; 4],
 }
 
+// Caps on what one call into a script may do, unset fields use the engine's defaults
+#[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
+pub struct ScriptLimits {
+    #[serde(default)]
+    pub max_operations: Option<u64>,
+    #[serde(default)]
+    pub max_call_levels: Option<usize>,
+    #[serde(default)]
+    pub max_string_size: Option<usize>,
+    #[serde(default)]
+    pub max_array_size: Option<usize>,
+    #[serde(default)]
+    pub max_map_size: Option<usize>,
+}
+
+impl ScriptLimits {
+    // self, with anything unset taken from fallback
+    pub fn or(&self, fallback: &ScriptLimits) -> ScriptLimits {
+        ScriptLimits {
+            max_operations: self.max_operations.or(fallback.max_operations),
+            max_call_levels: self.max_call_levels.or(fallback.max_call_levels),
+            max_string_size: self.max_string_size.or(fallback.max_string_size),
+            max_array_size: self.max_array_size.or(fallback.max_array_size),
+            max_map_size: self.max_map_size.or(fallback.max_map_size),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
+pub struct ScriptSettings {
+    #[serde(default)]
+    pub limits: ScriptLimits, // for every script
+    #[serde(default)]
+    pub overrides: HashMap<String, ScriptLimits>, // by rhai_script_path, on top of limits
+    #[serde(default)]
+    pub frame_budget_ms: Option<f32>, // time all hooks in a frame may take, 0 for no budget
+    #[serde(default)]
+    pub allow_imports: bool, // lets scripts `import` other script files, only for trusted projects
+}
+
+impl ScriptSettings {
+    pub fn limits_for(&self, script_path: &str) -> ScriptLimits {
+        match self.overrides.get(script_path) {
+            Some(limits) => limits.or(&self.limits),
+            None => self.limits.clone(),
+        }
+    }
+}
+
+// What a component's Rhai script keeps between calls. Values are JSON so ints, floats, bools,
+// arrays and maps keep their types through midpoint.json and save games.
+pub type ScriptState = HashMap<String, serde_json::Value>;
+
 #[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
 pub struct ComponentData {
     pub id: String,
@@ -123,9 +337,42 @@ pub struct ComponentData {
     pub collectable_properties: Option<CollectableProperties>,
     pub player_properties: Option<PlayerProperties>,
     #[serde(default)]
+    pub portal_properties: Option<PortalProperties>,
+    #[serde(default)]
+    pub trigger_properties: Option<TriggerProperties>,
+    #[serde(default)]
     pub scatter: Option<ScatterSettings>,
     pub rhai_script_path: Option<String>,
-    pub script_state: Option<HashMap<String, String>>,
+    pub script_state: Option<ScriptState>,
+    #[serde(default)]
+    pub prefab: Option<PrefabInstance>, // set on instances, see helpers::prefabs
+    #[serde(default)]
+    pub parent_id: Option<String>, // when set, generic_properties are relative to this component
+}
+
+// Links a component to the prefab it was placed from
+#[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
+pub struct PrefabInstance {
+    pub prefab_id: String,
+    #[serde(default)]
+    pub component_id: Option<String>, // which member of a group prefab, defaults to the first
+}
+
+// A reusable component setup. Instances only store the fields they change.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
+pub struct PrefabData {
+    pub id: String,
+    pub name: String,
+    pub components: Vec<ComponentData>,
+}
+
+impl PrefabData {
+    pub fn member(&self, component_id: Option<&str>) -> Option<&ComponentData> {
+        match component_id {
+            Some(id) => self.components.iter().find(|c| c.id == id),
+            None => self.components.first(),
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
@@ -160,6 +407,8 @@ pub struct ProjectsDataFile {
 pub struct GameSettings {
     pub third_person: bool,
     pub show_hitscan_line: bool,
+    #[serde(default)]
+    pub show_navmesh: bool, // the editor's navmesh overlay
 }
 
 #[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
@@ -205,8 +454,205 @@ pub struct PBRTextureData {
     pub ao: Option<File>, // will be an .exr for now
 }
 
+// A quest runs through its stages in order, a stage is done once all of its objectives are
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
+pub struct QuestData {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub stages: Vec<QuestStage>,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
+pub struct QuestStage {
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub objectives: Vec<QuestObjective>, // a stage without any only advances from a script
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct QuestObjective {
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    pub kind: ObjectiveKind,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+pub enum ObjectiveKind {
+    // item_id matches a collectable's component id, model asset id or prefab id
+    Collect { item_id: String, count: u32 },
+    Kill { npc_id: String },
+    // any node of the NPC's dialogue when node isn't set
+    Talk { npc_id: String, node: Option<String> },
+    Reach { component_id: String, radius: f32 },
+}
+
+impl ObjectiveKind {
+    // How many matching events finish the objective
+    pub fn required(&self) -> u32 {
+        match self {
+            ObjectiveKind::Collect { count, .. } => (*count).max(1),
+            _ => 1,
+        }
+    }
+}
+
+// A conversation authored as data, run by game_behaviors::dialogue_graph. Lives in
+// SavedState::dialogues or in a JSON file listed in SavedState::dialogue_files.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
+pub struct DialogueData {
+    pub id: String,
+    #[serde(default)]
+    pub start_node: Option<String>, // falls back to the first node
+    pub nodes: Vec<DialogueNode>,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
+pub struct DialogueNode {
+    pub id: String,
+    #[serde(default)]
+    pub speaker: Option<String>, // the NPC's name when not set
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub actions: Vec<DialogueAction>, // run when the node is shown
+    #[serde(default)]
+    pub options: Vec<DialogueChoice>, // none shown means the next confirm closes the dialogue
+    // a custom node: calls this function of the NPC's Rhai script with the dialogue instead,
+    // which shows its own text and options
+    #[serde(default)]
+    pub script_hook: Option<String>,
+    #[serde(default)]
+    pub script_exits: Vec<String>, // nodes the script's options can lead to, for the validator
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
+pub struct DialogueChoice {
+    pub text: String,
+    #[serde(default)]
+    pub next_node: Option<String>, // closes the dialogue when not set
+    #[serde(default)]
+    pub conditions: Vec<DialogueCondition>, // all have to hold for the option to show
+    #[serde(default)]
+    pub actions: Vec<DialogueAction>, // run when it's chosen
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+pub enum DialogueCondition {
+    // variables are the NPC's own unless `global` is set, see game_behaviors::blackboard
+    VarEquals { name: String, value: serde_json::Value, #[serde(default)] global: bool },
+    VarNotEquals { name: String, value: serde_json::Value, #[serde(default)] global: bool },
+    VarAtLeast { name: String, value: f64, #[serde(default)] global: bool },
+    // whether an earlier conversation with this NPC was at the node
+    Visited { node: String },
+    NotVisited { node: String },
+    QuestNotStarted { quest_id: String },
+    QuestActive { quest_id: String },
+    QuestComplete { quest_id: String },
+    // item_id matches like a Collect objective: component id, model asset id or prefab id
+    HasItem { item_id: String },
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+pub enum DialogueAction {
+    SetVar { name: String, value: serde_json::Value, #[serde(default)] global: bool },
+    AddVar { name: String, amount: f64, #[serde(default)] global: bool },
+    StartQuest { quest_id: String },
+    AdvanceQuest { quest_id: String },
+    GiveItem { item_id: String }, // a collectable of the current level
+}
+
+// An NPC archetype's AI, run by game_behaviors::behavior_tree. NPCs pick one with npc_properties.behavior_tree_id
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct BehaviorTreeData {
+    pub id: String,
+    pub root: BehaviorNodeData,
+    #[serde(default)]
+    pub blackboard: ScriptState, // starting values, the tree and its script leaves read and write them
+}
+
+// Composites and decorators hold other nodes, everything else is a leaf. Times are in seconds of game time.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+pub enum BehaviorNodeData {
+    // runs children in order until one fails, `reactive` checks the ones before the running child again each tick
+    Sequence { children: Vec<BehaviorNodeData>, #[serde(default)] reactive: bool },
+    // runs children in order until one succeeds, `reactive` lets an earlier child take over from the running one
+    Selector { children: Vec<BehaviorNodeData>, #[serde(default)] reactive: bool },
+    // ticks every child each tick
+    Parallel { children: Vec<BehaviorNodeData>, #[serde(default)] policy: ParallelPolicy },
+    Inverter { child: Box<BehaviorNodeData> },
+    Succeeder { child: Box<BehaviorNodeData> }, // succeeds once the child finishes either way
+    Repeat { child: Box<BehaviorNodeData>, #[serde(default)] times: Option<u32> }, // until the child fails, or `times` successes
+    Cooldown { child: Box<BehaviorNodeData>, seconds: f32 }, // fails for a while after the child finishes
+    TimeLimit { child: Box<BehaviorNodeData>, seconds: f32 }, // fails the child when it runs too long
+
+    // conditions, they succeed or fail straight away
+    TargetWithin { distance: f32 },
+    HealthBelow { value: f32 },
+    StaminaBelow { value: f32 },
+    RecentlyHit { seconds: f32 },
+    Check { key: String, value: serde_json::Value }, // a blackboard value, missing ones are null
+
+    // tasks built on the game behaviors
+    Chase { speed: f32, detection_radius: f32, #[serde(default = "default_stop_distance")] stop_distance: f32 }, // fails once the target is out of reach
+    Wander { radius: f32, speed: f32 }, // runs until interrupted
+    Evade { speed: f32, distance: f32, #[serde(default = "default_task_seconds")] seconds: f32 },
+    Attack { stats: AttackStats, #[serde(default)] ranged: bool }, // fails when the target is out of range
+    Defend { block_chance: f32, #[serde(default = "default_task_seconds")] seconds: f32 }, // blocks hits while it runs
+    Wait { seconds: f32 },
+    Set { key: String, value: serde_json::Value },
+    // calls function(npc, system, script_state, task) in `script`, or the NPC's own script.
+    // task.succeed() / task.fail() finish the leaf, otherwise it keeps running.
+    Script { function: String, #[serde(default)] script: Option<String> },
+}
+
+impl BehaviorNodeData {
+    // Every Script leaf under this node as (function, script), script is None for the NPC's own
+    pub fn script_leaves<'a>(&'a self, leaves: &mut Vec<(&'a str, Option<&'a str>)>) {
+        match self {
+            BehaviorNodeData::Sequence { children, .. } | BehaviorNodeData::Selector { children, .. } | BehaviorNodeData::Parallel { children, .. } => {
+                for child in children {
+                    child.script_leaves(leaves);
+                }
+            }
+            BehaviorNodeData::Inverter { child }
+            | BehaviorNodeData::Succeeder { child }
+            | BehaviorNodeData::Repeat { child, .. }
+            | BehaviorNodeData::Cooldown { child, .. }
+            | BehaviorNodeData::TimeLimit { child, .. } => child.script_leaves(leaves),
+            BehaviorNodeData::Script { function, script } => leaves.push((function, script.as_deref())),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+pub enum ParallelPolicy {
+    #[default]
+    RequireOne, // succeeds when any child does, fails when all have
+    RequireAll, // succeeds when all children have, fails when any does
+}
+
+fn default_stop_distance() -> f32 {
+    1.0
+}
+
+fn default_task_seconds() -> f32 {
+    0.5
+}
+
 #[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
 pub struct SavedState {
+    #[serde(default)]
+    pub schema_version: u32, // see helpers::migrations
     pub id: Option<String>,
     pub project_name: String,
     // games
@@ -217,8 +663,138 @@ pub struct SavedState {
     pub pbr_textures: Option<Vec<PBRTextureData>>, // counts as Assets
     pub stats: Option<Vec<StatData>>, // Stats can be used to record a value or change tied to whatever references it
     pub levels: Option<Vec<LevelData>>, // contains Components, which are active instances of library Assets
+    #[serde(default)]
+    pub prefabs: Option<Vec<PrefabData>>,
+    #[serde(default)]
+    pub start_level_id: Option<String>, // falls back to the first level
+    #[serde(default)]
+    pub quests: Option<Vec<QuestData>>, // tracked at runtime by game_behaviors::quests
+    #[serde(default)]
+    pub dialogues: Option<Vec<DialogueData>>,
+    #[serde(default)]
+    pub dialogue_files: Option<Vec<String>>, // project-relative JSON files, one DialogueData each
+    #[serde(default)]
+    pub behavior_trees: Option<Vec<BehaviorTreeData>>, // one per NPC archetype
     // videos
     pub sequences: Option<Vec<Sequence>>,
     pub timeline_state: Option<SavedTimelineStateConfig>,
     pub global_rhai_scripts: Option<Vec<String>>,
+    #[serde(default)]
+    pub script_settings: Option<ScriptSettings>, // limits and sandbox for every Rhai script, see rhai_engine
+}
+
+impl SavedState {
+    pub fn start_level_id(&self) -> Option<String> {
+        let levels = self.levels.as_ref()?;
+
+        if let Some(start_id) = &self.start_level_id {
+            if levels.iter().any(|l| &l.id == start_id) {
+                return Some(start_id.clone());
+            }
+        }
+
+        levels.first().map(|l| l.id.clone())
+    }
+
+    // Looks up a level by id, or the start level when no id is given
+    pub fn level(&self, level_id: Option<&str>) -> Option<&LevelData> {
+        let level_id = match level_id {
+            Some(id) => id.to_string(),
+            None => self.start_level_id()?,
+        };
+
+        self.levels.as_ref()?.iter().find(|l| l.id == level_id)
+    }
+
+    pub fn level_mut(&mut self, level_id: Option<&str>) -> Option<&mut LevelData> {
+        let level_id = match level_id {
+            Some(id) => id.to_string(),
+            None => self.start_level_id()?,
+        };
+
+        self.levels.as_mut()?.iter_mut().find(|l| l.id == level_id)
+    }
+
+    pub fn level_components(&self, level_id: Option<&str>) -> Option<&Vec<ComponentData>> {
+        self.level(level_id).and_then(|l| l.components.as_ref())
+    }
+
+    // Every asset File in the project
+    pub fn files_mut(&mut self) -> Vec<&mut File> {
+        let mut files: Vec<&mut File> = Vec::new();
+
+        files.extend(self.concepts.iter_mut());
+        files.extend(self.models.iter_mut());
+        files.extend(self.textures.iter_mut().flatten());
+
+        for landscape in self.landscapes.iter_mut().flatten() {
+            files.extend(landscape.heightmap.iter_mut());
+            files.extend(landscape.rockmap.iter_mut());
+            files.extend(landscape.soil.iter_mut());
+        }
+
+        for pbr in self.pbr_textures.iter_mut().flatten() {
+            files.extend(pbr.diff.iter_mut());
+            files.extend(pbr.disp.iter_mut());
+            files.extend(pbr.nor_gl.iter_mut());
+            files.extend(pbr.rough.iter_mut());
+            files.extend(pbr.metallic.iter_mut());
+            files.extend(pbr.ao.iter_mut());
+        }
+
+        files
+    }
+
+    // Rewrites absolute normalFilePaths to project-relative ones, returning (old, new) for each change
+    pub fn relativize_paths(&mut self, project_id: &str) -> Vec<(String, String)> {
+        let mut changes = Vec::new();
+
+        for file in self.files_mut() {
+            if let Some(relative) = file.relative_path(project_id) {
+                if relative != file.normalFilePath {
+                    changes.push((file.normalFilePath.clone(), relative.clone()));
+                    file.normalFilePath = relative;
+                }
+            }
+        }
+
+        changes
+    }
+
+    pub fn quest(&self, quest_id: &str) -> Option<&QuestData> {
+        self.quests.as_ref()?.iter().find(|q| q.id == quest_id)
+    }
+
+    pub fn behavior_tree(&self, tree_id: &str) -> Option<&BehaviorTreeData> {
+        self.behavior_trees.as_ref()?.iter().find(|t| t.id == tree_id)
+    }
+
+    // The tree an NPC component's archetype runs
+    pub fn npc_behavior_tree(&self, component: &ComponentData) -> Option<&BehaviorTreeData> {
+        let tree_id = component.npc_properties.as_ref()?.behavior_tree_id.as_ref()?;
+        self.behavior_tree(tree_id)
+    }
+
+    pub fn stat(&self, stat_id: &str) -> Option<&StatData> {
+        self.stats.as_ref()?.iter().find(|s| s.id == stat_id)
+    }
+
+    pub fn prefab(&self, prefab_id: &str) -> Option<&PrefabData> {
+        self.prefabs.as_ref()?.iter().find(|p| p.id == prefab_id)
+    }
+
+    // The prefab component an instance resolves against
+    pub fn prefab_member(&self, instance: &PrefabInstance) -> Option<&ComponentData> {
+        self.prefab(&instance.prefab_id)?.member(instance.component_id.as_deref())
+    }
+
+    // Component ids are unique across the project, so this searches every level
+    pub fn find_component_mut(&mut self, component_id: &str) -> Option<&mut ComponentData> {
+        self.levels
+            .as_mut()?
+            .iter_mut()
+            .filter_map(|l| l.components.as_mut())
+            .flat_map(|components| components.iter_mut())
+            .find(|c| c.id == component_id)
+    }
 }
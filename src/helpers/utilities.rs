@@ -1,4 +1,4 @@
-use std::{fs, path::PathBuf};
+use std::{fs, path::{Path, PathBuf}};
 
 use directories::{BaseDirs, UserDirs};
 use nalgebra::Matrix4;
@@ -6,6 +6,10 @@ use uuid::Uuid;
 
 use crate::helpers::saved_data::{ComponentData, ProceduralSkyConfig};
 
+use super::migrations::{self, MigrationReport};
+use super::pak;
+use super::prefabs;
+use super::project_error::ProjectError;
 use super::saved_data::{LevelData, SavedState};
 #[cfg(target_arch = "wasm32")]
 use super::wasm_loaders;
@@ -23,9 +27,23 @@ pub fn get_common_os_dir() -> Option<PathBuf> {
     })
 }
 
-pub fn get_projects_dir() -> Option<PathBuf> {
+// Where projects live. Set ENTROPY_PROJECTS_DIR to keep them somewhere other than Documents/CommonOS.
+pub fn get_projects_root() -> PathBuf {
+    if let Ok(dir) = std::env::var("ENTROPY_PROJECTS_DIR") {
+        return PathBuf::from(dir);
+    }
+
     let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
-    let projects_dir = sync_dir.join("midpoint/projects");
+    sync_dir.join("midpoint").join("projects")
+}
+
+// All project files are stored relative to this folder
+pub fn get_project_root(project_id: &str) -> PathBuf {
+    get_projects_root().join(project_id)
+}
+
+pub fn get_projects_dir() -> Option<PathBuf> {
+    let projects_dir = get_projects_root();
 
     fs::create_dir_all(&projects_dir)
         .ok()
@@ -35,8 +53,7 @@ pub fn get_projects_dir() -> Option<PathBuf> {
 }
 
 pub fn get_project_dir(project_id: &str) -> Option<PathBuf> {
-    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
-    let project_dir = sync_dir.join("midpoint/projects").join(project_id);
+    let project_dir = get_project_root(project_id);
 
     fs::create_dir_all(&project_dir)
         .ok()
@@ -125,62 +142,201 @@ pub fn get_models_dir(project_id: &str) -> Option<PathBuf> {
 // }
 
 #[cfg(not(target_arch = "wasm32"))]
-pub async fn load_project_state(project_id: &str) -> Result<SavedState, Box<dyn std::error::Error + Send>> {
-    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
-    let project_dir = sync_dir.join("midpoint/projects").join(project_id);
+pub async fn load_project_state(project_id: &str) -> Result<SavedState, ProjectError> {
+    // packaged projects are already migrated and relative, so there's nothing to write back
+    if let Some(archive) = pak::mounted_pak(project_id) {
+        let path_label = format!("{}:{}", archive.path.to_string_lossy(), pak::PAK_PROJECT_FILE);
+        let bytes = archive
+            .read(pak::PAK_PROJECT_FILE)
+            .map_err(|reason| ProjectError::MissingFile { path: path_label.clone(), reason })?;
+        let (state, _) = parse_project_state(&String::from_utf8_lossy(&bytes), &path_label)?;
+        return Ok(state);
+    }
+
+    let project_dir = get_project_root(project_id);
     let json_path = project_dir.join("midpoint.json");
-
-    // Check if the project directory and json file exist
-    // if !project_dir.exists() {
-    //     return Err(format!("Project directory '{}' not found", project_id).into());
-    // }
-    // if !json_path.exists() {
-    //     return Err(format!("midpoint.json not found in project '{}'", project_id).into());
-    // }
+    let path_label = json_path.to_string_lossy().to_string();
 
     // Read and parse the JSON file - now using tokio::fs
-    let json_content = tokio::fs::read_to_string(json_path).await;
-    let json_content = json_content.as_ref().expect("Couldn't get json");
-    let state: SavedState = serde_json::from_str(&json_content).expect("Couldn't get state");
+    let json_content = tokio::fs::read_to_string(&json_path)
+        .await
+        .map_err(|e| ProjectError::MissingFile { path: path_label.clone(), reason: e.to_string() })?;
+    let (mut state, report) = parse_project_state(&json_content, &path_label)?;
+
+    // older projects stored absolute paths, resolve them against wherever the project is now
+    let relocated = state.relativize_paths(project_id);
+    if !relocated.is_empty() && !report.migrated() {
+        println!("Resolved {} absolute asset paths relative to the project, run the relocate command to save them", relocated.len());
+    } else if !relocated.is_empty() {
+        println!("Resolved {} absolute asset paths relative to the project, saving them with the migration", relocated.len());
+    }
+
+    if report.migrated() {
+        report.print();
+
+        // keep the original around in case the migration got something wrong
+        let backup_path = project_dir.join(format!("midpoint.v{}.backup.json", report.from_version));
+        let backed_up = backup_path.exists() || fs::write(&backup_path, &json_content).is_ok();
+
+        if backed_up {
+            println!("Backed up original project to {:?}", backup_path);
+
+            if let Err(e) = update_project_state(project_id, &state) {
+                println!("Couldn't save migrated project: {}", e);
+            }
+        } else {
+            println!("Couldn't back up original project, leaving it unmigrated on disk");
+        }
+    }
 
     Ok(state)
 }
 
+// Accepts a project id, a project directory, or a path to a midpoint.json
+pub fn find_project_json(target: &str) -> PathBuf {
+    let target_path = Path::new(target);
+
+    if target_path.is_file() {
+        target_path.to_path_buf()
+    } else if target_path.join("midpoint.json").is_file() {
+        target_path.join("midpoint.json")
+    } else {
+        get_project_root(target).join("midpoint.json")
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RelocateReport {
+    pub json_path: PathBuf,
+    pub rewritten: Vec<(String, String)>,
+    pub unresolved: Vec<String>, // absolute paths that don't point into a project folder
+    pub missing: Vec<String>, // relative paths with nothing on disk
+}
+
+// One-shot rewrite of absolute normalFilePaths to project-relative ones.
+// The original file is backed up next to midpoint.json unless this is a dry run.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn relocate_project(target: &str, dry_run: bool) -> Result<RelocateReport, ProjectError> {
+    let json_path = find_project_json(target);
+    let path_label = json_path.to_string_lossy().to_string();
+
+    let json_content = fs::read_to_string(&json_path)
+        .map_err(|e| ProjectError::MissingFile { path: path_label.clone(), reason: e.to_string() })?;
+    let (mut state, _) = parse_project_state(&json_content, &path_label)?;
+
+    let project_root = json_path.parent().map(Path::to_path_buf).unwrap_or_default();
+    // the folder name wins over the stored id, in case the project was copied under a new name
+    let project_id = project_root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .or_else(|| state.id.clone())
+        .unwrap_or_default();
+
+    let mut report = RelocateReport {
+        json_path: json_path.clone(),
+        rewritten: state.relativize_paths(&project_id),
+        ..Default::default()
+    };
+
+    // a project copied from elsewhere may still carry its old id in the paths
+    if let Some(stored_id) = state.id.clone() {
+        if stored_id != project_id {
+            report.rewritten.extend(state.relativize_paths(&stored_id));
+        }
+    }
+
+    for file in state.files_mut() {
+        if file.normalFilePath.is_empty() {
+            continue;
+        }
+
+        match file.resolve(&project_root, &project_id) {
+            Some(path) if !path.exists() => report.missing.push(file.normalFilePath.clone()),
+            Some(_) => {}
+            None => report.unresolved.push(file.normalFilePath.clone()),
+        }
+    }
+
+    if !dry_run && !report.rewritten.is_empty() {
+        let backup_path = json_path.with_file_name("midpoint.absolute-paths.backup.json");
+        if !backup_path.exists() {
+            fs::write(&backup_path, &json_content)
+                .map_err(|e| ProjectError::MissingFile { path: backup_path.to_string_lossy().to_string(), reason: e.to_string() })?;
+        }
+
+        let json = serialize_project_state(&state).map_err(|e| ProjectError::parse(&path_label, &e))?;
+        fs::write(&json_path, json)
+            .map_err(|e| ProjectError::MissingFile { path: path_label.clone(), reason: e.to_string() })?;
+    }
+
+    Ok(report)
+}
+
+// Parses a midpoint.json document, upgrading older schemas in memory
+pub fn parse_project_state(json_content: &str, path_label: &str) -> Result<(SavedState, MigrationReport), ProjectError> {
+    let mut doc: serde_json::Value = serde_json::from_str(json_content).map_err(|e| ProjectError::parse(path_label, &e))?;
+
+    let report = migrations::migrate(&mut doc).map_err(ProjectError::Migration)?;
+    prefabs::resolve_instances(&mut doc);
+    let state: SavedState = serde_json::from_value(doc).map_err(|e| {
+        // Value errors carry no position. When the file's own text fails the same way, that error has one.
+        match serde_json::from_str::<SavedState>(json_content) {
+            Err(located) if located.line() > 0 && located.to_string().starts_with(&e.to_string()) => {
+                ProjectError::parse(path_label, &located)
+            }
+            _ => ProjectError::parse(path_label, &e),
+        }
+    })?;
+
+    Ok((state, report))
+}
+
+// Serializes a project for midpoint.json, writing prefab instances back out as overrides only
+pub fn serialize_project_state(state: &SavedState) -> Result<String, serde_json::Error> {
+    let mut doc = serde_json::to_value(state)?;
+    prefabs::collapse_instances(&mut doc);
+    serde_json::to_string_pretty(&doc)
+}
+
 #[cfg(target_arch = "wasm32")]
-pub async fn load_project_state(project_id: &str) -> Result<SavedState, Box<dyn std::error::Error>> {
+pub async fn load_project_state(project_id: &str) -> Result<SavedState, ProjectError> {
     wasm_loaders::load_project_state_wasm(project_id).await
 }
 
 
 pub fn update_project_state_component(project_id: &str, component: &ComponentData) -> Result<(), Box<dyn std::error::Error>> {
+    if pak::mounted_pak(project_id).is_some() {
+        return Err(format!("Project {} is running from a package and can't be saved", project_id).into());
+    }
+
     let project_dir = get_project_dir(project_id).expect("Couldn't get project directory");
     let json_path = project_dir.join("midpoint.json");
 
-    let mut existing_state = pollster::block_on(load_project_state(project_id)).expect("Couldn't load saved state!");
+    let mut existing_state = pollster::block_on(load_project_state(project_id))?;
 
-    let level = existing_state.levels.as_mut().expect("Couldn't get levels");
-
-    level[0].components.as_mut().expect("Couldn't get components").iter_mut().for_each(|c| {
-        if c.id == component.id {
-            *c = component.clone();
-        }
-    });
+    if let Some(existing) = existing_state.find_component_mut(&component.id) {
+        *existing = component.clone();
+    }
 
     println!("update_project_state_component");
 
-    let json = serde_json::to_string_pretty(&existing_state)?;
+    let json = serialize_project_state(&existing_state)?;
     fs::write(json_path, json)?;
 
     Ok(())
 }
 
 pub fn update_project_state(project_id: &str, saved_state: &SavedState) -> Result<(), Box<dyn std::error::Error>> {
+    if pak::mounted_pak(project_id).is_some() {
+        return Err(format!("Project {} is running from a package and can't be saved", project_id).into());
+    }
+
     let project_dir = get_project_dir(project_id).expect("Couldn't get project directory");
     let json_path = project_dir.join("midpoint.json");
 
     println!("update_project_state");
 
-    let json = serde_json::to_string_pretty(saved_state)?;
+    let json = serialize_project_state(saved_state)?;
     fs::write(json_path, json)?;
 
     Ok(())
@@ -202,6 +358,7 @@ pub fn create_project_state(project_id: &str) -> Result<SavedState, Box<dyn std:
         project_name: project_id.to_string(), // Initialize the new project_name field
         id: Some(project_id.to_string()),
         levels: Some(levels),
+        schema_version: migrations::CURRENT_SCHEMA_VERSION,
         // concepts: Vec::new(),
         // models: Vec::new(),
         // landscapes: Some(Vec::new()),
@@ -1,6 +1,7 @@
 #[cfg(target_arch = "wasm32")]
 use crate::helpers::saved_data::LandscapeTextureKinds;
 use crate::helpers::saved_data::SavedState;
+use crate::helpers::project_error::ProjectError;
 use crate::helpers::landscapes::{TextureData, LandscapePixelData, PixelData}; // Import TextureData, LandscapePixelData and PixelData
 use reqwest;
 use image::{self, GenericImageView};
@@ -12,10 +13,15 @@ use exr::prelude::ReadLayers;
 use exr::image::read as exr_read;
 
 #[cfg(target_arch = "wasm32")]
-pub async fn load_project_state_wasm(project_id: &str) -> Result<SavedState, Box<dyn std::error::Error>> {
+pub async fn load_project_state_wasm(project_id: &str) -> Result<SavedState, ProjectError> {
     let url = format!("http://asset.localhost/midpoint/projects/{}/midpoint.json", project_id);
-    let json_content = reqwest::get(&url).await?.text().await?;
-    let state: SavedState = serde_json::from_str(&json_content)?;
+    let missing = |e: reqwest::Error| ProjectError::MissingFile { path: url.clone(), reason: e.to_string() };
+    let json_content = reqwest::get(&url).await.map_err(missing)?.text().await.map_err(missing)?;
+    // no backup on the web, the migrated state lives in memory only
+    let (state, report) = crate::helpers::utilities::parse_project_state(&json_content, &url)?;
+    if report.migrated() {
+        report.print();
+    }
     Ok(state)
 }
 
@@ -133,19 +139,19 @@ pub fn read_tiff_heightmap_wasm(
 pub async fn get_landscape_pixels_wasm(
     project_id: String,
     landscape_asset_id: String,
-    landscape_filename: String,
-) -> LandscapePixelData {
+    heightmap_path: String,
+) -> Result<LandscapePixelData, String> {
     let url = format!(
-        "http://asset.localhost/midpoint/projects/{}/landscapes/{}/heightmaps/{}",
-        project_id, landscape_asset_id, landscape_filename
+        "http://asset.localhost/midpoint/projects/{}/{}",
+        project_id, heightmap_path
     );
 
     let tiff_bytes = reqwest::get(&url)
         .await
-        .expect("Failed to fetch tiff file")
+        .map_err(|e| format!("Failed to fetch tiff file: {}", e))?
         .bytes()
         .await
-        .expect("Failed to get tiff bytes")
+        .map_err(|e| format!("Failed to get tiff bytes: {}", e))?
         .to_vec();
 
     let square_size = 1024.0 * 4.0;
@@ -157,25 +163,25 @@ pub async fn get_landscape_pixels_wasm(
         square_height,
     );
 
-    LandscapePixelData {
+    Ok(LandscapePixelData {
         width,
         height,
         pixel_data,
         rapier_heights,
         raw_heights,
         max_height,
-    }
+    })
 }
 
 #[cfg(target_arch = "wasm32")]
 pub async fn read_landscape_heightmap_as_texture_wasm(
     project_id: String,
     landscape_id: String,
-    texture_filename: String,
+    heightmap_path: String,
 ) -> Result<TextureData, String> {
     let url = format!(
-        "http://asset.localhost/midpoint/projects/{}/landscapes/{}/heightmaps/{}",
-        project_id, landscape_id, texture_filename
+        "http://asset.localhost/midpoint/projects/{}/{}",
+        project_id, heightmap_path
     );
 
     let response_bytes = reqwest::get(&url)
@@ -245,19 +251,11 @@ pub async fn read_landscape_texture_wasm(
 pub async fn read_landscape_mask_wasm(
     project_id: String,
     landscape_id: String,
-    mask_filename: String,
-    mask_kind: LandscapeTextureKinds,
+    mask_path: String,
 ) -> Result<TextureData, String> {
-    let kind_slug = match mask_kind {
-        LandscapeTextureKinds::Primary => "heightmaps",
-        LandscapeTextureKinds::Rockmap => "rockmaps",
-        LandscapeTextureKinds::Soil => "soils",
-        _ => "", // Handle other cases if necessary
-    };
-
     let url = format!(
-        "http://asset.localhost/midpoint/projects/{}/landscapes/{}/{}/{}",
-        project_id, landscape_id, kind_slug, mask_filename
+        "http://asset.localhost/midpoint/projects/{}/{}",
+        project_id, mask_path
     );
 
     load_image_from_url(&url)
@@ -271,9 +269,9 @@ pub async fn read_texture_bytes_wasm(
     _asset_id: String, // This could be landscapeId or pbr_texture_id
     file_name: String,
 ) -> Result<(Vec<u8>, u32, u32), String> {
-    // Determine the base directory based on asset_id type
+    // file_name is relative to the project root, see File::load_path
     let url = format!(
-        "http://asset.localhost/midpoint/projects/{}/textures/{}",
+        "http://asset.localhost/midpoint/projects/{}/{}",
         project_id, file_name
     );
 
@@ -386,11 +384,11 @@ pub async fn read_texture_bytes_wasm(
 
 pub async fn read_model_wasm(
     projectId: String,
-    modelFilename: String,
+    modelPath: String,
 ) -> Result<Vec<u8>, String> {
     let url = format!(
-        "http://asset.localhost/midpoint/projects/{}/models/{}",
-        projectId, modelFilename
+        "http://asset.localhost/midpoint/projects/{}/{}",
+        projectId, modelPath
     );
 
     println!("Attempting to read texture from URL: {:?}", url);
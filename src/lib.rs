@@ -1,6 +1,6 @@
 #![allow(warnings)]
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 pub mod startup;
 
 pub mod core;
@@ -25,6 +25,12 @@ pub mod procedural_particles;
 pub mod model_components;
 pub mod procedural_heightmaps;
 pub mod rhai_engine;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod validate;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod script_tests;
 
 // I noticed that `pipeline.rs` has some dependencies that are not in the file system.
 // I'm adding them here so the compiler can find them.
@@ -1,6 +1,6 @@
 use std::sync::MutexGuard;
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
@@ -15,23 +15,82 @@ use rapier3d::{
 use uuid::Uuid;
 use rapier3d::prelude::{QueryPipeline, Shape};
 
-use crate::helpers::saved_data::{AttackStats, CharacterStats};
+use crate::helpers::saved_data::{
+    AttackStats, BehaviorTreeData, CharacterStats, CollectableType, ComponentData, ComponentKind, NPCBehaviorKind, NPCProperties, SavedState,
+};
 use crate::{
     game_behaviors::{
         melee::{MeleeCombatBehavior},
         ranged::{RangedCombatBehavior},
         wander::WanderBehavior,
         inventory::Inventory,
+        navmesh::NavMesh,
+        behavior_tree::{BehaviorTree, TickContext},
+        quests::matches_item,
     },
     art_assets::Model::Model,
     core::AnimationState::AnimationState,
 };
-use crate::core::Transform_2::Transform;
+
+// Fighters whose stat has no attack or defense use these
+pub const DEFAULT_ATTACK: AttackStats = AttackStats {
+    damage: 15.0,
+    range: 3.0,
+    cooldown: 0.2,
+    wind_up_time: 0.1,
+    recovery_time: 0.1,
+};
+pub const DEFAULT_BLOCK_CHANCE: f32 = 0.7;
+
+// An NPC component's properties with what they refer to looked up in the project. It's owned, so
+// it can be resolved before the level's placement borrows the renderer.
+#[derive(Clone, Debug)]
+pub struct NPCArchetype {
+    pub name: String,
+    pub properties: NPCProperties,
+    pub attack: AttackStats,
+    pub block_chance: f32,
+    pub inventory: Vec<ComponentData>, // the loadout's collectables from the level
+    pub behavior_tree: Option<BehaviorTreeData>,
+}
+
+impl NPCArchetype {
+    pub fn new(saved_state: &SavedState, component: &ComponentData, level_components: &[ComponentData]) -> Self {
+        let properties = component.npc_properties.clone().unwrap_or_default();
+        let stat = properties.stat_id.as_ref().and_then(|stat_id| saved_state.stat(stat_id));
+
+        let mut inventory = Vec::new();
+        for item_id in &properties.inventory {
+            match loadout_item(level_components, item_id) {
+                Some(item) => inventory.push(item.clone()),
+                None => println!("NPC {} carries {}, which no collectable in the level matches", component.id, item_id),
+            }
+        }
+
+        NPCArchetype {
+            name: properties.name.clone().unwrap_or_else(|| component.generic_properties.name.clone()),
+            attack: stat.and_then(|s| s.attack.clone()).unwrap_or(DEFAULT_ATTACK),
+            block_chance: stat.and_then(|s| s.defense.as_ref()).map(|d| d.block_chance).unwrap_or(DEFAULT_BLOCK_CHANCE),
+            inventory,
+            behavior_tree: saved_state.npc_behavior_tree(component).cloned(),
+            properties,
+        }
+    }
+}
+
+// The collectable a loadout entry resolves to: the first in the level it matches by id, asset or prefab.
+// The level leaves that one out of the world, the NPC starts with it.
+pub fn loadout_item<'a>(level_components: &'a [ComponentData], item_id: &str) -> Option<&'a ComponentData> {
+    level_components
+        .iter()
+        .find(|c| c.kind == Some(ComponentKind::Collectable) && matches_item(c, item_id))
+}
 
 pub enum NPCBehavior {
     Melee(MeleeCombatBehavior),
     Ranged(RangedCombatBehavior),
-    Wander(WanderBehavior)
+    Wander(WanderBehavior),
+    Tree(BehaviorTree),
 }
 
 impl NPCBehavior {
@@ -40,11 +99,12 @@ impl NPCBehavior {
         rigid_body_set: &mut RigidBodySet,
         collider_set: &ColliderSet,
         query_pipeline: &QueryPipeline,
+        navmesh: &mut NavMesh,
         entity_handle: RigidBodyHandle,
         target_handle: RigidBodyHandle,
         collider: &Collider,
-        transform: &mut Transform,
-        current_stamina: f32,
+        current_pos: Vector3<f32>,
+        stats: &CharacterStats,
         dt: f32,
     ) -> Option<(f32, Option<(Point3<f32>, Point3<f32>)>)> {
         match self {
@@ -52,28 +112,42 @@ impl NPCBehavior {
                 rigid_body_set,
                 collider_set,
                 query_pipeline,
+                navmesh,
                 entity_handle,
                 target_handle,
                 collider,
-                transform,
-                current_stamina,
+                current_pos,
+                stats.stamina,
                 dt,
             ).map(|damage| (damage, None)),
             NPCBehavior::Ranged(behavior) => behavior.update(
                 rigid_body_set,
                 collider_set,
                 query_pipeline,
+                navmesh,
                 entity_handle,
                 target_handle,
                 collider,
-                transform,
-                current_stamina,
+                current_pos,
+                stats.stamina,
                 dt,
             ),
             NPCBehavior::Wander(behavior) => {
-                behavior.update(rigid_body_set, collider_set, query_pipeline, entity_handle, collider, transform, dt);
+                behavior.update(rigid_body_set, collider_set, query_pipeline, navmesh, entity_handle, collider, current_pos, dt);
                 None
             },
+            NPCBehavior::Tree(tree) => tree.update(TickContext {
+                rigid_body_set,
+                collider_set,
+                query_pipeline,
+                navmesh,
+                entity_handle,
+                target_handle,
+                collider,
+                current_pos,
+                stats,
+                dt,
+            }),
         }
     }
 
@@ -82,6 +156,7 @@ impl NPCBehavior {
             NPCBehavior::Melee(behavior) => behavior.handle_incoming_damage(damage, stats),
             NPCBehavior::Ranged(behavior) => behavior.handle_incoming_damage(damage, stats),
             NPCBehavior::Wander(behavior) => return,
+            NPCBehavior::Tree(tree) => tree.handle_incoming_damage(damage, stats),
         }
     }
 
@@ -90,12 +165,38 @@ impl NPCBehavior {
             NPCBehavior::Melee(behavior) => behavior.get_animation_name(),
             NPCBehavior::Ranged(behavior) => behavior.get_animation_name(),
             NPCBehavior::Wander(behavior) => behavior.get_animation_name(),
+            NPCBehavior::Tree(tree) => tree.get_animation_name(),
+        }
+    }
+
+    // The navmesh waypoints still ahead, for the debug overlay
+    pub fn path(&self) -> &[Vector3<f32>] {
+        match self {
+            NPCBehavior::Melee(behavior) => behavior.chase.path(),
+            NPCBehavior::Ranged(behavior) => behavior.chase.path(),
+            NPCBehavior::Wander(behavior) => behavior.path(),
+            NPCBehavior::Tree(tree) => tree.path(),
+        }
+    }
+
+    pub fn tree(&self) -> Option<&BehaviorTree> {
+        match self {
+            NPCBehavior::Tree(tree) => Some(tree),
+            _ => None,
+        }
+    }
+
+    pub fn tree_mut(&mut self) -> Option<&mut BehaviorTree> {
+        match self {
+            NPCBehavior::Tree(tree) => Some(tree),
+            _ => None,
         }
     }
 }
 
 pub struct NPC {
     pub id: String,
+    pub name: String, // display name
     pub model_id: String,
     pub rigid_body_handle: RigidBodyHandle,
     pub test_behavior: NPCBehavior,
@@ -106,51 +207,76 @@ pub struct NPC {
 }
 
 impl NPC {
+    // A wandering NPC with the default properties, apply_archetype sets it up from its component
     pub fn new(component_id: String, model_id: String, rigid_body_handle: RigidBodyHandle) -> Self {
-        let wander = WanderBehavior::new(50.0, 100.0);
-
-        let test_behavior = NPCBehavior::Wander(wander);
-        
-        // let attack_stats = AttackStats {
-        //     damage: 15.0,
-        //     range: 3.0,
-        //     cooldown: 0.2,
-        //     wind_up_time: 0.1,
-        //     recovery_time: 0.1,
-        // };
-
-        // // let melee_combat = MeleeCombatBehavior::new(
-        // //     200.0, // chase_speed
-        // //     50.0,  // detection_radius
-        // //     attack_stats,
-        // //     75.0, // evade_speed
-        // //     0.7,  // block_chance
-        // // );
-
-        // // let test_behavior = NPCBehavior::Melee(melee_combat);
-
-        // let melee_combat = RangedCombatBehavior::new(
-        //     200.0, // chase_speed
-        //     50.0,  // detection_radius
-        //     attack_stats,
-        //     75.0, // evade_speed
-        //     0.7,  // block_chance
-        // );
-
-        // let test_behavior = NPCBehavior::Ranged(melee_combat);
+        let properties = NPCProperties::default();
+        let wander = WanderBehavior::new(properties.wander_radius, properties.wander_speed);
 
         NPC {
+            name: component_id.clone(),
             id: component_id,
             model_id,
             rigid_body_handle,
-            test_behavior,
+            test_behavior: NPCBehavior::Wander(wander),
             animation_state: AnimationState::new(0),
             stats: CharacterStats {
-                health: 100.0,
-                stamina: 100.0,
+                health: properties.health,
+                stamina: properties.stamina,
             },
             inventory: Inventory::new(),
             is_talking: false,
         }
     }
+
+    // Behavior, starting stats and loadout from the component's properties. A behavior tree
+    // takes over from `behavior` when it has one.
+    pub fn apply_archetype(&mut self, archetype: &NPCArchetype) {
+        let properties = &archetype.properties;
+
+        self.name = archetype.name.clone();
+        self.stats = CharacterStats {
+            health: properties.health,
+            stamina: properties.stamina,
+        };
+        self.test_behavior = match (&archetype.behavior_tree, properties.behavior) {
+            (Some(tree), _) => NPCBehavior::Tree(BehaviorTree::new(tree)),
+            (None, NPCBehaviorKind::Wander) => NPCBehavior::Wander(WanderBehavior::new(properties.wander_radius, properties.wander_speed)),
+            (None, NPCBehaviorKind::Melee) => NPCBehavior::Melee(MeleeCombatBehavior::new(
+                properties.chase_speed,
+                properties.detection_radius,
+                archetype.attack.clone(),
+                properties.evade_speed,
+                archetype.block_chance,
+            )),
+            (None, NPCBehaviorKind::Ranged) => NPCBehavior::Ranged(RangedCombatBehavior::new(
+                properties.chase_speed,
+                properties.detection_radius,
+                archetype.attack.clone(),
+                properties.evade_speed,
+                archetype.block_chance,
+            )),
+        };
+
+        // the first weapon and armor in the loadout are equipped
+        self.inventory = Inventory::new();
+        for item in &archetype.inventory {
+            self.inventory.add_item(item);
+            match item.collectable_properties.as_ref().and_then(|p| p.collectable_type.as_ref()) {
+                Some(CollectableType::MeleeWeapon | CollectableType::RangedWeapon) if self.inventory.equipped_weapon.is_none() => {
+                    self.inventory.equip_weapon(item)
+                }
+                Some(CollectableType::Armor) if self.inventory.equipped_armor.is_none() => self.inventory.equip_armor(item),
+                _ => {}
+            }
+        }
+    }
+
+    // Runs a tree instead of whatever it was doing
+    pub fn set_behavior_tree(&mut self, data: &BehaviorTreeData) {
+        self.test_behavior = NPCBehavior::Tree(BehaviorTree::new(data));
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.stats.health > 0.0
+    }
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
 use std::{collections::HashMap, str::FromStr};
 use std::sync::MutexGuard;
 
-#[cfg(target_os = "windows")]
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
 #[cfg(target_arch = "wasm32")]
@@ -10,14 +10,14 @@ use wasm_timer::Instant;
 use nalgebra::{Isometry3, Point3, Vector3};
 use rapier3d::{
     control::{CharacterAutostep, KinematicCharacterController}, parry::shape::Capsule, prelude::{
-        ActiveCollisionTypes, Collider, ColliderBuilder, ColliderHandle, ColliderSet, QueryFilter, RigidBody, RigidBodyBuilder, RigidBodyHandle, RigidBodySet, TypedShape
+        ActiveCollisionTypes, ActiveEvents, Collider, ColliderBuilder, ColliderHandle, ColliderSet, QueryFilter, RigidBody, RigidBodyBuilder, RigidBodyHandle, RigidBodySet, TypedShape
     }
 };
 use uuid::Uuid;
 use rapier3d::prelude::{QueryPipeline, Shape, Ray};
 
 use crate::core::{AnimationState::AnimationState, SimpleCamera::SimpleCamera};
-use crate::helpers::saved_data::{AttackStats, CharacterStats, CollectableType, ComponentData};
+use crate::helpers::saved_data::{AttackStats, CharacterStats, CollectableType, ComponentData, ScriptState};
 use crate::model_components::NPC::{NPC};
 use crate::{
     game_behaviors::{
@@ -28,12 +28,9 @@ use crate::{
     art_assets::Model::Model,
 };
 
-use crate::shape_primitives::Sphere::Sphere;
-
 pub struct PlayerCharacter {
     pub id: String,
     pub model_id: Option<String>,
-    pub sphere: Option<Sphere>,
 
     // Physics components
     pub character_controller: KinematicCharacterController,
@@ -55,7 +52,7 @@ pub struct PlayerCharacter {
 
     pub animation_state: AnimationState,
     pub is_moving: bool,
-    pub script_state: Option<HashMap<String, String>>,
+    pub script_state: Option<ScriptState>,
 }
 
 impl PlayerCharacter {
@@ -63,12 +60,6 @@ impl PlayerCharacter {
         id: String,
         rigid_body_set: &mut RigidBodySet,
         collider_set: &mut ColliderSet,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        model_bind_group_layout: &wgpu::BindGroupLayout,
-        group_bind_group_layout: &wgpu::BindGroupLayout,
-        texture_render_mode_buffer: &wgpu::Buffer,
-        camera: &SimpleCamera,
         isometry: Isometry3<f32>,
         scale: Vector3<f32>,
         default_weapon: Option<ComponentData>
@@ -83,6 +74,7 @@ impl PlayerCharacter {
             .density(1.0)
             .user_data(uuid.as_u128())
             .active_collision_types(ActiveCollisionTypes::all())
+            .active_events(ActiveEvents::COLLISION_EVENTS)
             .build();
 
         let movement_shape = movement_collider.clone();
@@ -106,19 +98,6 @@ impl PlayerCharacter {
             rigid_body_set,
         );
 
-        let sphere = Sphere::new(
-            device,
-            queue,
-            model_bind_group_layout,
-            group_bind_group_layout,
-            texture_render_mode_buffer,
-            camera,
-            1.0,
-            32,
-            16,
-            [1.0, 1.0, 1.0]
-        );
-
         let mut inventory = Inventory::new();
 
         if let Some(default_weapon)  = default_weapon.clone() {
@@ -129,7 +108,6 @@ impl PlayerCharacter {
         Self {
             id,
             model_id: None,
-            sphere: Some(sphere),
             character_controller: KinematicCharacterController {
                 autostep: Some(CharacterAutostep {
                     max_height: rapier3d::control::CharacterLength::Relative((40.0)), // helps with jagged terrain?
@@ -0,0 +1,39 @@
+use nalgebra::{Isometry3, Vector3};
+use rapier3d::prelude::*;
+
+use crate::helpers::saved_data::{TriggerProperties, TriggerShape};
+
+// An invisible sensor on a fixed body. Anything moving through it shows up as trigger events,
+// see World::step_physics. Scale multiplies the shape like it would a model.
+pub fn create_physics(
+    user_data: u128,
+    properties: &TriggerProperties,
+    isometry: Isometry3<f32>,
+    scale: Vector3<f32>,
+) -> (RigidBody, Collider) {
+    let builder = match properties.shape {
+        TriggerShape::Box => ColliderBuilder::cuboid(
+            properties.half_extents[0] * scale.x,
+            properties.half_extents[1] * scale.y,
+            properties.half_extents[2] * scale.z,
+        ),
+        TriggerShape::Sphere => ColliderBuilder::ball(properties.radius * scale.max()),
+        TriggerShape::Capsule => ColliderBuilder::capsule_y(
+            properties.half_height * scale.y,
+            properties.radius * scale.x.max(scale.z),
+        ),
+    };
+
+    let collider = builder
+        .sensor(true)
+        .active_events(ActiveEvents::COLLISION_EVENTS)
+        .user_data(user_data)
+        .build();
+
+    let rigid_body = RigidBodyBuilder::fixed()
+        .position(isometry)
+        .user_data(user_data)
+        .build();
+
+    (rigid_body, collider)
+}
@@ -1,3 +1,4 @@
 pub mod PlayerCharacter;
 pub mod NPC;
-pub mod Collectable;
\ No newline at end of file
+pub mod Collectable;
+pub mod TriggerVolume;
\ No newline at end of file
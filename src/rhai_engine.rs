@@ -1,14 +1,40 @@
-use rhai::{Engine, Scope, AST, Dynamic, Array, CustomType, TypeBuilder};
-use std::collections::HashMap;
+use rhai::{Engine, EvalAltResult, Position, Scope, AST, Dynamic, Array, CustomType, TypeBuilder};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::cell::Cell;
 use std::fs;
+use std::time::{Duration, SystemTime};
+use rhai::module_resolvers::DummyModuleResolver;
+
+#[cfg(not(target_arch = "wasm32"))]
+use rhai::module_resolvers::FileModuleResolver;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_timer::Instant;
 use std::rc::Rc;
 use std::cell::RefCell;
-use nalgebra::Vector3;
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use nalgebra::Isometry3;
+use rapier3d::parry::query::RayCast;
+use rapier3d::prelude::{Ray, SharedShape};
+use uuid::Uuid;
 
 use crate::core::RendererState::RendererState;
+use crate::core::SceneHierarchy::{component_placements, Placement};
+use crate::core::World::World;
+use crate::helpers::pak;
 use crate::helpers::saved_data::ComponentData;
+use crate::game_behaviors::behavior_tree::{NodeStatus, ScriptLeafRequest};
+use crate::game_behaviors::blackboard::Blackboard;
 use crate::game_behaviors::dialogue_state::{DialogueState, DialogueOption};
-use crate::helpers::saved_data::ComponentKind;
+use crate::game_behaviors::quests::QuestLog;
+use crate::game_behaviors::timers::{ScriptTimer, Timers};
+use crate::helpers::saved_data::{ComponentKind, ScriptLimits, ScriptSettings, ScriptState};
+use crate::model_components::NPC::NPC;
+use crate::water_plane::config::WaterConfig;
 
 #[derive(Clone, CustomType, Debug)]            // <- auto-implement 'CustomType'
 pub struct Vec3 {                       //    for normal structs
@@ -40,18 +66,189 @@ pub struct ScriptParticleConfig {
     pub position: Vector3<f32>,
 }
 
+// Everything the scripts in one hook call asked for, one entry per component they touched
+pub type ChangeLog = Rc<RefCell<Vec<ComponentChanges>>>;
+
+fn record(changes: &ChangeLog, component_id: &str, apply: impl FnOnce(&mut ComponentChanges)) {
+    let mut changes = changes.borrow_mut();
+    let index = match changes.iter().position(|c| c.component_id == component_id) {
+        Some(index) => index,
+        None => {
+            changes.push(ComponentChanges {
+                component_id: component_id.to_string(),
+                ..Default::default()
+            });
+            changes.len() - 1
+        }
+    };
+    apply(&mut changes[index]);
+}
+
+fn to_vec3(v: Vector3<f32>) -> Vec3 {
+    Vec3 { x: v.x, y: v.y, z: v.z }
+}
+
+fn from_vec3(v: Vec3) -> Vector3<f32> {
+    Vector3::new(v.x, v.y, v.z)
+}
+
+// Scripts write 2 as often as 2.0
+fn to_number(value: &Dynamic) -> f32 {
+    value.as_float().or_else(|_| value.as_int().map(|i| i as f32)).unwrap_or(0.0)
+}
+
+// [x, y, z] from a script, ints or floats
+fn array_to_vector(values: &Array) -> Option<Vector3<f32>> {
+    if values.len() != 3 {
+        return None;
+    }
+    Some(Vector3::new(to_number(&values[0]), to_number(&values[1]), to_number(&values[2])))
+}
+
+// Scripts see rotations as euler angles in degrees, like GenericProperties
+pub fn euler_degrees(rotation: &UnitQuaternion<f32>) -> Vector3<f32> {
+    let (roll, pitch, yaw) = rotation.euler_angles();
+    Vector3::new(roll.to_degrees(), pitch.to_degrees(), yaw.to_degrees())
+}
+
 #[derive(Clone)]
 pub struct SystemWrapper {
-    pub particle_spawns: Rc<RefCell<Vec<ScriptParticleConfig>>>,
+    pub owner_id: String, // component or global script whose hook is running, requests are filed under it
+    pub script_path: String,
+    pub scene: Rc<ScriptScene>,
+    pub changes: ChangeLog,
+    pub timers: Rc<RefCell<Timers>>,
 }
 
 impl SystemWrapper {
-    pub fn new() -> Self {
+    pub fn new(owner_id: &str, script_path: &str, scene: Rc<ScriptScene>, changes: ChangeLog, timers: Rc<RefCell<Timers>>) -> Self {
         Self {
-            particle_spawns: Rc::new(RefCell::new(Vec::new())),
+            owner_id: owner_id.to_string(),
+            script_path: script_path.to_string(),
+            scene,
+            changes,
+            timers,
+        }
+    }
+
+    // Calls fn_name(component, system, script_state) on this script once, `seconds` of game time from now.
+    // Returns an id for cancel_timer.
+    pub fn after(&mut self, seconds: Dynamic, function: String) -> i64 {
+        self.timers.borrow_mut().after(&self.owner_id, &self.script_path, to_number(&seconds), &function, None)
+    }
+
+    // Same, with args passed after script_state
+    pub fn after_with(&mut self, seconds: Dynamic, function: String, args: Dynamic) -> i64 {
+        let args = Some(dynamic_to_json(&args));
+        self.timers.borrow_mut().after(&self.owner_id, &self.script_path, to_number(&seconds), &function, args)
+    }
+
+    // Calls fn_name every `seconds` until cancelled or the component is gone
+    pub fn every(&mut self, seconds: Dynamic, function: String) -> i64 {
+        self.timers.borrow_mut().every(&self.owner_id, &self.script_path, to_number(&seconds), &function, None)
+    }
+
+    pub fn every_with(&mut self, seconds: Dynamic, function: String, args: Dynamic) -> i64 {
+        let args = Some(dynamic_to_json(&args));
+        self.timers.borrow_mut().every(&self.owner_id, &self.script_path, to_number(&seconds), &function, args)
+    }
+
+    pub fn cancel_timer(&mut self, id: i64) -> bool {
+        self.timers.borrow_mut().cancel(id)
+    }
+
+    // Seconds since the last frame
+    pub fn get_dt(&mut self) -> f32 {
+        self.timers.borrow().dt
+    }
+
+    // Seconds of game time since the project started, kept in save games
+    pub fn get_time(&mut self) -> f32 {
+        self.timers.borrow().elapsed
+    }
+
+    // Switches level at the start of the next frame
+    pub fn load_level(&mut self, level_id: String) {
+        record(&self.changes, &self.owner_id, |c| c.load_level = Some(level_id));
+    }
+
+    // Writes a save game once the frame's scripts have run
+    pub fn save(&mut self, slot: String) {
+        record(&self.changes, &self.owner_id, |c| c.save_slot = Some(slot));
+    }
+
+    // Loads a save game at the start of the next frame, replacing the current level
+    pub fn load_save(&mut self, slot: String) {
+        record(&self.changes, &self.owner_id, |c| c.load_slot = Some(slot));
+    }
+
+    // Looks a component up by id, then by name. Returns () when there's no match.
+    pub fn find(&mut self, key: String) -> Dynamic {
+        self.scene
+            .find(&key)
+            .map(|c| Dynamic::from(ComponentRef::new(c, self.changes.clone())))
+            .unwrap_or(Dynamic::UNIT)
+    }
+
+    pub fn components(&mut self) -> Array {
+        self.scene
+            .components
+            .iter()
+            .map(|c| Dynamic::from(ComponentRef::new(c, self.changes.clone())))
+            .collect()
+    }
+
+    // First thing along the ray, skipping the component running the script.
+    // Returns #{ id, distance, point } or () when nothing is hit.
+    pub fn raycast(&mut self, origin: Vec3, direction: Vec3, max_distance: f32) -> Dynamic {
+        let exclude = Uuid::parse_str(&self.owner_id).ok().map(|id| id.as_u128());
+
+        match self.scene.raycast(from_vec3(origin), from_vec3(direction), max_distance, exclude) {
+            Some((id, distance, point)) => {
+                let mut hit = rhai::Map::new();
+                hit.insert("id".into(), id.into());
+                hit.insert("distance".into(), Dynamic::from_float(distance));
+                hit.insert("point".into(), Dynamic::from(to_vec3(point)));
+                Dynamic::from_map(hit)
+            }
+            None => Dynamic::UNIT,
         }
     }
 
+    // () when the level has no player
+    pub fn player_position(&mut self) -> Dynamic {
+        self.scene
+            .player_position
+            .map(|p| Dynamic::from(to_vec3(p)))
+            .unwrap_or(Dynamic::UNIT)
+    }
+
+    // -1.0 when the level has no player
+    pub fn distance_to_player(&mut self, position: Vec3) -> f32 {
+        self.scene
+            .player_position
+            .map(|p| (p - from_vec3(position)).norm())
+            .unwrap_or(-1.0)
+    }
+
+    // Places a prefab or model asset at the start of the next frame and returns the new component's id.
+    // Spawned components only last until the level unloads, and aren't kept in save games.
+    pub fn spawn_prefab(&mut self, source_id: String, position: Vec3) -> String {
+        let component_id = Uuid::new_v4().to_string();
+        let position = from_vec3(position);
+
+        record(&self.changes, &component_id, |c| {
+            c.spawn_from = Some(source_id);
+            c.new_position = Some(position);
+        });
+
+        component_id
+    }
+
+    pub fn destroy(&mut self, component_id: String) {
+        record(&self.changes, &component_id, |c| c.destroy = true);
+    }
+
     pub fn debug_name(&mut self, pos: f32) -> String {
         // "SystemWrapper".to_string()
         format!("SystemWrapper! {:?}", pos)
@@ -154,7 +351,7 @@ impl SystemWrapper {
             mode: 0.0,
             position: pos,
         };
-        self.particle_spawns.borrow_mut().push(config);
+        record(&self.changes, &self.owner_id, |c| c.particle_spawns.get_or_insert_with(Vec::new).push(config));
     }
 }
 
@@ -198,24 +395,220 @@ impl PlayerWrapper {
 }
 
 
+// The transform of a component a script can see, its own or one it looked up.
+// Writes land in the hook's change log and are applied after the hook returns.
 #[derive(Clone)]
-pub struct ModelWrapper {
+pub struct ScriptTransform {
     pub id: String,
     pub position: Vector3<f32>,
-    pub position_changed: bool,
+    pub rotation: Vector3<f32>, // euler angles in degrees
+    pub scale: Vector3<f32>,
+    changes: ChangeLog,
 }
 
-impl ModelWrapper {
-    pub fn set_position(&mut self, pos: Array) {
-        if pos.len() == 3 {
-            self.position = Vector3::new(
-                pos[0].as_float().unwrap_or(0.0) as f32,
-                pos[1].as_float().unwrap_or(0.0) as f32,
-                pos[2].as_float().unwrap_or(0.0) as f32,
-            );
-            self.position_changed = true;
+impl ScriptTransform {
+    pub fn new(id: &str, position: Vector3<f32>, rotation: Vector3<f32>, scale: Vector3<f32>, changes: ChangeLog) -> Self {
+        ScriptTransform {
+            id: id.to_string(),
+            position,
+            rotation,
+            scale,
+            changes,
         }
     }
+
+    pub fn set_position(&mut self, position: Vector3<f32>) {
+        self.position = position;
+        record(&self.changes, &self.id, |c| c.new_position = Some(position));
+    }
+
+    pub fn set_rotation(&mut self, rotation: Vector3<f32>) {
+        self.rotation = rotation;
+        record(&self.changes, &self.id, |c| c.new_rotation = Some(rotation));
+    }
+
+    pub fn set_scale(&mut self, scale: Vector3<f32>) {
+        self.scale = scale;
+        record(&self.changes, &self.id, |c| c.new_scale = Some(scale));
+    }
+
+    // Impulses add up when a script applies several in one call
+    pub fn apply_impulse(&mut self, impulse: Vector3<f32>) {
+        record(&self.changes, &self.id, |c| c.impulse = Some(c.impulse.unwrap_or_else(Vector3::zeros) + impulse));
+    }
+
+    pub fn destroy(&mut self) {
+        record(&self.changes, &self.id, |c| c.destroy = true);
+    }
+}
+
+// Implemented by every wrapper that has a transform, so they share one api
+pub trait ScriptComponent: Clone + 'static {
+    fn transform(&mut self) -> &mut ScriptTransform;
+}
+
+fn register_transform<T: ScriptComponent>(engine: &mut Engine) {
+    engine
+        .register_get("id", |c: &mut T| c.transform().id.clone())
+        .register_get("position", |c: &mut T| to_vec3(c.transform().position))
+        .register_get("rotation", |c: &mut T| to_vec3(c.transform().rotation))
+        .register_get("scale", |c: &mut T| to_vec3(c.transform().scale))
+        .register_fn("get_position", |c: &mut T| to_vec3(c.transform().position))
+        .register_fn("set_position", |c: &mut T, v: Array| {
+            if let Some(v) = array_to_vector(&v) {
+                c.transform().set_position(v)
+            }
+        })
+        .register_fn("set_position", |c: &mut T, v: Vec3| c.transform().set_position(from_vec3(v)))
+        .register_fn("set_rotation", |c: &mut T, v: Array| {
+            if let Some(v) = array_to_vector(&v) {
+                c.transform().set_rotation(v)
+            }
+        })
+        .register_fn("set_rotation", |c: &mut T, v: Vec3| c.transform().set_rotation(from_vec3(v)))
+        .register_fn("set_scale", |c: &mut T, v: Array| {
+            if let Some(v) = array_to_vector(&v) {
+                c.transform().set_scale(v)
+            }
+        })
+        .register_fn("set_scale", |c: &mut T, v: Vec3| c.transform().set_scale(from_vec3(v)))
+        .register_fn("apply_impulse", |c: &mut T, v: Array| {
+            if let Some(v) = array_to_vector(&v) {
+                c.transform().apply_impulse(v)
+            }
+        })
+        .register_fn("apply_impulse", |c: &mut T, v: Vec3| c.transform().apply_impulse(from_vec3(v)))
+        .register_fn("destroy", |c: &mut T| c.transform().destroy());
+}
+
+#[derive(Clone)]
+pub struct ModelWrapper {
+    pub transform: ScriptTransform,
+}
+
+impl ScriptComponent for ModelWrapper {
+    fn transform(&mut self) -> &mut ScriptTransform {
+        &mut self.transform
+    }
+}
+
+#[derive(Clone)]
+pub struct NpcWrapper {
+    pub transform: ScriptTransform,
+    pub health: f32,
+    pub stamina: f32,
+}
+
+impl ScriptComponent for NpcWrapper {
+    fn transform(&mut self) -> &mut ScriptTransform {
+        &mut self.transform
+    }
+}
+
+#[derive(Clone)]
+pub struct CollectableWrapper {
+    pub transform: ScriptTransform,
+    pub collectable_type: String,
+}
+
+impl ScriptComponent for CollectableWrapper {
+    fn transform(&mut self) -> &mut ScriptTransform {
+        &mut self.transform
+    }
+}
+
+#[derive(Clone)]
+pub struct LightWrapper {
+    pub transform: ScriptTransform,
+    pub intensity: f32,
+    pub color: [f32; 3],
+}
+
+impl LightWrapper {
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity;
+        record(&self.transform.changes, &self.transform.id, |c| c.light_intensity = Some(intensity));
+    }
+
+    pub fn set_color(&mut self, color: Array) {
+        if let Some(color) = array_to_vector(&color) {
+            self.color = [color.x, color.y, color.z];
+            record(&self.transform.changes, &self.transform.id, |c| c.light_color = Some([color.x, color.y, color.z]));
+        }
+    }
+
+    pub fn get_color(&mut self) -> Array {
+        self.color.iter().map(|c| Dynamic::from_float(*c)).collect()
+    }
+}
+
+impl ScriptComponent for LightWrapper {
+    fn transform(&mut self) -> &mut ScriptTransform {
+        &mut self.transform
+    }
+}
+
+#[derive(Clone)]
+pub struct WaterWrapper {
+    pub transform: ScriptTransform,
+    pub config: WaterConfig,
+}
+
+impl WaterWrapper {
+    fn changed(&mut self) {
+        let config = self.config;
+        record(&self.transform.changes, &self.transform.id, |c| c.water_config = Some(config));
+    }
+
+    pub fn set_shallow_color(&mut self, color: Vec4) {
+        self.config.shallow_color = [color.x, color.y, color.z, color.w];
+        self.changed();
+    }
+
+    pub fn set_medium_color(&mut self, color: Vec4) {
+        self.config.medium_color = [color.x, color.y, color.z, color.w];
+        self.changed();
+    }
+
+    pub fn set_deep_color(&mut self, color: Vec4) {
+        self.config.deep_color = [color.x, color.y, color.z, color.w];
+        self.changed();
+    }
+
+    // scales all three waves together
+    pub fn set_wave_amplitude(&mut self, amplitude: f32) {
+        let scale = if self.config.wave1_amplitude.abs() > f32::EPSILON {
+            amplitude / self.config.wave1_amplitude
+        } else {
+            1.0
+        };
+        self.config.wave1_amplitude = amplitude;
+        self.config.wave2_amplitude *= scale;
+        self.config.wave3_amplitude *= scale;
+        self.changed();
+    }
+
+    pub fn set_ripple_speed(&mut self, speed: f32) {
+        self.config.ripple_speed = speed;
+        self.changed();
+    }
+}
+
+impl ScriptComponent for WaterWrapper {
+    fn transform(&mut self) -> &mut ScriptTransform {
+        &mut self.transform
+    }
+}
+
+// What a component kind's wrapper carries besides its transform. Hosts fill it in from wherever they
+// keep the component, so the editor and headless runs hand scripts the same wrappers.
+#[derive(Clone, Debug)]
+pub enum WrapperFields {
+    Model, // models and trigger volumes only have a transform
+    NPC { health: f32, stamina: f32 },
+    Collectable { collectable_type: String },
+    PointLight { intensity: f32, color: [f32; 3] },
+    WaterPlane { config: WaterConfig },
 }
 
 #[derive(Clone)]
@@ -226,6 +619,8 @@ pub struct DialogueWrapper {
     pub is_open: bool,
     pub npc_name: String,
     pub current_node: String,
+    pub npc_id: String,
+    pub blackboard: Rc<RefCell<Blackboard>>,
 }
 
 impl DialogueWrapper {
@@ -237,7 +632,7 @@ impl DialogueWrapper {
     }
 
     pub fn add_option(&mut self, text: String, next_node: String) {
-        self.options.push(DialogueOption { text, next_node });
+        self.options.push(DialogueOption { text, next_node, choice: None });
         self.changed = true;
     }
 
@@ -254,11 +649,442 @@ impl DialogueWrapper {
     pub fn get_node(&mut self) -> String {
         self.current_node.clone()
     }
+
+    // This NPC's memory, () when never set
+    pub fn get_var(&mut self, name: String) -> Dynamic {
+        self.blackboard.borrow().var(&self.npc_id, &name).map(json_to_dynamic).unwrap_or(Dynamic::UNIT)
+    }
+
+    pub fn set_var(&mut self, name: String, value: Dynamic) {
+        self.blackboard.borrow_mut().set_var(&self.npc_id, &name, dynamic_to_json(&value));
+    }
+
+    // Shared by every NPC and global script
+    pub fn get_global(&mut self, name: String) -> Dynamic {
+        self.blackboard.borrow().global(&name).map(json_to_dynamic).unwrap_or(Dynamic::UNIT)
+    }
+
+    pub fn set_global(&mut self, name: String, value: Dynamic) {
+        self.blackboard.borrow_mut().set_global(&name, dynamic_to_json(&value));
+    }
+
+    // How many times conversations with this NPC were at `node` before, not counting this one
+    pub fn times_visited(&mut self, node: String) -> i64 {
+        self.blackboard.borrow().times_visited(&self.npc_id, &node) as i64
+    }
+}
+
+// The `task` a behavior tree's script leaf gets. The leaf keeps running until task.succeed() or task.fail().
+#[derive(Clone)]
+pub struct BehaviorTaskWrapper {
+    pub blackboard: Rc<RefCell<ScriptState>>, // the tree's, copied back after the call
+    pub status: Rc<Cell<NodeStatus>>,
+    pub target_distance: f32,
+}
+
+impl BehaviorTaskWrapper {
+    // () when never set
+    pub fn get(&mut self, key: String) -> Dynamic {
+        self.blackboard.borrow().get(&key).map(json_to_dynamic).unwrap_or(Dynamic::UNIT)
+    }
+
+    pub fn set(&mut self, key: String, value: Dynamic) {
+        self.blackboard.borrow_mut().insert(key, dynamic_to_json(&value));
+    }
+
+    pub fn succeed(&mut self) {
+        self.status.set(NodeStatus::Success);
+    }
+
+    pub fn fail(&mut self) {
+        self.status.set(NodeStatus::Failure);
+    }
+}
+
+// The `quests` variable scripts see, backed by the game's quest log
+#[derive(Clone)]
+pub struct QuestsWrapper {
+    pub log: Rc<RefCell<QuestLog>>,
+}
+
+impl QuestsWrapper {
+    pub fn start(&mut self, quest_id: String) -> bool {
+        self.log.borrow_mut().start(&quest_id)
+    }
+
+    pub fn advance(&mut self, quest_id: String) -> bool {
+        self.log.borrow_mut().advance(&quest_id)
+    }
+
+    pub fn is_active(&mut self, quest_id: String) -> bool {
+        self.log.borrow().is_active(&quest_id)
+    }
+
+    pub fn is_complete(&mut self, quest_id: String) -> bool {
+        self.log.borrow().is_complete(&quest_id)
+    }
+
+    pub fn stage(&mut self, quest_id: String) -> String {
+        self.log.borrow().stage_id(&quest_id)
+    }
+}
+
+// A component a script looked up, with the transform it had when the frame started
+#[derive(Clone)]
+pub struct ComponentRef {
+    pub name: String,
+    pub kind: String,
+    pub transform: ScriptTransform,
+}
+
+impl ComponentRef {
+    pub fn new(component: &SceneComponent, changes: ChangeLog) -> Self {
+        ComponentRef {
+            name: component.name.clone(),
+            kind: component.kind.clone(),
+            transform: ScriptTransform::new(&component.id, component.position, component.rotation, component.scale, changes),
+        }
+    }
+}
+
+impl ScriptComponent for ComponentRef {
+    fn transform(&mut self) -> &mut ScriptTransform {
+        &mut self.transform
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SceneComponent {
+    pub id: String,
+    pub name: String,
+    pub kind: String,
+    pub position: Vector3<f32>,
+    pub rotation: Vector3<f32>, // euler angles in degrees
+    pub scale: Vector3<f32>,
+}
+
+// What scripts can query during a frame: every placed component, the player and the colliders
+// raycasts can hit. Captured once per frame and shared by every hook that runs in it.
+#[derive(Default)]
+pub struct ScriptScene {
+    pub level_id: String,
+    pub components: Vec<SceneComponent>,
+    pub player_position: Option<Vector3<f32>>,
+    colliders: Vec<RaycastCollider>,
+}
+
+// A collider as raycasts see it. Shapes are shared, so this is cheap next to copying the physics sets.
+struct RaycastCollider {
+    shape: SharedShape,
+    position: Isometry3<f32>,
+    user_data: u128,
+}
+
+impl ScriptScene {
+    pub fn new(level_id: &str, components: Vec<SceneComponent>) -> Self {
+        ScriptScene {
+            level_id: level_id.to_string(),
+            components,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_world(mut self, world: &World) -> Self {
+        self.player_position = world.player_handle().and_then(|h| world.body_position(h));
+        self.colliders = world
+            .collider_set
+            .iter()
+            .filter(|(_, c)| c.is_enabled())
+            .map(|(_, c)| RaycastCollider {
+                shape: c.shared_shape().clone(),
+                position: *c.position(),
+                user_data: c.user_data,
+            })
+            .collect();
+        self
+    }
+
+    // The placed level plus anything scripts spawned into it, where it is right now
+    pub fn from_level(
+        renderer_state: &RendererState,
+        level_id: &str,
+        level_components: &[ComponentData],
+        spawned: &[ComponentData],
+    ) -> Self {
+        let placements = component_placements(level_components);
+
+        let components = level_components
+            .iter()
+            .chain(spawned.iter())
+            .map(|component| {
+                let placement = placements
+                    .get(&component.id)
+                    .copied()
+                    .unwrap_or_else(|| Placement::from_generic(&component.generic_properties));
+                let (position, rotation, scale) = renderer_state
+                    .component_transform(&component.id)
+                    .unwrap_or((placement.isometry.translation.vector, placement.isometry.rotation, placement.scale));
+
+                SceneComponent {
+                    id: component.id.clone(),
+                    name: component.generic_properties.name.clone(),
+                    kind: component.kind.as_ref().map(|k| format!("{:?}", k)).unwrap_or_default(),
+                    position,
+                    rotation: euler_degrees(&rotation),
+                    scale,
+                }
+            })
+            .collect();
+
+        ScriptScene::new(level_id, components).with_world(&renderer_state.world)
+    }
+
+    pub fn find(&self, key: &str) -> Option<&SceneComponent> {
+        self.components
+            .iter()
+            .find(|c| c.id == key)
+            .or_else(|| self.components.iter().find(|c| c.name == key))
+    }
+
+    // Hit component id (empty for colliders that don't belong to one), distance and point
+    pub fn raycast(
+        &self,
+        origin: Vector3<f32>,
+        direction: Vector3<f32>,
+        max_distance: f32,
+        exclude: Option<u128>,
+    ) -> Option<(String, f32, Vector3<f32>)> {
+        let direction = direction.try_normalize(1.0e-6)?;
+        let ray = Ray::new(Point3::from(origin), direction);
+
+        let (collider, distance) = self
+            .colliders
+            .iter()
+            .filter(|c| Some(c.user_data) != exclude)
+            .filter_map(|c| c.shape.cast_ray(&c.position, &ray, max_distance, true).map(|d| (c, d)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+        let id = if collider.user_data != 0 { Uuid::from_u128(collider.user_data).to_string() } else { String::new() };
+
+        Some((id, distance, ray.point_at(distance).coords))
+    }
+}
+
+// The `game` argument of global scripts: the current level and everything placed in it
+#[derive(Clone)]
+pub struct GameWrapper {
+    pub scene: Rc<ScriptScene>,
+    changes: ChangeLog,
+}
+
+impl GameWrapper {
+    // Looks a component up by id, then by name. Returns () when there's no match.
+    pub fn component(&mut self, key: String) -> Dynamic {
+        self.scene
+            .find(&key)
+            .map(|c| Dynamic::from(ComponentRef::new(c, self.changes.clone())))
+            .unwrap_or(Dynamic::UNIT)
+    }
+
+    pub fn components(&mut self) -> Array {
+        self.scene
+            .components
+            .iter()
+            .map(|c| Dynamic::from(ComponentRef::new(c, self.changes.clone())))
+            .collect()
+    }
+
+    pub fn get_level_id(&mut self) -> String {
+        self.scene.level_id.clone()
+    }
+}
+
+// Limits for scripts that don't set their own in ScriptSettings. Enough for gameplay logic,
+// small enough that `while true {}` errors out instead of hanging the frame.
+pub const DEFAULT_MAX_OPERATIONS: u64 = 500_000;
+pub const DEFAULT_MAX_CALL_LEVELS: usize = 64;
+pub const DEFAULT_MAX_STRING_SIZE: usize = 1 << 20;
+pub const DEFAULT_MAX_ARRAY_SIZE: usize = 100_000;
+pub const DEFAULT_MAX_MAP_SIZE: usize = 100_000;
+// Nesting depth at the top level and inside functions. Rhai's own 64/32 is too shallow for a
+// dialogue's `if node == ... else if ...` chain.
+pub const DEFAULT_MAX_EXPR_DEPTH: usize = 256;
+pub const DEFAULT_MAX_FUNCTION_EXPR_DEPTH: usize = 128;
+// Time every hook in one frame may take together
+pub const DEFAULT_FRAME_BUDGET_MS: f32 = 10.0;
+
+// A script that fails this many calls in a row stops being called until it's edited or re-enabled
+pub const SCRIPT_FAILURE_LIMIT: u32 = 10;
+// Seconds between checks for edited scripts
+const SCRIPT_POLL_INTERVAL: f32 = 0.5;
+// Oldest errors are dropped past this
+const MAX_SCRIPT_ERRORS: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptErrorKind {
+    Compile,
+    Runtime,
+    Disabled, // hit SCRIPT_FAILURE_LIMIT
+    Budget, // used up the frame's script time
+}
+
+// A problem with a script, shown in the editor's Script Errors panel
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptError {
+    pub kind: ScriptErrorKind,
+    pub script_path: String,
+    pub owner_id: Option<String>, // component or global script the hook ran for, None for compile errors
+    pub hook: Option<String>,
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub repeats: u32, // the same error again in a row, counted rather than listed
+}
+
+impl ScriptError {
+    fn new(kind: ScriptErrorKind, script_path: &str, message: String, position: Position) -> Self {
+        ScriptError {
+            kind,
+            script_path: script_path.to_string(),
+            owner_id: None,
+            hook: None,
+            message,
+            line: position.line(),
+            column: position.position(),
+            repeats: 0,
+        }
+    }
+
+    // Errors raised inside script functions come wrapped in the call that reached them,
+    // the innermost one has the script's own line and column
+    fn from_eval(kind: ScriptErrorKind, script_path: &str, error: &EvalAltResult) -> Self {
+        let inner = error.unwrap_inner();
+        let message = inner.to_string();
+        // Display appends the position, which is kept separately here
+        let message = match message.rfind(" (line ") {
+            Some(index) => message[..index].to_string(),
+            None => message,
+        };
+        ScriptError::new(kind, script_path, message, inner.position())
+    }
+
+    fn same_as(&self, other: &ScriptError) -> bool {
+        self.kind == other.kind
+            && self.script_path == other.script_path
+            && self.owner_id == other.owner_id
+            && self.hook == other.hook
+            && self.message == other.message
+            && self.line == other.line
+            && self.column == other.column
+    }
+}
+
+// scripts/door.rhai:12:5 on_update (component-id): Variable not found: x
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.script_path)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        if let Some(hook) = &self.hook {
+            write!(f, " {}", hook)?;
+        }
+        if let Some(owner_id) = &self.owner_id {
+            if owner_id != &self.script_path {
+                write!(f, " ({})", owner_id)?;
+            }
+        }
+        write!(f, ": {}", self.message)?;
+        if self.repeats > 0 {
+            write!(f, " (x{})", self.repeats + 1)?;
+        }
+        Ok(())
+    }
+}
+
+// Modified time of a script on disk, None for scripts in a pak or that don't exist
+fn script_stamp(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
 }
 
 pub struct RhaiEngine {
     engine: Engine,
     ast_cache: HashMap<String, AST>,
+    script_stamps: HashMap<String, Option<SystemTime>>, // every script we've tried to load, see reload_changed_scripts
+    last_poll: f32,
+    pub errors: Vec<ScriptError>, // oldest first
+    failures: HashMap<(String, String), u32>, // (script path, owner id) -> failed calls in a row
+    pub disabled: HashSet<(String, String)>,
+    settings: ScriptSettings,
+    deadline: Rc<Cell<Option<Instant>>>, // end of this frame's script budget, see begin_frame
+    budget_used: bool,
+    pub global_states: HashMap<String, ScriptState>, // keyed by global script path
+    pub timers: Rc<RefCell<Timers>>, // what system.after and system.every scheduled
+    pub blackboard: Rc<RefCell<Blackboard>>, // dialogue variables and NPC memory, shared with the dialogue graphs
+    scene: Rc<ScriptScene>, // what lookups and raycasts see, see set_scene
+}
+
+// Script values to JSON. Custom types like Vec3 have no JSON form and are kept as their display string.
+pub fn dynamic_to_json(value: &Dynamic) -> serde_json::Value {
+    if value.is_unit() {
+        serde_json::Value::Null
+    } else if let Ok(b) = value.as_bool() {
+        serde_json::Value::Bool(b)
+    } else if let Ok(i) = value.as_int() {
+        serde_json::Value::from(i)
+    } else if let Ok(f) = value.as_float() {
+        // go through the shortest decimal form so 0.1f32 is saved as 0.1, not 0.10000000149011612
+        let f = f.to_string().parse::<f64>().unwrap_or(f as f64);
+        serde_json::Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null)
+    } else if value.is_string() || value.is_char() {
+        serde_json::Value::String(value.to_string())
+    } else if value.is_array() {
+        let array = value.clone().cast::<Array>();
+        serde_json::Value::Array(array.iter().map(dynamic_to_json).collect())
+    } else if value.is_map() {
+        let map = value.clone().cast::<rhai::Map>();
+        serde_json::Value::Object(map.iter().map(|(k, v)| (k.to_string(), dynamic_to_json(v))).collect())
+    } else {
+        serde_json::Value::String(value.to_string())
+    }
+}
+
+pub fn json_to_dynamic(value: &serde_json::Value) -> Dynamic {
+    match value {
+        serde_json::Value::Null => Dynamic::UNIT,
+        serde_json::Value::Bool(b) => Dynamic::from_bool(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Dynamic::from_int(i),
+            None => Dynamic::from_float(n.as_f64().unwrap_or(0.0) as f32),
+        },
+        serde_json::Value::String(s) => Dynamic::from(s.clone()),
+        serde_json::Value::Array(array) => Dynamic::from_array(array.iter().map(json_to_dynamic).collect()),
+        serde_json::Value::Object(object) => {
+            Dynamic::from_map(object.iter().map(|(k, v)| (k.as_str().into(), json_to_dynamic(v))).collect())
+        }
+    }
+}
+
+fn to_rhai_state(script_state: &Option<ScriptState>) -> rhai::Map {
+    let mut rhai_script_state = rhai::Map::new();
+    if let Some(current_state) = script_state.as_ref() {
+        for (key, value) in current_state {
+            rhai_script_state.insert(key.as_str().into(), json_to_dynamic(value));
+        }
+    }
+    rhai_script_state
+}
+
+// Hooks return the updated script_state map, anything else leaves the state as it was
+fn from_rhai_state(result: Dynamic, script_state: &mut Option<ScriptState>) {
+    if let Some(map) = result.try_cast::<rhai::Map>() {
+        let updated = map.iter().map(|(key, value)| (key.to_string(), dynamic_to_json(value))).collect();
+        *script_state = Some(updated);
+    }
 }
 
 impl RhaiEngine {
@@ -269,6 +1095,26 @@ impl RhaiEngine {
             println!("[RHAI] {}", text);
         });
 
+        // Sandbox: no reading other files through `import` and no `eval`, so mods can't reach
+        // past the api registered here. Trusted projects can allow imports, see configure.
+        engine.set_module_resolver(DummyModuleResolver::new());
+        engine.disable_symbol("eval");
+        engine.set_max_expr_depths(DEFAULT_MAX_EXPR_DEPTH, DEFAULT_MAX_FUNCTION_EXPR_DEPTH);
+
+        // long-running hooks are stopped once the frame's budget is gone
+        let deadline: Rc<Cell<Option<Instant>>> = Rc::new(Cell::new(None));
+        let progress_deadline = deadline.clone();
+        engine.on_progress(move |operations| {
+            if operations % 1024 == 0 {
+                if let Some(deadline) = progress_deadline.get() {
+                    if Instant::now() > deadline {
+                        return Some("the frame's script budget ran out".into());
+                    }
+                }
+            }
+            None
+        });
+
         engine.build_type::<Vec3>();
         engine.build_type::<Vec4>();
 
@@ -280,11 +1126,41 @@ impl RhaiEngine {
         });
         engine.register_fn("vec4", |x: f32, y: f32, z: f32, w: f32| Vec4 { x, y, z, w });
 
-        // Register the ModelWrapper with mutation methods
-        engine.register_type_with_name::<ModelWrapper>("ComponentModel")
-            .register_get("id", |m: &mut ModelWrapper| m.id.clone())
-            .register_get("position", |m: &mut ModelWrapper| m.position)
-            .register_fn("set_position", ModelWrapper::set_position);
+        // Register the component wrappers, they all share the transform api
+        engine.register_type_with_name::<ModelWrapper>("ComponentModel");
+        register_transform::<ModelWrapper>(&mut engine);
+
+        engine.register_type_with_name::<NpcWrapper>("NPC")
+            .register_get("health", |n: &mut NpcWrapper| n.health)
+            .register_get("stamina", |n: &mut NpcWrapper| n.stamina)
+            .register_get("is_alive", |n: &mut NpcWrapper| n.health > 0.0);
+        register_transform::<NpcWrapper>(&mut engine);
+
+        engine.register_type_with_name::<CollectableWrapper>("Collectable")
+            .register_get("collectable_type", |c: &mut CollectableWrapper| c.collectable_type.clone());
+        register_transform::<CollectableWrapper>(&mut engine);
+
+        engine.register_type_with_name::<LightWrapper>("PointLight")
+            .register_get("intensity", |l: &mut LightWrapper| l.intensity)
+            .register_get("color", LightWrapper::get_color)
+            .register_fn("set_intensity", LightWrapper::set_intensity)
+            .register_fn("set_color", LightWrapper::set_color);
+        register_transform::<LightWrapper>(&mut engine);
+
+        engine.register_type_with_name::<WaterWrapper>("WaterPlane")
+            .register_get("wave_amplitude", |w: &mut WaterWrapper| w.config.wave1_amplitude)
+            .register_get("ripple_speed", |w: &mut WaterWrapper| w.config.ripple_speed)
+            .register_fn("set_shallow_color", WaterWrapper::set_shallow_color)
+            .register_fn("set_medium_color", WaterWrapper::set_medium_color)
+            .register_fn("set_deep_color", WaterWrapper::set_deep_color)
+            .register_fn("set_wave_amplitude", WaterWrapper::set_wave_amplitude)
+            .register_fn("set_ripple_speed", WaterWrapper::set_ripple_speed);
+        register_transform::<WaterWrapper>(&mut engine);
+
+        engine.register_type_with_name::<ComponentRef>("Component")
+            .register_get("name", |c: &mut ComponentRef| c.name.clone())
+            .register_get("kind", |c: &mut ComponentRef| c.kind.clone());
+        register_transform::<ComponentRef>(&mut engine);
             
         // Register Vector3 for direct use in Rhai
         // engine.register_type_with_name::<Vector3<f32>>("Vector3")
@@ -305,7 +1181,19 @@ impl RhaiEngine {
             .register_fn("add_option", DialogueWrapper::add_option)
             .register_fn("set_node", DialogueWrapper::set_node)
             .register_fn("get_node", DialogueWrapper::get_node)
-            .register_fn("close", DialogueWrapper::close);
+            .register_fn("close", DialogueWrapper::close)
+            .register_fn("get_var", DialogueWrapper::get_var)
+            .register_fn("set_var", DialogueWrapper::set_var)
+            .register_fn("get_global", DialogueWrapper::get_global)
+            .register_fn("set_global", DialogueWrapper::set_global)
+            .register_fn("times_visited", DialogueWrapper::times_visited);
+
+        engine.register_type_with_name::<BehaviorTaskWrapper>("BehaviorTask")
+            .register_fn("get", BehaviorTaskWrapper::get)
+            .register_fn("set", BehaviorTaskWrapper::set)
+            .register_fn("succeed", BehaviorTaskWrapper::succeed)
+            .register_fn("fail", BehaviorTaskWrapper::fail)
+            .register_get("target_distance", |t: &mut BehaviorTaskWrapper| t.target_distance);
 
         // Register SystemWrapper
         // engine.register_type_with_name::<SystemWrapper>("System")
@@ -314,7 +1202,24 @@ impl RhaiEngine {
             .register_fn("spawn_particles", SystemWrapper::spawn_particles)
             .register_fn("log_particles", SystemWrapper::log_particles)
             .register_fn("debug_name", SystemWrapper::debug_name)
-            .register_fn("vec3", SystemWrapper::vec3);
+            .register_fn("vec3", SystemWrapper::vec3)
+            .register_fn("load_level", SystemWrapper::load_level)
+            .register_fn("save", SystemWrapper::save)
+            .register_fn("load_save", SystemWrapper::load_save)
+            .register_fn("find", SystemWrapper::find)
+            .register_fn("components", SystemWrapper::components)
+            .register_fn("raycast", SystemWrapper::raycast)
+            .register_fn("player_position", SystemWrapper::player_position)
+            .register_fn("distance_to_player", SystemWrapper::distance_to_player)
+            .register_fn("spawn_prefab", SystemWrapper::spawn_prefab)
+            .register_fn("destroy", SystemWrapper::destroy)
+            .register_fn("after", SystemWrapper::after)
+            .register_fn("after", SystemWrapper::after_with)
+            .register_fn("every", SystemWrapper::every)
+            .register_fn("every", SystemWrapper::every_with)
+            .register_fn("cancel_timer", SystemWrapper::cancel_timer)
+            .register_get("dt", SystemWrapper::get_dt)
+            .register_get("time", SystemWrapper::get_time);
 
         // engine
         //     .register_type_with_name::<Rc<RefCell<SystemWrapper>>>("System")
@@ -343,91 +1248,344 @@ impl RhaiEngine {
             .register_fn("get_equipped_weapon_name", PlayerWrapper::get_equipped_weapon_name)
             .register_fn("get_position", PlayerWrapper::get_position);
 
+        // Register the global script type
+        engine.register_type_with_name::<GameWrapper>("Game")
+            .register_get("level_id", GameWrapper::get_level_id)
+            .register_fn("component", GameWrapper::component)
+            .register_fn("components", GameWrapper::components);
+
         RhaiEngine {
             engine,
             ast_cache: HashMap::new(),
+            script_stamps: HashMap::new(),
+            last_poll: 0.0,
+            errors: Vec::new(),
+            failures: HashMap::new(),
+            disabled: HashSet::new(),
+            settings: ScriptSettings::default(),
+            deadline,
+            budget_used: false,
+            global_states: HashMap::new(),
+            timers: Rc::new(RefCell::new(Timers::default())),
+            blackboard: Rc::new(RefCell::new(Blackboard::default())),
+            scene: Rc::new(ScriptScene::default()),
         }
     }
 
-    pub fn load_script(&mut self, path: &str) -> Result<(), Box<rhai::EvalAltResult>> {
-        let script_content = fs::read_to_string(path).map_err(|e| e.to_string())?;
-        let ast = self.engine.compile(script_content)?;
-        println!("load script");
-        self.ast_cache.insert(path.to_string(), ast);
-        Ok(())
+    // Exposes `quests` to every script, including inside hook functions
+    pub fn bind_quests(&mut self, log: Rc<RefCell<QuestLog>>) {
+        self.engine.register_type_with_name::<QuestsWrapper>("Quests")
+            .register_fn("start", QuestsWrapper::start)
+            .register_fn("advance", QuestsWrapper::advance)
+            .register_fn("is_active", QuestsWrapper::is_active)
+            .register_fn("is_complete", QuestsWrapper::is_complete)
+            .register_fn("stage", QuestsWrapper::stage);
+
+        self.engine.on_var(move |name, _, _| match name {
+            "quests" => Ok(Some(Dynamic::from(QuestsWrapper { log: log.clone() }))),
+            _ => Ok(None),
+        });
+    }
+
+    // Applies a project's script limits and sandbox settings, before any of its scripts run
+    pub fn configure(&mut self, settings: &Option<ScriptSettings>) {
+        self.settings = settings.clone().unwrap_or_default();
+
+        // there are no script files to import from in the browser
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.settings.allow_imports {
+            self.engine.set_module_resolver(FileModuleResolver::new());
+            return;
+        }
+
+        self.engine.set_module_resolver(DummyModuleResolver::new());
+    }
+
+    // The limits a call into script_path runs under
+    pub fn limits_for(&self, script_path: &str) -> ScriptLimits {
+        let defaults = ScriptLimits {
+            max_operations: Some(DEFAULT_MAX_OPERATIONS),
+            max_call_levels: Some(DEFAULT_MAX_CALL_LEVELS),
+            max_string_size: Some(DEFAULT_MAX_STRING_SIZE),
+            max_array_size: Some(DEFAULT_MAX_ARRAY_SIZE),
+            max_map_size: Some(DEFAULT_MAX_MAP_SIZE),
+        };
+        self.settings.limits_for(script_path).or(&defaults)
+    }
+
+    fn apply_limits(&mut self, script_path: &str) {
+        let limits = self.limits_for(script_path);
+        self.engine.set_max_operations(limits.max_operations.unwrap_or_default());
+        self.engine.set_max_call_levels(limits.max_call_levels.unwrap_or_default());
+        self.engine.set_max_string_size(limits.max_string_size.unwrap_or_default());
+        self.engine.set_max_array_size(limits.max_array_size.unwrap_or_default());
+        self.engine.set_max_map_size(limits.max_map_size.unwrap_or_default());
+    }
+
+    // Starts the frame's script time budget. Hooks past it are stopped, the rest of the frame's
+    // hooks are skipped, and the script that ran over is reported.
+    pub fn begin_frame(&mut self) {
+        let budget_ms = self.settings.frame_budget_ms.unwrap_or(DEFAULT_FRAME_BUDGET_MS);
+        self.budget_used = false;
+        self.deadline.set(if budget_ms > 0.0 {
+            Some(Instant::now() + Duration::from_secs_f32(budget_ms / 1000.0))
+        } else {
+            None
+        });
+    }
+
+    // Hooks outside a frame (loads, spawns, input) run without a budget
+    pub fn end_frame(&mut self) {
+        self.deadline.set(None);
+        self.budget_used = false;
+    }
+
+    fn report_budget(&mut self, owner_id: &str, script_path: &str, hook_name: &str, took: Duration) {
+        let budget_ms = self.settings.frame_budget_ms.unwrap_or(DEFAULT_FRAME_BUDGET_MS);
+        let mut error = ScriptError::new(
+            ScriptErrorKind::Budget,
+            script_path,
+            format!(
+                "used up the frame's {} ms script budget ({:.1} ms in this call), later hooks were skipped",
+                budget_ms,
+                took.as_secs_f32() * 1000.0
+            ),
+            Position::NONE,
+        );
+        error.owner_id = Some(owner_id.to_string());
+        error.hook = Some(hook_name.to_string());
+        self.report(error);
+        self.budget_used = true;
     }
 
+    // Compiles a script into the cache. When it doesn't compile the error is reported and
+    // whatever version compiled last keeps running.
+    pub fn load_script(&mut self, path: &str) -> Result<(), ScriptError> {
+        self.script_stamps.insert(path.to_string(), script_stamp(path));
+
+        let compiled = pak::read_script(path)
+            .map_err(|e| ScriptError::new(ScriptErrorKind::Compile, path, e, Position::NONE))
+            .and_then(|content| {
+                self.engine
+                    .compile(content)
+                    .map_err(|e| ScriptError::new(ScriptErrorKind::Compile, path, e.0.to_string(), e.1))
+            });
+
+        match compiled {
+            Ok(ast) => {
+                println!("Loaded Rhai script {}", path);
+                self.ast_cache.insert(path.to_string(), ast);
+                Ok(())
+            }
+            Err(error) => {
+                self.report(error.clone());
+                Err(error)
+            }
+        }
+    }
+
+    // Compiles source under the same sandbox and depth limits as load_script, without caching it
+    pub fn check_source(&self, source: &str) -> Result<(), rhai::ParseError> {
+        self.engine.compile(source).map(|_| ())
+    }
+
+    // Scripts that failed to load aren't retried every call, only once they change on disk
+    fn ensure_loaded(&mut self, script_path: &str) -> bool {
+        if !self.ast_cache.contains_key(script_path) && !self.script_stamps.contains_key(script_path) {
+            self.load_script(script_path).ok();
+        }
+        self.ast_cache.contains_key(script_path)
+    }
+
+    // Recompiles scripts edited on disk since they were loaded, at most every SCRIPT_POLL_INTERVAL
+    // seconds of `now`. Scripts that compile again are re-enabled. Returns the paths reloaded.
+    pub fn reload_changed_scripts(&mut self, now: f32) -> Vec<String> {
+        if now - self.last_poll < SCRIPT_POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = now;
+
+        let changed: Vec<String> = self
+            .script_stamps
+            .iter()
+            .filter(|(path, stamp)| script_stamp(path) != **stamp)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        let mut reloaded = Vec::new();
+        for path in changed {
+            if self.load_script(&path).is_ok() {
+                self.errors.retain(|e| e.script_path != path);
+                self.enable_script(&path);
+                reloaded.push(path);
+            }
+        }
+        reloaded
+    }
+
+    // Gives a script disabled for failing another go, for every component using it
+    pub fn enable_script(&mut self, script_path: &str) {
+        self.disabled.retain(|(path, _)| path != script_path);
+        self.failures.retain(|(path, _), _| path != script_path);
+    }
+
+    pub fn is_disabled(&self, script_path: &str, owner_id: &str) -> bool {
+        self.disabled.contains(&(script_path.to_string(), owner_id.to_string()))
+    }
+
+    fn report(&mut self, error: ScriptError) {
+        eprintln!("Rhai error: {}", error);
+
+        if let Some(last) = self.errors.last_mut() {
+            if last.same_as(&error) {
+                last.repeats += 1;
+                return;
+            }
+        }
+
+        self.errors.push(error);
+        if self.errors.len() > MAX_SCRIPT_ERRORS {
+            let excess = self.errors.len() - MAX_SCRIPT_ERRORS;
+            self.errors.drain(..excess);
+        }
+    }
+
+    // Counts a failed hook call, and disables the script for that owner once it keeps failing
+    fn report_failure(&mut self, owner_id: &str, script_path: &str, hook_name: &str, error: &EvalAltResult) {
+        let mut error = ScriptError::from_eval(ScriptErrorKind::Runtime, script_path, error);
+        error.owner_id = Some(owner_id.to_string());
+        error.hook = Some(hook_name.to_string());
+        self.report(error.clone());
+
+        let key = (script_path.to_string(), owner_id.to_string());
+        let failures = self.failures.entry(key.clone()).or_insert(0);
+        *failures += 1;
+
+        if *failures >= SCRIPT_FAILURE_LIMIT {
+            error.kind = ScriptErrorKind::Disabled;
+            error.message = format!("disabled after {} failed calls in a row, fix the script or re-enable it", SCRIPT_FAILURE_LIMIT);
+            error.line = None;
+            error.column = None;
+            self.report(error);
+            self.disabled.insert(key);
+        }
+    }
+
+    // Compiles a project's global scripts up front and forgets any state from a previous project
+    pub fn load_global_scripts(&mut self, scripts: &Option<Vec<String>>) {
+        self.global_states.clear();
+        *self.timers.borrow_mut() = Timers::default();
+        self.errors.clear();
+        self.failures.clear();
+        self.disabled.clear();
+
+        for script_path in scripts.iter().flatten() {
+            self.load_script(script_path).ok();
+        }
+    }
+
+    // Lifecycle hooks are optional, so check before calling rather than logging a missing function every frame
+    pub fn has_hook(&mut self, script_path: &str, hook_name: &str) -> bool {
+        self.ensure_loaded(script_path)
+            && self
+                .ast_cache
+                .get(script_path)
+                .map(|ast| ast.iter_functions().any(|f| f.name == hook_name))
+                .unwrap_or(false)
+    }
+
+    // Runs a hook of a component's script with the wrapper for its kind. Model and NPC state lives
+    // on the model, the player's on the player, everything else in RendererState::script_states.
     pub fn execute_component_script(
         &mut self,
         renderer_state: &mut RendererState,
         component: &ComponentData,
         script_path: &str,
         hook_name: &str,
-    ) -> Option<ComponentChanges> {
-        let ast = if let Some(ast) = self.ast_cache.get(script_path) {
-            ast
-        } else {
-            if self.load_script(script_path).is_err() {
-                eprintln!("Failed to load Rhai script: {}", script_path);
-                return None;
+    ) -> Vec<ComponentChanges> {
+        self.execute_component_hook(renderer_state, component, script_path, hook_name, None)
+    }
+
+    // Collision and trigger hooks get the other component's id as a fourth argument,
+    // e.g. on_trigger_enter(component, system, script_state, other_id)
+    pub fn execute_collision_hook(
+        &mut self,
+        renderer_state: &mut RendererState,
+        component: &ComponentData,
+        script_path: &str,
+        hook_name: &str,
+        other_id: &str,
+    ) -> Vec<ComponentChanges> {
+        self.execute_component_hook(renderer_state, component, script_path, hook_name, Some(other_id.into()))
+    }
+
+    // Calls a timer's function on the component that scheduled it, with the timer's args after script_state
+    pub fn execute_timer(
+        &mut self,
+        renderer_state: &mut RendererState,
+        component: &ComponentData,
+        timer: &ScriptTimer,
+    ) -> Vec<ComponentChanges> {
+        if !self.has_hook(&timer.script_path, &timer.function) {
+            if self.ast_cache.contains_key(&timer.script_path) {
+                let mut error = ScriptError::new(
+                    ScriptErrorKind::Runtime,
+                    &timer.script_path,
+                    format!("timer function {} not found", timer.function),
+                    Position::NONE,
+                );
+                error.owner_id = Some(timer.owner_id.clone());
+                self.report(error);
             }
-            self.ast_cache.get(script_path).unwrap()
-        };
+            return Vec::new();
+        }
 
-        let mut scope = Scope::new();
-        let mut system = SystemWrapper::new();
-        // let system = Rc::new(RefCell::new(SystemWrapper::new()));
-        // scope.push("system", system.clone());
-
-        match component.kind.as_ref().unwrap() {
-            crate::helpers::saved_data::ComponentKind::Model => {
-                if let Some(model) = renderer_state.models.iter_mut().find(|m| m.id == component.id) {
-                    let mut wrapper = ModelWrapper {
-                        id: model.id.clone(),
-                        position: model.meshes[0].transform.position,
-                        position_changed: false,
-                    };
-                    
-                    // Prepare script_state
-                    let mut rhai_script_state = rhai::Map::new();
-                    if let Some(current_state) = &model.script_state {
-                        for (key, value) in current_state {
-                            rhai_script_state.insert(key.clone().into(), value.clone().into());
-                        }
-                    }
-                    
-                    match self.engine.call_fn::<Dynamic>(&mut scope, &ast, hook_name, (wrapper.clone(), system.clone(), rhai_script_state)) {
-                        Ok(result) => {
-                            // Script returns just the updated script_state map
-                            if let Some(map) = result.try_cast::<rhai::Map>() {
-                                let mut updated_hashmap = HashMap::new();
-                                for (key, value) in map {
-                                    updated_hashmap.insert(key.to_string(), value.to_string());
-                                }
-                                model.script_state = Some(updated_hashmap);
-                            }
-                            
-                            let particle_spawns = system.particle_spawns.borrow().clone();
-
-                            // Check if wrapper was mutated
-                            if wrapper.position_changed || !particle_spawns.is_empty() {
-                                return Some(ComponentChanges {
-                                    component_id: wrapper.id,
-                                    new_position: if wrapper.position_changed { Some(wrapper.position) } else { None },
-                                    particle_spawns: if !particle_spawns.is_empty() { Some(particle_spawns) } else { None },
-                                });
-                            }
-                        }
-                        Err(e) => {
-                            // if !matches!(*e, rhai::EvalAltResult::ErrorFunctionNotFound(_, _)) {
-                                eprintln!("Error executing hook '{}' in Rhai script for component {}: {:?}", hook_name, component.id, e);
-                            // }
-                        }
-                    }
+        let args = timer.args.as_ref().map(json_to_dynamic);
+        self.execute_component_hook(renderer_state, component, &timer.script_path, &timer.function, args)
+    }
+
+    fn execute_component_hook(
+        &mut self,
+        renderer_state: &mut RendererState,
+        component: &ComponentData,
+        script_path: &str,
+        hook_name: &str,
+        extra: Option<Dynamic>,
+    ) -> Vec<ComponentChanges> {
+        if !self.has_hook(script_path, hook_name) {
+            return Vec::new();
+        }
+
+        let changes: ChangeLog = Rc::new(RefCell::new(Vec::new()));
+        let (position, rotation, scale) = renderer_state.component_transform(&component.id).unwrap_or_else(|| {
+            let placement = Placement::from_generic(&component.generic_properties);
+            (placement.isometry.translation.vector, placement.isometry.rotation, placement.scale)
+        });
+        let transform = ScriptTransform::new(&component.id, position, euler_degrees(&rotation), scale, changes.clone());
+
+        let fields = match component.kind.as_ref().unwrap() {
+            ComponentKind::Model | ComponentKind::TriggerVolume => WrapperFields::Model,
+            ComponentKind::NPC => match renderer_state.world.npcs.iter().find(|n| n.id == component.id) {
+                Some(npc) => WrapperFields::NPC { health: npc.stats.health, stamina: npc.stats.stamina },
+                None => return Vec::new(),
+            },
+            ComponentKind::Collectable => match renderer_state.world.collectables.iter().find(|c| c.id == component.id) {
+                Some(collectable) => WrapperFields::Collectable { collectable_type: format!("{:?}", collectable.collectable_type) },
+                None => return Vec::new(),
+            },
+            ComponentKind::PointLight => match renderer_state.point_light_ids.iter().position(|id| *id == component.id) {
+                Some(index) => {
+                    let light = &renderer_state.point_lights[index];
+                    WrapperFields::PointLight { intensity: light.intensity, color: light.color }
                 }
+                None => return Vec::new(),
+            },
+            ComponentKind::WaterPlane => match renderer_state.water_plane_ids.iter().position(|id| *id == component.id) {
+                Some(index) => WrapperFields::WaterPlane { config: renderer_state.water_planes[index].config },
+                None => return Vec::new(),
             },
-            crate::helpers::saved_data::ComponentKind::PlayerCharacter => {
-                if let Some(player) = &mut renderer_state.player_character {
+            ComponentKind::PlayerCharacter => {
+                if let Some(player) = &mut renderer_state.world.player_character {
                     // Assuming player model position or camera position
                     // We need a wrapper for player
                     let wrapper = PlayerWrapper {
@@ -443,7 +1601,7 @@ impl RhaiEngine {
                             "".to_string()
                         },
                         position: if let Some(rigidbody) = &player.movement_rigid_body_handle {
-                            let body = renderer_state.rigid_body_set.get(*rigidbody);
+                            let body = renderer_state.world.rigid_body_set.get(*rigidbody);
                             let body = body.as_ref().expect("Couldn't get body");
 
                             Vector3::new(body.translation().x, body.translation().y, body.translation().z)
@@ -451,51 +1609,310 @@ impl RhaiEngine {
                             Vector3::zeros()
                         }
                     };
-                    
-                    // Prepare script_state
-                    let mut rhai_script_state = rhai::Map::new();
-                    if let Some(current_state) = &player.script_state {
-                        for (key, value) in current_state {
-                            rhai_script_state.insert(key.clone().into(), value.clone().into());
-                        }
-                    }
 
-                    // Call Rhai function
-                     match self.engine.call_fn::<Dynamic>(&mut scope, &ast, hook_name, (wrapper.clone(), system.clone(), rhai_script_state)) {
-                        Ok(result) => {
-                             // Script returns just the updated script_state map
-                            if let Some(map) = result.try_cast::<rhai::Map>() {
-                                let mut updated_hashmap = HashMap::new();
-                                for (key, value) in map {
-                                    updated_hashmap.insert(key.to_string(), value.to_string());
-                                }
-                                player.script_state = Some(updated_hashmap);
-                            }
-
-                             let particle_spawns = system.particle_spawns.borrow().clone();
-
-                            if !particle_spawns.is_empty() {
-                                return Some(ComponentChanges {
-                                    component_id: wrapper.id,
-                                    new_position: None,
-                                    particle_spawns: Some(particle_spawns),
-                                });
-                            }
-                        },
-                        Err(e) => {
-                            //  if !matches!(*e, rhai::EvalAltResult::ErrorFunctionNotFound(_, _)) {
-                                eprintln!("Error executing hook '{}' in Rhai script for component {}: {:?}", hook_name, component.id, e);
-                            // }
-                        }
-                     }
+                    return self.call_hook(&component.id, wrapper, changes, &mut player.script_state, script_path, hook_name, extra);
                 }
+                return Vec::new();
             },
-            _ => {}
+            _ => return Vec::new(),
+        };
+
+        // models and NPCs keep their script state on their model, the other kinds in script_states
+        if matches!(component.kind, Some(ComponentKind::Model) | Some(ComponentKind::NPC)) {
+            return match renderer_state.models.iter_mut().find(|m| m.id == component.id) {
+                Some(model) => self.call_wrapped_hook(&component.id, fields, transform, changes, &mut model.script_state, script_path, hook_name, extra),
+                None => Vec::new(),
+            };
         }
-        
-        None
+
+        let mut script_state = renderer_state
+            .script_states
+            .get(&component.id)
+            .cloned()
+            .or_else(|| component.script_state.clone());
+
+        let result = self.call_wrapped_hook(&component.id, fields, transform, changes, &mut script_state, script_path, hook_name, extra);
+
+        if let Some(state) = script_state {
+            renderer_state.script_states.insert(component.id.clone(), state);
+        }
+
+        result
+    }
+
+    // A behavior tree script leaf in the editor, with the NPC's script state kept on its model like its other hooks
+    pub fn execute_behavior_leaf(
+        &mut self,
+        renderer_state: &mut RendererState,
+        request: &ScriptLeafRequest,
+        script_path: Option<&str>,
+    ) -> Vec<ComponentChanges> {
+        let (position, rotation, scale) = match renderer_state.component_transform(&request.npc_id) {
+            Some(transform) => transform,
+            None => return Vec::new(),
+        };
+        let npc = match renderer_state.world.npcs.iter_mut().find(|n| n.id == request.npc_id) {
+            Some(npc) => npc,
+            None => return Vec::new(),
+        };
+
+        let mut no_state: Option<ScriptState> = None;
+        let script_state = match renderer_state.models.iter_mut().find(|m| m.id == request.npc_id) {
+            Some(model) => &mut model.script_state,
+            None => &mut no_state,
+        };
+
+        self.call_behavior_leaf(npc, position, euler_degrees(&rotation), scale, script_state, script_path, request)
+    }
+
+    // Calls a script leaf's function as function(npc, system, script_state, task) and hands the tree
+    // what it decided. No script, a missing function or an error fails the leaf. Out of frame budget,
+    // it isn't called and asks again next tick.
+    pub fn call_behavior_leaf(
+        &mut self,
+        npc: &mut NPC,
+        position: Vector3<f32>,
+        rotation: Vector3<f32>, // euler angles in degrees
+        scale: Vector3<f32>,
+        script_state: &mut Option<ScriptState>,
+        script_path: Option<&str>,
+        request: &ScriptLeafRequest,
+    ) -> Vec<ComponentChanges> {
+        let (health, stamina) = (npc.stats.health, npc.stats.stamina);
+        let tree = match npc.test_behavior.tree_mut() {
+            Some(tree) => tree,
+            None => return Vec::new(),
+        };
+
+        let script_path = match script_path {
+            Some(script_path) if self.has_hook(script_path, &request.function) => script_path,
+            Some(script_path) => {
+                if self.ast_cache.contains_key(script_path) {
+                    let mut error = ScriptError::new(
+                        ScriptErrorKind::Runtime,
+                        script_path,
+                        format!("behavior tree function {} not found", request.function),
+                        Position::NONE,
+                    );
+                    error.owner_id = Some(npc.id.clone());
+                    self.report(error);
+                }
+                tree.resolve(request.node, NodeStatus::Failure);
+                return Vec::new();
+            }
+            None => {
+                println!("Behavior tree leaf {} on {} has no script to call", request.function, npc.id);
+                tree.resolve(request.node, NodeStatus::Failure);
+                return Vec::new();
+            }
+        };
+        if self.budget_used {
+            return Vec::new();
+        }
+
+        let changes: ChangeLog = Rc::new(RefCell::new(Vec::new()));
+        let wrapper = NpcWrapper {
+            transform: ScriptTransform::new(&npc.id, position, rotation, scale, changes.clone()),
+            health,
+            stamina,
+        };
+        let task = BehaviorTaskWrapper {
+            blackboard: Rc::new(RefCell::new(tree.blackboard.clone())),
+            status: Rc::new(Cell::new(NodeStatus::Running)),
+            target_distance: tree.target_distance,
+        };
+
+        let result = self.call_hook(&npc.id, wrapper, changes, script_state, script_path, &request.function, Some(Dynamic::from(task.clone())));
+
+        let failed = self.failures.contains_key(&(script_path.to_string(), npc.id.clone())) || self.is_disabled(script_path, &npc.id);
+        tree.blackboard = task.blackboard.borrow().clone();
+        tree.resolve(request.node, if failed { NodeStatus::Failure } else { task.status.get() });
+
+        result
+    }
+
+    // Builds the wrapper for a component kind around its transform and calls the hook with it
+    fn call_wrapped_hook(
+        &mut self,
+        owner_id: &str,
+        fields: WrapperFields,
+        transform: ScriptTransform,
+        changes: ChangeLog,
+        script_state: &mut Option<ScriptState>,
+        script_path: &str,
+        hook_name: &str,
+        extra: Option<Dynamic>,
+    ) -> Vec<ComponentChanges> {
+        match fields {
+            WrapperFields::Model => self.call_hook(owner_id, ModelWrapper { transform }, changes, script_state, script_path, hook_name, extra),
+            WrapperFields::NPC { health, stamina } => {
+                let wrapper = NpcWrapper { transform, health, stamina };
+                self.call_hook(owner_id, wrapper, changes, script_state, script_path, hook_name, extra)
+            }
+            WrapperFields::Collectable { collectable_type } => {
+                let wrapper = CollectableWrapper { transform, collectable_type };
+                self.call_hook(owner_id, wrapper, changes, script_state, script_path, hook_name, extra)
+            }
+            WrapperFields::PointLight { intensity, color } => {
+                let wrapper = LightWrapper { transform, intensity, color };
+                self.call_hook(owner_id, wrapper, changes, script_state, script_path, hook_name, extra)
+            }
+            WrapperFields::WaterPlane { config } => {
+                let wrapper = WaterWrapper { transform, config };
+                self.call_hook(owner_id, wrapper, changes, script_state, script_path, hook_name, extra)
+            }
+        }
+    }
+
+    // Runs a hook against a Model component without touching RendererState, so it can be used headless
+    pub fn execute_model_hook(
+        &mut self,
+        component_id: &str,
+        position: Vector3<f32>,
+        rotation: Vector3<f32>, // euler angles in degrees
+        scale: Vector3<f32>,
+        script_state: &mut Option<ScriptState>,
+        script_path: &str,
+        hook_name: &str,
+        extra: Option<Dynamic>, // the other component's id for collision hooks, a timer's args
+    ) -> Vec<ComponentChanges> {
+        self.execute_wrapped_hook(component_id, WrapperFields::Model, position, rotation, scale, script_state, script_path, hook_name, extra)
+    }
+
+    // A hook on any kind of component, for hosts that keep their components outside a RendererState
+    pub fn execute_wrapped_hook(
+        &mut self,
+        component_id: &str,
+        fields: WrapperFields,
+        position: Vector3<f32>,
+        rotation: Vector3<f32>, // euler angles in degrees
+        scale: Vector3<f32>,
+        script_state: &mut Option<ScriptState>,
+        script_path: &str,
+        hook_name: &str,
+        extra: Option<Dynamic>,
+    ) -> Vec<ComponentChanges> {
+        let changes: ChangeLog = Rc::new(RefCell::new(Vec::new()));
+        let transform = ScriptTransform::new(component_id, position, rotation, scale, changes.clone());
+
+        self.call_wrapped_hook(component_id, fields, transform, changes, script_state, script_path, hook_name, extra)
+    }
+
+    pub fn execute_player_hook(
+        &mut self,
+        wrapper: PlayerWrapper,
+        script_state: &mut Option<ScriptState>,
+        script_path: &str,
+        hook_name: &str,
+        extra: Option<Dynamic>,
+    ) -> Vec<ComponentChanges> {
+        let owner_id = wrapper.id.clone();
+        let changes: ChangeLog = Rc::new(RefCell::new(Vec::new()));
+
+        self.call_hook(&owner_id, wrapper, changes, script_state, script_path, hook_name, extra)
     }
     
+    // Runs a hook of a level-wide script, called as (game, system, script_state).
+    // System requests are filed under the script path.
+    pub fn execute_global_hook(
+        &mut self,
+        script_path: &str,
+        hook_name: &str,
+    ) -> Vec<ComponentChanges> {
+        self.call_global_hook(script_path, hook_name, None)
+    }
+
+    // Timers scheduled by a global script call back into it with the game wrapper
+    pub fn execute_global_timer(&mut self, timer: &ScriptTimer) -> Vec<ComponentChanges> {
+        let args = timer.args.as_ref().map(json_to_dynamic);
+        self.call_global_hook(&timer.script_path, &timer.function, args)
+    }
+
+    fn call_global_hook(
+        &mut self,
+        script_path: &str,
+        hook_name: &str,
+        extra: Option<Dynamic>,
+    ) -> Vec<ComponentChanges> {
+        let changes: ChangeLog = Rc::new(RefCell::new(Vec::new()));
+        let game = GameWrapper {
+            scene: self.scene.clone(),
+            changes: changes.clone(),
+        };
+        let mut script_state = self.global_states.get(script_path).cloned();
+
+        let result = self.call_hook(script_path, game, changes, &mut script_state, script_path, hook_name, extra);
+
+        if let Some(state) = script_state {
+            self.global_states.insert(script_path.to_string(), state);
+        }
+
+        result
+    }
+
+    // Calls hook_name(wrapper, system, script_state), plus the extra argument when given, and returns what the script asked for
+    fn call_hook<W: Clone + 'static>(
+        &mut self,
+        owner_id: &str,
+        wrapper: W,
+        changes: ChangeLog,
+        script_state: &mut Option<ScriptState>,
+        script_path: &str,
+        hook_name: &str,
+        extra: Option<Dynamic>,
+    ) -> Vec<ComponentChanges> {
+        if !self.has_hook(script_path, hook_name) || self.is_disabled(script_path, owner_id) || self.budget_used {
+            return Vec::new();
+        }
+        self.apply_limits(script_path);
+        let ast = match self.ast_cache.get(script_path) {
+            Some(ast) => ast,
+            None => return Vec::new(),
+        };
+
+        let mut scope = Scope::new();
+        let system = SystemWrapper::new(owner_id, script_path, self.scene.clone(), changes.clone(), self.timers.clone());
+        let rhai_script_state = to_rhai_state(script_state);
+
+        let started = Instant::now();
+        let result = match extra {
+            Some(extra) => self.engine.call_fn::<Dynamic>(&mut scope, ast, hook_name, (wrapper, system, rhai_script_state, extra)),
+            None => self.engine.call_fn::<Dynamic>(&mut scope, ast, hook_name, (wrapper, system, rhai_script_state)),
+        };
+
+        if self.deadline.get().map_or(false, |deadline| Instant::now() > deadline) {
+            self.report_budget(owner_id, script_path, hook_name, started.elapsed());
+        }
+
+        match result {
+            Ok(result) => {
+                from_rhai_state(result, script_state);
+                self.failures.remove(&(script_path.to_string(), owner_id.to_string()));
+            }
+            Err(e) => {
+                // stopped for the budget, already reported above and not held against the script
+                if !matches!(e.unwrap_inner(), EvalAltResult::ErrorTerminated(..)) {
+                    self.report_failure(owner_id, script_path, hook_name, &e);
+                }
+                changes.borrow_mut().clear();
+                return Vec::new();
+            }
+        }
+
+        let result = std::mem::take(&mut *changes.borrow_mut());
+        result
+    }
+
+    // Pops the next due timer without holding the borrow, the call it makes may schedule or cancel more
+    pub fn next_timer(&mut self) -> Option<ScriptTimer> {
+        self.timers.borrow_mut().next_due()
+    }
+
+    // The scene lookups and raycasts see until the next call, usually captured once per frame
+    pub fn set_scene(&mut self, scene: ScriptScene) {
+        self.scene = Rc::new(scene);
+    }
+
     pub fn execute_interaction_script(
         &mut self,
         renderer_state: &mut RendererState,
@@ -505,19 +1922,32 @@ impl RhaiEngine {
         hook_name: &str,
     ) {
         // Set NPC is_talking to true
-        if let Some(npc) = renderer_state.npcs.iter_mut().find(|n| n.model_id == dialogue_state.current_npc_id) {
+        if let Some(npc) = renderer_state.world.npcs.iter_mut().find(|n| n.model_id == dialogue_state.current_npc_id) {
              npc.is_talking = true;
         }
 
-        let ast = if let Some(ast) = self.ast_cache.get(script_path) {
-            ast
-        } else {
-            if self.load_script(script_path).is_err() {
-                eprintln!("Failed to load Rhai script: {}", script_path);
-                return;
+        let changed = self.execute_dialogue_hook(dialogue_state, script_path, hook_name);
+
+        if changed && !dialogue_state.is_open {
+            if let Some(npc) = renderer_state.world.npcs.iter_mut().find(|n| n.model_id == dialogue_state.current_npc_id) {
+                npc.is_talking = false;
             }
-            self.ast_cache.get(script_path).unwrap()
-        };
+        }
+    }
+
+    // Calls hook_name(dialogue) and copies what the script showed back into dialogue_state.
+    // Returns whether the script changed anything.
+    pub fn execute_dialogue_hook(
+        &mut self,
+        dialogue_state: &mut DialogueState,
+        script_path: &str,
+        hook_name: &str,
+    ) -> bool {
+        if !self.ensure_loaded(script_path) {
+            return false;
+        }
+        self.apply_limits(script_path);
+        let ast = self.ast_cache.get(script_path).unwrap();
 
         let wrapper = DialogueWrapper {
             text: dialogue_state.current_text.clone(),
@@ -526,7 +1956,10 @@ impl RhaiEngine {
             is_open: dialogue_state.is_open,
             npc_name: dialogue_state.npc_name.clone(),
             current_node: dialogue_state.current_node.clone(),
+            npc_id: dialogue_state.current_npc_id.clone(),
+            blackboard: self.blackboard.clone(),
         };
+        let visited_node = dialogue_state.current_node.clone();
 
         let mut scope = Scope::new();
         
@@ -541,26 +1974,36 @@ impl RhaiEngine {
                     dialogue_state.current_node = updated_wrapper.current_node;
                     dialogue_state.selected_option_index = 0;
                     dialogue_state.ui_dirty = true;
-                    
-                    if !dialogue_state.is_open {
-                        if let Some(npc) = renderer_state.npcs.iter_mut().find(|n| n.model_id == dialogue_state.current_npc_id) {
-                            npc.is_talking = false;
-                        }
-                    }
                 }
+                self.blackboard.borrow_mut().visit(&dialogue_state.current_npc_id, &visited_node);
+                updated_wrapper.changed
             },
             Err(e) => {
-                // if !matches!(*e, rhai::EvalAltResult::ErrorFunctionNotFound(_, _)) {
-                    eprintln!("Error executing hook '{}': {:?}", hook_name, e);
-                // }
+                let mut error = ScriptError::from_eval(ScriptErrorKind::Runtime, script_path, &e);
+                error.owner_id = Some(dialogue_state.current_npc_id.clone());
+                error.hook = Some(hook_name.to_string());
+                self.report(error);
+                false
             }
         }
     }
 }
 
 // Simple struct to track what changed
+#[derive(Default, Debug)]
 pub struct ComponentChanges {
     pub component_id: String,
     pub new_position: Option<Vector3<f32>>,
+    pub new_rotation: Option<Vector3<f32>>, // euler angles in degrees
+    pub new_scale: Option<Vector3<f32>>,
+    pub impulse: Option<Vector3<f32>>,
+    pub light_intensity: Option<f32>,
+    pub light_color: Option<[f32; 3]>,
+    pub water_config: Option<WaterConfig>,
     pub particle_spawns: Option<Vec<ScriptParticleConfig>>,
-}
\ No newline at end of file
+    pub spawn_from: Option<String>, // prefab or model asset id, filed under the new component's id
+    pub destroy: bool,
+    pub load_level: Option<String>,
+    pub save_slot: Option<String>,
+    pub load_slot: Option<String>,
+}
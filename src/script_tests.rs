@@ -0,0 +1,582 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use nalgebra::Vector3;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::game_behaviors::blackboard::Blackboard;
+use crate::game_behaviors::dialogue_state::DialogueState;
+use crate::game_behaviors::quests::{QuestLog, QuestProgress};
+use crate::helpers::project_error::ProjectError;
+use crate::helpers::saved_data::{QuestData, ScriptState};
+use crate::rhai_engine::{
+    json_to_dynamic, ComponentChanges, PlayerWrapper, RhaiEngine, SceneComponent, ScriptScene,
+};
+
+// A fixture for the script_test binary: one script, the scene it can look around in and the
+// cases to run it through. Script paths are relative to the working directory, like a project's.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScriptSuite {
+    #[serde(default)]
+    pub name: String, // the fixture's file name when empty
+    pub script: String,
+    #[serde(default)]
+    pub level_id: String,
+    #[serde(default)]
+    pub components: Vec<MockComponent>, // what system lookups and the game wrapper can find
+    #[serde(default)]
+    pub quests: Vec<QuestData>,
+    pub cases: Vec<ScriptCase>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MockComponent {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    #[serde(default)]
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub rotation: [f32; 3], // euler angles in degrees
+    #[serde(default = "default_scale")]
+    pub scale: [f32; 3],
+}
+
+// Each case starts from a fresh engine, quest log and script_state
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScriptCase {
+    pub name: String,
+    pub wrapper: MockWrapper,
+    #[serde(default)]
+    pub state: Option<ScriptState>,
+    #[serde(default)]
+    pub quest_progress: Vec<QuestProgress>, // where the player is in the suite's quests when the case starts
+    #[serde(default)]
+    pub blackboard: Blackboard, // dialogue variables and NPC memory from earlier conversations
+    pub steps: Vec<ScriptStep>,
+}
+
+// The first argument the hooks get
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MockWrapper {
+    Player {
+        #[serde(default = "default_player_id")]
+        id: String,
+        #[serde(default)]
+        weapon_id: String,
+        #[serde(default)]
+        weapon_name: String,
+        #[serde(default)]
+        position: [f32; 3],
+    },
+    Model {
+        id: String,
+        #[serde(default)]
+        position: [f32; 3],
+        #[serde(default)]
+        rotation: [f32; 3],
+        #[serde(default = "default_scale")]
+        scale: [f32; 3],
+    },
+    // hooks are called as hook(dialogue), like interaction scripts
+    Dialogue {
+        #[serde(default)]
+        npc_id: String,
+        #[serde(default)]
+        npc_name: String,
+        #[serde(default)]
+        node: String,
+    },
+}
+
+// Calls `hook` once a frame for `frames` frames, running any timers that come due first
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScriptStep {
+    pub hook: String,
+    #[serde(default = "default_frames")]
+    pub frames: u32,
+    #[serde(default = "default_dt")]
+    pub dt: f32,
+    #[serde(default)]
+    pub args: Option<Value>, // passed after script_state
+    #[serde(default)]
+    pub choose: Option<usize>, // dialogue only, picks this option before calling the hook
+    #[serde(default)]
+    pub weapon_name: Option<String>, // player only, equips a weapon before the step
+    #[serde(default)]
+    pub expect: Expectation,
+}
+
+// Checked after the step's last frame. Particles are counted over the whole step.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Expectation {
+    #[serde(default)]
+    pub state: Option<serde_json::Map<String, Value>>, // keys not listed aren't checked
+    #[serde(default)]
+    pub missing: Vec<String>, // state keys that shouldn't be set
+    #[serde(default)]
+    pub particles: Option<usize>,
+    #[serde(default)]
+    pub position: Option<[f32; 3]>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub options: Option<Vec<String>>,
+    #[serde(default)]
+    pub node: Option<String>,
+    #[serde(default)]
+    pub open: Option<bool>,
+    #[serde(default)]
+    pub active_quests: Vec<String>,
+    #[serde(default)]
+    pub vars: Option<serde_json::Map<String, Value>>, // the dialogue NPC's blackboard variables
+    #[serde(default)]
+    pub globals: Option<serde_json::Map<String, Value>>,
+    #[serde(default)]
+    pub error: Option<String>, // the step should report an error containing this, any other error fails it
+}
+
+fn default_kind() -> String {
+    "Model".to_string()
+}
+
+fn default_scale() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_player_id() -> String {
+    "player".to_string()
+}
+
+fn default_frames() -> u32 {
+    1
+}
+
+fn default_dt() -> f32 {
+    1.0 / 60.0
+}
+
+fn vector(v: [f32; 3]) -> Vector3<f32> {
+    Vector3::new(v[0], v[1], v[2])
+}
+
+#[derive(Clone, Debug)]
+pub struct CaseResult {
+    pub suite: String,
+    pub name: String,
+    pub failures: Vec<String>,
+    pub time: Duration,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+pub fn load_suite(path: &Path) -> Result<ScriptSuite, ProjectError> {
+    let path_label = path.to_string_lossy().to_string();
+    let content = fs::read_to_string(path).map_err(|e| ProjectError::MissingFile {
+        path: path_label.clone(),
+        reason: e.to_string(),
+    })?;
+
+    let mut suite: ScriptSuite = serde_json::from_str(&content).map_err(|e| ProjectError::Parse {
+        path: path_label.clone(),
+        message: e.to_string(),
+        line: e.line(),
+        column: e.column(),
+    })?;
+
+    if suite.name.is_empty() {
+        suite.name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or(path_label);
+    }
+    Ok(suite)
+}
+
+// A fixture file as is, or every .json file in a directory, sorted
+pub fn fixture_paths(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(path)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    paths.retain(|p| p.extension().map_or(false, |e| e == "json"));
+    paths.sort();
+    paths
+}
+
+pub fn run_suite(suite: &ScriptSuite) -> Vec<CaseResult> {
+    suite
+        .cases
+        .iter()
+        .map(|case| {
+            let started = Instant::now();
+            let failures = CaseRun::new(suite, case).run(&case.steps);
+            CaseResult {
+                suite: suite.name.clone(),
+                name: case.name.clone(),
+                failures,
+                time: started.elapsed(),
+            }
+        })
+        .collect()
+}
+
+// One case in progress: the engine, the mock wrapper as the script left it and its state
+struct CaseRun<'a> {
+    suite: &'a ScriptSuite,
+    engine: RhaiEngine,
+    quests: Rc<RefCell<QuestLog>>,
+    wrapper: MockWrapper,
+    state: Option<ScriptState>,
+    dialogue: DialogueState,
+    particles: usize,
+}
+
+impl<'a> CaseRun<'a> {
+    fn new(suite: &'a ScriptSuite, case: &ScriptCase) -> Self {
+        let mut quest_log = QuestLog::new(suite.quests.clone());
+        quest_log.progress = case.quest_progress.clone();
+        let quests = Rc::new(RefCell::new(quest_log));
+
+        let mut engine = RhaiEngine::new();
+        engine.bind_quests(quests.clone());
+        *engine.blackboard.borrow_mut() = case.blackboard.clone();
+
+        let mut dialogue = DialogueState::default();
+        if let MockWrapper::Dialogue { npc_id, npc_name, node } = &case.wrapper {
+            dialogue.current_npc_id = npc_id.clone();
+            dialogue.npc_name = npc_name.clone();
+            dialogue.current_node = node.clone();
+        }
+
+        CaseRun {
+            suite,
+            engine,
+            quests,
+            wrapper: case.wrapper.clone(),
+            state: case.state.clone(),
+            dialogue,
+            particles: 0,
+        }
+    }
+
+    // Stops at the first step that fails, what comes after it would only fail along with it
+    fn run(&mut self, steps: &[ScriptStep]) -> Vec<String> {
+        for (index, step) in steps.iter().enumerate() {
+            let failures = self.step(step);
+            if !failures.is_empty() {
+                return failures
+                    .into_iter()
+                    .map(|f| format!("step {} ({}): {}", index + 1, step.hook, f))
+                    .collect();
+            }
+        }
+        Vec::new()
+    }
+
+    fn step(&mut self, step: &ScriptStep) -> Vec<String> {
+        let mut failures = Vec::new();
+        self.engine.errors.clear();
+        self.particles = 0;
+
+        if let (Some(name), MockWrapper::Player { weapon_name, .. }) = (&step.weapon_name, &mut self.wrapper) {
+            *weapon_name = name.clone();
+        }
+
+        if !self.engine.has_hook(&self.suite.script, &step.hook) && self.engine.errors.is_empty() {
+            return vec![format!("{} has no function {}", self.suite.script, step.hook)];
+        }
+
+        for _ in 0..step.frames {
+            if let MockWrapper::Dialogue { .. } = self.wrapper {
+                if let Some(choice) = step.choose {
+                    match self.dialogue.options.get(choice) {
+                        Some(option) => self.dialogue.current_node = option.next_node.clone(),
+                        None => {
+                            failures.push(format!("there is no option {}, {} are showing", choice, self.dialogue.options.len()));
+                            return failures;
+                        }
+                    }
+                }
+                self.engine.execute_dialogue_hook(&mut self.dialogue, &self.suite.script, &step.hook);
+                continue;
+            }
+
+            self.engine.set_scene(self.scene());
+            self.engine.timers.borrow_mut().advance(step.dt);
+            while let Some(timer) = self.engine.next_timer() {
+                let args = timer.args.as_ref().map(json_to_dynamic);
+                self.call(&timer.function, args);
+            }
+
+            let args = step.args.as_ref().map(json_to_dynamic);
+            self.call(&step.hook, args);
+        }
+
+        self.check(&step.expect, &mut failures);
+        failures
+    }
+
+    fn scene(&self) -> ScriptScene {
+        let components = self
+            .suite
+            .components
+            .iter()
+            .map(|c| SceneComponent {
+                id: c.id.clone(),
+                name: c.name.clone(),
+                kind: c.kind.clone(),
+                position: vector(c.position),
+                rotation: vector(c.rotation),
+                scale: vector(c.scale),
+            })
+            .collect();
+
+        let mut scene = ScriptScene::new(&self.suite.level_id, components);
+        if let MockWrapper::Player { position, .. } = &self.wrapper {
+            scene.player_position = Some(vector(*position));
+        }
+        scene
+    }
+
+    fn call(&mut self, hook_name: &str, extra: Option<rhai::Dynamic>) {
+        let script_path = self.suite.script.clone();
+
+        let changes = match &self.wrapper {
+            MockWrapper::Player { id, weapon_id, weapon_name, position } => {
+                let wrapper = PlayerWrapper {
+                    id: id.clone(),
+                    equipped_weapon_id: weapon_id.clone(),
+                    equipped_weapon_name: weapon_name.clone(),
+                    position: vector(*position),
+                };
+                self.engine.execute_player_hook(wrapper, &mut self.state, &script_path, hook_name, extra)
+            }
+            MockWrapper::Model { id, position, rotation, scale } => self.engine.execute_model_hook(
+                id,
+                vector(*position),
+                vector(*rotation),
+                vector(*scale),
+                &mut self.state,
+                &script_path,
+                hook_name,
+                extra,
+            ),
+            MockWrapper::Dialogue { .. } => Vec::new(),
+        };
+
+        for change in changes {
+            self.apply(change);
+        }
+    }
+
+    // Only what a mock can show: the wrapper's own transform and particles spawned
+    fn apply(&mut self, change: ComponentChanges) {
+        self.particles += change.particle_spawns.map_or(0, |spawns| spawns.len());
+
+        if let MockWrapper::Model { id, position, rotation, scale } = &mut self.wrapper {
+            if *id == change.component_id {
+                if let Some(p) = change.new_position {
+                    *position = [p.x, p.y, p.z];
+                }
+                if let Some(r) = change.new_rotation {
+                    *rotation = [r.x, r.y, r.z];
+                }
+                if let Some(s) = change.new_scale {
+                    *scale = [s.x, s.y, s.z];
+                }
+            }
+        }
+    }
+
+    fn check(&self, expect: &Expectation, failures: &mut Vec<String>) {
+        match &expect.error {
+            Some(expected) => {
+                if !self.engine.errors.iter().any(|e| e.to_string().contains(expected.as_str())) {
+                    failures.push(format!("expected an error containing {:?}", expected));
+                }
+            }
+            None => failures.extend(self.engine.errors.iter().map(|e| format!("script error: {}", e))),
+        }
+
+        if let Some(expected) = &expect.state {
+            check_values("state", expected, &self.state.clone().unwrap_or_default(), failures);
+        }
+
+        let blackboard = self.engine.blackboard.borrow();
+        if let Some(expected) = &expect.vars {
+            let vars = blackboard.npcs.get(&self.dialogue.current_npc_id).map(|m| m.vars.clone()).unwrap_or_default();
+            check_values("vars", expected, &vars, failures);
+        }
+        if let Some(expected) = &expect.globals {
+            check_values("globals", expected, &blackboard.globals, failures);
+        }
+        drop(blackboard);
+
+        for key in &expect.missing {
+            if let Some(found) = self.state.as_ref().and_then(|s| s.get(key)) {
+                failures.push(format!("state[{:?}] is {}, expected it to be unset", key, found));
+            }
+        }
+
+        if let Some(expected) = expect.particles {
+            if self.particles != expected {
+                failures.push(format!("{} particle spawns, expected {}", self.particles, expected));
+            }
+        }
+
+        if let Some(expected) = expect.position {
+            let actual = match &self.wrapper {
+                MockWrapper::Player { position, .. } | MockWrapper::Model { position, .. } => Some(*position),
+                MockWrapper::Dialogue { .. } => None,
+            };
+            match actual {
+                Some(actual) if (vector(actual) - vector(expected)).norm() <= 1e-4 => {}
+                Some(actual) => failures.push(format!("position is {:?}, expected {:?}", actual, expected)),
+                None => failures.push("dialogue has no position".to_string()),
+            }
+        }
+
+        if let Some(expected) = &expect.text {
+            if &self.dialogue.current_text != expected {
+                failures.push(format!("dialogue text is {:?}, expected {:?}", self.dialogue.current_text, expected));
+            }
+        }
+
+        if let Some(expected) = &expect.options {
+            let actual: Vec<&String> = self.dialogue.options.iter().map(|o| &o.text).collect();
+            if actual.len() != expected.len() || actual.iter().zip(expected).any(|(a, e)| *a != e) {
+                failures.push(format!("dialogue options are {:?}, expected {:?}", actual, expected));
+            }
+        }
+
+        if let Some(expected) = &expect.node {
+            if &self.dialogue.current_node != expected {
+                failures.push(format!("dialogue node is {:?}, expected {:?}", self.dialogue.current_node, expected));
+            }
+        }
+
+        if let Some(expected) = expect.open {
+            if self.dialogue.is_open != expected {
+                failures.push(format!("dialogue is_open is {}, expected {}", self.dialogue.is_open, expected));
+            }
+        }
+
+        for quest_id in &expect.active_quests {
+            if !self.quests.borrow().is_active(quest_id) {
+                failures.push(format!("quest {} isn't active", quest_id));
+            }
+        }
+    }
+}
+
+// Scripts run on f32, so numbers only have to be close. Objects only check the keys expected.
+// Keys not listed aren't checked
+fn check_values(label: &str, expected: &serde_json::Map<String, Value>, actual: &ScriptState, failures: &mut Vec<String>) {
+    for (key, value) in expected {
+        match actual.get(key) {
+            Some(found) if values_match(value, found) => {}
+            Some(found) => failures.push(format!("{}[{:?}] is {}, expected {}", label, key, found, value)),
+            None => failures.push(format!("{}[{:?}] isn't set, expected {}", label, key, value)),
+        }
+    }
+}
+
+fn values_match(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Number(e), Value::Number(a)) => match (e.as_f64(), a.as_f64()) {
+            (Some(e), Some(a)) => (e - a).abs() <= 1e-4 * e.abs().max(1.0),
+            _ => false,
+        },
+        (Value::Array(e), Value::Array(a)) => e.len() == a.len() && e.iter().zip(a).all(|(e, a)| values_match(e, a)),
+        (Value::Object(e), Value::Object(a)) => e.iter().all(|(k, v)| a.get(k).map_or(false, |a| values_match(v, a))),
+        _ => expected == actual,
+    }
+}
+
+// TAP version 13, failures in each test's YAML block
+pub fn to_tap(results: &[CaseResult]) -> String {
+    let mut out = format!("TAP version 13\n1..{}\n", results.len());
+
+    for (index, result) in results.iter().enumerate() {
+        let status = if result.passed() { "ok" } else { "not ok" };
+        out.push_str(&format!("{} {} - {}: {}\n", status, index + 1, result.suite, result.name));
+
+        if !result.passed() {
+            out.push_str("  ---\n  failures:\n");
+            for failure in &result.failures {
+                out.push_str(&format!("    - {}\n", serde_json::to_string(failure).unwrap_or_default()));
+            }
+            out.push_str("  ...\n");
+        }
+    }
+    out
+}
+
+// One <testsuite> per fixture, in the order they ran
+pub fn to_junit(results: &[CaseResult]) -> String {
+    let mut suites: Vec<(&str, Vec<&CaseResult>)> = Vec::new();
+    for result in results {
+        match suites.iter_mut().find(|(name, _)| *name == result.suite) {
+            Some((_, cases)) => cases.push(result),
+            None => suites.push((result.suite.as_str(), vec![result])),
+        }
+    }
+
+    let failed = results.iter().filter(|r| !r.passed()).count();
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!("<testsuites tests=\"{}\" failures=\"{}\">\n", results.len(), failed));
+
+    for (name, cases) in suites {
+        let time: Duration = cases.iter().map(|c| c.time).sum();
+        out.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+            xml_escape(name),
+            cases.len(),
+            cases.iter().filter(|c| !c.passed()).count(),
+            time.as_secs_f32()
+        ));
+
+        for case in cases {
+            out.push_str(&format!(
+                "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+                xml_escape(name),
+                xml_escape(&case.name),
+                case.time.as_secs_f32()
+            ));
+            if case.passed() {
+                out.push_str("/>\n");
+                continue;
+            }
+            out.push_str(">\n");
+            out.push_str(&format!(
+                "      <failure message=\"{}\">{}</failure>\n",
+                xml_escape(&case.failures[0]),
+                xml_escape(&case.failures.join("\n"))
+            ));
+            out.push_str("    </testcase>\n");
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>\n");
+    out
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -32,6 +32,7 @@ use crate::core::gpu_resources::{self, GpuResources};
 use crate::handlers::{EntropyElementState, EntropyMouseButton, EntropyPosition, EntropySize, handle_add_water_plane, handle_key_press, handle_mouse_move, handle_mouse_move_on_shift};
 use crate::core::pipeline::{ExportPipeline};
 use crate::helpers::load_project::load_project;
+use crate::helpers::pak;
 use crate::core::editor::WindowSize;
 use wgpu; // For wgpu::SurfaceConfiguration
 use pollster; // For pollster::block_on
@@ -48,10 +49,16 @@ use winit::platform::x11::WindowAttributesExtX11;
 /// The amount of points to around the window for drag resize direction calculations.
 const BORDER_SIZE: f64 = 20.;
 
-pub fn run_game(project_id: Option<String>) -> Result<(), Box<dyn Error>> {
+// `project` is a project id or a path to a .pak built by the pack command
+pub fn run_game(project: Option<String>) -> Result<(), Box<dyn Error>> {
     #[cfg(web_platform)]
     console_error_panic_hook::set_once();
 
+    let project_id = match project {
+        Some(target) => Some(pak::resolve_project(&target)?),
+        None => None,
+    };
+
     // tracing::init();
 
     let event_loop = EventLoop::<UserEvent>::with_user_event().build()?;
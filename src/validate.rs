@@ -0,0 +1,547 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::helpers::migrations::{MigrationReport, CURRENT_SCHEMA_VERSION};
+use crate::helpers::project_error::{component_errors, ProjectError};
+use crate::game_behaviors::dialogue_graph::{dialogue_issues, parse_dialogue};
+use crate::game_behaviors::quests::matches_item;
+use crate::helpers::saved_data::{ComponentData, ComponentKind, DialogueAction, DialogueCondition, File, ObjectiveKind, SavedState};
+use crate::helpers::utilities;
+use crate::rhai_engine::RhaiEngine;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    pub check: &'static str,
+    pub level_id: Option<String>,
+    pub component_id: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ValidationReport {
+    pub project: String,
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues.iter().filter(|i| i.severity == severity).count()
+    }
+
+    // 0 when clean, 1 for warnings only, 2 when anything is an error
+    pub fn exit_code(&self) -> i32 {
+        match self.issues.iter().map(|i| i.severity).max() {
+            Some(Severity::Error) => 2,
+            Some(Severity::Warning) => 1,
+            None => 0,
+        }
+    }
+
+    pub fn print(&self) {
+        println!("Validating {}", self.project);
+
+        for issue in &self.issues {
+            let severity = match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            let location = match (&issue.level_id, &issue.component_id) {
+                (Some(level), Some(component)) => format!(" [level {} / component {}]", level, component),
+                (Some(level), None) => format!(" [level {}]", level),
+                _ => String::new(),
+            };
+            println!("{}[{}]{}: {}", severity, issue.check, location, issue.message);
+        }
+
+        println!(
+            "{} errors, {} warnings",
+            self.count(Severity::Error),
+            self.count(Severity::Warning)
+        );
+    }
+
+    fn push(&mut self, severity: Severity, check: &'static str, level_id: Option<&str>, component_id: Option<&str>, message: String) {
+        self.issues.push(Issue {
+            severity,
+            check,
+            level_id: level_id.map(String::from),
+            component_id: component_id.map(String::from),
+            message,
+        });
+    }
+}
+
+pub struct ValidateOptions {
+    // holds midpoint.json, models/ and textures/
+    pub project_dir: PathBuf,
+    // rhai_script_path values are relative to this, same as the engine's working directory
+    pub script_root: PathBuf,
+}
+
+// Accepts a project id, a project directory, or a path to a midpoint.json.
+// Reads without writing, so older projects are migrated in memory only.
+pub fn load_for_validation(target: &str) -> Result<(SavedState, PathBuf, MigrationReport), ProjectError> {
+    let json_path = utilities::find_project_json(target);
+    let path_label = json_path.to_string_lossy().to_string();
+
+    let json_content = fs::read_to_string(&json_path).map_err(|e| ProjectError::MissingFile {
+        path: path_label.clone(),
+        reason: e.to_string(),
+    })?;
+    let (saved_state, report) = utilities::parse_project_state(&json_content, &path_label)?;
+
+    let project_dir = json_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    Ok((saved_state, project_dir, report))
+}
+
+pub fn validate_project(saved_state: &SavedState, options: &ValidateOptions) -> ValidationReport {
+    let mut report = ValidationReport {
+        project: saved_state.id.clone().unwrap_or_else(|| saved_state.project_name.clone()),
+        issues: Vec::new(),
+    };
+
+    check_duplicate_ids(saved_state, &mut report);
+    check_components(saved_state, &mut report);
+    check_quests(saved_state, &mut report);
+    check_dialogues(saved_state, options, &mut report);
+    check_behavior_trees(saved_state, &mut report);
+    check_npc_inventories(saved_state, &mut report);
+    check_scripts(saved_state, options, &mut report);
+    check_model_files(saved_state, options, &mut report);
+    check_pbr_textures(saved_state, options, &mut report);
+
+    report
+}
+
+fn check_duplicate_ids(saved_state: &SavedState, report: &mut ValidationReport) {
+    let files = |files: Option<&Vec<File>>| files.map(|f| f.iter().map(|f| f.id.clone()).collect::<Vec<_>>()).unwrap_or_default();
+
+    let groups: Vec<(&str, Vec<String>)> = vec![
+        ("model", files(Some(&saved_state.models))),
+        ("concept", files(Some(&saved_state.concepts))),
+        ("texture", files(saved_state.textures.as_ref())),
+        (
+            "landscape",
+            saved_state.landscapes.iter().flatten().map(|l| l.id.clone()).collect(),
+        ),
+        (
+            "pbr texture",
+            saved_state.pbr_textures.iter().flatten().map(|p| p.id.clone()).collect(),
+        ),
+        ("stat", saved_state.stats.iter().flatten().map(|s| s.id.clone()).collect()),
+        ("level", saved_state.levels.iter().flatten().map(|l| l.id.clone()).collect()),
+        ("prefab", saved_state.prefabs.iter().flatten().map(|p| p.id.clone()).collect()),
+        ("quest", saved_state.quests.iter().flatten().map(|q| q.id.clone()).collect()),
+        // component ids are looked up across levels, so they have to be unique project-wide
+        (
+            "component",
+            saved_state
+                .levels
+                .iter()
+                .flatten()
+                .filter_map(|l| l.components.as_ref())
+                .flatten()
+                .map(|c| c.id.clone())
+                .collect(),
+        ),
+    ];
+
+    for (label, ids) in groups {
+        let mut seen = HashSet::new();
+        let mut reported = HashSet::new();
+
+        for id in ids {
+            if !seen.insert(id.clone()) && reported.insert(id.clone()) {
+                report.push(Severity::Error, "duplicate_id", None, None, format!("Duplicate {} id {}", label, id));
+            }
+        }
+    }
+}
+
+fn check_components(saved_state: &SavedState, report: &mut ValidationReport) {
+    for level in saved_state.levels.iter().flatten() {
+        let components = match &level.components {
+            Some(components) => components,
+            None => continue,
+        };
+
+        for component in components {
+            for error in component_errors(saved_state, components, component) {
+                report.push(Severity::Error, "reference", Some(&level.id), Some(&component.id), error.to_string());
+            }
+
+            if component.kind == Some(ComponentKind::NPC) {
+                check_npc_model(saved_state, components, component, &level.id, report);
+            }
+        }
+    }
+}
+
+// Objectives that point at something the project doesn't have can never complete
+fn check_quests(saved_state: &SavedState, report: &mut ValidationReport) {
+    let components: Vec<&ComponentData> = saved_state
+        .levels
+        .iter()
+        .flatten()
+        .filter_map(|l| l.components.as_ref())
+        .flatten()
+        .collect();
+    let component_of_kind = |id: &str, kind: ComponentKind| components.iter().any(|c| c.id == id && c.kind == Some(kind.clone()));
+
+    for quest in saved_state.quests.iter().flatten() {
+        if quest.stages.is_empty() {
+            report.push(Severity::Warning, "quest", None, None, format!("Quest {} has no stages", quest.id));
+        }
+
+        for objective in quest.stages.iter().flat_map(|s| s.objectives.iter()) {
+            let problem = match &objective.kind {
+                ObjectiveKind::Collect { item_id, .. } => {
+                    let matches = components
+                        .iter()
+                        .any(|c| c.kind == Some(ComponentKind::Collectable) && matches_item(c, item_id));
+                    (!matches).then(|| format!("collects {}, which no collectable matches", item_id))
+                }
+                ObjectiveKind::Kill { npc_id } | ObjectiveKind::Talk { npc_id, .. } => {
+                    (!component_of_kind(npc_id, ComponentKind::NPC)).then(|| format!("needs NPC {}, which doesn't exist", npc_id))
+                }
+                ObjectiveKind::Reach { component_id, .. } => {
+                    (!components.iter().any(|c| &c.id == component_id)).then(|| format!("needs component {}, which doesn't exist", component_id))
+                }
+            };
+
+            if let Some(problem) = problem {
+                report.push(
+                    Severity::Error,
+                    "quest",
+                    None,
+                    None,
+                    format!("Quest {} objective {} {}", quest.id, objective.id, problem),
+                );
+            }
+        }
+    }
+}
+
+// Broken links inside graphs, and dialogue that needs quests, items or graphs the project doesn't have
+fn check_dialogues(saved_state: &SavedState, options: &ValidateOptions, report: &mut ValidationReport) {
+    let mut dialogues = saved_state.dialogues.clone().unwrap_or_default();
+    for path in saved_state.dialogue_files.iter().flatten() {
+        let loaded = fs::read_to_string(options.project_dir.join(path))
+            .map_err(|e| ProjectError::MissingFile { path: path.clone(), reason: e.to_string() })
+            .and_then(|json| parse_dialogue(&json, path));
+
+        match loaded {
+            Ok(dialogue) => dialogues.push(dialogue),
+            Err(e) => report.push(Severity::Error, "dialogue_file", None, None, e.to_string()),
+        }
+    }
+
+    let components: Vec<&ComponentData> = saved_state
+        .levels
+        .iter()
+        .flatten()
+        .filter_map(|l| l.components.as_ref())
+        .flatten()
+        .collect();
+    let quest_exists = |id: &str| saved_state.quests.iter().flatten().any(|q| q.id == id);
+    let item_exists = |id: &str| components.iter().any(|c| c.kind == Some(ComponentKind::Collectable) && matches_item(c, id));
+
+    let mut seen = HashSet::new();
+    for dialogue in &dialogues {
+        if !seen.insert(dialogue.id.clone()) {
+            report.push(Severity::Error, "duplicate_id", None, None, format!("Duplicate dialogue id {}", dialogue.id));
+        }
+
+        for issue in dialogue_issues(dialogue) {
+            let severity = if issue.is_error() { Severity::Error } else { Severity::Warning };
+            report.push(severity, "dialogue", None, None, format!("Dialogue {} {}", dialogue.id, issue));
+        }
+
+        for node in &dialogue.nodes {
+            let mut problems = Vec::new();
+
+            for action in node.actions.iter().chain(node.options.iter().flat_map(|o| o.actions.iter())) {
+                match action {
+                    DialogueAction::StartQuest { quest_id } | DialogueAction::AdvanceQuest { quest_id } if !quest_exists(quest_id) => {
+                        problems.push(format!("uses quest {}, which doesn't exist", quest_id));
+                    }
+                    DialogueAction::GiveItem { item_id } if !item_exists(item_id) => {
+                        problems.push(format!("gives {}, which no collectable matches", item_id));
+                    }
+                    _ => {}
+                }
+            }
+
+            for condition in node.options.iter().flat_map(|o| o.conditions.iter()) {
+                match condition {
+                    DialogueCondition::QuestNotStarted { quest_id }
+                    | DialogueCondition::QuestActive { quest_id }
+                    | DialogueCondition::QuestComplete { quest_id }
+                        if !quest_exists(quest_id) =>
+                    {
+                        problems.push(format!("checks quest {}, which doesn't exist", quest_id));
+                    }
+                    DialogueCondition::HasItem { item_id } if !item_exists(item_id) => {
+                        problems.push(format!("checks for {}, which no collectable matches", item_id));
+                    }
+                    _ => {}
+                }
+            }
+
+            for problem in problems {
+                report.push(Severity::Error, "dialogue", None, None, format!("Dialogue {} node {} {}", dialogue.id, node.id, problem));
+            }
+        }
+    }
+
+    for level in saved_state.levels.iter().flatten() {
+        for component in level.components.iter().flatten() {
+            let dialogue_id = match component.npc_properties.as_ref().and_then(|p| p.dialogue_id.as_ref()) {
+                Some(dialogue_id) => dialogue_id,
+                None => continue,
+            };
+
+            let message = match dialogues.iter().find(|d| &d.id == dialogue_id) {
+                None => Some(format!("NPC uses dialogue {}, which doesn't exist", dialogue_id)),
+                Some(dialogue) if component.rhai_script_path.is_none() && dialogue.nodes.iter().any(|n| n.script_hook.is_some()) => {
+                    Some(format!("Dialogue {} has script nodes but the NPC has no script", dialogue_id))
+                }
+                _ => None,
+            };
+
+            if let Some(message) = message {
+                report.push(Severity::Error, "dialogue", Some(&level.id), Some(&component.id), message);
+            }
+        }
+    }
+}
+
+fn check_behavior_trees(saved_state: &SavedState, report: &mut ValidationReport) {
+    for level in saved_state.levels.iter().flatten() {
+        for component in level.components.iter().flatten() {
+            let tree_id = match component.npc_properties.as_ref().and_then(|p| p.behavior_tree_id.as_ref()) {
+                Some(tree_id) => tree_id,
+                None => continue,
+            };
+
+            let message = match saved_state.behavior_tree(tree_id) {
+                None => Some(format!("NPC uses behavior tree {}, which doesn't exist", tree_id)),
+                Some(tree) if component.rhai_script_path.is_none() => {
+                    let mut leaves = Vec::new();
+                    tree.root.script_leaves(&mut leaves);
+                    leaves
+                        .iter()
+                        .find(|(_, script)| script.is_none())
+                        .map(|(function, _)| format!("Behavior tree {} calls {} but the NPC has no script", tree_id, function))
+                }
+                _ => None,
+            };
+
+            if let Some(message) = message {
+                report.push(Severity::Error, "behavior_tree", Some(&level.id), Some(&component.id), message);
+            }
+        }
+    }
+}
+
+fn check_npc_inventories(saved_state: &SavedState, report: &mut ValidationReport) {
+    for level in saved_state.levels.iter().flatten() {
+        let components = level.components.as_deref().unwrap_or(&[]);
+        for component in components {
+            let inventory = component.npc_properties.iter().flat_map(|p| p.inventory.iter());
+            for item_id in inventory {
+                let found = components
+                    .iter()
+                    .any(|c| c.kind == Some(ComponentKind::Collectable) && matches_item(c, item_id));
+                if !found {
+                    report.push(
+                        Severity::Error,
+                        "npc_inventory",
+                        Some(&level.id),
+                        Some(&component.id),
+                        format!("NPC carries {}, which no collectable in the level matches", item_id),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn check_npc_model(
+    saved_state: &SavedState,
+    level_components: &[ComponentData],
+    component: &ComponentData,
+    level_id: &str,
+    report: &mut ValidationReport,
+) {
+    let model_id = match &component.npc_properties {
+        Some(properties) => &properties.model_id,
+        None => {
+            report.push(
+                Severity::Warning,
+                "npc_model",
+                Some(level_id),
+                Some(&component.id),
+                "NPC has no npc_properties".to_string(),
+            );
+            return;
+        }
+    };
+
+    let matches_model = saved_state.models.iter().any(|m| &m.id == model_id)
+        || level_components
+            .iter()
+            .any(|c| &c.id == model_id && c.kind == Some(ComponentKind::Model));
+
+    if !matches_model {
+        report.push(
+            Severity::Error,
+            "npc_model",
+            Some(level_id),
+            Some(&component.id),
+            format!("NPC model_id {} matches no Model", model_id),
+        );
+    }
+}
+
+fn check_scripts(saved_state: &SavedState, options: &ValidateOptions, report: &mut ValidationReport) {
+    // compiled by the game's own engine, so eval, imports and expression depth are judged the same way
+    let mut engine = RhaiEngine::new();
+    engine.configure(&saved_state.script_settings);
+
+    // each script is checked once, against the first component that uses it
+    let mut scripts: Vec<(String, Option<String>, Option<String>)> = Vec::new();
+    let mut seen = HashSet::new();
+
+    for script in saved_state.global_rhai_scripts.iter().flatten() {
+        if seen.insert(script.clone()) {
+            scripts.push((script.clone(), None, None));
+        }
+    }
+    for tree in saved_state.behavior_trees.iter().flatten() {
+        let mut leaves = Vec::new();
+        tree.root.script_leaves(&mut leaves);
+        for script in leaves.into_iter().filter_map(|(_, script)| script) {
+            if seen.insert(script.to_string()) {
+                scripts.push((script.to_string(), None, None));
+            }
+        }
+    }
+    for level in saved_state.levels.iter().flatten() {
+        for component in level.components.iter().flatten() {
+            if let Some(script) = &component.rhai_script_path {
+                if seen.insert(script.clone()) {
+                    scripts.push((script.clone(), Some(level.id.clone()), Some(component.id.clone())));
+                }
+            }
+        }
+    }
+
+    for (script, level_id, component_id) in scripts {
+        let path = options.script_root.join(&script);
+
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                report.push(
+                    Severity::Error,
+                    "missing_script",
+                    level_id.as_deref(),
+                    component_id.as_deref(),
+                    format!("Couldn't read script {}: {}", script, e),
+                );
+                continue;
+            }
+        };
+
+        if let Err(e) = engine.check_source(&source) {
+            report.push(
+                Severity::Error,
+                "script_compile",
+                level_id.as_deref(),
+                component_id.as_deref(),
+                format!("{} doesn't compile: {}", script, e),
+            );
+        }
+    }
+}
+
+fn check_model_files(saved_state: &SavedState, options: &ValidateOptions, report: &mut ValidationReport) {
+    let models_dir = options.project_dir.join("models");
+
+    for model in &saved_state.models {
+        if !models_dir.join(&model.fileName).is_file() {
+            report.push(
+                Severity::Error,
+                "missing_file",
+                None,
+                None,
+                format!("Model {} file {} not found in {:?}", model.id, model.fileName, models_dir),
+            );
+        }
+    }
+}
+
+fn check_pbr_textures(saved_state: &SavedState, options: &ValidateOptions, report: &mut ValidationReport) {
+    let textures_dir = options.project_dir.join("textures");
+
+    for pbr in saved_state.pbr_textures.iter().flatten() {
+        let maps = [
+            ("diff", &pbr.diff),
+            ("nor_gl", &pbr.nor_gl),
+            ("rough", &pbr.rough),
+            ("metallic", &pbr.metallic),
+            ("ao", &pbr.ao),
+        ];
+
+        for (name, map) in maps {
+            match map {
+                Some(file) if !textures_dir.join(&file.fileName).is_file() => report.push(
+                    Severity::Error,
+                    "pbr_missing_map",
+                    None,
+                    None,
+                    format!("PBR texture {} {} map file {} not found", pbr.id, name, file.fileName),
+                ),
+                Some(_) => {}
+                None => report.push(
+                    Severity::Warning,
+                    "pbr_missing_map",
+                    None,
+                    None,
+                    format!("PBR texture {} has no {} map", pbr.id, name),
+                ),
+            }
+        }
+    }
+}
+
+// Notes an in-memory migration, since the engine will rewrite the file on next load
+pub fn report_migration(migration: &MigrationReport, report: &mut ValidationReport) {
+    if migration.migrated() {
+        report.push(
+            Severity::Warning,
+            "schema_version",
+            None,
+            None,
+            format!(
+                "Project is schema v{}, the engine will migrate it to v{} on load",
+                migration.from_version, CURRENT_SCHEMA_VERSION
+            ),
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use entropy_engine::art_assets::Model::read_model;
+use entropy_engine::helpers::saved_data::{relative_asset_path, File};
+
+use std::fs;
+
+const PROJECT_ID: &str = "5fa6dd47-4355-4de5-b5a3-a7f61e979fcc";
+
+#[test]
+fn absolute_windows_paths_become_relative() {
+    let path = r"C:\Users\alex\OneDrive\Documents\CommonOS\midpoint/projects\5fa6dd47-4355-4de5-b5a3-a7f61e979fcc\models\Player.glb";
+
+    assert_eq!(relative_asset_path(path, PROJECT_ID).as_deref(), Some("models/Player.glb"));
+}
+
+#[test]
+fn renamed_project_folders_still_resolve() {
+    let path = "/home/alex/Documents/CommonOS/midpoint/projects/old-name/textures/rock_diff.jpg";
+
+    assert_eq!(relative_asset_path(path, PROJECT_ID).as_deref(), Some("textures/rock_diff.jpg"));
+}
+
+#[test]
+fn relative_paths_are_normalized() {
+    assert_eq!(relative_asset_path(r".\models\Player.glb", PROJECT_ID).as_deref(), Some("models/Player.glb"));
+    assert_eq!(relative_asset_path("models/Player.glb", PROJECT_ID).as_deref(), Some("models/Player.glb"));
+}
+
+#[test]
+fn unrelated_absolute_paths_are_left_alone() {
+    assert_eq!(relative_asset_path(r"D:\downloads\Player.glb", PROJECT_ID), None);
+}
+
+fn model(file_name: &str, normal_file_path: &str) -> File {
+    File {
+        id: "player-model".to_string(),
+        fileName: file_name.to_string(),
+        normalFilePath: normal_file_path.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn loaders_read_from_the_stored_path() {
+    let absolute = r"C:\Users\alex\Documents\CommonOS\midpoint\projects\5fa6dd47-4355-4de5-b5a3-a7f61e979fcc\characters\Player.glb";
+
+    assert_eq!(model("Player.glb", "characters/Player.glb").load_path(PROJECT_ID, "models"), "characters/Player.glb");
+    assert_eq!(model("Player.glb", absolute).load_path(PROJECT_ID, "models"), "characters/Player.glb");
+
+    // nothing stored, or nothing usable, falls back to the import folder
+    assert_eq!(model("Player.glb", "").load_path(PROJECT_ID, "models"), "models/Player.glb");
+    assert_eq!(model("Player.glb", r"D:\downloads\Player.glb").load_path(PROJECT_ID, "models"), "models/Player.glb");
+}
+
+#[test]
+fn models_outside_the_models_folder_load() {
+    let root = std::env::temp_dir().join(format!("entropy-asset-paths-{}", std::process::id()));
+    let project_dir = root.join(PROJECT_ID);
+    fs::create_dir_all(project_dir.join("characters")).unwrap();
+    fs::write(project_dir.join("characters/Player.glb"), b"glb").unwrap();
+    unsafe { std::env::set_var("ENTROPY_PROJECTS_DIR", &root) };
+
+    let file = model("Player.glb", "characters/Player.glb");
+    let bytes = read_model(PROJECT_ID.to_string(), file.load_path(PROJECT_ID, "models")).unwrap();
+    assert_eq!(bytes, b"glb");
+
+    fs::remove_dir_all(&root).ok();
+}
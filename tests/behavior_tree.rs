@@ -0,0 +1,306 @@
+use entropy_engine::core::World::World;
+use entropy_engine::game_behaviors::behavior_tree::{BehaviorTree, NodeStatus, TickContext};
+use entropy_engine::helpers::saved_data::{BehaviorTreeData, CharacterStats, ComponentData, ComponentKind, LevelData, NPCProperties, SavedState};
+use entropy_engine::model_components::NPC::NPC;
+use entropy_engine::rhai_engine::RhaiEngine;
+use entropy_engine::validate::{validate_project, Severity, ValidateOptions};
+use nalgebra::Vector3;
+use rapier3d::prelude::*;
+use serde_json::{json, Value};
+
+use std::fs;
+use std::path::PathBuf;
+
+const GUARD: &str = "tests/fixtures/behavior_trees/guard.json";
+const SENTRY: &str = "tests/fixtures/scripts/sentry.rhai";
+
+// An NPC and a target on a 40 x 40 floor
+struct Arena {
+    world: World,
+    npc: RigidBodyHandle,
+    npc_collider: ColliderHandle,
+    target: RigidBodyHandle,
+    stats: CharacterStats,
+}
+
+fn arena(target_x: f32) -> Arena {
+    let mut world = World::new();
+    world.insert_body(
+        RigidBodyBuilder::fixed().translation(vector![0.0, -0.5, 0.0]).build(),
+        ColliderBuilder::cuboid(20.0, 0.5, 20.0).build(),
+    );
+    let (npc, npc_collider) = world.insert_body(
+        RigidBodyBuilder::dynamic().translation(vector![0.0, 1.5, 0.0]).build(),
+        ColliderBuilder::capsule_y(1.0, 0.5).build(),
+    );
+    let (target, _) = world.insert_body(
+        RigidBodyBuilder::dynamic().translation(vector![target_x, 1.5, 0.0]).build(),
+        ColliderBuilder::capsule_y(1.0, 0.5).build(),
+    );
+    world.update_query_pipeline();
+    world.navmesh.sync(&world.rigid_body_set, &world.collider_set);
+
+    Arena {
+        world,
+        npc,
+        npc_collider,
+        target,
+        stats: CharacterStats { health: 100.0, stamina: 100.0 },
+    }
+}
+
+fn parse_tree(data: Value) -> BehaviorTree {
+    BehaviorTree::new(&serde_json::from_value::<BehaviorTreeData>(data).expect("Couldn't parse tree"))
+}
+
+// what World::update_npcs does for one NPC, returns the root's status
+fn tick(arena: &mut Arena, tree: &mut BehaviorTree, dt: f32) -> NodeStatus {
+    let collider = arena.world.collider_set[arena.npc_collider].clone();
+    let current_pos = *arena.world.rigid_body_set[arena.npc].translation();
+    tree.update(TickContext {
+        rigid_body_set: &mut arena.world.rigid_body_set,
+        collider_set: &arena.world.collider_set,
+        query_pipeline: &arena.world.query_pipeline,
+        navmesh: &mut arena.world.navmesh,
+        entity_handle: arena.npc,
+        target_handle: arena.target,
+        collider: &collider,
+        current_pos,
+        stats: &arena.stats,
+        dt,
+    });
+    tree.root.status.expect("Root wasn't ticked")
+}
+
+fn move_target(arena: &mut Arena, x: f32) {
+    arena.world.rigid_body_set[arena.target].set_translation(vector![x, 1.5, 0.0], true);
+}
+
+#[test]
+fn composites_combine_their_children() {
+    let mut arena = arena(30.0);
+    let mut tree = parse_tree(json!({
+        "id": "steps",
+        "root": {
+            "type": "Sequence",
+            "children": [
+                { "type": "Set", "key": "stage", "value": 1 },
+                {
+                    "type": "Selector",
+                    "children": [
+                        { "type": "Check", "key": "stage", "value": 2 },
+                        { "type": "Inverter", "child": { "type": "Check", "key": "stage", "value": 2 } }
+                    ]
+                },
+                {
+                    "type": "Parallel",
+                    "policy": "RequireAll",
+                    "children": [
+                        { "type": "Set", "key": "ready", "value": true },
+                        { "type": "Wait", "seconds": 0.5 }
+                    ]
+                }
+            ]
+        }
+    }));
+
+    assert_eq!(tick(&mut arena, &mut tree, 0.25), NodeStatus::Running);
+    assert_eq!(tree.blackboard.get("stage"), Some(&json!(1)));
+    assert_eq!(tree.blackboard.get("ready"), Some(&json!(true)));
+    assert_eq!(tree.root.children[1].status, Some(NodeStatus::Success));
+
+    // picks up at the running parallel, the steps before it aren't run again
+    assert_eq!(tick(&mut arena, &mut tree, 0.25), NodeStatus::Success);
+    assert_eq!(tree.root.children[1].last_tick, 1);
+    assert_eq!(tree.root.children[2].last_tick, 2);
+
+    // one success is enough by default, one failure is too many when all have to succeed
+    let mut any = parse_tree(json!({
+        "id": "any",
+        "root": {
+            "type": "Parallel",
+            "children": [{ "type": "Wait", "seconds": 1.0 }, { "type": "Wait", "seconds": 0.25 }]
+        }
+    }));
+    assert_eq!(tick(&mut arena, &mut any, 0.25), NodeStatus::Success);
+
+    let mut all = parse_tree(json!({
+        "id": "all",
+        "root": {
+            "type": "Parallel",
+            "policy": "RequireAll",
+            "children": [{ "type": "Wait", "seconds": 1.0 }, { "type": "Check", "key": "missing", "value": true }]
+        }
+    }));
+    assert_eq!(tick(&mut arena, &mut all, 0.25), NodeStatus::Failure);
+}
+
+#[test]
+fn reactive_selectors_switch_branches() {
+    let data: BehaviorTreeData = serde_json::from_str(&fs::read_to_string(GUARD).unwrap()).expect("Couldn't parse guard");
+    assert_eq!(data.blackboard.get("alert"), Some(&json!(false)));
+
+    let mut arena = arena(30.0);
+    let mut tree = BehaviorTree::new(&data);
+    let (defend, chase, wander) = (0, 1, 2);
+
+    // nothing around, it wanders
+    assert_eq!(tick(&mut arena, &mut tree, 1.0 / 60.0), NodeStatus::Running);
+    assert_eq!(tree.root.children[wander].status, Some(NodeStatus::Running));
+    assert_eq!(tree.get_animation_name(), "Walking");
+    assert_eq!(tree.blackboard.get("alert"), Some(&json!(false)));
+
+    // the target comes close, chasing takes over from wandering
+    move_target(&mut arena, 5.0);
+    assert_eq!(tick(&mut arena, &mut tree, 1.0 / 60.0), NodeStatus::Running);
+    assert_eq!(tree.root.children[chase].status, Some(NodeStatus::Running));
+    assert_eq!(tree.root.children[wander].status, None);
+    assert_eq!(tree.blackboard.get("alert"), Some(&json!(true)));
+    assert!(arena.world.rigid_body_set[arena.npc].linvel().x > 0.0);
+
+    // and defending from the chase once it's right there
+    move_target(&mut arena, 1.0);
+    assert_eq!(tick(&mut arena, &mut tree, 1.0 / 60.0), NodeStatus::Running);
+    assert_eq!(tree.root.children[defend].status, Some(NodeStatus::Running));
+    assert_eq!(tree.root.children[chase].status, None);
+    assert_eq!(tree.get_animation_name(), "Defend");
+    assert!((tree.target_distance - 1.0).abs() < 0.01);
+}
+
+#[test]
+fn timed_nodes_use_game_time() {
+    let mut arena = arena(30.0);
+    let mut cooldown = parse_tree(json!({
+        "id": "cooldown",
+        "root": { "type": "Cooldown", "seconds": 1.0, "child": { "type": "Wait", "seconds": 0.5 } }
+    }));
+
+    let statuses: Vec<_> = (0..6).map(|_| tick(&mut arena, &mut cooldown, 0.25)).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            NodeStatus::Running,
+            NodeStatus::Success,
+            // cooling down until a second after the wait finished
+            NodeStatus::Failure,
+            NodeStatus::Failure,
+            NodeStatus::Failure,
+            NodeStatus::Running,
+        ]
+    );
+
+    let mut time_limit = parse_tree(json!({
+        "id": "time_limit",
+        "root": { "type": "TimeLimit", "seconds": 0.5, "child": { "type": "Wait", "seconds": 1.0 } }
+    }));
+    assert_eq!(tick(&mut arena, &mut time_limit, 0.25), NodeStatus::Running);
+    assert_eq!(tick(&mut arena, &mut time_limit, 0.25), NodeStatus::Running);
+    assert_eq!(tick(&mut arena, &mut time_limit, 0.25), NodeStatus::Failure);
+}
+
+#[test]
+fn script_leaves_run_their_functions() {
+    let mut arena = arena(30.0);
+    let mut engine = RhaiEngine::new();
+    let mut npc = NPC::new("sentry".to_string(), "sentry".to_string(), arena.npc);
+    npc.set_behavior_tree(&serde_json::from_value(json!({
+        "id": "sentry",
+        "root": {
+            "type": "Sequence",
+            "children": [
+                { "type": "Script", "function": "look_around" },
+                { "type": "Set", "key": "done", "value": true }
+            ]
+        }
+    })).unwrap());
+    let mut script_state = None;
+
+    // the function answers between ticks, until then the leaf runs
+    for _ in 0..3 {
+        let tree = npc.test_behavior.tree_mut().unwrap();
+        assert_eq!(tick(&mut arena, tree, 0.1), NodeStatus::Running);
+        let requests = tree.take_script_requests("sentry");
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].function, "look_around");
+
+        let zero = Vector3::zeros();
+        engine.call_behavior_leaf(&mut npc, zero, zero, Vector3::new(1.0, 1.0, 1.0), &mut script_state, Some(SENTRY), &requests[0]);
+    }
+    assert!(engine.errors.is_empty(), "{:?}", engine.errors.iter().map(|e| e.to_string()).collect::<Vec<_>>());
+
+    let tree = npc.test_behavior.tree_mut().unwrap();
+    assert_eq!(tick(&mut arena, tree, 0.1), NodeStatus::Success);
+    assert!(tree.take_script_requests("sentry").is_empty());
+    assert_eq!(tree.blackboard.get("looks"), Some(&json!(3)));
+    assert_eq!(tree.blackboard.get("done"), Some(&json!(true)));
+    assert_eq!(script_state.unwrap().get("health"), Some(&json!(100.0)));
+
+    // failing, throwing and missing functions all fail the leaf
+    for function in ["give_up", "broken", "missing"] {
+        npc.set_behavior_tree(&serde_json::from_value(json!({
+            "id": function,
+            "root": { "type": "Script", "function": function, "script": SENTRY }
+        })).unwrap());
+        let tree = npc.test_behavior.tree_mut().unwrap();
+        tick(&mut arena, tree, 0.1);
+        let request = tree.take_script_requests("sentry").remove(0);
+        assert_eq!(request.script.as_deref(), Some(SENTRY));
+
+        engine.call_behavior_leaf(&mut npc, Vector3::zeros(), Vector3::zeros(), Vector3::new(1.0, 1.0, 1.0), &mut None, request.script.as_deref(), &request);
+        let tree = npc.test_behavior.tree_mut().unwrap();
+        assert_eq!(tick(&mut arena, tree, 0.1), NodeStatus::Failure, "{}", function);
+    }
+    assert_eq!(engine.errors.len(), 2);
+}
+
+#[test]
+fn validate_checks_behavior_tree_references() {
+    let npc = |id: &str, tree_id: &str| ComponentData {
+        id: id.to_string(),
+        kind: Some(ComponentKind::NPC),
+        npc_properties: Some(NPCProperties {
+            model_id: "sentry-model".to_string(),
+            behavior_tree_id: Some(tree_id.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let sentry: BehaviorTreeData = serde_json::from_value(json!({
+        "id": "sentry",
+        "root": {
+            "type": "Selector",
+            "children": [{ "type": "Script", "function": "look_around" }, { "type": "Wait", "seconds": 1.0 }]
+        }
+    }))
+    .unwrap();
+
+    let saved_state = SavedState {
+        levels: Some(vec![LevelData {
+            id: "level-a".to_string(),
+            components: Some(vec![npc("sentry-id", "sentry"), npc("ghost-id", "ghost")]),
+            ..Default::default()
+        }]),
+        behavior_trees: Some(vec![sentry]),
+        ..Default::default()
+    };
+    let options = ValidateOptions {
+        project_dir: PathBuf::from("tests/fixtures"),
+        script_root: PathBuf::from("."),
+    };
+
+    let report = validate_project(&saved_state, &options);
+    let tree_errors: Vec<&str> = report
+        .issues
+        .iter()
+        .filter(|i| i.check == "behavior_tree" && i.severity == Severity::Error)
+        .map(|i| i.message.as_str())
+        .collect();
+
+    assert_eq!(
+        tree_errors,
+        vec![
+            "Behavior tree sentry calls look_around but the NPC has no script",
+            "NPC uses behavior tree ghost, which doesn't exist",
+        ]
+    );
+}
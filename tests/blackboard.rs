@@ -0,0 +1,102 @@
+use entropy_engine::game_behaviors::blackboard::Blackboard;
+use entropy_engine::game_behaviors::dialogue_graph::{self, DialogueContext};
+use entropy_engine::game_behaviors::dialogue_state::DialogueState;
+use entropy_engine::game_behaviors::quests::QuestLog;
+use entropy_engine::helpers::saved_data::{DialogueAction, DialogueChoice, DialogueCondition, DialogueData, DialogueNode};
+use entropy_engine::rhai_engine::RhaiEngine;
+use serde_json::json;
+
+const GOSSIP: &str = "tests/fixtures/scripts/gossip.rhai";
+
+// A greeting that changes once the NPC has met the player, and a rumor every NPC hears about
+fn greeter() -> DialogueData {
+    DialogueData {
+        id: "greeter".to_string(),
+        start_node: None,
+        nodes: vec![DialogueNode {
+            id: "hello".to_string(),
+            text: "Hello.".to_string(),
+            options: vec![
+                DialogueChoice {
+                    text: "Nice to meet you.".to_string(),
+                    conditions: vec![DialogueCondition::NotVisited { node: "hello".to_string() }],
+                    ..Default::default()
+                },
+                DialogueChoice {
+                    text: "Good to see you again.".to_string(),
+                    conditions: vec![DialogueCondition::Visited { node: "hello".to_string() }],
+                    ..Default::default()
+                },
+                DialogueChoice {
+                    text: "The bridge is out.".to_string(),
+                    conditions: vec![DialogueCondition::VarNotEquals {
+                        name: "bridge_out".to_string(),
+                        value: json!(true),
+                        global: true,
+                    }],
+                    actions: vec![DialogueAction::SetVar { name: "bridge_out".to_string(), value: json!(true), global: true }],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }],
+    }
+}
+
+fn talk(dialogue: &DialogueData, blackboard: &mut Blackboard, npc_id: &str) -> Vec<String> {
+    let mut quests = QuestLog::new(Vec::new());
+    let mut context = DialogueContext {
+        npc_id,
+        npc_name: npc_id,
+        blackboard,
+        quests: &mut quests,
+        inventory: None,
+        items: &[],
+    };
+    let mut state = DialogueState::default();
+    dialogue_graph::start(dialogue, &mut state, &mut context);
+    let options = state.options.iter().map(|o| o.text.clone()).collect();
+
+    if let Some(index) = state.options.iter().position(|o| o.text == "The bridge is out.") {
+        dialogue_graph::choose(dialogue, &mut state, index, &mut context);
+    }
+    options
+}
+
+#[test]
+fn npcs_remember_conversations_and_share_globals() {
+    let dialogue = greeter();
+    let mut blackboard = Blackboard::default();
+
+    assert_eq!(talk(&dialogue, &mut blackboard, "miller"), vec!["Nice to meet you.", "The bridge is out."]);
+    assert_eq!(blackboard.global("bridge_out"), Some(&json!(true)));
+    assert_eq!(blackboard.times_visited("miller", "hello"), 1);
+
+    // the miller remembers, the smith hasn't met the player but heard about the bridge
+    assert_eq!(talk(&dialogue, &mut blackboard, "miller"), vec!["Good to see you again."]);
+    assert_eq!(talk(&dialogue, &mut blackboard, "smith"), vec!["Nice to meet you."]);
+    assert_eq!(blackboard.times_visited("miller", "hello"), 2);
+    assert_eq!(blackboard.var("smith", "bridge_out"), None);
+}
+
+#[test]
+fn scripts_read_and_write_the_blackboard() {
+    let mut engine = RhaiEngine::new();
+    let mut state = DialogueState { current_npc_id: "baker".to_string(), ..Default::default() };
+
+    assert!(engine.execute_dialogue_hook(&mut state, GOSSIP, "interact"));
+    assert_eq!(state.current_text, "Heard anything interesting?");
+
+    state.current_npc_id = "farmer".to_string();
+    engine.execute_dialogue_hook(&mut state, GOSSIP, "interact");
+    engine.execute_dialogue_hook(&mut state, GOSSIP, "interact");
+    assert_eq!(state.current_text, "They say there's a dragon in the hills.");
+    assert!(engine.errors.is_empty(), "{:?}", engine.errors.iter().map(|e| e.to_string()).collect::<Vec<_>>());
+
+    let blackboard = engine.blackboard.borrow();
+    assert_eq!(blackboard.global("rumor"), Some(&json!("dragon")));
+    assert_eq!(blackboard.var("baker", "talks"), Some(&json!(1)));
+    assert_eq!(blackboard.var("farmer", "talks"), Some(&json!(2)));
+    // script conversations start at the empty node, which isn't counted
+    assert_eq!(blackboard.times_visited("farmer", ""), 0);
+}
@@ -0,0 +1,87 @@
+use entropy_engine::core::World::{ComponentCollision, World};
+use entropy_engine::helpers::saved_data::{TriggerProperties, TriggerShape};
+use entropy_engine::model_components::TriggerVolume;
+use nalgebra::{Isometry3, Vector3};
+use rapier3d::prelude::*;
+use uuid::Uuid;
+
+const TRAP_ID: &str = "0b6f6c55-1f0e-4d8a-9a53-6f2f4c7d9e01";
+const BARREL_ID: &str = "7c1e2d3f-5a6b-4c8d-9e0f-1a2b3c4d5e6f";
+
+fn user_data(id: &str) -> u128 {
+    Uuid::parse_str(id).unwrap().as_u128()
+}
+
+fn trap() -> TriggerProperties {
+    TriggerProperties {
+        shape: TriggerShape::Box,
+        half_extents: [2.0, 2.0, 2.0],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn trigger_volumes_report_enter_and_exit() {
+    let mut world = World::new();
+
+    let (trap_body, trap_collider) = TriggerVolume::create_physics(
+        user_data(TRAP_ID),
+        &trap(),
+        Isometry3::identity(),
+        Vector3::new(1.0, 1.0, 1.0),
+    );
+    world.insert_body(trap_body, trap_collider);
+
+    let (barrel, _) = world.insert_body(
+        RigidBodyBuilder::dynamic().user_data(user_data(BARREL_ID)).build(),
+        ColliderBuilder::ball(0.5).user_data(user_data(BARREL_ID)).build(),
+    );
+
+    let collisions = world.step(1.0 / 60.0).collisions;
+    assert_eq!(collisions.len(), 1);
+    assert!(collisions[0].started);
+    assert!(collisions[0].sensor);
+    assert_eq!(collisions[0].hook_name(), "on_trigger_enter");
+
+    let ids = [collisions[0].a.as_str(), collisions[0].b.as_str()];
+    assert!(ids.contains(&TRAP_ID) && ids.contains(&BARREL_ID));
+
+    // nothing new while it stays inside
+    assert!(world.step(1.0 / 60.0).collisions.is_empty());
+
+    world.rigid_body_set[barrel].set_translation(vector![0.0, 50.0, 0.0], true);
+    let collisions = world.step(1.0 / 60.0).collisions;
+    assert_eq!(collisions.len(), 1);
+    assert!(!collisions[0].started);
+    assert_eq!(collisions[0].hook_name(), "on_trigger_exit");
+}
+
+#[test]
+fn colliders_without_a_component_are_ignored() {
+    let mut world = World::new();
+
+    world.insert_body(
+        RigidBodyBuilder::fixed().build(),
+        ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+    );
+    world.insert_body(
+        RigidBodyBuilder::dynamic().translation(vector![0.0, 0.9, 0.0]).user_data(user_data(BARREL_ID)).build(),
+        ColliderBuilder::ball(0.5).user_data(user_data(BARREL_ID)).build(),
+    );
+
+    for _ in 0..30 {
+        assert!(world.step(1.0 / 60.0).collisions.is_empty());
+    }
+}
+
+#[test]
+fn hook_names_follow_the_event() {
+    let collision = ComponentCollision {
+        a: TRAP_ID.to_string(),
+        b: BARREL_ID.to_string(),
+        started: false,
+        sensor: false,
+    };
+
+    assert_eq!(collision.hook_name(), "on_collision_exit");
+}
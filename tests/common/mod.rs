@@ -0,0 +1,46 @@
+// Helpers shared by the script test binaries, not every binary uses all of them
+#![allow(dead_code)]
+
+use entropy_engine::helpers::saved_data::ScriptState;
+use entropy_engine::rhai_engine::RhaiEngine;
+use nalgebra::Vector3;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// writes <name>.rhai into its own temp dir, so tests running in parallel never share a script
+pub fn scratch_script(name: &str, source: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("entropy-scripts-{}-{}", name, uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).expect("Couldn't create scratch dir");
+    let path = dir.join(format!("{}.rhai", name));
+    fs::write(&path, source).expect("Couldn't write script");
+    path
+}
+
+pub fn cleanup(path: &Path) {
+    fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+// rewrites the script with a modified time far enough ahead that the change is always seen
+pub fn edit(path: &Path, source: &str, seconds: u64) {
+    fs::write(path, source).expect("Couldn't write script");
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|f| f.set_modified(SystemTime::now() + Duration::from_secs(seconds)))
+        .expect("Couldn't touch script");
+}
+
+// calls the script's on_update as a model hook owned by owner_id
+pub fn run(engine: &mut RhaiEngine, owner_id: &str, path: &Path, state: &mut Option<ScriptState>) {
+    engine.execute_model_hook(
+        owner_id,
+        Vector3::zeros(),
+        Vector3::zeros(),
+        Vector3::new(1.0, 1.0, 1.0),
+        state,
+        path.to_str().unwrap(),
+        "on_update",
+        None,
+    );
+}
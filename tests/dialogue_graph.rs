@@ -0,0 +1,215 @@
+use entropy_engine::game_behaviors::blackboard::Blackboard;
+use entropy_engine::game_behaviors::dialogue_graph::{self, dialogue_issues, parse_dialogue, DialogueContext, DialogueIssue, DialogueStep};
+use entropy_engine::game_behaviors::dialogue_state::DialogueState;
+use entropy_engine::game_behaviors::inventory::Inventory;
+use entropy_engine::game_behaviors::quests::QuestLog;
+use entropy_engine::helpers::saved_data::{
+    ComponentData, ComponentKind, DialogueChoice, DialogueData, DialogueNode, LevelData, NPCProperties, QuestData, QuestStage,
+    SavedState,
+};
+use entropy_engine::validate::{validate_project, Severity, ValidateOptions};
+use serde_json::json;
+
+use std::fs;
+use std::path::PathBuf;
+
+const VILLAGER: &str = "tests/fixtures/dialogues/villager.json";
+
+fn villager() -> DialogueData {
+    parse_dialogue(&fs::read_to_string(VILLAGER).unwrap(), VILLAGER).expect("Couldn't parse dialogue")
+}
+
+fn find_the_elder() -> QuestData {
+    QuestData {
+        id: "find_the_elder".to_string(),
+        name: "Find the Elder".to_string(),
+        stages: vec![QuestStage { id: "climb_the_hill".to_string(), ..Default::default() }],
+        ..Default::default()
+    }
+}
+
+fn herb() -> ComponentData {
+    ComponentData {
+        id: "herb-1".to_string(),
+        kind: Some(ComponentKind::Collectable),
+        asset_id: "herb".to_string(),
+        ..Default::default()
+    }
+}
+
+fn option_texts(state: &DialogueState) -> Vec<&str> {
+    state.options.iter().map(|o| o.text.as_str()).collect()
+}
+
+// picks the option showing `text`
+fn pick(dialogue: &DialogueData, state: &mut DialogueState, text: &str, context: &mut DialogueContext) -> DialogueStep {
+    let index = state.options.iter().position(|o| o.text == text).expect("Option isn't showing");
+    dialogue_graph::choose(dialogue, state, index, context)
+}
+
+#[test]
+fn conditions_and_actions_drive_the_conversation() {
+    let dialogue = villager();
+    let mut quests = QuestLog::new(vec![find_the_elder()]);
+    let mut inventory = Inventory::new();
+    let items = vec![herb()];
+    let mut blackboard = Blackboard::default();
+    // the context borrows the inventory until the conversation is over
+    {
+        let mut context = DialogueContext {
+            npc_id: "villager-id",
+            npc_name: "Villager",
+            blackboard: &mut blackboard,
+            quests: &mut quests,
+            inventory: Some(&mut inventory),
+            items: &items,
+        };
+        let mut state = DialogueState::default();
+
+        assert_eq!(dialogue_graph::start(&dialogue, &mut state, &mut context), DialogueStep::Shown);
+        assert!(state.is_open);
+        assert_eq!(state.npc_name, "Villager");
+        assert_eq!(option_texts(&state), vec!["They often are in these parts.", "I seek information.", "Goodbye."]);
+
+        // variables unlock options
+        pick(&dialogue, &mut state, "They often are in these parts.", &mut context);
+        pick(&dialogue, &mut state, "I love storms.", &mut context);
+        assert_eq!(context.blackboard.var("villager-id", "storms_loved"), Some(&json!(1)));
+        pick(&dialogue, &mut state, "Back.", &mut context);
+        pick(&dialogue, &mut state, "I will watch my step.", &mut context);
+        assert!(option_texts(&state).contains(&"Tell me a riddle."));
+
+        // node actions start quests, which hide and show options
+        pick(&dialogue, &mut state, "I seek information.", &mut context);
+        pick(&dialogue, &mut state, "Where is the elder?", &mut context);
+        assert_eq!(state.current_text, "He lives in the house on the hill. Can't miss it.");
+        assert!(context.quests.is_active("find_the_elder"));
+
+        pick(&dialogue, &mut state, "Thank you.", &mut context);
+        pick(&dialogue, &mut state, "I seek information.", &mut context);
+        assert_eq!(option_texts(&state), vec!["Never mind."]);
+        pick(&dialogue, &mut state, "Never mind.", &mut context);
+
+        pick(&dialogue, &mut state, "I found the elder.", &mut context);
+        assert_eq!(state.npc_name, "Elder's Grandson");
+        assert!(state.options.is_empty());
+        assert!(context.quests.is_complete("find_the_elder"));
+        assert_eq!(context.blackboard.var("villager-id", "met_elder"), Some(&json!(true)));
+    }
+    assert_eq!(inventory.items[0].id, "herb-1");
+}
+
+#[test]
+fn script_nodes_and_unknown_nodes_go_to_the_npc_script() {
+    let dialogue = villager();
+    let mut quests = QuestLog::new(vec![find_the_elder()]);
+    let mut blackboard = Blackboard::default();
+    blackboard.set_var("villager-id", "storms_loved", json!(2));
+    let mut context = DialogueContext {
+        npc_id: "villager-id",
+        npc_name: "Villager",
+        blackboard: &mut blackboard,
+        quests: &mut quests,
+        inventory: None,
+        items: &[],
+    };
+    let mut state = DialogueState::default();
+
+    dialogue_graph::start(&dialogue, &mut state, &mut context);
+    assert_eq!(pick(&dialogue, &mut state, "Tell me a riddle.", &mut context), DialogueStep::Script("riddle".to_string()));
+    assert_eq!(state.current_node, "riddle");
+    assert_eq!(state.dialogue_id.as_deref(), Some("villager"));
+
+    // what the riddle script would show, an option leading outside the graph
+    state.options = vec![entropy_engine::game_behaviors::dialogue_state::DialogueOption {
+        text: "I give up.".to_string(),
+        next_node: "answer".to_string(),
+        choice: None,
+    }];
+    assert_eq!(dialogue_graph::choose(&dialogue, &mut state, 0, &mut context), DialogueStep::Script("interact".to_string()));
+    assert_eq!(state.current_node, "answer");
+
+    // options without a next node close the dialogue
+    dialogue_graph::start(&dialogue, &mut state, &mut context);
+    assert_eq!(pick(&dialogue, &mut state, "Goodbye.", &mut context), DialogueStep::Closed);
+    assert!(!state.is_open);
+    assert!(state.dialogue_id.is_none());
+    // variables outlive the conversation
+    assert_eq!(context.blackboard.var("villager-id", "storms_loved"), Some(&json!(2)));
+}
+
+fn node(id: &str, links: &[&str]) -> DialogueNode {
+    DialogueNode {
+        id: id.to_string(),
+        text: id.to_string(),
+        options: links
+            .iter()
+            .map(|link| DialogueChoice { text: link.to_string(), next_node: Some(link.to_string()), ..Default::default() })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn broken_graphs_are_reported() {
+    assert!(dialogue_issues(&villager()).is_empty());
+
+    let broken = DialogueData {
+        id: "broken".to_string(),
+        start_node: None,
+        nodes: vec![node("start", &["middle", "nowhere"]), node("middle", &["start"]), node("middle", &[]), node("island", &["start"])],
+    };
+    let issues = dialogue_issues(&broken);
+    assert_eq!(
+        issues,
+        vec![
+            DialogueIssue::DuplicateNode("middle".to_string()),
+            DialogueIssue::DanglingLink { node_id: "start".to_string(), target: "nowhere".to_string() },
+            DialogueIssue::Unreachable("island".to_string()),
+        ]
+    );
+    assert!(!issues[2].is_error());
+
+    let no_start = DialogueData { start_node: Some("intro".to_string()), ..broken.clone() };
+    assert!(dialogue_issues(&no_start).contains(&DialogueIssue::MissingStart("intro".to_string())));
+}
+
+#[test]
+fn validate_checks_dialogue_references() {
+    let npc = ComponentData {
+        id: "villager-id".to_string(),
+        kind: Some(ComponentKind::NPC),
+        npc_properties: Some(NPCProperties { model_id: "villager-model".to_string(), dialogue_id: Some("villager".to_string()), ..Default::default() }),
+        ..Default::default()
+    };
+    let stranger = ComponentData {
+        id: "stranger-id".to_string(),
+        kind: Some(ComponentKind::NPC),
+        npc_properties: Some(NPCProperties { model_id: "villager-model".to_string(), dialogue_id: Some("stranger".to_string()), ..Default::default() }),
+        ..Default::default()
+    };
+
+    let saved_state = SavedState {
+        levels: Some(vec![LevelData { id: "level-a".to_string(), components: Some(vec![npc, stranger]), ..Default::default() }]),
+        dialogue_files: Some(vec!["dialogues/villager.json".to_string()]),
+        ..Default::default()
+    };
+    let options = ValidateOptions {
+        project_dir: PathBuf::from("tests/fixtures"),
+        script_root: PathBuf::from("."),
+    };
+
+    let report = validate_project(&saved_state, &options);
+    let dialogue_errors: Vec<&str> = report
+        .issues
+        .iter()
+        .filter(|i| i.check == "dialogue" && i.severity == Severity::Error)
+        .map(|i| i.message.as_str())
+        .collect();
+
+    // no quests or herbs in this project, and the villager has no script for its riddle node
+    assert!(dialogue_errors.contains(&"Dialogue villager node info checks quest find_the_elder, which doesn't exist"));
+    assert!(dialogue_errors.contains(&"Dialogue villager node elder_found gives herb, which no collectable matches"));
+    assert!(dialogue_errors.contains(&"Dialogue villager has script nodes but the NPC has no script"));
+    assert!(dialogue_errors.contains(&"NPC uses dialogue stranger, which doesn't exist"));
+}
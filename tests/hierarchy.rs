@@ -0,0 +1,109 @@
+use entropy_engine::core::SceneHierarchy::{component_placements, is_descendant, parent_depth, Placement};
+use entropy_engine::helpers::project_error::{component_errors, ProjectError};
+use entropy_engine::helpers::saved_data::{ComponentData, GenericProperties, SavedState};
+
+fn component(id: &str, parent_id: Option<&str>, position: [f32; 3], rotation: [f32; 3], scale: [f32; 3]) -> ComponentData {
+    ComponentData {
+        id: id.to_string(),
+        generic_properties: GenericProperties {
+            name: id.to_string(),
+            position,
+            rotation,
+            scale,
+        },
+        parent_id: parent_id.map(String::from),
+        ..Default::default()
+    }
+}
+
+fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+    for i in 0..3 {
+        assert!((actual[i] - expected[i]).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+    }
+}
+
+// a cart, a lamp on the cart and a bulb in the lamp
+fn cart() -> Vec<ComponentData> {
+    vec![
+        component("bulb", Some("lamp"), [0.0, 0.5, 0.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]),
+        component("lamp", Some("cart"), [1.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]),
+        component("cart", None, [10.0, 0.0, 0.0], [0.0, 90.0, 0.0], [2.0, 2.0, 2.0]),
+    ]
+}
+
+#[test]
+fn children_are_placed_relative_to_their_parents() {
+    let placements = component_placements(&cart());
+
+    assert_close(placements["cart"].position(), [10.0, 0.0, 0.0]);
+    // offsets are in the parent's scaled, rotated space
+    assert_close(placements["lamp"].position(), [10.0, 0.0, -2.0]);
+    assert_close(placements["bulb"].position(), [10.0, 1.0, -2.0]);
+    assert_eq!(placements["bulb"].scale, nalgebra::Vector3::new(2.0, 2.0, 2.0));
+}
+
+#[test]
+fn local_transforms_round_trip_through_world_space() {
+    let components = cart();
+    let placements = component_placements(&components);
+
+    let lamp = &components[1];
+    let local = placements["cart"].local_generic(&placements["lamp"], &lamp.generic_properties);
+
+    assert_close(local.position, lamp.generic_properties.position);
+    assert_close(local.rotation, lamp.generic_properties.rotation);
+    assert_close(local.scale, lamp.generic_properties.scale);
+
+    // and re-rooting under nothing gives the world transform
+    let root = Placement::from_generic(&GenericProperties {
+        scale: [1.0, 1.0, 1.0],
+        ..Default::default()
+    });
+    let world = root.local_generic(&placements["bulb"], &components[0].generic_properties);
+    assert_close(world.position, [10.0, 1.0, -2.0]);
+}
+
+#[test]
+fn parent_cycles_are_placed_as_roots_and_reported() {
+    let components = vec![
+        component("a", Some("b"), [1.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]),
+        component("b", Some("a"), [0.0, 2.0, 0.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]),
+        component("c", Some("a"), [0.0, 0.0, 3.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]),
+    ];
+
+    assert_eq!(parent_depth(&components, &components[0]), None);
+    assert_eq!(parent_depth(&components, &components[2]), None);
+
+    let placements = component_placements(&components);
+    assert_close(placements["a"].position(), [1.0, 0.0, 0.0]);
+    assert_close(placements["b"].position(), [0.0, 2.0, 0.0]);
+
+    let state = SavedState::default();
+    assert_eq!(
+        component_errors(&state, &components, &components[0])[0],
+        ProjectError::ParentCycle {
+            component_id: "a".to_string()
+        }
+    );
+    // c isn't in the loop itself, it just hangs off it
+    assert!(!component_errors(&state, &components, &components[2]).contains(&ProjectError::ParentCycle {
+        component_id: "c".to_string()
+    }));
+}
+
+#[test]
+fn missing_parents_are_dangling_references() {
+    let components = vec![component("a", Some("gone"), [0.0; 3], [0.0; 3], [1.0; 3])];
+
+    let errors = component_errors(&SavedState::default(), &components, &components[0]);
+    assert_eq!(errors[0], ProjectError::dangling(&components[0], "parent", "gone"));
+}
+
+#[test]
+fn descendants_cant_be_chosen_as_parents() {
+    let components = cart();
+
+    assert!(is_descendant(&components, "bulb", "cart"));
+    assert!(is_descendant(&components, "lamp", "cart"));
+    assert!(!is_descendant(&components, "cart", "bulb"));
+}
@@ -0,0 +1,210 @@
+use entropy_engine::core::World::World;
+use entropy_engine::headless::HeadlessSim;
+use entropy_engine::helpers::saved_data::{
+    CollectableType, ComponentData, ComponentKind, File, GenericProperties, LevelData, PortalProperties, SavedState, StatData,
+};
+use entropy_engine::model_components::NPC::NPCArchetype;
+use nalgebra::{Isometry3, Vector3};
+use serde_json::json;
+
+// Level switching through a headless sim, so none of it needs a window or GPU
+
+const SCRIPT: &str = "tests/fixtures/scripts/levels.rhai";
+const PLAYER_ID: &str = "1c2d3e4f-5a6b-4c7d-8e9f-0a1b2c3d4e5f";
+const GUARD_A_ID: &str = "6e5d4c3b-2a19-4f8e-9d7c-6b5a4f3e2d1c";
+const GUARD_B_ID: &str = "0f1e2d3c-4b5a-4968-8776-655443322110";
+const SWORD_ID: &str = "8a9b0c1d-2e3f-4a5b-9c6d-7e8f9a0b1c2d";
+
+fn component(id: &str, kind: ComponentKind, position: [f32; 3]) -> ComponentData {
+    ComponentData {
+        id: id.to_string(),
+        kind: Some(kind),
+        asset_id: "body-model".to_string(),
+        generic_properties: GenericProperties { position, ..Default::default() },
+        ..Default::default()
+    }
+}
+
+fn portal(position: [f32; 3], target_level_id: &str, spawn_position: Option<[f32; 3]>) -> ComponentData {
+    ComponentData {
+        id: "portal-id".to_string(),
+        kind: Some(ComponentKind::Portal),
+        generic_properties: GenericProperties { position, ..Default::default() },
+        portal_properties: Some(PortalProperties {
+            target_level_id: target_level_id.to_string(),
+            radius: 2.0,
+            spawn_position,
+        }),
+        ..Default::default()
+    }
+}
+
+fn level(id: &str, components: Vec<ComponentData>) -> LevelData {
+    LevelData { id: id.to_string(), components: Some(components), ..Default::default() }
+}
+
+// level-a has the player and a guard, level-b another guard and where the player starts in it
+fn project(level_a_extra: Vec<ComponentData>) -> SavedState {
+    let mut level_a = vec![
+        component(PLAYER_ID, ComponentKind::PlayerCharacter, [0.0, 0.0, 0.0]),
+        component(GUARD_A_ID, ComponentKind::NPC, [5.0, 0.0, 5.0]),
+    ];
+    level_a.extend(level_a_extra);
+
+    SavedState {
+        models: vec![File { id: "body-model".to_string(), ..Default::default() }],
+        levels: Some(vec![
+            level("level-a", level_a),
+            level(
+                "level-b",
+                vec![
+                    component(PLAYER_ID, ComponentKind::PlayerCharacter, [10.0, 0.0, -10.0]),
+                    component(GUARD_B_ID, ComponentKind::NPC, [-5.0, 0.0, 5.0]),
+                ],
+            ),
+        ]),
+        ..Default::default()
+    }
+}
+
+fn start(saved_state: SavedState) -> HeadlessSim {
+    let mut sim = HeadlessSim::new("levels-test", saved_state);
+    sim.place_components();
+    sim.start();
+    sim
+}
+
+fn npc_ids(world: &World) -> Vec<&str> {
+    world.npcs.iter().map(|n| n.id.as_str()).collect()
+}
+
+fn player_position(sim: &HeadlessSim) -> Vector3<f32> {
+    sim.world.body_position(sim.world.player_handle().unwrap()).unwrap()
+}
+
+#[test]
+fn the_start_level_falls_back_to_the_first_level() {
+    let mut state = project(vec![]);
+    assert_eq!(state.start_level_id().as_deref(), Some("level-a"));
+
+    state.start_level_id = Some("level-b".to_string());
+    assert_eq!(state.start_level_id().as_deref(), Some("level-b"));
+    assert_eq!(state.level(None).unwrap().id, "level-b");
+
+    // a start level that was deleted
+    state.start_level_id = Some("level-c".to_string());
+    assert_eq!(state.start_level_id().as_deref(), Some("level-a"));
+
+    let sim = start(state);
+    assert_eq!(sim.current_level_id.as_deref(), Some("level-a"));
+    assert_eq!(npc_ids(&sim.world), [GUARD_A_ID]);
+}
+
+#[test]
+fn scripts_switch_levels_at_the_next_step_and_the_player_carries_over() {
+    let mut state = project(vec![]);
+    state.global_rhai_scripts = Some(vec![SCRIPT.to_string()]);
+
+    let mut sim = start(state);
+    let player_handle = sim.world.player_handle();
+
+    sim.step(1.0 / 60.0);
+    assert_eq!(sim.pending_level.as_ref().map(|l| l.level_id.as_str()), Some("level-b"));
+    assert_eq!(sim.current_level_id.as_deref(), Some("level-a"));
+
+    sim.step(1.0 / 60.0);
+    assert!(sim.pending_level.is_none());
+    assert_eq!(sim.current_level_id.as_deref(), Some("level-b"));
+
+    // level-a's guard is gone, the same player body moved to level-b's start
+    assert_eq!(npc_ids(&sim.world), [GUARD_B_ID]);
+    assert_eq!(sim.world.player_handle(), player_handle);
+    assert_eq!(sim.world.rigid_body_set.len(), 2);
+    let position = player_position(&sim);
+    assert_eq!((position.x, position.z), (10.0, -10.0));
+
+    assert_eq!(
+        sim.rhai_engine.global_states[SCRIPT]["log"],
+        json!("load level-a;unload level-a;load level-b;")
+    );
+}
+
+#[test]
+fn portals_fire_once_the_player_has_stepped_out() {
+    let mut sim = start(project(vec![portal([0.0, 0.0, 0.0], "level-b", Some([3.0, 1.0, 4.0]))]));
+
+    // arriving on top of a portal doesn't fire it
+    sim.step(1.0 / 60.0);
+    assert!(sim.pending_level.is_none());
+
+    sim.world.teleport_player([20.0, 0.0, 20.0]);
+    sim.step(1.0 / 60.0);
+    assert!(sim.pending_level.is_none());
+    assert!(sim.world.portal_armed);
+
+    sim.world.teleport_player([0.0, 0.0, 0.0]);
+    sim.step(1.0 / 60.0);
+    assert_eq!(sim.pending_level.as_ref().map(|l| l.level_id.as_str()), Some("level-b"));
+
+    // the portal's spawn position wins over level-b's player start
+    sim.step(1.0 / 60.0);
+    assert_eq!(sim.current_level_id.as_deref(), Some("level-b"));
+    let position = player_position(&sim);
+    assert_eq!((position.x, position.z), (3.0, 4.0));
+}
+
+#[test]
+fn parented_portals_fire_at_their_world_position() {
+    // the portal sits 5 along x from a light that is itself at 10, 0, 0
+    let mut light = component("light-id", ComponentKind::PointLight, [10.0, 0.0, 0.0]);
+    light.generic_properties.scale = [1.0, 1.0, 1.0];
+    let mut gate = portal([5.0, 0.0, 0.0], "level-b", None);
+    gate.parent_id = Some("light-id".to_string());
+    let mut sim = start(project(vec![light, gate]));
+
+    // where the portal would be without its parent
+    sim.world.teleport_player([5.0, 0.0, 0.0]);
+    sim.step(1.0 / 60.0);
+    assert!(sim.pending_level.is_none());
+
+    sim.world.teleport_player([15.0, 0.0, 0.0]);
+    sim.step(1.0 / 60.0);
+    assert_eq!(sim.pending_level.as_ref().map(|l| l.level_id.as_str()), Some("level-b"));
+}
+
+#[test]
+fn unknown_levels_leave_the_current_one_in_place() {
+    let mut sim = start(project(vec![]));
+
+    sim.load_level("level-c", None);
+
+    assert_eq!(sim.current_level_id.as_deref(), Some("level-a"));
+    assert_eq!(npc_ids(&sim.world), [GUARD_A_ID]);
+}
+
+#[test]
+fn unloading_keeps_the_player_and_their_weapon() {
+    let mut world = World::new();
+    let sword = component(SWORD_ID, ComponentKind::Collectable, [0.0, 0.0, 0.0]);
+    let guard = component(GUARD_A_ID, ComponentKind::NPC, [5.0, 0.0, 5.0]);
+
+    let player = world
+        .place_player(PLAYER_ID, Isometry3::identity(), Vector3::new(1.0, 1.0, 1.0), None)
+        .unwrap();
+    let sword_body = world
+        .place_collectable(SWORD_ID, "sword-model", Isometry3::identity(), CollectableType::MeleeWeapon, StatData::default())
+        .unwrap();
+    world
+        .place_npc(GUARD_A_ID, Isometry3::identity(), &NPCArchetype::new(&SavedState::default(), &guard, &[]))
+        .unwrap();
+    world.player_character.as_mut().unwrap().inventory.equipped_weapon = Some(sword);
+    world.portal_armed = true;
+
+    world.unload_level();
+
+    assert!(world.npcs.is_empty());
+    assert_eq!(world.collectables.len(), 1);
+    assert_eq!(world.rigid_body_set.len(), 2);
+    assert!(world.rigid_body_set.contains(player) && world.rigid_body_set.contains(sword_body));
+    assert!(!world.portal_armed);
+}
@@ -0,0 +1,118 @@
+use entropy_engine::helpers::migrations::{migrate, migrate_to, schema_version, typed_script_value, CURRENT_SCHEMA_VERSION};
+use entropy_engine::helpers::saved_data::SavedState;
+use serde_json::{json, Value};
+
+use std::fs;
+use std::path::PathBuf;
+
+fn fixture(name: &str) -> Value {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/migrations")
+        .join(name);
+    let content = fs::read_to_string(&path).expect("Couldn't read fixture");
+    serde_json::from_str(&content).expect("Couldn't parse fixture")
+}
+
+#[test]
+fn v0_to_v1_fills_missing_fields() {
+    let mut doc = fixture("v0_partial_components.json");
+
+    let report = migrate_to(&mut doc, 1).expect("Couldn't migrate");
+
+    assert_eq!(report.from_version, 0);
+    assert_eq!(report.to_version, 1);
+    assert!(report.changes.iter().any(|c| c.contains("levels[0].components[1].generic_properties")));
+    assert_eq!(doc, fixture("v0_partial_components.v1.json"));
+}
+
+#[test]
+fn v0_to_v1_creates_a_level_for_levelless_projects() {
+    let mut doc = fixture("v0_no_levels.json");
+
+    migrate_to(&mut doc, 1).expect("Couldn't migrate");
+
+    assert_eq!(doc["project_name"], "old-project");
+    let levels = doc["levels"].as_array().expect("levels should be an array");
+    assert_eq!(levels.len(), 1);
+    assert_eq!(levels[0]["components"], serde_json::json!([]));
+}
+
+#[test]
+fn v1_to_v2_sets_start_level() {
+    let mut doc = fixture("v1_single_level.json");
+
+    let report = migrate_to(&mut doc, 2).expect("Couldn't migrate");
+
+    assert_eq!(report.from_version, 1);
+    assert_eq!(doc, fixture("v1_single_level.v2.json"));
+}
+
+#[test]
+fn v2_to_v3_types_script_state() {
+    let mut doc = fixture("v2_script_state.json");
+
+    let report = migrate_to(&mut doc, 3).expect("Couldn't migrate");
+
+    assert_eq!(report.from_version, 2);
+    assert!(report.changes.iter().any(|c| c.contains("prefabs[0].components[0].script_state")));
+    // so does a prefab instance's overridden state
+    assert!(report.changes.iter().any(|c| c.contains("levels[0].components[1].script_state")));
+    assert_eq!(doc["levels"][0]["components"][1]["script_state"]["pulled"], true);
+    // strings that were only ever strings stay that way, numbers have to read back the same
+    let door = &doc["levels"][0]["components"][0]["script_state"];
+    assert_eq!(door["label"], "front door");
+    assert_eq!((&door["code"], &door["reach"], &door["offset"]), (&json!("007"), &json!("1e5"), &json!("+3")));
+    assert_eq!(doc, fixture("v2_script_state.v3.json"));
+}
+
+#[test]
+fn current_version_is_left_alone() {
+    let original = fixture("v3_current.json");
+    let mut doc = original.clone();
+
+    let report = migrate(&mut doc).expect("Couldn't migrate");
+
+    assert!(!report.migrated());
+    assert!(report.changes.is_empty());
+    assert_eq!(doc, original);
+}
+
+#[test]
+fn newer_versions_are_rejected() {
+    let mut doc = fixture("v3_current.json");
+    doc["schema_version"] = serde_json::json!(CURRENT_SCHEMA_VERSION + 1);
+
+    assert!(migrate(&mut doc).is_err());
+}
+
+#[test]
+fn every_fixture_loads_after_migrating() {
+    for name in [
+        "v0_no_levels.json",
+        "v0_partial_components.json",
+        "v1_single_level.json",
+        "v2_current.json",
+        "v2_script_state.json",
+        "v3_current.json",
+    ] {
+        let mut doc = fixture(name);
+        migrate(&mut doc).expect("Couldn't migrate");
+
+        assert_eq!(schema_version(&doc), CURRENT_SCHEMA_VERSION, "{}", name);
+
+        let state: SavedState = serde_json::from_value(doc).expect(name);
+        assert_eq!(state.schema_version, CURRENT_SCHEMA_VERSION);
+        assert!(state.start_level_id().is_some(), "{}", name);
+    }
+}
+
+#[test]
+fn only_strings_that_read_back_the_same_are_typed() {
+    assert_eq!(typed_script_value("true"), json!(true));
+    assert_eq!(typed_script_value("-12"), json!(-12));
+    assert_eq!(typed_script_value("0.25"), json!(0.25));
+
+    for kept in ["007", "1e5", "+3", "1.50", "-0", "NaN", "True", ""] {
+        assert_eq!(typed_script_value(kept), json!(kept), "{}", kept);
+    }
+}
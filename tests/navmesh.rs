@@ -0,0 +1,199 @@
+use entropy_engine::core::World::World;
+use entropy_engine::game_behaviors::chase::ChaseBehavior;
+use entropy_engine::game_behaviors::navmesh::{NavSource, PathFollower};
+use nalgebra::{UnitQuaternion, Vector3};
+use rapier3d::prelude::*;
+
+// a 40 x 40 floor with its top at y = 0
+fn floor() -> World {
+    let mut world = World::new();
+    add_box(&mut world, [0.0, -0.5, 0.0], [20.0, 0.5, 20.0]);
+    refresh(&mut world);
+    world
+}
+
+fn add_box(world: &mut World, center: [f32; 3], half_extents: [f32; 3]) -> RigidBodyHandle {
+    world
+        .insert_body(
+            RigidBodyBuilder::fixed().translation(vector![center[0], center[1], center[2]]).build(),
+            ColliderBuilder::cuboid(half_extents[0], half_extents[1], half_extents[2]).build(),
+        )
+        .0
+}
+
+// what World::step does for the navmesh after physics
+fn refresh(world: &mut World) {
+    world.update_query_pipeline();
+    world.navmesh.sync(&world.rigid_body_set, &world.collider_set);
+}
+
+fn find_path(world: &mut World, start: [f32; 3], goal: [f32; 3], max_slope: f32) -> Option<Vec<Vector3<f32>>> {
+    let source = NavSource {
+        bodies: &world.rigid_body_set,
+        colliders: &world.collider_set,
+        query_pipeline: &world.query_pipeline,
+    };
+    world
+        .navmesh
+        .find_path(&source, Vector3::from(start), Vector3::from(goal), max_slope)
+}
+
+fn length(path: &[Vector3<f32>]) -> f32 {
+    path.windows(2).map(|pair| (pair[1] - pair[0]).xz().norm()).sum()
+}
+
+// whether any segment passes through the x/z rectangle
+fn crosses(path: &[Vector3<f32>], min: [f32; 2], max: [f32; 2]) -> bool {
+    path.windows(2).any(|pair| {
+        (0..=100).any(|i| {
+            let p = pair[0].lerp(&pair[1], i as f32 / 100.0);
+            p.x > min[0] && p.x < max[0] && p.z > min[1] && p.z < max[1]
+        })
+    })
+}
+
+#[test]
+fn open_ground_gives_a_straight_path() {
+    let mut world = floor();
+    let path = find_path(&mut world, [-5.0, 1.5, 0.0], [5.0, 1.5, 3.0], 45.0).expect("No path");
+
+    assert_eq!(path.len(), 2);
+    assert!((length(&path) - (10.0f32.powi(2) + 9.0).sqrt()).abs() < 0.01);
+    // points are on the ground
+    assert!(path.iter().all(|p| p.y.abs() < 0.01));
+}
+
+#[test]
+fn paths_go_around_walls() {
+    let mut world = floor();
+    // a wall across the middle with a way around past z = 6
+    add_box(&mut world, [0.0, 2.0, 0.0], [0.25, 2.0, 6.0]);
+    refresh(&mut world);
+
+    let path = find_path(&mut world, [-5.0, 1.5, 0.0], [5.0, 1.5, 0.0], 45.0).expect("No path");
+    assert!(path.len() > 2);
+    assert!(!crosses(&path, [-0.25, -6.0], [0.25, 6.0]));
+    // pulled taut around the wall's end rather than following the cells, which would be about 23
+    assert!(length(&path) < 18.0);
+
+    // nothing fits inside a closed box
+    add_box(&mut world, [10.0, 2.0, -10.0], [3.0, 2.0, 0.25]);
+    add_box(&mut world, [10.0, 2.0, -4.0], [3.0, 2.0, 0.25]);
+    add_box(&mut world, [7.0, 2.0, -7.0], [0.25, 2.0, 3.0]);
+    add_box(&mut world, [13.0, 2.0, -7.0], [0.25, 2.0, 3.0]);
+    refresh(&mut world);
+    assert!(find_path(&mut world, [-5.0, 1.5, 0.0], [10.0, 1.5, -7.0], 45.0).is_none());
+}
+
+#[test]
+fn max_slope_decides_which_ramps_are_walkable() {
+    let mut world = floor();
+
+    // a 42 degree ramp rising along x from the floor at x = 2
+    let angle = 42.0f32.to_radians();
+    let (half_length, half_thickness) = (4.0, 0.1);
+    let center = vector![
+        2.0 + half_length * angle.cos() + half_thickness * angle.sin(),
+        half_length * angle.sin() - half_thickness * angle.cos(),
+        0.0
+    ];
+    world.insert_body(
+        RigidBodyBuilder::fixed()
+            .position(Isometry::from_parts(center.into(), UnitQuaternion::from_euler_angles(0.0, 0.0, angle)))
+            .build(),
+        ColliderBuilder::cuboid(half_length, half_thickness, 3.0).build(),
+    );
+    refresh(&mut world);
+
+    let on_ramp = [5.0, 3.0 * angle.tan(), 0.0];
+    let path = find_path(&mut world, [-4.0, 1.5, 0.0], on_ramp, 45.0).expect("No path up the ramp");
+    assert!((path.last().unwrap().y - on_ramp[1]).abs() < 0.3);
+    assert!(find_path(&mut world, [-4.0, 1.5, 0.0], on_ramp, 40.0).is_none());
+}
+
+#[test]
+fn tiles_are_rebuilt_when_colliders_change() {
+    let mut world = floor();
+    let mut follower = PathFollower::default();
+    let start = Vector3::new(-5.0, 1.5, 0.0);
+    let goal = Vector3::new(5.0, 1.5, 0.0);
+
+    let seek = |world: &mut World, follower: &mut PathFollower| {
+        let source = NavSource {
+            bodies: &world.rigid_body_set,
+            colliders: &world.collider_set,
+            query_pipeline: &world.query_pipeline,
+        };
+        follower.seek(&mut world.navmesh, &source, start, goal, 45.0)
+    };
+
+    assert!(seek(&mut world, &mut follower));
+    assert_eq!(follower.path.len(), 2);
+    let built = world.navmesh.built_tiles();
+    assert!(built > 0);
+
+    // unchanged colliders keep the tiles
+    refresh(&mut world);
+    assert_eq!(world.navmesh.built_tiles(), built);
+
+    let revision = world.navmesh.revision;
+    let wall = add_box(&mut world, [0.0, 2.0, 0.0], [0.25, 2.0, 6.0]);
+    refresh(&mut world);
+    assert!(world.navmesh.revision > revision);
+    assert!(world.navmesh.built_tiles() < built);
+
+    // the follower notices and plans around the new wall
+    assert!(seek(&mut world, &mut follower));
+    assert!(follower.path.len() > 2);
+    assert!(!crosses(&follower.path, [-0.25, -6.0], [0.25, 6.0]));
+
+    // and goes straight again once it's gone
+    world.remove_body(wall);
+    refresh(&mut world);
+    assert!(seek(&mut world, &mut follower));
+    assert_eq!(follower.path.len(), 2);
+
+    // moved colliders count too
+    let pillar = add_box(&mut world, [0.0, 2.0, 0.0], [1.0, 2.0, 1.0]);
+    refresh(&mut world);
+    let revision = world.navmesh.revision;
+    world.rigid_body_set.get_mut(pillar).unwrap().set_translation(vector![0.0, 2.0, 8.0], true);
+    world.step_physics();
+    world.navmesh.sync(&world.rigid_body_set, &world.collider_set);
+    assert!(world.navmesh.revision > revision);
+}
+
+#[test]
+fn chasing_npcs_steer_around_walls() {
+    let mut world = floor();
+    add_box(&mut world, [0.0, 2.0, 0.0], [0.25, 2.0, 6.0]);
+
+    let (npc, npc_collider) = world.insert_body(
+        RigidBodyBuilder::dynamic().translation(vector![-3.0, 1.5, 1.0]).build(),
+        ColliderBuilder::capsule_y(1.0, 0.5).build(),
+    );
+    let (target, _) = world.insert_body(
+        RigidBodyBuilder::dynamic().translation(vector![3.0, 1.5, 1.0]).build(),
+        ColliderBuilder::capsule_y(1.0, 0.5).build(),
+    );
+    refresh(&mut world);
+
+    let mut chase = ChaseBehavior::new(100.0, 50.0);
+    let collider = world.collider_set[npc_collider].clone();
+    chase.update(
+        &mut world.rigid_body_set,
+        &world.collider_set,
+        &world.query_pipeline,
+        &mut world.navmesh,
+        npc,
+        target,
+        &collider,
+        Vector3::new(-3.0, 1.5, 1.0),
+        1.0 / 60.0,
+    );
+
+    // heads for the end of the wall instead of straight into it
+    let velocity = world.rigid_body_set[npc].linvel();
+    assert!(velocity.z > velocity.x.abs());
+    assert!(!chase.path().is_empty());
+}
@@ -0,0 +1,171 @@
+use entropy_engine::helpers::project_error::component_errors;
+use entropy_engine::helpers::saved_data::{
+    AttackStats, BehaviorTreeData, CollectableProperties, CollectableType, ComponentData, ComponentKind, DefenseStats, GenericProperties,
+    LevelData, NPCBehaviorKind, NPCProperties, SavedState, StatData,
+};
+use entropy_engine::model_components::NPC::{loadout_item, NPCArchetype, NPCBehavior, NPC, DEFAULT_ATTACK};
+use entropy_engine::validate::{validate_project, Severity, ValidateOptions};
+use rapier3d::prelude::RigidBodyHandle;
+use serde_json::json;
+
+use std::path::PathBuf;
+
+fn collectable(id: &str, asset_id: &str, collectable_type: CollectableType) -> ComponentData {
+    ComponentData {
+        id: id.to_string(),
+        kind: Some(ComponentKind::Collectable),
+        asset_id: asset_id.to_string(),
+        collectable_properties: Some(CollectableProperties {
+            collectable_type: Some(collectable_type),
+            stat_id: Some("potion-stat".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+fn bandit(properties: NPCProperties) -> ComponentData {
+    ComponentData {
+        id: "bandit-id".to_string(),
+        kind: Some(ComponentKind::NPC),
+        asset_id: "bandit-model".to_string(),
+        generic_properties: GenericProperties { name: "Bandit".to_string(), ..Default::default() },
+        npc_properties: Some(properties),
+        ..Default::default()
+    }
+}
+
+fn project() -> SavedState {
+    let sword_stat = StatData {
+        id: "sword-stat".to_string(),
+        name: "Sword".to_string(),
+        attack: Some(AttackStats { damage: 25.0, range: 2.0, cooldown: 1.0, wind_up_time: 0.2, recovery_time: 0.3 }),
+        defense: Some(DefenseStats { block_chance: 0.4 }),
+        ..Default::default()
+    };
+    let patrol: BehaviorTreeData = serde_json::from_value(json!({
+        "id": "patrol",
+        "root": { "type": "Wander", "radius": 10.0, "speed": 2.0 }
+    }))
+    .unwrap();
+
+    SavedState {
+        stats: Some(vec![sword_stat, StatData { id: "potion-stat".to_string(), ..Default::default() }]),
+        behavior_trees: Some(vec![patrol]),
+        ..Default::default()
+    }
+}
+
+fn level_components() -> Vec<ComponentData> {
+    vec![
+        collectable("potion-1", "potion", CollectableType::Item),
+        collectable("sword-1", "sword", CollectableType::MeleeWeapon),
+        collectable("sword-2", "sword", CollectableType::MeleeWeapon),
+    ]
+}
+
+#[test]
+fn older_npc_properties_get_the_defaults() {
+    let properties: NPCProperties = serde_json::from_value(json!({ "model_id": "bandit-model" })).unwrap();
+
+    assert_eq!(properties, NPCProperties { model_id: "bandit-model".to_string(), ..Default::default() });
+    assert_eq!(properties.behavior, NPCBehaviorKind::Wander);
+    assert_eq!((properties.health, properties.stamina), (100.0, 100.0));
+
+    // and what NPC::new starts with matches them
+    let npc = NPC::new("bandit-id".to_string(), "bandit-id".to_string(), RigidBodyHandle::invalid());
+    assert_eq!((npc.stats.health, npc.stats.stamina), (properties.health, properties.stamina));
+    match &npc.test_behavior {
+        NPCBehavior::Wander(wander) => assert_eq!((wander.radius, wander.speed), (properties.wander_radius, properties.wander_speed)),
+        _ => panic!("Expected a wandering NPC"),
+    }
+}
+
+#[test]
+fn archetypes_set_up_behavior_stats_and_loadout() {
+    let saved_state = project();
+    let component = bandit(NPCProperties {
+        model_id: "bandit-model".to_string(),
+        name: Some("Black Bart".to_string()),
+        behavior: NPCBehaviorKind::Melee,
+        chase_speed: 150.0,
+        detection_radius: 30.0,
+        stat_id: Some("sword-stat".to_string()),
+        health: 60.0,
+        stamina: 40.0,
+        inventory: vec!["potion".to_string(), "sword".to_string(), "shield".to_string()],
+        ..Default::default()
+    });
+
+    let archetype = NPCArchetype::new(&saved_state, &component, &level_components());
+    let mut npc = NPC::new(component.id.clone(), component.id.clone(), RigidBodyHandle::invalid());
+    npc.apply_archetype(&archetype);
+
+    assert_eq!(npc.name, "Black Bart");
+    assert_eq!((npc.stats.health, npc.stats.stamina), (60.0, 40.0));
+    match &npc.test_behavior {
+        NPCBehavior::Melee(melee) => {
+            assert_eq!((melee.chase.speed, melee.chase.detection_radius), (150.0, 30.0));
+            assert_eq!(melee.attack.stats.damage, 25.0);
+            assert_eq!(melee.defense.block_chance, 0.4);
+        }
+        _ => panic!("Expected a melee NPC"),
+    }
+
+    // the first sword is equipped, nothing matches the shield. It's the one the level leaves out.
+    assert_eq!(loadout_item(&level_components(), "sword").map(|c| c.id.as_str()), Some("sword-1"));
+    assert_eq!(loadout_item(&level_components(), "potion-1").map(|c| c.id.as_str()), Some("potion-1"));
+    assert_eq!(npc.inventory.equipped_weapon.as_ref().map(|w| w.id.as_str()), Some("sword-1"));
+    assert_eq!(npc.inventory.items.iter().map(|i| i.id.as_str()).collect::<Vec<_>>(), vec!["potion-1"]);
+    assert!(npc.inventory.equipped_armor.is_none());
+
+    // without a stat it fights with the defaults, and uses the component's name
+    let component = bandit(NPCProperties { behavior: NPCBehaviorKind::Ranged, ..Default::default() });
+    npc.apply_archetype(&NPCArchetype::new(&saved_state, &component, &[]));
+    assert_eq!(npc.name, "Bandit");
+    match &npc.test_behavior {
+        NPCBehavior::Ranged(ranged) => assert_eq!(ranged.attack.stats, DEFAULT_ATTACK),
+        _ => panic!("Expected a ranged NPC"),
+    }
+    assert!(npc.inventory.items.is_empty() && npc.inventory.equipped_weapon.is_none());
+
+    // a behavior tree takes over from the behavior kind
+    let component = bandit(NPCProperties {
+        behavior: NPCBehaviorKind::Melee,
+        behavior_tree_id: Some("patrol".to_string()),
+        ..Default::default()
+    });
+    npc.apply_archetype(&NPCArchetype::new(&saved_state, &component, &[]));
+    assert_eq!(npc.test_behavior.tree().map(|t| t.id.as_str()), Some("patrol"));
+}
+
+#[test]
+fn missing_stats_and_items_are_reported() {
+    let mut saved_state = project();
+    let component = bandit(NPCProperties {
+        model_id: "bandit-model".to_string(),
+        stat_id: Some("axe-stat".to_string()),
+        inventory: vec!["sword-2".to_string(), "axe".to_string()],
+        ..Default::default()
+    });
+    let mut components = level_components();
+    components.push(component.clone());
+
+    let errors: Vec<String> = component_errors(&saved_state, &components, &component).iter().map(|e| e.to_string()).collect();
+    assert!(errors.iter().any(|e| e.contains("axe-stat")), "{:?}", errors);
+
+    saved_state.levels = Some(vec![LevelData { id: "level-a".to_string(), components: Some(components), ..Default::default() }]);
+    let options = ValidateOptions {
+        project_dir: PathBuf::from("tests/fixtures"),
+        script_root: PathBuf::from("."),
+    };
+    let report = validate_project(&saved_state, &options);
+    let inventory_errors: Vec<&str> = report
+        .issues
+        .iter()
+        .filter(|i| i.check == "npc_inventory" && i.severity == Severity::Error)
+        .map(|i| i.message.as_str())
+        .collect();
+
+    assert_eq!(inventory_errors, vec!["NPC carries axe, which no collectable in the level matches"]);
+}
@@ -0,0 +1,151 @@
+use entropy_engine::helpers::pak::{mount_pak, pack_project, read_project_file, read_script, PakArchive, PAK_PROJECT_FILE};
+use entropy_engine::helpers::saved_data::SavedState;
+use serde_json::json;
+
+use std::fs;
+use std::path::PathBuf;
+
+// A throwaway project with one model, one heightmap, a global script and scripts only a prefab
+// member and a behavior tree leaf use
+fn scratch_project(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("entropy-pak-{}-{}", name, uuid::Uuid::new_v4()));
+    let project_dir = root.join(name);
+
+    fs::create_dir_all(project_dir.join("models")).expect("Couldn't create models dir");
+    fs::create_dir_all(project_dir.join("landscapes/land-1/heightmaps")).expect("Couldn't create landscapes dir");
+    fs::create_dir_all(root.join("scripts")).expect("Couldn't create scripts dir");
+
+    let project = json!({
+        "schema_version": 2,
+        "id": name,
+        "project_name": "Packed",
+        "concepts": [],
+        "models": [],
+        "global_rhai_scripts": ["scripts/global.rhai"],
+        "levels": [{ "id": "level-a", "components": [] }],
+        "prefabs": [{ "id": "door-prefab", "name": "Door", "components": [{
+            "id": "door-member",
+            "kind": "Model",
+            "asset_id": "door-model",
+            "generic_properties": { "name": "Door", "position": [0.0, 0.0, 0.0], "rotation": [0.0, 0.0, 0.0], "scale": [1.0, 1.0, 1.0] },
+            "rhai_script_path": "scripts/door.rhai"
+        }] }],
+        "behavior_trees": [{ "id": "guard", "root": { "type": "Script", "function": "look_around", "script": "scripts/guard.rhai" } }]
+    });
+    fs::write(project_dir.join("midpoint.json"), project.to_string()).expect("Couldn't write project");
+    fs::write(project_dir.join("midpoint.v1.backup.json"), "{}").expect("Couldn't write backup");
+    fs::write(project_dir.join("models/Player.glb"), b"glTF fake model").expect("Couldn't write model");
+    fs::write(project_dir.join("landscapes/land-1/heightmaps/height.png"), b"not really a png").expect("Couldn't write heightmap");
+    fs::write(root.join("scripts/global.rhai"), "let x = 1;").expect("Couldn't write script");
+    fs::write(root.join("scripts/door.rhai"), "let open = false;").expect("Couldn't write script");
+    fs::write(root.join("scripts/guard.rhai"), "fn look_around(npc, system, script_state, task) { task.succeed(); }").expect("Couldn't write script");
+
+    root
+}
+
+#[test]
+fn packed_files_read_back_unchanged() {
+    let root = scratch_project("pak-roundtrip");
+    let output = root.join("game.pak");
+
+    let manifest = pack_project(root.join("pak-roundtrip").to_str().unwrap(), &root, &output).expect("Couldn't pack");
+
+    let paths: Vec<&str> = manifest.entries.iter().map(|e| e.path.as_str()).collect();
+    assert!(paths.contains(&PAK_PROJECT_FILE));
+    assert!(paths.contains(&"models/Player.glb"));
+    assert!(paths.contains(&"landscapes/land-1/heightmaps/height.png"));
+    assert!(paths.contains(&"scripts/global.rhai"));
+    assert!(paths.contains(&"scripts/door.rhai"));
+    assert!(paths.contains(&"scripts/guard.rhai"));
+    assert!(!paths.iter().any(|p| p.contains("backup")));
+
+    let archive = PakArchive::open(&output).expect("Couldn't open package");
+    assert_eq!(archive.read("models/Player.glb").unwrap(), b"glTF fake model");
+    assert!(archive.verify().is_empty());
+
+    let state: SavedState = serde_json::from_slice(&archive.read(PAK_PROJECT_FILE).unwrap()).expect("Couldn't parse packed project");
+    assert_eq!(state.project_name, "Packed");
+
+    fs::remove_dir_all(&root).ok();
+}
+
+#[test]
+fn mounted_packages_serve_project_files_and_scripts() {
+    let root = scratch_project("pak-mounted");
+    let output = root.join("game.pak");
+
+    pack_project(root.join("pak-mounted").to_str().unwrap(), &root, &output).expect("Couldn't pack");
+    // the loose files shouldn't be needed once the package is mounted
+    fs::remove_dir_all(root.join("pak-mounted")).expect("Couldn't remove project");
+    fs::remove_dir_all(root.join("scripts")).expect("Couldn't remove scripts");
+
+    let project_id = mount_pak(&output).expect("Couldn't mount package");
+    assert_eq!(project_id, "pak-mounted");
+
+    assert_eq!(read_project_file(&project_id, "models/Player.glb").unwrap(), b"glTF fake model");
+    assert_eq!(read_script("scripts/global.rhai").unwrap(), "let x = 1;");
+    assert!(read_project_file(&project_id, "models/Missing.glb").is_err());
+
+    fs::remove_dir_all(&root).ok();
+}
+
+#[test]
+fn corrupted_entries_fail_their_hash() {
+    let root = scratch_project("pak-corrupt");
+    let output = root.join("game.pak");
+
+    pack_project(root.join("pak-corrupt").to_str().unwrap(), &root, &output).expect("Couldn't pack");
+
+    // flip the last byte, which belongs to the last entry
+    let mut bytes = fs::read(&output).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    fs::write(&output, bytes).unwrap();
+
+    let archive = PakArchive::open(&output).expect("Couldn't open package");
+    let last_entry = archive.manifest.entries.iter().max_by_key(|e| e.offset).unwrap().path.clone();
+
+    assert_eq!(archive.verify(), vec![last_entry.clone()]);
+    assert!(archive.read(&last_entry).is_err());
+
+    fs::remove_dir_all(&root).ok();
+}
+
+#[test]
+fn manifest_lengths_past_the_end_are_rejected() {
+    let root = scratch_project("pak-length");
+    let output = root.join("game.pak");
+
+    pack_project(root.join("pak-length").to_str().unwrap(), &root, &output).expect("Couldn't pack");
+
+    // the manifest length follows the magic and version
+    let mut bytes = fs::read(&output).unwrap();
+    bytes[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+    fs::write(&output, bytes).unwrap();
+
+    let error = PakArchive::open(&output).err().expect("Expected the length to be rejected");
+    assert!(error.contains("manifest"), "{}", error);
+
+    fs::remove_dir_all(&root).ok();
+}
+
+#[test]
+fn entries_past_the_end_are_rejected() {
+    let root = scratch_project("pak-truncated");
+    let output = root.join("game.pak");
+
+    pack_project(root.join("pak-truncated").to_str().unwrap(), &root, &output).expect("Couldn't pack");
+
+    // cut the last entry short, its manifest size now points past the end
+    let mut bytes = fs::read(&output).unwrap();
+    bytes.truncate(bytes.len() - 4);
+    fs::write(&output, bytes).unwrap();
+
+    let archive = PakArchive::open(&output).expect("Couldn't open package");
+    let last_entry = archive.manifest.entries.iter().max_by_key(|e| e.offset).unwrap().path.clone();
+
+    let error = archive.read(&last_entry).unwrap_err();
+    assert!(error.contains("past the end"), "{}", error);
+
+    fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,120 @@
+use entropy_engine::helpers::prefabs::{apply_to_prefab, spawn_template, update_prefab};
+use entropy_engine::helpers::project_error::{component_errors, ProjectError};
+use entropy_engine::helpers::saved_data::{ComponentData, ComponentKind, SavedState};
+use entropy_engine::helpers::utilities::{parse_project_state, serialize_project_state};
+use serde_json::Value;
+
+use std::fs;
+use std::path::PathBuf;
+
+fn load_fixture() -> SavedState {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/prefabs/torches.json");
+    let content = fs::read_to_string(&path).expect("Couldn't read fixture");
+    let (state, _) = parse_project_state(&content, "torches.json").expect("Couldn't parse fixture");
+    state
+}
+
+fn component<'a>(state: &'a SavedState, id: &str) -> &'a ComponentData {
+    state
+        .level_components(Some("level-a"))
+        .and_then(|components| components.iter().find(|c| c.id == id))
+        .expect("Couldn't find component")
+}
+
+#[test]
+fn instances_resolve_against_their_prefab() {
+    let state = load_fixture();
+
+    let torch = component(&state, "torch-1");
+    assert_eq!(torch.kind, Some(ComponentKind::PointLight));
+    assert_eq!(torch.generic_properties.name, "Torch");
+    assert_eq!(torch.generic_properties.position, [4.0, 1.5, -2.0]);
+    assert_eq!(torch.light_properties.as_ref().unwrap().intensity, 2.0);
+
+    // nested overrides only replace the fields they name
+    let bright = component(&state, "torch-2").light_properties.as_ref().unwrap();
+    assert_eq!(bright.intensity, 5.0);
+    assert_eq!(bright.color, [1.0, 0.5, 0.25, 1.0]);
+}
+
+#[test]
+fn instances_are_saved_as_overrides_only() {
+    let state = load_fixture();
+
+    let doc: Value = serde_json::from_str(&serialize_project_state(&state).expect("Couldn't serialize")).unwrap();
+    let components = doc["levels"][0]["components"].as_array().unwrap();
+
+    let torch = components[0].as_object().unwrap();
+    let mut keys: Vec<&str> = torch.keys().map(String::as_str).collect();
+    keys.sort();
+    assert_eq!(keys, vec!["generic_properties", "id", "prefab"]);
+    assert_eq!(torch["generic_properties"], serde_json::json!({ "position": [4.0, 1.5, -2.0] }));
+
+    assert_eq!(components[1]["light_properties"], serde_json::json!({ "intensity": 5.0 }));
+
+    // and reading it back gives the same project
+    let (reloaded, _) = parse_project_state(&doc.to_string(), "saved").expect("Couldn't reparse");
+    assert_eq!(reloaded, state);
+}
+
+#[test]
+fn prefab_edits_reach_instances_but_keep_overrides() {
+    let mut state = load_fixture();
+
+    let mut prefab = state.prefab("torch").unwrap().clone();
+    let light = prefab.components[0].light_properties.as_mut().unwrap();
+    light.intensity = 3.0;
+    light.color = [0.5, 0.5, 1.0, 1.0];
+
+    let updated = update_prefab(&mut state, prefab);
+    assert_eq!(updated, vec!["torch-1".to_string(), "torch-2".to_string()]);
+
+    let torch = component(&state, "torch-1");
+    assert_eq!(torch.light_properties.as_ref().unwrap().intensity, 3.0);
+    assert_eq!(torch.generic_properties.position, [4.0, 1.5, -2.0]);
+
+    let bright = component(&state, "torch-2").light_properties.as_ref().unwrap();
+    assert_eq!(bright.intensity, 5.0);
+    assert_eq!(bright.color, [0.5, 0.5, 1.0, 1.0]);
+}
+
+#[test]
+fn applying_an_instance_keeps_other_placements() {
+    let mut state = load_fixture();
+
+    apply_to_prefab(&mut state, "torch-2");
+
+    assert_eq!(state.prefab("torch").unwrap().components[0].light_properties.as_ref().unwrap().intensity, 5.0);
+
+    let torch = component(&state, "torch-1");
+    assert_eq!(torch.light_properties.as_ref().unwrap().intensity, 5.0);
+    assert_eq!(torch.generic_properties.position, [4.0, 1.5, -2.0]);
+}
+
+#[test]
+fn missing_prefabs_are_reported() {
+    let state = load_fixture();
+    let components = state.level_components(Some("level-a")).unwrap();
+
+    let errors = component_errors(&state, components, component(&state, "torch-3"));
+    assert_eq!(
+        errors,
+        vec![ProjectError::DanglingReference {
+            component_id: "torch-3".to_string(),
+            field: "prefab".to_string(),
+            target_id: "lantern".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn spawns_use_the_prefab_first_member() {
+    let state = load_fixture();
+
+    let torch = spawn_template(&state, "torch").expect("Couldn't make a spawn template");
+    assert_eq!(torch.kind, Some(ComponentKind::PointLight));
+    assert_eq!(torch.prefab.as_ref().map(|p| p.prefab_id.as_str()), Some("torch"));
+    assert!(torch.parent_id.is_none());
+
+    assert!(spawn_template(&state, "not-an-asset").is_none());
+}
@@ -0,0 +1,127 @@
+use entropy_engine::headless::HeadlessSim;
+use entropy_engine::helpers::project_error::{check_component, ProjectError};
+use entropy_engine::helpers::utilities::{load_project_state, parse_project_state};
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Once;
+
+const FIXTURES: &str = "tests/fixtures/project_errors";
+const GUARD_ID: &str = "6e5d4c3b-2a19-4f8e-9d7c-6b5a4f3e2d1c";
+const GHOST_ID: &str = "0f1e2d3c-4b5a-4968-8776-655443322110";
+
+fn fixture(name: &str) -> String {
+    fs::read_to_string(format!("{}/{}", FIXTURES, name)).expect("Couldn't read fixture")
+}
+
+// every test in this binary shares one projects dir, each project gets its own folder in it
+fn install_project(project_id: &str, fixture_name: Option<&str>) {
+    static PROJECTS_DIR: Once = Once::new();
+    let root = std::env::temp_dir().join(format!("entropy-project-errors-{}", std::process::id()));
+    PROJECTS_DIR.call_once(|| unsafe { std::env::set_var("ENTROPY_PROJECTS_DIR", &root) });
+
+    let project_dir = root.join(project_id);
+    fs::create_dir_all(&project_dir).expect("Couldn't create project dir");
+    if let Some(name) = fixture_name {
+        fs::write(project_dir.join("midpoint.json"), fixture(name)).expect("Couldn't write project");
+    }
+}
+
+#[test]
+fn syntax_errors_point_at_the_line() {
+    let error = parse_project_state(&fixture("trailing_comma.json"), "midpoint.json").unwrap_err();
+
+    match &error {
+        ProjectError::Parse { path, line, column, .. } => {
+            assert_eq!(path, "midpoint.json");
+            assert_eq!((*line, *column), (7, 1));
+        }
+        other => panic!("Expected a parse error, got {:?}", other),
+    }
+    assert!(error.to_string().starts_with("Couldn't parse midpoint.json at line 7, column 1"));
+}
+
+#[test]
+fn missing_fields_point_at_the_line() {
+    // the document is valid JSON, the error only shows up once it's read into a SavedState
+    let error = parse_project_state(&fixture("missing_file_name.json"), "midpoint.json").unwrap_err();
+
+    match &error {
+        ProjectError::Parse { message, line, .. } => {
+            assert!(message.contains("missing field `fileName`"), "{}", message);
+            assert_eq!(*line, 10);
+        }
+        other => panic!("Expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_errors_without_a_position_leave_it_out() {
+    let error = ProjectError::Parse {
+        path: "midpoint.json".to_string(),
+        message: "invalid type".to_string(),
+        line: 0,
+        column: 0,
+    };
+
+    assert_eq!(error.to_string(), "Couldn't parse midpoint.json: invalid type");
+}
+
+#[tokio::test]
+async fn missing_projects_name_the_file() {
+    install_project("no-midpoint", None);
+
+    let error = load_project_state("no-midpoint").await.unwrap_err();
+
+    match &error {
+        ProjectError::MissingFile { path, .. } => {
+            assert!(PathBuf::from(path).ends_with("no-midpoint/midpoint.json"), "{}", path)
+        }
+        other => panic!("Expected a missing file, got {:?}", other),
+    }
+    assert_eq!(error.component_id(), None);
+}
+
+#[tokio::test]
+async fn broken_components_are_skipped_and_the_rest_load() {
+    install_project("broken-component", Some("broken_component.json"));
+
+    let state = load_project_state("broken-component").await.expect("Expected the project to load");
+    let components = state.level_components(None).unwrap();
+    assert_eq!(components.len(), 2);
+
+    assert_eq!(check_component(&state, components, &components[0]), Ok(()));
+    let error = check_component(&state, components, &components[1]).unwrap_err();
+    assert_eq!(
+        error,
+        ProjectError::DanglingReference {
+            component_id: GHOST_ID.to_string(),
+            field: "model".to_string(),
+            target_id: "deleted-model".to_string(),
+        }
+    );
+    assert_eq!(error.component_id(), Some(GHOST_ID));
+
+    // placing the level leaves the ghost out and keeps going
+    let mut sim = HeadlessSim::new("broken-component", state);
+    sim.place_components();
+
+    assert_eq!(sim.world.npcs.len(), 1);
+    assert_eq!(sim.world.npcs[0].id, GUARD_ID);
+    assert_eq!(sim.skipped_components, vec![error.to_string()]);
+}
+
+#[tokio::test]
+async fn missing_heightmaps_skip_the_landscape() {
+    install_project("missing-heightmap", Some("missing_heightmap.json"));
+
+    let state = load_project_state("missing-heightmap").await.expect("Expected the project to load");
+    let mut sim = HeadlessSim::new("missing-heightmap", state);
+    sim.place_components();
+
+    // the guard still loads, the landscape is listed instead of taking the sim down
+    assert_eq!(sim.world.npcs.len(), 1);
+    assert_eq!(sim.world.rigid_body_set.len(), 1);
+    assert_eq!(sim.skipped_components.len(), 1);
+    assert!(sim.skipped_components[0].contains("Missing file landscapes/hills/heightmaps/hills.tif"), "{:?}", sim.skipped_components);
+}
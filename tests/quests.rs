@@ -0,0 +1,124 @@
+use entropy_engine::core::World::World;
+use entropy_engine::game_behaviors::quests::{QuestEvent, QuestLog};
+use entropy_engine::helpers::saved_data::{ComponentData, ObjectiveKind, QuestData, QuestObjective, QuestStage};
+use entropy_engine::model_components::NPC::NPC;
+use rapier3d::prelude::*;
+
+const BANDIT_ID: &str = "0c7e5a3b-2d1f-4e6a-9b8c-1a2b3c4d5e6f";
+
+fn objective(id: &str, kind: ObjectiveKind) -> QuestObjective {
+    QuestObjective {
+        id: id.to_string(),
+        description: id.to_string(),
+        kind,
+    }
+}
+
+fn stage(id: &str, objectives: Vec<QuestObjective>) -> QuestStage {
+    QuestStage {
+        id: id.to_string(),
+        description: id.to_string(),
+        objectives,
+    }
+}
+
+// talk to the elder, bring back three herbs, deal with the bandit
+fn herbalist() -> QuestData {
+    QuestData {
+        id: "herbalist".to_string(),
+        name: "The Herbalist".to_string(),
+        description: String::new(),
+        stages: vec![
+            stage("ask", vec![objective("talk", ObjectiveKind::Talk { npc_id: "elder".to_string(), node: Some("herbs".to_string()) })]),
+            stage("gather", vec![objective("herbs", ObjectiveKind::Collect { item_id: "herb".to_string(), count: 3 })]),
+            stage("bandit", vec![objective("kill", ObjectiveKind::Kill { npc_id: BANDIT_ID.to_string() })]),
+        ],
+    }
+}
+
+fn herb(id: &str) -> ComponentData {
+    ComponentData {
+        id: id.to_string(),
+        asset_id: "herb".to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn objectives_advance_stages_until_complete() {
+    let mut log = QuestLog::new(vec![herbalist()]);
+
+    // nothing counts before the quest is started
+    log.notify(&QuestEvent::Talked { npc_id: "elder".to_string(), node: "herbs".to_string() });
+    assert!(!log.is_active("herbalist"));
+
+    assert!(log.start("herbalist"));
+    assert!(!log.start("herbalist"));
+    assert_eq!(log.stage_id("herbalist"), "ask");
+
+    // only the named node counts
+    log.notify(&QuestEvent::Talked { npc_id: "elder".to_string(), node: "start".to_string() });
+    assert_eq!(log.stage_id("herbalist"), "ask");
+    let advanced = log.notify(&QuestEvent::Talked { npc_id: "elder".to_string(), node: "herbs".to_string() });
+    assert_eq!(advanced, vec!["herbalist".to_string()]);
+    assert_eq!(log.stage_id("herbalist"), "gather");
+
+    log.notify(&QuestEvent::Collected(herb("herb-1")));
+    log.notify(&QuestEvent::Collected(herb("herb-2")));
+
+    let journal = log.journal();
+    assert_eq!(journal[0].objectives, vec![("herbs".to_string(), 2, 3)]);
+    assert!(!journal[0].complete);
+
+    log.notify(&QuestEvent::Collected(herb("herb-3")));
+    assert_eq!(log.stage_id("herbalist"), "bandit");
+
+    log.notify(&QuestEvent::Killed(BANDIT_ID.to_string()));
+    assert!(log.is_complete("herbalist"));
+    assert!(!log.is_active("herbalist"));
+    assert!(log.journal()[0].complete);
+}
+
+#[test]
+fn scripts_can_skip_stages() {
+    let mut log = QuestLog::new(vec![herbalist()]);
+
+    assert!(!log.advance("herbalist"));
+    assert!(!log.start("missing"));
+
+    log.start("herbalist");
+    assert!(log.advance("herbalist"));
+    assert!(log.advance("herbalist"));
+    assert_eq!(log.stage_id("herbalist"), "bandit");
+    assert!(log.advance("herbalist"));
+    assert!(log.is_complete("herbalist"));
+    assert!(!log.advance("herbalist"));
+}
+
+#[test]
+fn observed_deaths_count_once_and_not_before_priming() {
+    let mut world = World::new();
+    let (body, _) = world.insert_body(
+        RigidBodyBuilder::dynamic().build(),
+        ColliderBuilder::capsule_y(1.0, 0.5).build(),
+    );
+    world.npcs.push(NPC::new(BANDIT_ID.to_string(), BANDIT_ID.to_string(), body));
+
+    let mut log = QuestLog::new(vec![herbalist()]);
+    log.start("herbalist");
+    log.advance("herbalist");
+    log.advance("herbalist");
+
+    // a bandit that was already dead when the level loaded doesn't count
+    world.npcs[0].stats.health = 0.0;
+    log.observe(&world, &[]);
+    assert!(log.is_active("herbalist"));
+
+    // but one that dies while we watch does
+    world.npcs[0].stats.health = 100.0;
+    log.reset_observed();
+    log.observe(&world, &[]);
+    world.npcs[0].stats.health = 0.0;
+    assert_eq!(log.observe(&world, &[]), vec!["herbalist".to_string()]);
+    assert!(log.is_complete("herbalist"));
+}
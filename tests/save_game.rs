@@ -0,0 +1,149 @@
+use entropy_engine::core::World::World;
+use entropy_engine::helpers::save_game::{is_valid_slot, list_saves, read_save, write_save, SaveGame, SAVE_FORMAT_VERSION};
+use entropy_engine::helpers::saved_data::{CollectableType, ComponentData, ComponentKind, StatData};
+use entropy_engine::model_components::{Collectable::Collectable, NPC::NPC};
+use rapier3d::prelude::*;
+use serde_json::json;
+
+use std::fs;
+
+const NPC_ID: &str = "6f1c2f0e-8f5b-4d4e-9a51-0d3c1a2b3c4d";
+const COIN_ID: &str = "9b2d7c1a-3e4f-4a5b-8c6d-7e8f9a0b1c2d";
+
+// one npc and one coin, the way place_level leaves them
+fn placed_world() -> World {
+    let mut world = World::new();
+
+    let (npc_body, _) = world.insert_body(
+        RigidBodyBuilder::dynamic().translation(vector![0.0, 1.0, 0.0]).build(),
+        ColliderBuilder::capsule_y(1.0, 0.5).build(),
+    );
+    world.npcs.push(NPC::new(NPC_ID.to_string(), NPC_ID.to_string(), npc_body));
+
+    let (coin_body, _) = world.insert_body(
+        RigidBodyBuilder::fixed().translation(vector![5.0, 0.0, 5.0]).build(),
+        ColliderBuilder::ball(0.5).sensor(true).build(),
+    );
+    world.collectables.push(Collectable::new(
+        COIN_ID.to_string(),
+        "coin-model".to_string(),
+        CollectableType::Item,
+        StatData::default(),
+        coin_body,
+    ));
+
+    world
+}
+
+fn level_components() -> Vec<ComponentData> {
+    vec![ComponentData {
+        id: COIN_ID.to_string(),
+        kind: Some(ComponentKind::Collectable),
+        ..Default::default()
+    }]
+}
+
+#[test]
+fn restoring_puts_npcs_and_pickups_back_as_saved() {
+    let mut world = placed_world();
+
+    // play a little: the npc is killed where it stands and the coin gets picked up
+    world.npcs[0].stats.health = 0.0;
+    let npc_body = world.npcs[0].rigid_body_handle;
+    world.rigid_body_set.get_mut(npc_body).unwrap().set_translation(vector![3.0, 1.0, -2.0], true);
+    world.collectables.clear();
+
+    let save = SaveGame::capture("project", "level-a", &world, &level_components());
+    assert_eq!(save.format_version, SAVE_FORMAT_VERSION);
+    assert_eq!(save.collected, vec![COIN_ID.to_string()]);
+    assert!(!save.npcs[0].alive);
+
+    // a freshly placed level comes back with everything reset
+    let mut fresh = placed_world();
+    let removed = save.restore(&mut fresh);
+
+    assert_eq!(removed, vec!["coin-model".to_string()]);
+    assert!(fresh.collectables.is_empty());
+    assert_eq!(fresh.rigid_body_set.len(), 1);
+
+    let npc = &fresh.npcs[0];
+    assert!(!npc.is_alive());
+    let position = fresh.body_position(npc.rigid_body_handle).unwrap();
+    assert_eq!([position.x, position.y, position.z], [3.0, 1.0, -2.0]);
+}
+
+#[test]
+fn destroyed_npcs_stay_gone_after_loading() {
+    let mut world = placed_world();
+    let mut components = level_components();
+    components.push(ComponentData {
+        id: NPC_ID.to_string(),
+        kind: Some(ComponentKind::NPC),
+        ..Default::default()
+    });
+
+    // a script destroys the npc, which takes it out of the world rather than killing it
+    let npc = world.npcs.remove(0);
+    world.remove_body(npc.rigid_body_handle);
+
+    let save = SaveGame::capture("project", "level-a", &world, &components);
+    assert_eq!(save.destroyed, vec![NPC_ID.to_string()]);
+    assert!(save.npcs.is_empty());
+
+    let mut fresh = placed_world();
+    let removed = save.restore(&mut fresh);
+
+    assert_eq!(removed, vec![NPC_ID.to_string()]);
+    assert!(fresh.npcs.is_empty());
+    assert_eq!(fresh.collectables.len(), 1);
+    assert_eq!(fresh.rigid_body_set.len(), 1);
+}
+
+#[test]
+fn saves_round_trip_through_slots() {
+    let dir = std::env::temp_dir().join(format!("entropy-saves-{}", uuid::Uuid::new_v4()));
+    // only this test touches the saves dir
+    unsafe { std::env::set_var("ENTROPY_SAVES_DIR", &dir) };
+
+    let mut save = SaveGame::capture("project", "level-a", &placed_world(), &level_components());
+    save.script_states.insert(
+        "door".to_string(),
+        [("open".to_string(), json!(true)), ("hinges".to_string(), json!([1, 2.5, { "oiled": false }]))].into(),
+    );
+    save.dialogue.current_node = "greeting".to_string();
+    save.blackboard.set_var("villager-id", "storms_loved", json!(2));
+    save.blackboard.set_global("dragon_slain", json!(false));
+    save.blackboard.visit("villager-id", "greeting");
+
+    write_save("slot1", &save).expect("Couldn't write save");
+    assert_eq!(list_saves("project"), vec!["slot1".to_string()]);
+
+    let loaded = read_save("project", "slot1").expect("Couldn't read save");
+    assert_eq!(loaded.level_id, "level-a");
+    assert_eq!(loaded.script_states["door"]["open"], json!(true));
+    assert_eq!(loaded.script_states["door"]["hinges"], json!([1, 2.5, { "oiled": false }]));
+    assert_eq!(loaded.dialogue.current_node, "greeting");
+    assert_eq!(loaded.blackboard, save.blackboard);
+    assert_eq!(loaded.npcs[0].stats.health, 100.0);
+
+    // saves from a newer engine are refused rather than half-loaded
+    save.format_version = SAVE_FORMAT_VERSION + 1;
+    write_save("future", &save).expect("Couldn't write save");
+    assert!(read_save("project", "future").is_err());
+
+    // v1 saves flattened script state to strings, reading brings the types back
+    let mut old = serde_json::to_value(&save).unwrap();
+    old["format_version"] = json!(1);
+    old["script_states"] = json!({ "door": { "open": "true", "count": "4", "name": "front" } });
+    fs::write(dir.join("project").join("old.json"), old.to_string()).expect("Couldn't write save");
+
+    let upgraded = read_save("project", "old").expect("Couldn't read save");
+    assert_eq!(upgraded.script_states["door"]["open"], json!(true));
+    assert_eq!(upgraded.script_states["door"]["count"], json!(4));
+    assert_eq!(upgraded.script_states["door"]["name"], json!("front"));
+
+    assert!(!is_valid_slot("../midpoint"));
+    assert!(write_save("../midpoint", &save).is_err());
+
+    fs::remove_dir_all(&dir).ok();
+}
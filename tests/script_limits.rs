@@ -0,0 +1,138 @@
+mod common;
+
+use common::{cleanup, run, scratch_script};
+use entropy_engine::helpers::saved_data::{ScriptLimits, ScriptSettings};
+use entropy_engine::rhai_engine::{RhaiEngine, ScriptErrorKind, DEFAULT_MAX_OPERATIONS};
+use serde_json::json;
+use std::collections::HashMap;
+
+const COUNT_TO_5000: &str = "fn on_update(component, system, script_state) {
+    let total = 0;
+    for i in 0..5000 { total += 1; }
+    script_state[\"total\"] = total;
+    script_state
+}";
+
+#[test]
+fn endless_loops_stop_at_the_operation_limit() {
+    let path = scratch_script("loop", "fn on_update(component, system, script_state) {\n    loop { }\n}");
+    let mut engine = RhaiEngine::new();
+    let mut state = None;
+
+    run(&mut engine, "spinner", &path, &mut state);
+
+    let error = engine.errors.last().expect("Expected the loop to be stopped");
+    assert_eq!(error.kind, ScriptErrorKind::Runtime);
+    assert_eq!(error.owner_id.as_deref(), Some("spinner"));
+    assert_eq!(engine.limits_for(path.to_str().unwrap()).max_operations, Some(DEFAULT_MAX_OPERATIONS));
+
+    cleanup(&path);
+}
+
+#[test]
+fn scripts_can_have_their_own_limits() {
+    let path = scratch_script("count", COUNT_TO_5000);
+    let mut engine = RhaiEngine::new();
+    let mut state = None;
+
+    run(&mut engine, "counter", &path, &mut state);
+    assert_eq!(state.as_ref().unwrap()["total"], json!(5000));
+
+    let mut overrides = HashMap::new();
+    overrides.insert(path.to_str().unwrap().to_string(), ScriptLimits { max_operations: Some(1000), ..Default::default() });
+    engine.configure(&Some(ScriptSettings {
+        limits: ScriptLimits { max_string_size: Some(64), ..Default::default() },
+        overrides,
+        ..Default::default()
+    }));
+
+    // the override sits on top of the project's limits, which sit on top of the defaults
+    let limits = engine.limits_for(path.to_str().unwrap());
+    assert_eq!(limits.max_operations, Some(1000));
+    assert_eq!(limits.max_string_size, Some(64));
+    assert!(limits.max_array_size.is_some());
+
+    state = None;
+    run(&mut engine, "counter", &path, &mut state);
+    assert!(state.is_none());
+    assert_eq!(engine.errors.last().unwrap().kind, ScriptErrorKind::Runtime);
+
+    cleanup(&path);
+}
+
+#[test]
+fn string_growth_is_capped() {
+    let path = scratch_script(
+        "strings",
+        "fn on_update(component, system, script_state) {\n    let s = \"xx\";\n    for i in 0..40 { s += s; }\n    script_state\n}",
+    );
+    let mut engine = RhaiEngine::new();
+    let mut state = None;
+
+    run(&mut engine, "doubler", &path, &mut state);
+    assert_eq!(engine.errors.last().unwrap().kind, ScriptErrorKind::Runtime);
+
+    cleanup(&path);
+}
+
+#[test]
+fn the_frame_budget_stops_slow_hooks_and_skips_the_rest() {
+    let slow = scratch_script(
+        "slow",
+        "fn on_update(component, system, script_state) {\n    let i = 0;\n    while i < 100000000 { i += 1; }\n    script_state[\"done\"] = true;\n    script_state\n}",
+    );
+    let fast = scratch_script("fast", COUNT_TO_5000);
+    let mut engine = RhaiEngine::new();
+    engine.configure(&Some(ScriptSettings {
+        limits: ScriptLimits { max_operations: Some(0), ..Default::default() }, // no operation limit
+        frame_budget_ms: Some(1.0),
+        ..Default::default()
+    }));
+
+    let mut slow_state = None;
+    let mut fast_state = None;
+
+    engine.begin_frame();
+    run(&mut engine, "slow-id", &slow, &mut slow_state);
+    run(&mut engine, "fast-id", &fast, &mut fast_state);
+    engine.end_frame();
+
+    assert!(slow_state.is_none());
+    assert!(fast_state.is_none());
+
+    let budget = engine.errors.iter().find(|e| e.kind == ScriptErrorKind::Budget).expect("Expected a budget error");
+    assert_eq!(budget.owner_id.as_deref(), Some("slow-id"));
+    assert_eq!(budget.script_path, slow.to_str().unwrap());
+
+    // the next frame starts fresh. It runs without a budget so a slow machine can't fail it.
+    engine.configure(&Some(ScriptSettings { frame_budget_ms: Some(0.0), ..Default::default() }));
+    engine.begin_frame();
+    run(&mut engine, "fast-id", &fast, &mut fast_state);
+    engine.end_frame();
+    assert_eq!(fast_state.unwrap()["total"], json!(5000));
+
+    cleanup(&slow);
+    cleanup(&fast);
+}
+
+#[test]
+fn eval_and_imports_are_off_by_default() {
+    let eval = scratch_script("eval", "fn on_update(component, system, script_state) {\n    eval(\"40 + 2\")\n}");
+    let import = scratch_script(
+        "import",
+        "fn on_update(component, system, script_state) {\n    import \"helpers\" as helpers;\n    script_state\n}",
+    );
+    let mut engine = RhaiEngine::new();
+    let mut state = None;
+
+    run(&mut engine, "mod-id", &eval, &mut state);
+    assert_eq!(engine.errors.last().unwrap().kind, ScriptErrorKind::Compile);
+
+    run(&mut engine, "mod-id", &import, &mut state);
+    let error = engine.errors.last().unwrap();
+    assert_eq!(error.kind, ScriptErrorKind::Runtime);
+    assert_eq!(error.script_path, import.to_str().unwrap());
+
+    cleanup(&eval);
+    cleanup(&import);
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use common::{cleanup, edit, run, scratch_script};
+use entropy_engine::rhai_engine::{RhaiEngine, ScriptErrorKind, SCRIPT_FAILURE_LIMIT};
+use serde_json::json;
+
+const VERSION_1: &str = "fn on_update(component, system, script_state) { script_state[\"version\"] = 1; script_state }";
+const VERSION_2: &str = "fn on_update(component, system, script_state) { script_state[\"version\"] = 2; script_state }";
+const BROKEN: &str = "fn on_update(component, system, script_state) {\n    let x = ;\n}";
+const THROWS: &str = "fn on_update(component, system, script_state) {\n    throw \"stuck\";\n}";
+
+#[test]
+fn edited_scripts_are_recompiled() {
+    let path = scratch_script("edit", VERSION_1);
+    let mut engine = RhaiEngine::new();
+    let mut state = None;
+
+    run(&mut engine, "door-id", &path, &mut state);
+    assert_eq!(state.as_ref().unwrap()["version"], json!(1));
+
+    edit(&path, VERSION_2, 5);
+    // polls are spaced out
+    assert!(engine.reload_changed_scripts(0.1).is_empty());
+    assert_eq!(engine.reload_changed_scripts(1.0), vec![path.to_str().unwrap().to_string()]);
+
+    run(&mut engine, "door-id", &path, &mut state);
+    assert_eq!(state.as_ref().unwrap()["version"], json!(2));
+
+    // nothing changed since
+    assert!(engine.reload_changed_scripts(2.0).is_empty());
+
+    cleanup(&path);
+}
+
+#[test]
+fn broken_edits_keep_the_last_good_version() {
+    let path = scratch_script("broken", VERSION_1);
+    let mut engine = RhaiEngine::new();
+    let mut state = None;
+
+    run(&mut engine, "door-id", &path, &mut state);
+    edit(&path, BROKEN, 5);
+    assert!(engine.reload_changed_scripts(1.0).is_empty());
+
+    let error = engine.errors.last().expect("Expected a compile error");
+    assert_eq!(error.kind, ScriptErrorKind::Compile);
+    assert_eq!(error.line, Some(2));
+    assert!(error.column.is_some());
+    assert!(error.to_string().starts_with(&format!("{}:2:", path.to_str().unwrap())));
+
+    state = None;
+    run(&mut engine, "door-id", &path, &mut state);
+    assert_eq!(state.as_ref().unwrap()["version"], json!(1));
+
+    // fixing it clears the error
+    edit(&path, VERSION_2, 10);
+    assert_eq!(engine.reload_changed_scripts(2.0).len(), 1);
+    assert!(engine.errors.is_empty());
+
+    cleanup(&path);
+}
+
+#[test]
+fn failing_scripts_are_disabled_until_fixed() {
+    let path = scratch_script("throws", THROWS);
+    let script_path = path.to_str().unwrap();
+    let mut engine = RhaiEngine::new();
+    let mut state = None;
+
+    for _ in 0..SCRIPT_FAILURE_LIMIT {
+        run(&mut engine, "door-id", &path, &mut state);
+    }
+
+    // the same runtime error is counted, not repeated
+    let runtime = &engine.errors[0];
+    assert_eq!(runtime.kind, ScriptErrorKind::Runtime);
+    assert_eq!(runtime.line, Some(2));
+    assert_eq!(runtime.hook.as_deref(), Some("on_update"));
+    assert_eq!(runtime.repeats, SCRIPT_FAILURE_LIMIT - 1);
+
+    assert_eq!(engine.errors.last().unwrap().kind, ScriptErrorKind::Disabled);
+    assert!(engine.is_disabled(script_path, "door-id"));
+
+    // disabled scripts aren't called, so no new errors
+    let count = engine.errors.len();
+    run(&mut engine, "door-id", &path, &mut state);
+    assert_eq!(engine.errors.len(), count);
+    assert_eq!(engine.errors[0].repeats, SCRIPT_FAILURE_LIMIT - 1);
+
+    edit(&path, VERSION_1, 5);
+    engine.reload_changed_scripts(1.0);
+    assert!(!engine.is_disabled(script_path, "door-id"));
+
+    run(&mut engine, "door-id", &path, &mut state);
+    assert_eq!(state.as_ref().unwrap()["version"], json!(1));
+
+    cleanup(&path);
+}
@@ -0,0 +1,95 @@
+use entropy_engine::script_tests::{fixture_paths, load_suite, run_suite, to_junit, to_tap, ScriptSuite};
+use serde_json::json;
+use std::path::Path;
+
+const FIXTURES: &str = "tests/fixtures/script_tests";
+
+fn suite(value: serde_json::Value) -> ScriptSuite {
+    serde_json::from_value(value).expect("Couldn't parse suite")
+}
+
+#[test]
+fn fixture_suites_pass() {
+    let paths = fixture_paths(Path::new(FIXTURES));
+    assert_eq!(paths.len(), 3);
+
+    for path in paths {
+        let suite = load_suite(&path).expect("Couldn't load suite");
+        for result in run_suite(&suite) {
+            assert!(result.passed(), "{}: {} failed: {:?}", result.suite, result.name, result.failures);
+        }
+    }
+}
+
+#[test]
+fn failing_expectations_stop_the_case() {
+    let suite = suite(json!({
+        "name": "broken",
+        "script": "tests/fixtures/scripts/lifecycle.rhai",
+        "cases": [
+            {
+                "name": "wrong count",
+                "wrapper": { "type": "model", "id": "crate-id" },
+                "state": { "count": 0 },
+                "steps": [
+                    { "hook": "on_count", "frames": 2, "expect": { "state": { "count": 3 }, "particles": 1 } },
+                    { "hook": "on_teleport" }
+                ]
+            },
+            {
+                "name": "missing hook",
+                "wrapper": { "type": "model", "id": "crate-id" },
+                "steps": [{ "hook": "on_teleport" }]
+            },
+            {
+                "name": "runtime error",
+                "wrapper": { "type": "model", "id": "crate-id" },
+                "steps": [{ "hook": "on_count" }]
+            }
+        ]
+    }));
+
+    let results = run_suite(&suite);
+    assert_eq!(results[0].failures, vec![
+        "step 1 (on_count): state[\"count\"] is 2, expected 3".to_string(),
+        "step 1 (on_count): 0 particle spawns, expected 1".to_string(),
+    ]);
+    assert!(results[1].failures[0].contains("has no function on_teleport"));
+    // count isn't in an empty script_state
+    assert!(results[2].failures[0].starts_with("step 1 (on_count): script error:"));
+}
+
+#[test]
+fn expected_errors_pass() {
+    let suite = suite(json!({
+        "script": "tests/fixtures/scripts/lifecycle.rhai",
+        "cases": [{
+            "name": "count without a count",
+            "wrapper": { "type": "model", "id": "crate-id" },
+            "steps": [{ "hook": "on_count", "expect": { "error": "on_count" } }]
+        }]
+    }));
+
+    assert!(run_suite(&suite)[0].passed());
+}
+
+#[test]
+fn results_print_as_tap_and_junit() {
+    let suite = suite(json!({
+        "name": "report",
+        "script": "tests/fixtures/scripts/lifecycle.rhai",
+        "cases": [
+            { "name": "starts", "wrapper": { "type": "model", "id": "a" }, "steps": [{ "hook": "on_start", "expect": { "state": { "started": true } } }] },
+            { "name": "<missing>", "wrapper": { "type": "model", "id": "a" }, "steps": [{ "hook": "on_teleport" }] }
+        ]
+    }));
+    let results = run_suite(&suite);
+
+    let tap = to_tap(&results);
+    assert!(tap.starts_with("TAP version 13\n1..2\nok 1 - report: starts\nnot ok 2 - report: <missing>\n  ---\n"));
+
+    let junit = to_junit(&results);
+    assert!(junit.contains("<testsuites tests=\"2\" failures=\"1\">"));
+    assert!(junit.contains("<testcase classname=\"report\" name=\"&lt;missing&gt;\""));
+    assert!(junit.contains("<failure message=\"step 1 (on_teleport): "));
+}
@@ -0,0 +1,226 @@
+use entropy_engine::core::World::World;
+use entropy_engine::headless::HeadlessSim;
+use entropy_engine::helpers::saved_data::{ComponentData, ComponentKind, GenericProperties, LevelData, LightProperties, SavedState, ScriptState};
+use entropy_engine::rhai_engine::{dynamic_to_json, json_to_dynamic, ComponentChanges, RhaiEngine, SceneComponent, ScriptScene};
+use nalgebra::Vector3;
+use rapier3d::prelude::*;
+use serde_json::json;
+use uuid::Uuid;
+
+const SCRIPT: &str = "tests/fixtures/scripts/lifecycle.rhai";
+const CRATE_ID: &str = "5f0c2a6e-8d3b-4c1e-9a7f-2b6d4e8c1a30";
+
+fn component(id: &str, name: &str, position: Vector3<f32>) -> SceneComponent {
+    SceneComponent {
+        id: id.to_string(),
+        name: name.to_string(),
+        kind: "Model".to_string(),
+        position,
+        rotation: Vector3::zeros(),
+        scale: Vector3::new(1.0, 1.0, 1.0),
+    }
+}
+
+fn scene() -> ScriptScene {
+    ScriptScene::new(
+        "level-a",
+        vec![
+            component("chest-id", "Chest", Vector3::new(1.0, 2.0, 3.0)),
+            component("door-id", "Door", Vector3::zeros()),
+        ],
+    )
+}
+
+fn model_hook(engine: &mut RhaiEngine, state: &mut Option<ScriptState>, hook: &str) -> Vec<ComponentChanges> {
+    engine.execute_model_hook("chest-id", Vector3::zeros(), Vector3::zeros(), Vector3::new(1.0, 1.0, 1.0), state, SCRIPT, hook, None)
+}
+
+#[test]
+fn global_scripts_reach_components_by_name_and_keep_state() {
+    let mut engine = RhaiEngine::new();
+    engine.set_scene(scene());
+
+    assert!(engine.execute_global_hook(SCRIPT, "on_level_load").is_empty());
+    assert_eq!(engine.global_states[SCRIPT]["loads"], json!(2));
+
+    let changes = engine.execute_global_hook(SCRIPT, "on_update");
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].component_id, "chest-id");
+    assert_eq!(changes[0].new_position, Some(Vector3::new(1.0, 3.0, 3.0)));
+
+    // state from earlier hooks is carried along
+    assert_eq!(engine.global_states[SCRIPT]["loads"], json!(2));
+    assert_eq!(engine.global_states[SCRIPT]["missing"], json!("unit"));
+}
+
+#[test]
+fn missing_hooks_are_skipped() {
+    let mut engine = RhaiEngine::new();
+
+    assert!(engine.has_hook(SCRIPT, "on_start"));
+    assert!(!engine.has_hook(SCRIPT, "on_destroy"));
+    assert!(engine.execute_global_hook(SCRIPT, "on_level_unload").is_empty());
+    assert!(!engine.global_states.contains_key(SCRIPT));
+
+    let mut state = None;
+    assert!(model_hook(&mut engine, &mut state, "on_destroy").is_empty());
+    assert!(state.is_none());
+
+    model_hook(&mut engine, &mut state, "on_start");
+    assert_eq!(state.unwrap()["started"], json!(true));
+}
+
+#[test]
+fn script_state_keeps_its_types() {
+    let state = json!({
+        "count": 3,
+        "speed": 0.1,
+        "open": true,
+        "name": "door",
+        "path": [1, 2, 3],
+        "nested": { "visited": ["a", "b"], "gone": null },
+    });
+
+    let value = json_to_dynamic(&state);
+    assert!(value.is_map());
+    assert_eq!(dynamic_to_json(&value), state);
+
+    let mut engine = RhaiEngine::new();
+    let mut state = Some([("count".to_string(), json!(41))].into());
+    model_hook(&mut engine, &mut state, "on_count");
+    assert_eq!(state.unwrap()["count"], json!(42));
+}
+
+#[test]
+fn transform_changes_are_merged_per_component() {
+    let mut engine = RhaiEngine::new();
+    let mut state = None;
+
+    let changes = model_hook(&mut engine, &mut state, "on_push");
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].component_id, "chest-id");
+    assert_eq!(changes[0].new_rotation, Some(Vector3::new(0.0, 90.0, 0.0)));
+    assert_eq!(changes[0].new_scale, Some(Vector3::new(2.0, 2.0, 2.0)));
+    assert_eq!(changes[0].impulse, Some(Vector3::new(1.0, 5.0, 0.0)));
+}
+
+#[test]
+fn scripts_can_spawn_and_destroy_components() {
+    let mut engine = RhaiEngine::new();
+    engine.set_scene(scene());
+    let mut state = None;
+
+    let changes = model_hook(&mut engine, &mut state, "on_spawn");
+    let spawned = state.unwrap()["spawned"].as_str().unwrap().to_string();
+
+    let door = changes.iter().find(|c| c.component_id == "door-id").unwrap();
+    assert!(door.destroy);
+
+    let spawn = changes.iter().find(|c| c.component_id == spawned).unwrap();
+    assert_eq!(spawn.spawn_from.as_deref(), Some("torch"));
+    assert_eq!(spawn.new_position, Some(Vector3::new(0.0, 1.0, 0.0)));
+}
+
+#[test]
+fn raycasts_hit_components_through_the_query_pipeline() {
+    let mut world = World::new();
+    let user_data = Uuid::parse_str(CRATE_ID).unwrap().as_u128();
+    world.insert_body(
+        RigidBodyBuilder::fixed().user_data(user_data).build(),
+        ColliderBuilder::cuboid(1.0, 1.0, 1.0).user_data(user_data).build(),
+    );
+    world.update_query_pipeline();
+
+    let mut engine = RhaiEngine::new();
+    engine.set_scene(scene().with_world(&world));
+    let mut state = None;
+
+    model_hook(&mut engine, &mut state, "on_look");
+    let state = state.unwrap();
+    assert_eq!(state["hit"], json!(CRATE_ID));
+    assert_eq!(state["distance"].as_f64(), Some(9.0));
+
+    // no player in this world
+    assert_eq!(state["to_player"].as_f64(), Some(-1.0));
+}
+
+#[test]
+fn trigger_hooks_get_the_other_component() {
+    let mut engine = RhaiEngine::new();
+    let mut state = None;
+
+    engine.execute_model_hook(
+        "trap-id",
+        Vector3::zeros(),
+        Vector3::zeros(),
+        Vector3::new(1.0, 1.0, 1.0),
+        &mut state,
+        SCRIPT,
+        "on_trigger_enter",
+        Some("player-id".into()),
+    );
+    assert_eq!(state.unwrap()["entered"], json!("player-id"));
+}
+
+#[test]
+fn timers_call_back_on_game_time() {
+    let mut engine = RhaiEngine::new();
+    let mut state = None;
+
+    assert!(model_hook(&mut engine, &mut state, "on_arm").is_empty());
+    assert_eq!(engine.timers.borrow().pending.len(), 2);
+
+    let mut destroyed = false;
+    for _ in 0..12 {
+        engine.timers.borrow_mut().advance(0.25);
+        while let Some(timer) = engine.next_timer() {
+            let args = timer.args.as_ref().map(json_to_dynamic);
+            let changes = engine.execute_model_hook(
+                &timer.owner_id,
+                Vector3::zeros(),
+                Vector3::zeros(),
+                Vector3::new(1.0, 1.0, 1.0),
+                &mut state,
+                &timer.script_path,
+                &timer.function,
+                args,
+            );
+            destroyed |= changes.iter().any(|c| c.destroy);
+        }
+    }
+
+    let state = state.unwrap();
+    // ticks at 0.5, 1.0 and 1.5, the fuse goes first at 2.0 and stops the ticker
+    assert_eq!(state["ticks"], json!(3));
+    assert_eq!(state["time"], json!(1.5));
+    assert_eq!(state["power"], json!(3));
+    assert_eq!(state["stopped"], json!(true));
+    assert!(destroyed);
+    assert!(engine.timers.borrow().pending.is_empty());
+}
+
+#[test]
+fn headless_runs_give_every_kind_its_wrapper() {
+    let lamp = ComponentData {
+        id: "9b2f4c1d-3e6a-4f8b-a1c2-7d5e9f0b3a64".to_string(),
+        kind: Some(ComponentKind::PointLight),
+        generic_properties: GenericProperties { position: [4.0, 3.0, 0.0], ..Default::default() },
+        light_properties: Some(LightProperties { intensity: 1.5, color: [1.0, 0.8, 0.6, 1.0] }),
+        rhai_script_path: Some("tests/fixtures/scripts/lamp.rhai".to_string()),
+        ..Default::default()
+    };
+    let saved_state = SavedState {
+        levels: Some(vec![LevelData { id: "level-a".to_string(), components: Some(vec![lamp]), ..Default::default() }]),
+        ..Default::default()
+    };
+
+    let mut sim = HeadlessSim::new("headless-test", saved_state);
+    sim.place_components();
+    sim.start();
+    sim.step(1.0 / 60.0);
+
+    // on_start's set_intensity reached on_update, and the light is where it was placed
+    let state = sim.scripted_components[0].script_state.as_ref().expect("Expected the lamp's hooks to run");
+    assert_eq!(state["intensity"], json!(3.0));
+    assert_eq!(state["x"], json!(4.0));
+}
@@ -0,0 +1,81 @@
+use entropy_engine::game_behaviors::timers::{ScriptTimer, Timers};
+use serde_json::json;
+
+const SCRIPT: &str = "scripts/door.rhai";
+const GLOBAL: &str = "scripts/director.rhai";
+
+fn fired(timers: &mut Timers, dt: f32) -> Vec<String> {
+    timers.advance(dt);
+
+    let mut names = Vec::new();
+    while let Some(timer) = timers.next_due() {
+        names.push(timer.function);
+    }
+    names
+}
+
+#[test]
+fn timers_fire_in_order_of_game_time() {
+    let mut timers = Timers::default();
+    timers.after("door", SCRIPT, 1.0, "close", None);
+    timers.after("door", SCRIPT, 0.5, "creak", Some(json!({ "volume": 2 })));
+    timers.every("door", SCRIPT, 0.25, "sway", None);
+
+    assert_eq!(fired(&mut timers, 0.25), vec!["sway"]);
+    assert_eq!(fired(&mut timers, 0.25), vec!["creak", "sway"]);
+
+    // a long frame fires a repeating timer once, not once for every interval it missed
+    assert_eq!(fired(&mut timers, 1.0), vec!["sway", "close"]);
+    assert_eq!(timers.elapsed, 1.5);
+    assert_eq!(timers.pending.len(), 1);
+    assert_eq!(timers.pending[0].due, 1.75);
+}
+
+#[test]
+fn cancelling_stops_timers_due_this_frame() {
+    let mut timers = Timers::default();
+    let first = timers.after("door", SCRIPT, 1.0, "open", None);
+    let second = timers.after("door", SCRIPT, 1.0, "close", None);
+
+    timers.advance(1.0);
+    assert_eq!(timers.next_due().map(|t| t.id), Some(first));
+
+    // what open() would do
+    assert!(timers.cancel(second));
+    assert!(timers.next_due().is_none());
+    assert!(!timers.cancel(second));
+}
+
+#[test]
+fn unloading_a_level_keeps_global_timers() {
+    let mut timers = Timers::default();
+    timers.every("door", SCRIPT, 1.0, "sway", None);
+    timers.after("lamp", SCRIPT, 1.0, "flicker", None);
+    timers.after(GLOBAL, GLOBAL, 5.0, "next_wave", None);
+
+    timers.cancel_owner("lamp");
+    assert_eq!(timers.pending.len(), 2);
+
+    timers.unload_level();
+    let remaining: Vec<&ScriptTimer> = timers.pending.iter().collect();
+    assert_eq!(remaining.len(), 1);
+    assert!(remaining[0].is_global());
+}
+
+#[test]
+fn timers_round_trip_through_save_games() {
+    let mut timers = Timers::default();
+    timers.advance(3.0);
+    timers.after("door", SCRIPT, 2.0, "close", Some(json!([1, "two"])));
+
+    let saved = serde_json::to_string(&timers).unwrap();
+    let mut loaded: Timers = serde_json::from_str(&saved).unwrap();
+
+    assert_eq!(loaded.elapsed, 3.0);
+    assert_eq!(loaded.pending[0].args, Some(json!([1, "two"])));
+
+    // ids keep counting from where the save left off
+    let id = loaded.after("door", SCRIPT, 1.0, "open", None);
+    assert!(id > loaded.pending[0].id);
+    assert_eq!(fired(&mut loaded, 2.0), vec!["open", "close"]);
+}
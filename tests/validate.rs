@@ -0,0 +1,118 @@
+use entropy_engine::validate::{load_for_validation, report_migration, validate_project, Severity, ValidateOptions, ValidationReport};
+use entropy_engine::helpers::saved_data::SavedState;
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const FIXTURES: &str = "tests/fixtures/validate";
+
+fn validate(project: &str) -> ValidationReport {
+    let (saved_state, project_dir, migration) = load_for_validation(&format!("{}/{}", FIXTURES, project)).expect("Couldn't load fixture");
+    let options = ValidateOptions {
+        project_dir,
+        script_root: PathBuf::from(FIXTURES).join("scripts"),
+    };
+
+    let mut report = validate_project(&saved_state, &options);
+    report_migration(&migration, &mut report);
+    report
+}
+
+fn checks(report: &ValidationReport, severity: Severity) -> Vec<&str> {
+    let mut checks: Vec<&str> = report.issues.iter().filter(|i| i.severity == severity).map(|i| i.check).collect();
+    checks.sort();
+    checks
+}
+
+// runs the validate binary the way CI would
+fn exit_code(project: &str) -> i32 {
+    Command::new(env!("CARGO_BIN_EXE_validate"))
+        // targets that aren't a project dir are looked up as project ids in here
+        .env("ENTROPY_PROJECTS_DIR", std::env::temp_dir().join("entropy-validate-projects"))
+        .arg(format!("{}/{}", FIXTURES, project))
+        .args(["--scripts", &format!("{}/scripts", FIXTURES), "--json"])
+        .output()
+        .expect("Couldn't run validate")
+        .status
+        .code()
+        .expect("validate was killed")
+}
+
+#[test]
+fn clean_projects_have_no_issues() {
+    let report = validate("clean");
+
+    assert!(report.issues.is_empty(), "{:?}", report.issues);
+    assert_eq!(report.project, "clean");
+    assert_eq!(report.exit_code(), 0);
+}
+
+#[test]
+fn older_schemas_and_missing_maps_are_warnings() {
+    let before = fs::read_to_string(format!("{}/warnings/midpoint.json", FIXTURES)).unwrap();
+    let report = validate("warnings");
+
+    assert!(checks(&report, Severity::Error).is_empty(), "{:?}", report.issues);
+    // rock only has its diffuse map
+    assert_eq!(
+        checks(&report, Severity::Warning),
+        ["pbr_missing_map", "pbr_missing_map", "pbr_missing_map", "pbr_missing_map", "schema_version"]
+    );
+    assert_eq!(report.exit_code(), 1);
+
+    // validating only migrates in memory
+    assert_eq!(fs::read_to_string(format!("{}/warnings/midpoint.json", FIXTURES)).unwrap(), before);
+}
+
+#[test]
+fn broken_references_files_and_scripts_are_errors() {
+    let report = validate("errors");
+
+    assert_eq!(
+        checks(&report, Severity::Error),
+        [
+            "duplicate_id",
+            "missing_file",
+            "missing_file",
+            "missing_script",
+            "npc_model",
+            "reference",
+            "script_compile",
+        ]
+    );
+    assert_eq!(report.exit_code(), 2);
+
+    let reference = report.issues.iter().find(|i| i.check == "reference").unwrap();
+    assert_eq!(reference.level_id.as_deref(), Some("level-a"));
+    assert_eq!(reference.component_id.as_deref(), Some("barrel"));
+
+    let compile = report.issues.iter().find(|i| i.check == "script_compile").unwrap();
+    assert_eq!(compile.component_id.as_deref(), Some("crate"));
+}
+
+#[test]
+fn the_binary_exits_with_the_worst_severity() {
+    assert_eq!(exit_code("clean"), 0);
+    assert_eq!(exit_code("warnings"), 1);
+    assert_eq!(exit_code("errors"), 2);
+
+    // a project that can't be read at all
+    assert_eq!(exit_code("missing"), 2);
+}
+
+#[test]
+fn scripts_compile_under_the_games_limits() {
+    // a long else-if chain is past rhai's default expression depth but fine in the game
+    let saved_state = SavedState {
+        global_rhai_scripts: Some(vec!["long_chain.rhai".to_string()]),
+        ..Default::default()
+    };
+    let options = ValidateOptions {
+        project_dir: PathBuf::from(FIXTURES),
+        script_root: PathBuf::from(FIXTURES).join("scripts"),
+    };
+
+    let report = validate_project(&saved_state, &options);
+    assert!(checks(&report, Severity::Error).is_empty(), "{:?}", report.issues);
+}
@@ -0,0 +1,131 @@
+use entropy_engine::core::World::World;
+use entropy_engine::helpers::saved_data::{
+    CollectableType, ComponentData, ComponentKind, GenericProperties, NPCProperties, SavedState, StatData, TriggerProperties,
+    TriggerShape,
+};
+use entropy_engine::model_components::NPC::NPCArchetype;
+use nalgebra::{Isometry3, Vector3};
+use rapier3d::prelude::*;
+use uuid::Uuid;
+
+// World placement runs the same way for the renderer and headless runs, none of it needs a GPU
+
+const GUARD_ID: &str = "3f0b2c1d-8e7a-4d6c-9b5a-1e2f3a4b5c6d";
+const POTION_ID: &str = "9a8b7c6d-5e4f-4a3b-8c2d-1e0f9a8b7c6d";
+const POTION_ASSET_ID: &str = "potion-model";
+const GATE_ID: &str = "5d4c3b2a-1f0e-4d9c-8b7a-6f5e4d3c2b1a";
+const PLAYER_ID: &str = "2e1d0c9b-8a7f-4e6d-9c5b-4a3f2e1d0c9b";
+
+fn user_data(id: &str) -> u128 {
+    Uuid::parse_str(id).unwrap().as_u128()
+}
+
+fn guard_archetype() -> NPCArchetype {
+    let guard = ComponentData {
+        id: GUARD_ID.to_string(),
+        kind: Some(ComponentKind::NPC),
+        generic_properties: GenericProperties { name: "Guard".to_string(), ..Default::default() },
+        npc_properties: Some(NPCProperties { health: 250.0, ..Default::default() }),
+        ..Default::default()
+    };
+
+    NPCArchetype::new(&SavedState::default(), &guard, std::slice::from_ref(&guard))
+}
+
+fn gate() -> TriggerProperties {
+    TriggerProperties {
+        shape: TriggerShape::Box,
+        half_extents: [1.0, 1.0, 1.0],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn npcs_get_an_upright_capsule_and_their_archetype() {
+    let mut world = World::new();
+
+    let handle = world
+        .place_npc(GUARD_ID, Isometry3::translation(0.0, 5.0, 0.0), &guard_archetype())
+        .unwrap();
+
+    let body = &world.rigid_body_set[handle];
+    assert!(body.is_dynamic());
+    assert_eq!(body.user_data, user_data(GUARD_ID));
+    assert_eq!(body.locked_axes(), LockedAxes::ROTATION_LOCKED_X | LockedAxes::ROTATION_LOCKED_Z);
+
+    let collider = &world.collider_set[body.colliders()[0]];
+    assert!(collider.shape().as_capsule().is_some());
+    assert_eq!(collider.user_data, user_data(GUARD_ID));
+
+    assert_eq!(world.npcs.len(), 1);
+    assert_eq!(world.npcs[0].id, GUARD_ID);
+    assert_eq!(world.npcs[0].rigid_body_handle, handle);
+    assert_eq!(world.npcs[0].name, "Guard");
+    assert_eq!(world.npcs[0].stats.health, 250.0);
+    assert_eq!(world.component_body(GUARD_ID), Some(handle));
+}
+
+#[test]
+fn collectables_are_fixed_sensors_keyed_by_component() {
+    let mut world = World::new();
+
+    let stats = StatData { id: "potion-stat".to_string(), ..Default::default() };
+    let handle = world
+        .place_collectable(POTION_ID, POTION_ASSET_ID, Isometry3::identity(), CollectableType::Item, stats)
+        .unwrap();
+
+    let body = &world.rigid_body_set[handle];
+    assert!(body.is_fixed());
+    assert_eq!(body.user_data, user_data(POTION_ID));
+    assert!(world.collider_set[body.colliders()[0]].is_sensor());
+
+    assert_eq!(world.collectables.len(), 1);
+    assert_eq!(world.collectables[0].id, POTION_ID);
+    assert_eq!(world.collectables[0].model_id, POTION_ASSET_ID);
+}
+
+#[test]
+fn placed_bodies_report_collisions_by_component() {
+    let mut world = World::new();
+
+    world
+        .place_trigger_volume(GATE_ID, &gate(), Isometry3::identity(), Vector3::new(1.0, 1.0, 1.0))
+        .unwrap();
+    world
+        .place_npc(GUARD_ID, Isometry3::identity(), &guard_archetype())
+        .unwrap();
+
+    let collisions = world.step(1.0 / 60.0).collisions;
+    assert_eq!(collisions.len(), 1);
+    assert!(collisions[0].started);
+    assert_eq!(collisions[0].hook_name(), "on_trigger_enter");
+
+    let ids = [collisions[0].a.as_str(), collisions[0].b.as_str()];
+    assert!(ids.contains(&GATE_ID) && ids.contains(&GUARD_ID));
+}
+
+#[test]
+fn the_player_is_placed_with_its_model() {
+    let mut world = World::new();
+
+    let handle = world
+        .place_player(PLAYER_ID, Isometry3::translation(0.0, 2.0, 0.0), Vector3::new(1.0, 1.0, 1.0), None)
+        .unwrap();
+
+    assert_eq!(world.player_handle(), Some(handle));
+    let player = world.player_character.as_ref().unwrap();
+    assert_eq!(player.model_id.as_deref(), Some(PLAYER_ID));
+}
+
+#[test]
+fn components_without_a_uuid_are_not_placed() {
+    let mut world = World::new();
+
+    assert!(world.place_npc("guard", Isometry3::identity(), &guard_archetype()).is_err());
+    assert!(world
+        .place_trigger_volume("gate", &gate(), Isometry3::identity(), Vector3::new(1.0, 1.0, 1.0))
+        .is_err());
+
+    assert!(world.npcs.is_empty());
+    assert_eq!(world.rigid_body_set.len(), 0);
+}